@@ -0,0 +1,173 @@
+//! Per-message parse time for the trade and depth feeds.
+//!
+//! Run with `cargo bench --bench parse`. Each case is timed twice: the way
+//! messages used to be read (owned `String` fields or a `serde_json::Value`
+//! tree, then `str::parse::<f64>`) and with the borrowed structs the
+//! connectors use now.
+
+use latency_x_core::connectors::binance::{BinanceDepthUpdate, BinanceStreamData, BinanceTrade};
+use latency_x_core::connectors::kraken::{KrakenTradeMessage, KrakenV2Message};
+use serde::Deserialize;
+use std::hint::black_box;
+use std::time::Instant;
+
+const ITERATIONS: u32 = 200_000;
+
+const BINANCE_TRADE: &str = r#"{"stream":"btcusdt@trade","data":{"e":"trade","E":1672515782136,"s":"BTCUSDT","t":12345,"p":"27123.45000000","q":"0.01230000","b":88,"a":50,"T":1672515782136,"m":true,"M":true}}"#;
+
+const BINANCE_DEPTH: &str = r#"{"stream":"btcusdt@depth@100ms","data":{"e":"depthUpdate","E":1672515782136,"s":"BTCUSDT","U":157,"u":160,"b":[["27123.45000000","0.50000000"],["27123.40000000","1.25000000"],["27122.10000000","0.00000000"],["27121.00000000","3.10000000"],["27120.50000000","0.75000000"]],"a":[["27123.46000000","0.40000000"],["27123.90000000","2.00000000"],["27124.00000000","0.00000000"],["27125.30000000","1.10000000"],["27126.00000000","5.00000000"]]}}"#;
+
+const KRAKEN_TRADE: &str = r#"[0,[["27123.40000","0.01230000","1672515782.136059","b","l",""],["27123.50000","0.50000000","1672515782.137001","s","m",""]],"trade","XBT/USD"]"#;
+
+const KRAKEN_DEPTH: &str = r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD","bids":[{"price":27123.4,"qty":0.5},{"price":27122.1,"qty":0.0},{"price":27121.0,"qty":3.1}],"asks":[{"price":27123.5,"qty":0.4},{"price":27124.0,"qty":0.0},{"price":27125.3,"qty":1.1}],"checksum":3310070434,"timestamp":"2023-01-01T00:00:00.136059Z"}]}"#;
+
+#[derive(Deserialize)]
+struct OwnedStream<T> {
+    data: T,
+}
+
+#[derive(Deserialize)]
+struct OwnedTrade {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "t")]
+    trade_id: u64,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    quantity: String,
+}
+
+#[derive(Deserialize)]
+struct OwnedDepth {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bids: Vec<[String; 2]>,
+    #[serde(rename = "a")]
+    asks: Vec<[String; 2]>,
+}
+
+fn binance_trade_owned(text: &str) -> (usize, u64, f64, f64) {
+    let trade: OwnedStream<OwnedTrade> = serde_json::from_str(text).unwrap();
+    let t = trade.data;
+    (t.symbol.len(), t.trade_id, t.price.parse().unwrap(), t.quantity.parse().unwrap())
+}
+
+fn binance_trade_borrowed(text: &str) -> (usize, u64, f64, f64) {
+    let trade: BinanceStreamData<BinanceTrade> = serde_json::from_str(text).unwrap();
+    let t = trade.data;
+    (t.symbol.len(), t.trade_id, t.price, t.quantity)
+}
+
+fn binance_depth_owned(text: &str) -> f64 {
+    let update: OwnedStream<OwnedDepth> = serde_json::from_str(text).unwrap();
+    let mut sum = update.data.symbol.len() as f64;
+    for [price, quantity] in update.data.bids.iter().chain(&update.data.asks) {
+        sum += price.parse::<f64>().unwrap() * quantity.parse::<f64>().unwrap();
+    }
+    sum
+}
+
+fn binance_depth_borrowed(text: &str) -> f64 {
+    let update: BinanceStreamData<BinanceDepthUpdate> = serde_json::from_str(text).unwrap();
+    let mut sum = update.data.symbol.len() as f64;
+    for [price, quantity] in update.data.bids.iter().chain(&update.data.asks) {
+        sum += price.0 * quantity.0;
+    }
+    sum
+}
+
+fn kraken_trade_value(text: &str) -> f64 {
+    let v: serde_json::Value = serde_json::from_str(text).unwrap();
+    let mut sum = v[3].as_str().unwrap_or_default().len() as f64;
+    for trade in v[1].as_array().unwrap() {
+        let price = trade[0].as_str().unwrap().parse::<f64>().unwrap();
+        let volume = trade[1].as_str().unwrap().parse::<f64>().unwrap();
+        let time = trade[2].as_str().unwrap().parse::<f64>().unwrap();
+        sum += price * volume + time;
+    }
+    sum
+}
+
+fn kraken_trade_borrowed(text: &str) -> f64 {
+    let KrakenTradeMessage(_, trades, _, pair) = serde_json::from_str(text).unwrap();
+    let mut sum = pair.len() as f64;
+    for trade in trades {
+        sum += trade.price * trade.volume + trade.time;
+    }
+    sum
+}
+
+#[derive(Deserialize)]
+struct KrakenValueMessage {
+    channel: Option<String>,
+    data: Option<serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct OwnedKrakenBook {
+    symbol: String,
+    bids: Vec<OwnedKrakenLevel>,
+    asks: Vec<OwnedKrakenLevel>,
+    checksum: u32,
+}
+
+#[derive(Deserialize)]
+struct OwnedKrakenLevel {
+    price: f64,
+    qty: f64,
+}
+
+fn kraken_depth_value(text: &str) -> f64 {
+    let msg: KrakenValueMessage = serde_json::from_str(text).unwrap();
+    assert_eq!(msg.channel.as_deref(), Some("book"));
+    let updates: Vec<OwnedKrakenBook> = serde_json::from_value(msg.data.unwrap()).unwrap();
+    let mut sum = 0.0;
+    for update in updates {
+        sum += update.symbol.len() as f64 + update.checksum as f64;
+        for level in update.bids.iter().chain(&update.asks) {
+            sum += level.price * level.qty;
+        }
+    }
+    sum
+}
+
+fn kraken_depth_borrowed(text: &str) -> f64 {
+    let KrakenV2Message::Book { updates, .. } = serde_json::from_str(text).unwrap() else {
+        panic!("not a book message");
+    };
+    let mut sum = 0.0;
+    for update in updates {
+        sum += update.symbol.len() as f64 + update.checksum as f64;
+        for level in update.bids.iter().chain(&update.asks) {
+            sum += level.price * level.qty;
+        }
+    }
+    sum
+}
+
+/// Nanoseconds per call, after a warm-up pass.
+fn time<R>(text: &str, parse: fn(&str) -> R) -> f64 {
+    for _ in 0..ITERATIONS / 10 {
+        black_box(parse(black_box(text)));
+    }
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(parse(black_box(text)));
+    }
+    start.elapsed().as_nanos() as f64 / ITERATIONS as f64
+}
+
+fn report<R>(name: &str, text: &str, before: fn(&str) -> R, after: fn(&str) -> R) {
+    let before = time(text, before);
+    let after = time(text, after);
+    println!("{:<16} before {:>8.1} ns/msg   after {:>8.1} ns/msg   {:>5.2}x", name, before, after, before / after);
+}
+
+fn main() {
+    report("binance trade", BINANCE_TRADE, binance_trade_owned, binance_trade_borrowed);
+    report("binance depth", BINANCE_DEPTH, binance_depth_owned, binance_depth_borrowed);
+    report("kraken trade", KRAKEN_TRADE, kraken_trade_value, kraken_trade_borrowed);
+    report("kraken depth", KRAKEN_DEPTH, kraken_depth_value, kraken_depth_borrowed);
+}
@@ -1,26 +1,137 @@
 use async_trait::async_trait;
-use std::error::Error;
+use chrono::Utc;
 use std::sync::{Arc, Mutex};
-use crate::execution::ExecutionGateway;
-use crate::models::Order;
+use uuid::Uuid;
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::models::{Amendment, Order, OrderReport, OrderStatus, OrderType};
 
+/// Records orders instead of sending them. Market orders count as filled;
+/// limit orders rest until they are canceled or amended.
 #[derive(Debug, Clone, Default)]
 pub struct BacktestExecutionGateway {
     pub orders: Arc<Mutex<Vec<Order>>>,
+    reports: Arc<Mutex<Vec<OrderReport>>>,
 }
 
 impl BacktestExecutionGateway {
     pub fn new() -> Self {
         Self::default()
     }
+
+    fn report(order: &Order) -> OrderReport {
+        let filled = order.order_type == OrderType::Market;
+        OrderReport {
+            order_id: Some(order.id),
+            exchange_order_id: order.id.to_string(),
+            symbol: order.symbol.clone(),
+            side: order.side,
+            order_type: order.order_type.clone(),
+            status: if filled { OrderStatus::Filled } else { OrderStatus::New },
+            amount: order.amount,
+            filled: if filled { order.amount } else { 0.0 },
+            price: order.price,
+            avg_fill_price: if filled { order.price } else { None },
+            source: order.source,
+            updated_at: Utc::now(),
+        }
+    }
+
+    fn cancel(report: &mut OrderReport) -> OrderReport {
+        report.status = OrderStatus::Canceled;
+        report.updated_at = Utc::now();
+        report.clone()
+    }
 }
 
 #[async_trait]
 impl ExecutionGateway for BacktestExecutionGateway {
-    async fn send_order(&self, order: Order) -> Result<String, Box<dyn Error + Send + Sync>> {
-        let order_id = order.id.clone();
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError> {
+        let order_id = order.id.to_string();
+        self.reports.lock().unwrap().push(Self::report(&order));
         self.orders.lock().unwrap().push(order);
         println!("[BACKTEST] Executed order: {}", order_id);
         Ok(order_id)
     }
-} 
\ No newline at end of file
+
+    async fn cancel_order(&self, _symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let mut reports = self.reports.lock().unwrap();
+        match reports.iter_mut().find(|report| report.order_id == Some(order_id)) {
+            Some(report) if report.status.is_open() => Ok(Self::cancel(report)),
+            Some(report) => Err(ExecutionError::UnknownOrder(format!("Order {} is already {}", order_id, report.status))),
+            None => Err(ExecutionError::UnknownOrder(format!("Unknown order {}", order_id))),
+        }
+    }
+
+    async fn cancel_all_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let mut reports = self.reports.lock().unwrap();
+        Ok(reports
+            .iter_mut()
+            .filter(|report| report.status.is_open() && symbol.as_ref().is_none_or(|symbol| &report.symbol == symbol))
+            .map(Self::cancel)
+            .collect())
+    }
+
+    async fn amend_order(&self, order_id: Uuid, replacement: Order) -> Result<Amendment, ExecutionError> {
+        let canceled = self.cancel_order(&replacement.symbol, order_id).await?;
+        let report = Self::report(&replacement);
+        self.send_order(replacement).await?;
+        Ok(Amendment { canceled, replacement: report })
+    }
+
+    async fn query_order(&self, _symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let reports = self.reports.lock().unwrap();
+        reports
+            .iter()
+            .find(|report| report.order_id == Some(order_id))
+            .cloned()
+            .ok_or_else(|| ExecutionError::UnknownOrder(format!("Unknown order {}", order_id)))
+    }
+
+    async fn open_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let reports = self.reports.lock().unwrap();
+        Ok(reports
+            .iter()
+            .filter(|report| report.status.is_open() && symbol.as_ref().is_none_or(|symbol| &report.symbol == symbol))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MarketDataSource, OrderSide};
+
+    fn limit(price: f64) -> Order {
+        Order {
+            order_type: OrderType::Limit,
+            price: Some(price),
+            ..Order::market("BTC/USDT".to_string(), OrderSide::Buy, 1.0, MarketDataSource::Binance, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_limit_orders_rest_until_amended_or_canceled() {
+        let gateway = BacktestExecutionGateway::new();
+        let market = Order::market("BTC/USDT".to_string(), OrderSide::Sell, 1.0, MarketDataSource::Binance, None);
+        gateway.send_order(market.clone()).await.unwrap();
+        let resting = limit(100.0);
+        gateway.send_order(resting.clone()).await.unwrap();
+
+        assert_eq!(gateway.query_order("BTC/USDT", market.id).await.unwrap().status, OrderStatus::Filled);
+        let open = gateway.open_orders(Some("BTC/USDT".to_string())).await.unwrap();
+        assert_eq!(open.iter().map(|report| report.order_id).collect::<Vec<_>>(), vec![Some(resting.id)]);
+
+        let replacement = limit(101.0);
+        let amended = gateway.amend_order(resting.id, replacement.clone()).await.unwrap();
+        assert_eq!((amended.replacement.order_id, amended.replacement.price), (Some(replacement.id), Some(101.0)));
+        assert_eq!((amended.canceled.order_id, amended.canceled.status), (Some(resting.id), OrderStatus::Canceled));
+        assert_eq!(gateway.query_order("BTC/USDT", resting.id).await.unwrap().status, OrderStatus::Canceled);
+        assert!(gateway.amend_order(resting.id, limit(102.0)).await.is_err());
+
+        let canceled = gateway.cancel_all_orders(None).await.unwrap();
+        assert_eq!(canceled.len(), 1);
+        assert!(gateway.open_orders(None).await.unwrap().is_empty());
+        assert!(gateway.cancel_order("BTC/USDT", market.id).await.is_err());
+    }
+}
@@ -0,0 +1,243 @@
+//! OHLCV bars built from the trade feed.
+//!
+//! Every tick is folded into one bar per configured interval for its source
+//! and symbol, bucketed by exchange time when the venue reports it. Bars are
+//! aligned to the Unix epoch, so a 60 s bar always opens on the minute. A bar
+//! closes once the clock passes its end plus a short grace period for trades
+//! still in flight, or earlier if a trade for a later bar arrives. Intervals
+//! without trades produce no bar.
+//!
+//! Closed bars are saved through `DatabaseManager` and broadcast as
+//! `DashboardEvent::Candle`.
+
+use crate::config::CandleConfig;
+use crate::dashboard::events::DashboardEvent;
+use crate::models::{Candle, MarketDataSource, Tick};
+use crate::persistence::db::DatabaseManager;
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Utc};
+use metrics::counter;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::broadcast;
+use tracing::{error, info};
+
+/// How often open bars are checked against the clock.
+const CANDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_millis(100);
+
+/// Builds bars for every source and symbol it sees. Holds no clock of its
+/// own; callers decide when bars are due.
+pub struct CandleAggregator {
+    intervals_secs: Vec<u64>,
+    grace: Duration,
+    /// One slot per entry of `intervals_secs`.
+    open: HashMap<(MarketDataSource, String), Vec<BarSlot>>,
+}
+
+#[derive(Clone, Default)]
+struct BarSlot {
+    bar: Option<Candle>,
+    /// End of the last closed bar; earlier trades are late.
+    closed_until: Option<DateTime<Utc>>,
+}
+
+impl BarSlot {
+    fn close(&mut self) -> Option<Candle> {
+        let bar = self.bar.take()?;
+        self.closed_until = Some(bar.close_time());
+        Some(bar)
+    }
+}
+
+impl CandleAggregator {
+    pub fn new(intervals_secs: &[u64], grace: Duration) -> Self {
+        Self {
+            intervals_secs: intervals_secs.to_vec(),
+            grace,
+            open: HashMap::new(),
+        }
+    }
+
+    /// Folds a trade into the open bars and returns any it closed. Trades
+    /// older than a symbol's open bar are dropped and counted.
+    pub fn on_tick(&mut self, tick: &Tick) -> Vec<Candle> {
+        let traded_at = tick.exchange_ts.unwrap_or(tick.received_at);
+        let slots = self
+            .open
+            .entry((tick.source, tick.symbol.clone()))
+            .or_insert_with(|| vec![BarSlot::default(); self.intervals_secs.len()]);
+
+        let mut closed = Vec::new();
+        for (slot, &interval_secs) in slots.iter_mut().zip(&self.intervals_secs) {
+            let open_time = bar_open_time(traded_at, interval_secs);
+            let late = slot.closed_until.is_some_and(|until| open_time < until)
+                || slot.bar.as_ref().is_some_and(|bar| open_time < bar.open_time);
+            if late {
+                counter!("candle_late_ticks", "source" => tick.source.to_string(), "symbol" => tick.symbol.clone()).increment(1);
+                continue;
+            }
+            match &mut slot.bar {
+                Some(bar) if bar.open_time == open_time => {
+                    bar.high = bar.high.max(tick.price);
+                    bar.low = bar.low.min(tick.price);
+                    bar.close = tick.price;
+                    bar.volume += tick.volume;
+                    bar.trades += 1;
+                }
+                _ => {
+                    let bar = Candle {
+                        source: tick.source,
+                        symbol: tick.symbol.clone(),
+                        interval_secs,
+                        open_time,
+                        open: tick.price,
+                        high: tick.price,
+                        low: tick.price,
+                        close: tick.price,
+                        volume: tick.volume,
+                        trades: 1,
+                    };
+                    closed.extend(slot.close());
+                    slot.bar = Some(bar);
+                }
+            }
+        }
+        closed
+    }
+
+    /// Closes every bar that ended more than the grace period before `now`.
+    pub fn close_due(&mut self, now: DateTime<Utc>) -> Vec<Candle> {
+        let mut closed = Vec::new();
+        for slots in self.open.values_mut() {
+            for slot in slots.iter_mut() {
+                if slot.bar.as_ref().is_some_and(|bar| bar.close_time() + self.grace <= now) {
+                    closed.extend(slot.close());
+                }
+            }
+        }
+        closed.sort_by_key(|bar| (bar.close_time(), bar.interval_secs));
+        closed
+    }
+}
+
+fn bar_open_time(at: DateTime<Utc>, interval_secs: u64) -> DateTime<Utc> {
+    let interval_ms = interval_secs as i64 * 1000;
+    let open_ms = at.timestamp_millis().div_euclid(interval_ms) * interval_ms;
+    DateTime::from_timestamp_millis(open_ms).unwrap_or(at)
+}
+
+/// Runs a `CandleAggregator` on its own task. Recording never blocks: when
+/// the task falls behind, ticks are dropped and counted instead.
+pub struct CandleService {
+    tx: mpsc::Sender<Tick>,
+}
+
+impl CandleService {
+    pub fn spawn(
+        config: &CandleConfig,
+        db_manager: Arc<DatabaseManager>,
+        dashboard_tx: broadcast::Sender<DashboardEvent>,
+    ) -> Result<Self> {
+        if config.intervals_secs.is_empty() || config.intervals_secs.contains(&0) {
+            bail!("[candles] intervals_secs must list positive intervals, got {:?}", config.intervals_secs);
+        }
+        let (tx, mut rx) = mpsc::channel::<Tick>(config.buffer);
+        let mut aggregator = CandleAggregator::new(&config.intervals_secs, Duration::milliseconds(config.grace_ms as i64));
+
+        tokio::spawn(async move {
+            let mut check = tokio::time::interval(CANDLE_CHECK_INTERVAL);
+            // Replayed ticks carry their original timestamps, so the clock
+            // runs on from the last tick seen rather than from the host.
+            let mut feed_clock: Option<(DateTime<Utc>, Instant)> = None;
+            loop {
+                let closed = tokio::select! {
+                    tick = rx.recv() => {
+                        let Some(tick) = tick else {
+                            break;
+                        };
+                        if feed_clock.is_none_or(|(at, _)| tick.received_at >= at) {
+                            feed_clock = Some((tick.received_at, Instant::now()));
+                        }
+                        aggregator.on_tick(&tick)
+                    }
+                    _ = check.tick() => {
+                        let now = match feed_clock {
+                            Some((at, seen)) => at + Duration::from_std(seen.elapsed()).unwrap_or_default(),
+                            None => Utc::now(),
+                        };
+                        aggregator.close_due(now)
+                    }
+                };
+                for candle in closed {
+                    if let Err(e) = db_manager.save_candle(&candle).await {
+                        error!("Failed to save candle to DB: {}", e);
+                    }
+                    let _ = dashboard_tx.send(DashboardEvent::Candle(candle));
+                }
+            }
+        });
+
+        info!(intervals_secs = ?config.intervals_secs, "Aggregating candles");
+        Ok(Self { tx })
+    }
+
+    pub fn record(&self, tick: &Tick) {
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(tick.clone()) {
+            counter!("candle_ticks_dropped").increment(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(symbol: &str, price: f64, volume: f64, at: &str) -> Tick {
+        let at = DateTime::parse_from_rfc3339(at).unwrap().with_timezone(&Utc);
+        Tick {
+            source: MarketDataSource::Binance,
+            symbol: symbol.to_string(),
+            price,
+            volume,
+            received_at: at,
+            exchange_ts: Some(at),
+            trade_id: None,
+        }
+    }
+
+    #[test]
+    fn test_bars_roll_over_on_the_next_interval() {
+        let mut aggregator = CandleAggregator::new(&[60, 300], Duration::zero());
+        assert!(aggregator.on_tick(&tick("BTC/USDT", 100.0, 1.0, "2024-01-01T00:00:05Z")).is_empty());
+        assert!(aggregator.on_tick(&tick("BTC/USDT", 103.0, 0.5, "2024-01-01T00:00:20Z")).is_empty());
+        assert!(aggregator.on_tick(&tick("BTC/USDT", 99.0, 2.0, "2024-01-01T00:00:40Z")).is_empty());
+        assert!(aggregator.on_tick(&tick("ETH/USDT", 10.0, 1.0, "2024-01-01T00:00:50Z")).is_empty());
+
+        let closed = aggregator.on_tick(&tick("BTC/USDT", 101.0, 1.0, "2024-01-01T00:01:00Z"));
+        assert_eq!(closed.len(), 1);
+        let bar = &closed[0];
+        assert_eq!((bar.symbol.as_str(), bar.interval_secs), ("BTC/USDT", 60));
+        assert_eq!(bar.open_time.to_rfc3339(), "2024-01-01T00:00:00+00:00");
+        assert_eq!((bar.open, bar.high, bar.low, bar.close), (100.0, 103.0, 99.0, 99.0));
+        assert_eq!((bar.volume, bar.trades), (3.5, 3));
+    }
+
+    #[test]
+    fn test_bars_close_on_the_clock_after_the_grace_period() {
+        let mut aggregator = CandleAggregator::new(&[60, 300], Duration::milliseconds(250));
+        aggregator.on_tick(&tick("BTC/USDT", 100.0, 1.0, "2024-01-01T00:00:05Z"));
+
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        assert!(aggregator.close_due(at("2024-01-01T00:01:00.100Z")).is_empty());
+        let closed = aggregator.close_due(at("2024-01-01T00:01:00.250Z"));
+        assert_eq!(closed.iter().map(|bar| bar.interval_secs).collect::<Vec<_>>(), vec![60]);
+
+        // A late trade for the closed minute is dropped; the 5 minute bar still takes it.
+        assert!(aggregator.on_tick(&tick("BTC/USDT", 90.0, 1.0, "2024-01-01T00:00:59Z")).is_empty());
+        let closed = aggregator.close_due(at("2024-01-01T00:06:00Z"));
+        assert_eq!(closed.len(), 1);
+        assert_eq!((closed[0].interval_secs, closed[0].trades, closed[0].low), (300, 2, 90.0));
+    }
+}
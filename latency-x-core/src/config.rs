@@ -1,3 +1,4 @@
+use crate::models::MarketDataSource;
 use serde::Deserialize;
 use std::env;
 
@@ -6,24 +7,203 @@ use std::env;
 pub struct Config {
     pub database_url: String,
     pub binance: ExchangeConfig,
+    /// USD-M perpetuals; the futures feeds only run when this is set.
+    #[serde(default)]
+    pub binance_futures: Option<ExchangeConfig>,
     pub kraken: ExchangeConfig,
     pub coinbase: ExchangeConfig,
     pub helius: HeliusConfig,
     pub mev_strategy: MevStrategyConfig,
     pub pump_strategy: PumpStrategyConfig,
     pub solana: SolanaConfig,
+    #[serde(default)]
+    pub feed: FeedConfig,
+    #[serde(default)]
+    pub instruments: InstrumentsConfig,
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub candles: CandleConfig,
+    #[serde(default)]
+    pub oms: OmsConfig,
+    #[serde(default)]
+    pub paper: PaperConfig,
+    #[serde(default)]
+    pub dashboard: DashboardConfig,
 }
 
 #[derive(Clone, Debug, Deserialize)]
 pub struct ExchangeConfig {
     pub api_key: String,
     pub api_secret: String,
+    /// Number of levels per side published for this venue's order books.
+    #[serde(default = "default_book_depth")]
+    pub book_depth: usize,
+}
+
+fn default_book_depth() -> usize {
+    20
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FeedConfig {
+    /// A symbol with no data for this long is reported stale, and a
+    /// connection with no frames at all for this long is torn down.
+    pub stale_after_ms: u64,
+    /// How often a WebSocket ping is sent and feed health is checked.
+    pub ping_interval_ms: u64,
+}
+
+impl Default for FeedConfig {
+    fn default() -> Self {
+        Self {
+            stale_after_ms: 10_000,
+            ping_interval_ms: 2_000,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct InstrumentsConfig {
+    /// Canonical `BASE/QUOTE` symbols to trade on every venue that lists them.
+    pub symbols: Vec<String>,
+    /// Last successfully fetched instrument list, used when exchanges are unreachable.
+    pub cache_path: String,
+}
+
+impl Default for InstrumentsConfig {
+    fn default() -> Self {
+        Self {
+            symbols: vec!["BTC/USDT".to_string()],
+            cache_path: "data/instruments.json".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CaptureConfig {
+    pub enabled: bool,
+    /// Directory receiving the rotated `capture-*.csv.gz` files.
+    pub dir: String,
+    /// A new file is started once the current one is this old.
+    pub rotate_secs: u64,
+    /// Records queued for the writer thread before new ones are dropped.
+    pub buffer: usize,
+}
+
+impl Default for CaptureConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: "data/capture".to_string(),
+            rotate_secs: 3600,
+            buffer: 65_536,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct CandleConfig {
+    pub enabled: bool,
+    /// Bar lengths built for every source and symbol.
+    pub intervals_secs: Vec<u64>,
+    /// How long a bar stays open past its end for trades still in flight.
+    pub grace_ms: u64,
+    /// Ticks queued for the aggregator before new ones are dropped.
+    pub buffer: usize,
+}
+
+impl Default for CandleConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            intervals_secs: vec![60, 300, 3600],
+            grace_ms: 250,
+            buffer: 65_536,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct OmsConfig {
+    /// How often working orders are checked against each venue's open orders.
+    pub reconcile_interval_secs: u64,
+}
+
+impl Default for OmsConfig {
+    fn default() -> Self {
+        Self {
+            reconcile_interval_secs: 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct PaperConfig {
+    /// How far past the touch paper market orders fill, in basis points.
+    pub slippage_bps: f64,
+    pub binance: FeeSchedule,
+    pub kraken: FeeSchedule,
+    pub coinbase: FeeSchedule,
+}
+
+impl PaperConfig {
+    /// Venues without a schedule of their own trade free.
+    pub fn fees(&self, venue: MarketDataSource) -> FeeSchedule {
+        match venue {
+            MarketDataSource::Binance => self.binance,
+            MarketDataSource::Kraken => self.kraken,
+            MarketDataSource::Coinbase => self.coinbase,
+            _ => FeeSchedule { maker_bps: 0.0, taker_bps: 0.0 },
+        }
+    }
+}
+
+impl Default for PaperConfig {
+    fn default() -> Self {
+        Self {
+            slippage_bps: 1.0,
+            binance: FeeSchedule { maker_bps: 10.0, taker_bps: 10.0 },
+            kraken: FeeSchedule { maker_bps: 25.0, taker_bps: 40.0 },
+            coinbase: FeeSchedule { maker_bps: 40.0, taker_bps: 60.0 },
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct DashboardConfig {
+    /// Bearer token required by the API routes that change what the bot
+    /// trades. Without one, those routes only answer local connections.
+    pub admin_token: Option<String>,
+}
+
+/// A venue's trading fees, in basis points of notional.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct FeeSchedule {
+    /// Charged when a resting order is filled.
+    pub maker_bps: f64,
+    /// Charged when an order fills on arrival.
+    pub taker_bps: f64,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct HeliusConfig {
     pub api_key: String,
     pub cluster: String,
+    /// How far a pump.fun fill may move against the bonding curve quote.
+    #[serde(default = "default_slippage_bps")]
+    pub slippage_bps: u64,
+}
+
+fn default_slippage_bps() -> u64 {
+    100
 }
 
 #[derive(Clone, Debug, Deserialize)]
@@ -57,12 +237,6 @@ pub struct RedisConfig {
     pub url: String,
 }
 
-// #[derive(Debug, Deserialize, Clone)]
-// pub struct CoinbaseConfig {
-//     pub api_key: String,
-//     pub api_secret: String,
-// }
-
 #[allow(dead_code)]
 fn default_mev_strategy_config() -> MevStrategyConfig {
     MevStrategyConfig {
@@ -81,13 +255,6 @@ fn default_redis_config() -> RedisConfig {
     }
 }
 
-// fn default_coinbase_config() -> CoinbaseConfig {
-//     CoinbaseConfig {
-//         api_key: "YOUR_COINBASE_API_KEY".to_string(),
-//         api_secret: "YOUR_COINBASE_API_SECRET".to_string(),
-//     }
-// }
-
 impl Config {
     pub fn from_file(path: &str) -> anyhow::Result<Self> {
         let settings = config::Config::builder()
@@ -96,6 +263,20 @@ impl Config {
         
         let mut config: Config = settings.try_deserialize()?;
 
+        if config.binance.api_key.starts_with('$') {
+            config.binance.api_key = env::var(&config.binance.api_key[1..])?;
+        }
+        if config.binance.api_secret.starts_with('$') {
+            config.binance.api_secret = env::var(&config.binance.api_secret[1..])?;
+        }
+        if let Some(futures) = config.binance_futures.as_mut() {
+            if futures.api_key.starts_with('$') {
+                futures.api_key = env::var(&futures.api_key[1..])?;
+            }
+            if futures.api_secret.starts_with('$') {
+                futures.api_secret = env::var(&futures.api_secret[1..])?;
+            }
+        }
         if config.kraken.api_key.starts_with('$') {
             config.kraken.api_key = env::var(&config.kraken.api_key[1..])?;
         }
@@ -111,12 +292,17 @@ impl Config {
         if config.solana.private_key.starts_with('$') {
             config.solana.private_key = env::var(&config.solana.private_key[1..])?;
         }
-        // if config.coinbase.api_key.starts_with('$') {
-        //     config.coinbase.api_key = env::var(&config.coinbase.api_key[1..])?;
-        // }
-        // if config.coinbase.api_secret.starts_with('$') {
-        //     config.coinbase.api_secret = env::var(&config.coinbase.api_secret[1..])?;
-        // }
+        if config.coinbase.api_key.starts_with('$') {
+            config.coinbase.api_key = env::var(&config.coinbase.api_key[1..])?;
+        }
+        if config.coinbase.api_secret.starts_with('$') {
+            config.coinbase.api_secret = env::var(&config.coinbase.api_secret[1..])?;
+        }
+        if let Some(token) = config.dashboard.admin_token.as_mut() {
+            if token.starts_with('$') {
+                *token = env::var(&token[1..])?;
+            }
+        }
 
         Ok(config)
     }
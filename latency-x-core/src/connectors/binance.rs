@@ -1,80 +1,1065 @@
-use crate::connectors::Connector;
-use crate::execution::ExecutionGateway;
-use crate::models::{Fill, MarketDataSource, Order, Tick};
+use crate::config::{ExchangeConfig, FeedConfig};
+use crate::connectors::sequence::{record_backfill, SequenceCheck, TradeSequence};
+use crate::connectors::decimal::{self, Decimal};
+use crate::connectors::subscriptions::{PendingRequests, SubscriptionChange, SubscriptionRequest, Subscriptions};
+use crate::connectors::watchdog::FeedWatchdog;
+use crate::connectors::{publish_fill, record_feed_latency, Connector, FeedBackoff, PublishedTrades, OrderBookConnector, QuoteConnector};
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::instruments::{canonical_symbol, Instrument, InstrumentRegistry};
+use crate::models::{Amendment, FeedStream, Fill, MarketDataSource, Order, OrderBook, OrderReport, OrderSide, OrderStatus, OrderType, Quote, Tick};
 use crate::settlement::Settlement;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
-use futures_util::stream::StreamExt;
+use futures_util::{SinkExt, StreamExt};
 use hmac_sha256;
 use hex;
 use reqwest::Client;
 use serde::Deserialize;
-use std::sync::Arc;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Sender;
 use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
 use url::Url;
 use std::error::Error;
-use backoff::ExponentialBackoff;
+use std::fmt;
 use backoff::future::retry;
-use chrono::{self, Utc};
+use chrono::{self, DateTime, Utc};
 use tracing::{info, warn, error};
 use crate::dashboard::events::DashboardEvent;
 use tokio::sync::broadcast;
 use crate::persistence::db::DatabaseManager;
 use uuid::Uuid;
+use metrics::counter;
 
-const BINANCE_API_KEY: &str = "YOUR_API_KEY";
-const BINANCE_API_SECRET: &str = "YOUR_API_SECRET";
 const BINANCE_API_URL: &str = "https://api.binance.com";
+const BINANCE_WS_URL: &str = "wss://stream.binance.com:9443";
+/// Depth requested for the REST snapshot. The local book is kept at full depth
+/// and only truncated when published.
+const BINANCE_SNAPSHOT_LIMIT: usize = 1000;
+/// Most trades `historicalTrades` returns per request.
+const BINANCE_HISTORICAL_TRADES_LIMIT: u64 = 1000;
+/// Longer gaps are only backfilled with their most recent trades.
+const BINANCE_BACKFILL_MAX: u64 = 5000;
+const BINANCE_USER_STREAM_PATH: &str = "/api/v3/userDataStream";
+const BINANCE_ORDER_PATH: &str = "/api/v3/order";
+const BINANCE_OPEN_ORDERS_PATH: &str = "/api/v3/openOrders";
+const BINANCE_CANCEL_REPLACE_PATH: &str = "/api/v3/order/cancelReplace";
+/// Trade ids remembered so a fill seen in an order response is not published
+/// again when the user data stream repeats it.
+const BINANCE_PUBLISHED_TRADES: usize = 4096;
+/// Listen keys expire after an hour without a keepalive.
+const BINANCE_LISTEN_KEY_KEEPALIVE: Duration = Duration::from_secs(30 * 60);
+
+/// Where the connector reaches Binance. Tests point this at the local simulator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BinanceEndpoints {
+    pub api_url: String,
+    pub ws_url: String,
+}
+
+impl Default for BinanceEndpoints {
+    fn default() -> Self {
+        Self {
+            api_url: BINANCE_API_URL.to_string(),
+            ws_url: BINANCE_WS_URL.to_string(),
+        }
+    }
+}
 
 pub struct BinanceConnector {
     http_client: Client,
-    settlement: Arc<dyn Settlement>,
+    endpoints: BinanceEndpoints,
+    api_key: String,
+    api_secret: String,
     fill_sender: Option<Sender<Fill>>,
     dashboard_tx: broadcast::Sender<DashboardEvent>,
     db_manager: Arc<DatabaseManager>,
+    watchdog: FeedWatchdog,
+    instruments: Arc<InstrumentRegistry>,
+    subscriptions: Subscriptions,
+    published_trades: Mutex<PublishedTrades>,
 }
 
 impl BinanceConnector {
+    /// Orders settle on the exchange, so `_settlement` is not used here; it
+    /// is kept so every connector is built the same way.
     pub fn new(
-        settlement: Arc<dyn Settlement>,
+        config: &ExchangeConfig,
+        _settlement: Arc<dyn Settlement>,
         fill_sender: Option<Sender<Fill>>,
         dashboard_tx: broadcast::Sender<DashboardEvent>,
         db_manager: Arc<DatabaseManager>,
     ) -> Self {
+        let watchdog = FeedWatchdog::new(MarketDataSource::Binance, &FeedConfig::default(), dashboard_tx.clone(), None);
         Self {
             http_client: Client::new(),
-            settlement,
+            endpoints: BinanceEndpoints::default(),
+            api_key: config.api_key.clone(),
+            api_secret: config.api_secret.clone(),
             fill_sender,
             dashboard_tx,
             db_manager,
+            watchdog,
+            instruments: Arc::new(InstrumentRegistry::new()),
+            subscriptions: Subscriptions::new(),
+            published_trades: Mutex::new(PublishedTrades::new(BINANCE_PUBLISHED_TRADES)),
         }
     }
 
+    pub fn with_watchdog(mut self, watchdog: FeedWatchdog) -> Self {
+        self.watchdog = watchdog;
+        self
+    }
+
+    pub fn with_instruments(mut self, instruments: Arc<InstrumentRegistry>) -> Self {
+        self.instruments = instruments;
+        self
+    }
+
+    pub fn with_endpoints(mut self, endpoints: BinanceEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Stream name prefix for a canonical symbol, e.g. `btcusdt`.
+    fn stream_symbol(&self, symbol: &str) -> String {
+        self.instruments.ws_symbol(MarketDataSource::Binance, symbol).to_lowercase()
+    }
+
+    /// A combined stream connection for one kind of stream, e.g. `trade`.
+    fn streams_url(&self, symbols: &[String], stream: &str) -> Result<Url> {
+        let streams = symbols
+            .iter()
+            .map(|s| format!("{}@{}", self.stream_symbol(s), stream))
+            .collect::<Vec<_>>()
+            .join("/");
+        Ok(Url::parse(&format!("{}/stream?streams={}", self.endpoints.ws_url, streams))?)
+    }
+
+    /// A `SUBSCRIBE` or `UNSUBSCRIBE` for one kind of stream on an open
+    /// connection. Binance answers it under the request's id.
+    fn subscription_command(&self, request: &SubscriptionRequest, stream: &str) -> serde_json::Value {
+        let (method, symbols) = match &request.change {
+            SubscriptionChange::Add(symbols) => ("SUBSCRIBE", symbols),
+            SubscriptionChange::Remove(symbols) => ("UNSUBSCRIBE", symbols),
+        };
+        let streams: Vec<String> = symbols.iter().map(|s| format!("{}@{}", self.stream_symbol(s), stream)).collect();
+        json!({
+            "method": method,
+            "params": streams,
+            "id": request.id,
+        })
+    }
+
+    async fn fetch_depth_snapshot(&self, symbol: &str) -> Result<BinanceDepthSnapshot> {
+        let url = format!(
+            "{}/api/v3/depth?symbol={}&limit={}",
+            self.endpoints.api_url,
+            symbol.to_uppercase(),
+            BINANCE_SNAPSHOT_LIMIT
+        );
+        let response_text = self
+            .http_client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        Ok(serde_json::from_str(&response_text)?)
+    }
+
+    /// Recovers the trades with `after < id < before`, oldest first.
+    async fn backfill_trades(&self, venue_symbol: &str, symbol: &str, after: u64, before: u64) -> Result<Vec<Tick>> {
+        let mut from_id = after + 1;
+        if before - from_id > BINANCE_BACKFILL_MAX {
+            warn!("Binance {} missed {} trades, backfilling the last {}", symbol, before - from_id, BINANCE_BACKFILL_MAX);
+            from_id = before - BINANCE_BACKFILL_MAX;
+        }
+
+        let mut ticks = Vec::new();
+        while from_id < before {
+            let url = format!(
+                "{}/api/v3/historicalTrades?symbol={}&fromId={}&limit={}",
+                self.endpoints.api_url,
+                venue_symbol.to_uppercase(),
+                from_id,
+                (before - from_id).min(BINANCE_HISTORICAL_TRADES_LIMIT)
+            );
+            let response_text = self
+                .http_client
+                .get(&url)
+                .header("X-MBX-APIKEY", &self.api_key)
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let trades: Vec<BinanceHistoricalTrade> = serde_json::from_str(&response_text)?;
+            let Some(last) = trades.last() else {
+                break;
+            };
+            from_id = last.id + 1;
+            for trade in trades.into_iter().filter(|trade| trade.id < before) {
+                ticks.push(Tick {
+                    source: MarketDataSource::Binance,
+                    symbol: symbol.to_string(),
+                    price: trade.price.parse()?,
+                    volume: trade.quantity.parse()?,
+                    received_at: Utc::now(),
+                    exchange_ts: DateTime::from_timestamp_millis(trade.time),
+                    trade_id: Some(trade.id),
+                });
+            }
+        }
+        Ok(ticks)
+    }
+
+    /// Creates (or, if one is active, returns) the listen key for the user data stream.
+    async fn create_listen_key(&self) -> Result<String> {
+        let response_text = self
+            .http_client
+            .post(format!("{}{}", self.endpoints.api_url, BINANCE_USER_STREAM_PATH))
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let response: BinanceListenKey = serde_json::from_str(&response_text)?;
+        Ok(response.listen_key)
+    }
+
+    async fn keepalive_listen_key(&self, listen_key: &str) -> Result<()> {
+        self.http_client
+            .put(format!("{}{}?listenKey={}", self.endpoints.api_url, BINANCE_USER_STREAM_PATH, listen_key))
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Streams account events and turns every execution into a `Fill` on the
+    /// fill channel, keeping the stored order status in step with Binance.
+    pub async fn subscribe_user_data(self: Arc<Self>) -> Result<()> {
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let listen_key = self.create_listen_key().await.map_err(backoff::Error::transient)?;
+            let url = Url::parse(&format!("{}/ws/{}", self.endpoints.ws_url, listen_key))
+                .map_err(|e| backoff::Error::transient(e.into()))?;
+            let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+            connection.up();
+            info!("Connected to Binance user data stream");
+
+            let (mut write, mut read) = ws_stream.split();
+            let mut keepalive = tokio::time::interval(BINANCE_LISTEN_KEY_KEEPALIVE);
+            // The first tick fires immediately and the key is brand new.
+            keepalive.tick().await;
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = keepalive.tick() => {
+                        self.keepalive_listen_key(&listen_key).await.map_err(backoff::Error::transient)?;
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Binance user data stream closed")));
+                };
+                let text = match message.map_err(|e| backoff::Error::transient(e.into()))? {
+                    Message::Text(text) => text,
+                    Message::Ping(payload) => {
+                        write.send(Message::Pong(payload)).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    _ => continue,
+                };
+                let event: BinanceUserEvent = match serde_json::from_str(&text) {
+                    Ok(event) => event,
+                    Err(e) => {
+                        warn!("Failed to parse Binance user data message: {:?}, error: {}", text, e);
+                        continue;
+                    }
+                };
+
+                match event {
+                    BinanceUserEvent::ExecutionReport(report) => self.on_execution_report(*report).await,
+                    BinanceUserEvent::ListenKeyExpired => {
+                        return Err(backoff::Error::transient(anyhow!("Binance listen key expired")));
+                    }
+                    BinanceUserEvent::Other => {}
+                }
+            }
+        };
+
+        retry(backoff, operation).await
+    }
+
+    async fn on_execution_report(&self, report: BinanceExecutionReport) {
+        let Some(order_id) = report.order_id() else {
+            warn!(client_order_id = %report.client_order_id, "Execution report for an order not placed by us");
+            return;
+        };
+        info!(
+            order_id = %order_id,
+            symbol = %report.symbol,
+            execution_type = %report.execution_type,
+            status = %report.order_status,
+            "Binance execution report"
+        );
+
+        if let Some(status) = report.status() {
+            if let Err(e) = self.db_manager.update_order_status(order_id, &status).await {
+                error!("Failed to update order status: {}", e);
+            }
+        }
+
+        match report.fill(order_id) {
+            Ok(Some(fill)) => self.publish_fill_once(fill).await,
+            Ok(None) => {}
+            Err(e) => error!("Failed to read Binance execution report: {}", e),
+        }
+    }
+
+    /// Publishes a fill under our symbol unless its trade was already
+    /// published from the other source.
+    async fn publish_fill_once(&self, mut fill: Fill) {
+        if let Some(trade_id) = &fill.trade_id {
+            let key = format!("{}:{}", fill.symbol, trade_id);
+            if !self.published_trades.lock().unwrap().insert(key) {
+                return;
+            }
+        }
+        fill.symbol = self.instruments.canonical(MarketDataSource::Binance, &fill.symbol);
+        publish_fill(fill, self.fill_sender.as_ref(), &self.db_manager, &self.dashboard_tx).await
+    }
+
+    /// The parameters shared by new orders and cancel-replace. Binance wants
+    /// a time in force on every limit order.
+    fn order_params(&self, order: &Order) -> Result<Vec<(&'static str, String)>> {
+        let mut params = vec![
+            ("symbol", self.instruments.venue_symbol(MarketDataSource::Binance, &order.symbol)),
+            ("side", side_param(order.side).to_string()),
+            ("type", order_type_param(&order.order_type).to_string()),
+            ("quantity", decimal::format_decimal(order.amount)),
+            ("newClientOrderId", order.id.to_string()),
+        ];
+        if order.order_type == OrderType::Limit {
+            let price = order.price.ok_or_else(|| anyhow!("Limit order {} has no price", order.id))?;
+            params.push(("price", decimal::format_decimal(price)));
+            params.push(("timeInForce", "GTC".to_string()));
+        }
+        Ok(params)
+    }
+
+    /// Sends a signed request with `params` in the query string and returns
+    /// the response body. Error responses are read as a [`BinanceApiError`]
+    /// and classified by its kind.
+    async fn signed_request(
+        &self,
+        method: reqwest::Method,
+        path: &str,
+        params: &[(&str, String)],
+    ) -> Result<String, ExecutionError> {
+        let mut query = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(params)
+            .append_pair("timestamp", &Utc::now().timestamp_millis().to_string())
+            .finish();
+        let signature = self.sign_request(&query);
+        query.push_str(&format!("&signature={}", signature));
+
+        let res = self
+            .http_client
+            .request(method.clone(), format!("{}{}?{}", self.endpoints.api_url, path, query))
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send()
+            .await?;
+        let status = res.status();
+        let response_text = res.text().await?;
+        if !status.is_success() {
+            let error = BinanceApiError::from_response(status.as_u16(), &response_text);
+            warn!(method = %method, path, code = error.code, "Binance request failed: {}", error);
+            return Err(error.into());
+        }
+        Ok(response_text)
+    }
+
+    fn order_report(&self, order: &BinanceOrder) -> Result<OrderReport> {
+        let mut report = order.report()?;
+        report.symbol = self.instruments.canonical(MarketDataSource::Binance, &report.symbol);
+        Ok(report)
+    }
+
+    /// Reports for a list of orders, skipping any kind we do not trade (stop
+    /// orders placed by hand, say).
+    fn order_reports(&self, response_text: &str) -> Result<Vec<OrderReport>, ExecutionError> {
+        let orders: Vec<BinanceOrder> = serde_json::from_str(response_text)?;
+        Ok(orders
+            .iter()
+            .filter_map(|order| match self.order_report(order) {
+                Ok(report) => Some(report),
+                Err(e) => {
+                    warn!(exchange_order_id = order.order_id, "Skipping Binance order: {}", e);
+                    None
+                }
+            })
+            .collect())
+    }
+
     fn sign_request(&self, params: &str) -> String {
-        let key = BINANCE_API_SECRET.as_bytes();
+        let key = self.api_secret.as_bytes();
         let signature = hmac_sha256::HMAC::mac(params.as_bytes(), key);
         hex::encode(signature)
     }
 }
 
 #[derive(Debug, Deserialize)]
-#[allow(dead_code)]
-struct BinanceTrade {
+struct BinanceExchangeInfo {
+    symbols: Vec<BinanceSymbolInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceSymbolInfo {
+    symbol: String,
+    status: String,
+    base_asset: String,
+    quote_asset: String,
+    /// Futures only, e.g. `PERPETUAL` or `CURRENT_QUARTER`.
+    #[serde(default)]
+    contract_type: Option<String>,
+    filters: Vec<BinanceSymbolFilter>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "filterType")]
+enum BinanceSymbolFilter {
+    #[serde(rename = "PRICE_FILTER", rename_all = "camelCase")]
+    Price { tick_size: String },
+    #[serde(rename = "LOT_SIZE", rename_all = "camelCase")]
+    LotSize { step_size: String },
+    #[serde(rename = "NOTIONAL", rename_all = "camelCase")]
+    Notional { min_notional: String },
+    /// Futures spell the field `notional`.
+    #[serde(rename = "MIN_NOTIONAL", rename_all = "camelCase")]
+    MinNotional {
+        #[serde(alias = "notional")]
+        min_notional: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+impl BinanceSymbolInfo {
+    fn instrument(&self, venue: MarketDataSource) -> Result<Instrument> {
+        let mut instrument = Instrument {
+            symbol: canonical_symbol(&self.base_asset, &self.quote_asset),
+            base: self.base_asset.clone(),
+            quote: self.quote_asset.clone(),
+            venue,
+            venue_symbol: self.symbol.clone(),
+            ws_symbol: self.symbol.clone(),
+            tick_size: 0.0,
+            lot_size: 0.0,
+            min_notional: 0.0,
+        };
+        for filter in &self.filters {
+            match filter {
+                BinanceSymbolFilter::Price { tick_size } => instrument.tick_size = tick_size.parse()?,
+                BinanceSymbolFilter::LotSize { step_size } => instrument.lot_size = step_size.parse()?,
+                BinanceSymbolFilter::Notional { min_notional }
+                | BinanceSymbolFilter::MinNotional { min_notional } => instrument.min_notional = min_notional.parse()?,
+                BinanceSymbolFilter::Other => {}
+            }
+        }
+        Ok(instrument)
+    }
+}
+
+/// Trading rules for every symbol currently trading on Binance spot.
+pub(crate) async fn fetch_instruments(http_client: &Client) -> Result<Vec<Instrument>> {
+    let response_text = http_client
+        .get(format!("{}/api/v3/exchangeInfo?permissions=SPOT", BINANCE_API_URL))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_exchange_info(&response_text, MarketDataSource::Binance)
+}
+
+/// Instruments from a spot or futures `exchangeInfo` response. Of futures
+/// only perpetuals are kept, so each canonical symbol names one contract.
+pub(crate) fn parse_exchange_info(text: &str, venue: MarketDataSource) -> Result<Vec<Instrument>> {
+    let info: BinanceExchangeInfo = serde_json::from_str(text)?;
+    info.symbols
+        .iter()
+        .filter(|s| s.status == "TRADING")
+        .filter(|s| s.contract_type.as_deref().is_none_or(|contract| contract == "PERPETUAL"))
+        .map(|s| s.instrument(venue))
+        .collect()
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceListenKey {
+    #[serde(rename = "listenKey")]
+    listen_key: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "e")]
+enum BinanceUserEvent {
+    #[serde(rename = "executionReport")]
+    ExecutionReport(Box<BinanceExecutionReport>),
+    #[serde(rename = "listenKeyExpired")]
+    ListenKeyExpired,
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceExecutionReport {
     #[serde(rename = "s")]
     symbol: String,
+    #[serde(rename = "c")]
+    client_order_id: String,
+    /// Set on cancellations, where `c` is the id of the cancel request.
+    #[serde(rename = "C", default)]
+    orig_client_order_id: Option<String>,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "x")]
+    execution_type: String,
+    #[serde(rename = "X")]
+    order_status: String,
+    #[serde(rename = "l")]
+    last_quantity: String,
+    #[serde(rename = "z", default)]
+    cumulative_quantity: String,
+    #[serde(rename = "L")]
+    last_price: String,
+    #[serde(rename = "n")]
+    commission: String,
+    #[serde(rename = "N", default)]
+    commission_asset: Option<String>,
     #[serde(rename = "T")]
-    timestamp: u64,
-    #[serde(rename = "p")]
+    transaction_time: i64,
+    #[serde(rename = "t")]
+    trade_id: i64,
+}
+
+impl BinanceExecutionReport {
+    /// Orders are placed with their `Uuid` as the client order id.
+    fn order_id(&self) -> Option<Uuid> {
+        let client_order_id = match &self.orig_client_order_id {
+            Some(orig) if !orig.is_empty() => orig,
+            _ => &self.client_order_id,
+        };
+        Uuid::parse_str(client_order_id).ok()
+    }
+
+    fn status(&self) -> Option<OrderStatus> {
+        let filled = self.cumulative_quantity.parse::<f64>().unwrap_or_default();
+        order_status(&self.order_status, filled)
+    }
+
+    /// Only `TRADE` executions move quantity; everything else is a status change.
+    fn fill(&self, order_id: Uuid) -> Result<Option<Fill>> {
+        if self.execution_type != "TRADE" {
+            return Ok(None);
+        }
+        let quantity: f64 = self.last_quantity.parse()?;
+        if quantity <= 0.0 {
+            return Ok(None);
+        }
+        let side = match self.side.as_str() {
+            "BUY" => OrderSide::Buy,
+            "SELL" => OrderSide::Sell,
+            other => anyhow::bail!("Unknown Binance order side: {}", other),
+        };
+        Ok(Some(Fill {
+            order_id,
+            symbol: self.symbol.clone(),
+            side,
+            price: self.last_price.parse()?,
+            quantity,
+            source: MarketDataSource::Binance,
+            executed_at: DateTime::from_timestamp_millis(self.transaction_time).unwrap_or_else(Utc::now),
+            commission: self.commission.parse()?,
+            commission_asset: self.commission_asset.clone(),
+            trade_id: Some(self.trade_id.to_string()),
+        }))
+    }
+}
+
+/// `filled` is the quantity executed so far. A `PENDING_CANCEL` order can
+/// still trade, so it keeps its working status until the cancel lands.
+fn order_status(status: &str, filled: f64) -> Option<OrderStatus> {
+    match status {
+        "NEW" | "PENDING_NEW" => Some(OrderStatus::New),
+        "PARTIALLY_FILLED" => Some(OrderStatus::PartiallyFilled),
+        "PENDING_CANCEL" if filled > 0.0 => Some(OrderStatus::PartiallyFilled),
+        "PENDING_CANCEL" => Some(OrderStatus::New),
+        "FILLED" => Some(OrderStatus::Filled),
+        "CANCELED" => Some(OrderStatus::Canceled),
+        "REJECTED" => Some(OrderStatus::Rejected),
+        "EXPIRED" | "EXPIRED_IN_MATCH" => Some(OrderStatus::Expired),
+        _ => None,
+    }
+}
+
+/// An order as the REST API returns it from queries and cancels.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceOrder {
+    symbol: String,
+    order_id: u64,
+    client_order_id: String,
+    /// Set on cancels, where `clientOrderId` is the id of the cancel request.
+    #[serde(default)]
+    orig_client_order_id: Option<String>,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    price: f64,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    orig_qty: f64,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    executed_qty: f64,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    cummulative_quote_qty: f64,
+    status: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    side: String,
+    /// Queries carry `updateTime`, cancels and new orders `transactTime`.
+    #[serde(alias = "transactTime", default)]
+    update_time: Option<i64>,
+    /// Only in `FULL` new-order responses.
+    #[serde(default)]
+    fills: Vec<BinanceOrderFill>,
+}
+
+/// One execution in a `FULL` new-order response.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceOrderFill {
+    #[serde(deserialize_with = "decimal::deserialize")]
+    price: f64,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    qty: f64,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    commission: f64,
+    #[serde(default)]
+    commission_asset: Option<String>,
+    trade_id: i64,
+}
+
+impl BinanceOrder {
+    /// The executions in a `FULL` response, under the venue symbol.
+    fn fills(&self, order_id: Uuid) -> Result<Vec<Fill>> {
+        let side = match self.side.as_str() {
+            "BUY" => OrderSide::Buy,
+            "SELL" => OrderSide::Sell,
+            other => anyhow::bail!("Unknown Binance order side: {}", other),
+        };
+        let executed_at = self.update_time.and_then(DateTime::from_timestamp_millis).unwrap_or_else(Utc::now);
+        Ok(self
+            .fills
+            .iter()
+            .map(|fill| Fill {
+                order_id,
+                symbol: self.symbol.clone(),
+                side,
+                price: fill.price,
+                quantity: fill.qty,
+                source: MarketDataSource::Binance,
+                executed_at,
+                commission: fill.commission,
+                commission_asset: fill.commission_asset.clone(),
+                trade_id: Some(fill.trade_id.to_string()),
+            })
+            .collect())
+    }
+
+    /// A report under the venue symbol; the caller maps it to ours.
+    fn report(&self) -> Result<OrderReport> {
+        let client_order_id = match &self.orig_client_order_id {
+            Some(orig) if !orig.is_empty() => orig,
+            _ => &self.client_order_id,
+        };
+        let side = match self.side.as_str() {
+            "BUY" => OrderSide::Buy,
+            "SELL" => OrderSide::Sell,
+            other => anyhow::bail!("Unknown Binance order side: {}", other),
+        };
+        let order_type = match self.order_type.as_str() {
+            "MARKET" => OrderType::Market,
+            "LIMIT" | "LIMIT_MAKER" => OrderType::Limit,
+            other => anyhow::bail!("Unsupported Binance order type: {}", other),
+        };
+        let status = order_status(&self.status, self.executed_qty).ok_or_else(|| anyhow!("Unknown Binance order status: {}", self.status))?;
+        let filled = self.executed_qty;
+        Ok(OrderReport {
+            order_id: Uuid::parse_str(client_order_id).ok(),
+            exchange_order_id: self.order_id.to_string(),
+            symbol: self.symbol.clone(),
+            side,
+            order_type,
+            status,
+            amount: self.orig_qty,
+            filled,
+            price: Some(self.price).filter(|price| *price > 0.0),
+            avg_fill_price: (filled > 0.0).then(|| self.cummulative_quote_qty / filled),
+            source: MarketDataSource::Binance,
+            updated_at: self.update_time.and_then(DateTime::from_timestamp_millis).unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+fn side_param(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "BUY",
+        OrderSide::Sell => "SELL",
+    }
+}
+
+fn order_type_param(order_type: &OrderType) -> &'static str {
+    match order_type {
+        OrderType::Market => "MARKET",
+        OrderType::Limit => "LIMIT",
+    }
+}
+
+/// What a Binance error means for the request that caused it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinanceErrorKind {
+    /// -1013: price, quantity or notional outside the symbol's filters.
+    FilterFailure,
+    /// -2010 for lack of funds, or -2019 for lack of futures margin.
+    InsufficientBalance,
+    /// -2010 otherwise, -2021 and -2022: the matching engine refused the order.
+    OrderRejected,
+    /// -2011 and -2013: the order does not exist or is already closed.
+    UnknownOrder,
+    /// -1021: the local clock is outside the receive window of Binance's.
+    Timestamp,
+    /// -1022, -2014 and -2015: bad signature, key or permissions.
+    Unauthorized,
+    /// -1003 and -1015, or HTTP 418 and 429.
+    RateLimited,
+    /// -1100 to -1199: the request itself is malformed.
+    BadRequest,
+    /// -1000, -1001, -1006, -1007 or a 5xx: Binance failed, and the order
+    /// may or may not have been placed.
+    Unknown,
+}
+
+impl fmt::Display for BinanceErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let description = match self {
+            BinanceErrorKind::FilterFailure => "order outside symbol filters",
+            BinanceErrorKind::InsufficientBalance => "insufficient balance",
+            BinanceErrorKind::OrderRejected => "order rejected",
+            BinanceErrorKind::UnknownOrder => "unknown order",
+            BinanceErrorKind::Timestamp => "clock out of sync",
+            BinanceErrorKind::Unauthorized => "unauthorized",
+            BinanceErrorKind::RateLimited => "rate limited",
+            BinanceErrorKind::BadRequest => "bad request",
+            BinanceErrorKind::Unknown => "unknown outcome",
+        };
+        f.write_str(description)
+    }
+}
+
+/// An error response from the Binance REST API.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct BinanceApiError {
+    #[serde(skip)]
+    pub status: u16,
+    pub code: i64,
+    pub msg: String,
+}
+
+impl BinanceApiError {
+    /// Bodies that are not Binance's `{"code", "msg"}`, such as a firewall's
+    /// HTML page, are kept whole with code 0.
+    pub(crate) fn from_response(status: u16, body: &str) -> Self {
+        match serde_json::from_str::<BinanceApiError>(body) {
+            Ok(error) => BinanceApiError { status, ..error },
+            Err(_) => BinanceApiError { status, code: 0, msg: body.to_string() },
+        }
+    }
+
+    pub fn kind(&self) -> BinanceErrorKind {
+        match (self.code, self.status) {
+            (-1003 | -1015, _) | (_, 418 | 429) => BinanceErrorKind::RateLimited,
+            (-1013, _) => BinanceErrorKind::FilterFailure,
+            (-2010, _) if self.msg.to_lowercase().contains("insufficient balance") => BinanceErrorKind::InsufficientBalance,
+            (-2019, _) => BinanceErrorKind::InsufficientBalance,
+            (-2010 | -2021 | -2022, _) => BinanceErrorKind::OrderRejected,
+            (-2011 | -2013, _) => BinanceErrorKind::UnknownOrder,
+            (-1021, _) => BinanceErrorKind::Timestamp,
+            (-1022 | -2014 | -2015, _) | (_, 401 | 403) => BinanceErrorKind::Unauthorized,
+            (-1199..=-1100, _) => BinanceErrorKind::BadRequest,
+            _ => BinanceErrorKind::Unknown,
+        }
+    }
+}
+
+impl fmt::Display for BinanceApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Binance {} ({}): {}", self.kind(), self.code, self.msg)
+    }
+}
+
+impl From<BinanceApiError> for ExecutionError {
+    fn from(error: BinanceApiError) -> Self {
+        let message = error.to_string();
+        match error.kind() {
+            // A skewed clock keeps failing until it is fixed.
+            BinanceErrorKind::FilterFailure | BinanceErrorKind::OrderRejected | BinanceErrorKind::Timestamp | BinanceErrorKind::BadRequest => {
+                ExecutionError::Rejected(message)
+            }
+            BinanceErrorKind::InsufficientBalance => ExecutionError::InsufficientFunds(message),
+            BinanceErrorKind::UnknownOrder => ExecutionError::UnknownOrder(message),
+            BinanceErrorKind::Unauthorized => ExecutionError::Unauthorized(message),
+            BinanceErrorKind::RateLimited => ExecutionError::RateLimited(message),
+            BinanceErrorKind::Unknown => ExecutionError::Timeout(message),
+        }
+    }
+}
+
+impl Error for BinanceApiError {}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceCancelReplace {
+    cancel_response: BinanceOrder,
+    new_order_response: BinanceOrder,
+}
+
+/// A `@trade` event, borrowed from the frame it was read from.
+#[derive(Debug, Deserialize)]
+pub struct BinanceTrade<'a> {
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "t")]
+    pub trade_id: u64,
+    #[serde(rename = "T")]
+    pub timestamp: i64,
+    #[serde(rename = "p", deserialize_with = "decimal::deserialize")]
+    pub price: f64,
+    #[serde(rename = "q", deserialize_with = "decimal::deserialize")]
+    pub quantity: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceHistoricalTrade {
+    id: u64,
     price: String,
-    #[serde(rename = "q")]
+    #[serde(rename = "qty")]
     quantity: String,
+    time: i64,
 }
 
+/// The answer to a `SUBSCRIBE` or `UNSUBSCRIBE` command.
 #[derive(Debug, Deserialize)]
-struct BinanceStreamData {
-    data: BinanceTrade,
+pub(crate) struct BinanceCommandReply {
+    pub(crate) id: u64,
+    #[serde(default)]
+    pub(crate) error: Option<serde_json::Value>,
+}
+
+impl BinanceCommandReply {
+    /// Hands the venue's answer to the request the command was sent for.
+    pub(crate) fn answer(self, pending: &mut PendingRequests) {
+        pending.reply(self.id, self.error.map_or(Ok(()), |error| Err(error.to_string())));
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceBookTicker {
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "b")]
+    bid: String,
+    #[serde(rename = "B")]
+    bid_size: String,
+    #[serde(rename = "a")]
+    ask: String,
+    #[serde(rename = "A")]
+    ask_size: String,
+}
+
+impl BinanceBookTicker {
+    fn quote(&self, symbol: String) -> Result<Quote> {
+        Ok(Quote {
+            source: MarketDataSource::Binance,
+            symbol,
+            bid: self.bid.parse()?,
+            bid_size: self.bid_size.parse()?,
+            ask: self.ask.parse()?,
+            ask_size: self.ask_size.parse()?,
+            received_at: Utc::now(),
+            // Spot bookTicker events carry no timestamp.
+            exchange_ts: None,
+        })
+    }
+}
+
+/// The envelope of a combined stream message.
+#[derive(Debug, Deserialize)]
+pub struct BinanceStreamData<T> {
+    pub data: T,
+}
+
+/// A `@depth` diff event, borrowed from the frame it was read from.
+#[derive(Debug, Deserialize)]
+pub struct BinanceDepthUpdate<'a> {
+    #[serde(rename = "s")]
+    pub symbol: &'a str,
+    #[serde(rename = "U")]
+    pub first_update_id: u64,
+    #[serde(rename = "u")]
+    pub final_update_id: u64,
+    #[serde(rename = "b")]
+    pub bids: Vec<[Decimal; 2]>,
+    #[serde(rename = "a")]
+    pub asks: Vec<[Decimal; 2]>,
+}
+
+/// The part of a depth update `DepthSync` keeps while waiting for a snapshot.
+#[derive(Debug)]
+struct DepthDiff {
+    first_update_id: u64,
+    final_update_id: u64,
+    bids: Vec<[Decimal; 2]>,
+    asks: Vec<[Decimal; 2]>,
+}
+
+impl From<BinanceDepthUpdate<'_>> for DepthDiff {
+    fn from(update: BinanceDepthUpdate<'_>) -> Self {
+        Self {
+            first_update_id: update.first_update_id,
+            final_update_id: update.final_update_id,
+            bids: update.bids,
+            asks: update.asks,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceDepthSnapshot {
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: u64,
+    bids: Vec<[Decimal; 2]>,
+    asks: Vec<[Decimal; 2]>,
+}
+
+#[derive(Debug, PartialEq)]
+enum DepthEvent {
+    /// No snapshot applied yet; the update was buffered.
+    Pending,
+    Updated,
+    /// The update is older than the book and was dropped.
+    Ignored,
+    /// Sequence broke; the book was cleared and needs a fresh snapshot.
+    Gap { expected: u64, received: u64 },
+}
+
+/// Keeps one symbol's book in step with the diff stream, following Binance's
+/// snapshot + `U`/`u` sequencing procedure.
+struct DepthSync {
+    book: OrderBook,
+    synced: bool,
+    buffer: Vec<DepthDiff>,
+}
+
+impl DepthSync {
+    fn new(symbol: String) -> Self {
+        Self {
+            book: OrderBook::new(MarketDataSource::Binance, symbol),
+            synced: false,
+            buffer: Vec::new(),
+        }
+    }
+
+    fn on_update(&mut self, update: DepthDiff) -> Result<DepthEvent> {
+        if !self.synced {
+            self.buffer.push(update);
+            return Ok(DepthEvent::Pending);
+        }
+        self.apply(&update)
+    }
+
+    fn on_snapshot(&mut self, snapshot: BinanceDepthSnapshot) -> Result<DepthEvent> {
+        self.book.clear();
+        for [price, quantity] in &snapshot.bids {
+            self.book.update_bid(price.0, quantity.0);
+        }
+        for [price, quantity] in &snapshot.asks {
+            self.book.update_ask(price.0, quantity.0);
+        }
+        self.book.last_update_id = snapshot.last_update_id;
+
+        let buffered: Vec<_> = std::mem::take(&mut self.buffer)
+            .into_iter()
+            .filter(|u| u.final_update_id > snapshot.last_update_id)
+            .collect();
+
+        // The snapshot must overlap the first buffered event, otherwise it is
+        // older than the stream and a newer one is needed.
+        if let Some(first) = buffered.first() {
+            if first.first_update_id > snapshot.last_update_id + 1 {
+                let event = DepthEvent::Gap {
+                    expected: snapshot.last_update_id + 1,
+                    received: first.first_update_id,
+                };
+                self.book.clear();
+                self.buffer = buffered;
+                return Ok(event);
+            }
+        }
+
+        self.synced = true;
+        for update in &buffered {
+            if let event @ DepthEvent::Gap { .. } = self.apply(update)? {
+                return Ok(event);
+            }
+        }
+        Ok(DepthEvent::Updated)
+    }
+
+    fn apply(&mut self, update: &DepthDiff) -> Result<DepthEvent> {
+        let last = self.book.last_update_id;
+        if update.final_update_id <= last {
+            return Ok(DepthEvent::Ignored);
+        }
+        if update.first_update_id > last + 1 {
+            self.synced = false;
+            self.book.clear();
+            return Ok(DepthEvent::Gap {
+                expected: last + 1,
+                received: update.first_update_id,
+            });
+        }
+
+        for [price, quantity] in &update.bids {
+            self.book.update_bid(price.0, quantity.0);
+        }
+        for [price, quantity] in &update.asks {
+            self.book.update_ask(price.0, quantity.0);
+        }
+        self.book.last_update_id = update.final_update_id;
+        self.book.received_at = Utc::now();
+        Ok(DepthEvent::Updated)
+    }
 }
 
 #[async_trait]
@@ -84,62 +1069,334 @@ impl Connector for BinanceConnector {
         symbols: &[String],
         sender: mpsc::Sender<Tick>,
     ) -> Result<()> {
+        self.subscriptions.reset(symbols);
+        // Kept across reconnects, so trades missed while disconnected are backfilled.
+        let sequence = std::sync::Mutex::new(TradeSequence::new(MarketDataSource::Binance));
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
         let operation = || async {
-            let streams = symbols
-                .iter()
-                .map(|s| format!("{}@trade", s.to_lowercase()))
-                .collect::<Vec<_>>()
-                .join("/");
-            let url = Url::parse(&format!(
-                "wss://stream.binance.com:9443/stream?streams={}",
-                streams
-            )).map_err(|e| backoff::Error::transient(e.into()))?;
+            let (symbols, mut changes) = self.subscriptions.connect();
+            let url = self.streams_url(&symbols, "trade").map_err(backoff::Error::transient)?;
 
             let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+
+            connection.up();
             info!("Connected to Binance WebSocket");
 
-            let (_, mut read) = ws_stream.split();
+            let (mut write, mut read) = ws_stream.split();
+            self.watchdog.on_connect(FeedStream::Trades, &symbols);
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
+            let mut pending = PendingRequests::default();
 
-            while let Some(message) = read.next().await {
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::Trades, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Binance trade stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        // A missed change is recovered by reconnecting with the current set.
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let command = self.subscription_command(&request, "trade");
+                        write.send(Message::Text(command.to_string())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        match &request.change {
+                            SubscriptionChange::Add(symbols) => self.watchdog.on_connect(FeedStream::Trades, symbols),
+                            SubscriptionChange::Remove(symbols) => {
+                                self.watchdog.forget(FeedStream::Trades, symbols);
+                                let mut sequence = sequence.lock().unwrap();
+                                for symbol in symbols {
+                                    sequence.forget(symbol);
+                                }
+                            }
+                        }
+                        pending.insert(request, 1);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Binance trade stream closed")));
+                };
                 let data = message.map_err(|e| backoff::Error::transient(e.into()))?;
-                let msg_str = data.to_text().map_err(|e| backoff::Error::transient(e.into()))?;
-                let trade_data: BinanceStreamData = match serde_json::from_str(msg_str) {
+                last_frame = Instant::now();
+                let Message::Text(msg_str) = data else {
+                    continue;
+                };
+                let trade_data: BinanceStreamData<BinanceTrade> = match serde_json::from_str(&msg_str) {
                     Ok(data) => data,
                     Err(e) => {
-                        warn!("Failed to parse message: {:?}, error: {}", msg_str, e);
+                        match serde_json::from_str::<BinanceCommandReply>(&msg_str) {
+                            Ok(reply) => reply.answer(&mut pending),
+                            Err(_) => warn!("Failed to parse message: {:?}, error: {}", msg_str, e),
+                        }
                         continue;
                     }
                 };
+                let symbol = self.instruments.canonical(MarketDataSource::Binance, trade_data.data.symbol);
+                self.watchdog.on_message(FeedStream::Trades, &symbol);
+
+                let check = sequence.lock().unwrap().check_id(&symbol, trade_data.data.trade_id);
+                match check {
+                    SequenceCheck::Duplicate => continue,
+                    SequenceCheck::Gap { after, before } => {
+                        warn!("Binance {} trade gap after id {}, backfilling up to {}", symbol, after, before);
+                        match self.backfill_trades(trade_data.data.symbol, &symbol, after, before).await {
+                            Ok(ticks) => {
+                                record_backfill(MarketDataSource::Binance, &symbol, ticks.len());
+                                for tick in ticks {
+                                    if let Err(e) = sender.send(tick.clone()).await {
+                                        error!("Failed to send tick: {}", e);
+                                        return Ok(());
+                                    }
+                                    let _ = self.dashboard_tx.send(DashboardEvent::Tick(tick));
+                                }
+                            }
+                            Err(e) => warn!("Failed to backfill Binance {} trades: {}", symbol, e),
+                        }
+                    }
+                    SequenceCheck::InOrder => (),
+                }
 
                 let tick = Tick {
                     source: MarketDataSource::Binance,
-                    symbol: trade_data.data.symbol.clone(),
-                    price: trade_data.data.price.parse().map_err(|e: std::num::ParseFloatError| backoff::Error::permanent(e.into()))?,
-                    volume: trade_data.data.quantity.parse().map_err(|e: std::num::ParseFloatError| backoff::Error::permanent(e.into()))?,
+                    symbol,
+                    price: trade_data.data.price,
+                    volume: trade_data.data.quantity,
                     received_at: Utc::now(),
+                    exchange_ts: DateTime::from_timestamp_millis(trade_data.data.timestamp),
+                    trade_id: Some(trade_data.data.trade_id),
                 };
 
                 if let Err(e) = sender.send(tick.clone()).await {
                     error!("Failed to send tick: {}", e);
-                    break;
+                    return Ok(());
                 }
+                record_feed_latency(&tick, &self.dashboard_tx);
                 let _ = self.dashboard_tx.send(DashboardEvent::Tick(tick));
             }
-
-            Ok(())
         };
 
-        retry(ExponentialBackoff::default(), operation).await
+        retry(backoff, operation).await
     }
 
     fn get_source(&self) -> MarketDataSource {
         MarketDataSource::Binance
     }
+
+    async fn add_symbols(&self, symbols: &[String]) -> Result<()> {
+        self.subscriptions.add(symbols).await?;
+        Ok(())
+    }
+
+    async fn remove_symbols(&self, symbols: &[String]) -> Result<()> {
+        self.subscriptions.remove(symbols).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrderBookConnector for BinanceConnector {
+    async fn subscribe_order_book(
+        self: Arc<Self>,
+        symbols: &[String],
+        depth: usize,
+        sender: mpsc::Sender<OrderBook>,
+    ) -> Result<()> {
+        self.subscriptions.reset(symbols);
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let (symbols, mut changes) = self.subscriptions.connect();
+            let url = self.streams_url(&symbols, "depth@100ms").map_err(backoff::Error::transient)?;
+
+            let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+
+            connection.up();
+            info!("Connected to Binance depth WebSocket");
+
+            let (mut write, mut read) = ws_stream.split();
+            self.watchdog.on_connect(FeedStream::Book, &symbols);
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
+            let mut pending = PendingRequests::default();
+            // Books are rebuilt from scratch on every (re)connect.
+            let mut books: HashMap<String, DepthSync> = HashMap::new();
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::Book, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Binance depth stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let command = self.subscription_command(&request, "depth@100ms");
+                        write.send(Message::Text(command.to_string())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        match &request.change {
+                            SubscriptionChange::Add(symbols) => self.watchdog.on_connect(FeedStream::Book, symbols),
+                            SubscriptionChange::Remove(symbols) => {
+                                self.watchdog.forget(FeedStream::Book, symbols);
+                                books.retain(|symbol, _| !symbols.contains(symbol));
+                            }
+                        }
+                        pending.insert(request, 1);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Binance depth stream closed")));
+                };
+                let data = message.map_err(|e| backoff::Error::transient(e.into()))?;
+                last_frame = Instant::now();
+                let Message::Text(msg_str) = data else {
+                    continue;
+                };
+                let update: BinanceStreamData<BinanceDepthUpdate> = match serde_json::from_str(&msg_str) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        match serde_json::from_str::<BinanceCommandReply>(&msg_str) {
+                            Ok(reply) => reply.answer(&mut pending),
+                            Err(_) => warn!("Failed to parse depth message: {:?}, error: {}", msg_str, e),
+                        }
+                        continue;
+                    }
+                };
+
+                let venue_symbol = update.data.symbol;
+                let symbol = self.instruments.canonical(MarketDataSource::Binance, venue_symbol);
+                self.watchdog.on_message(FeedStream::Book, &symbol);
+                let sync = books
+                    .entry(symbol.clone())
+                    .or_insert_with(|| DepthSync::new(symbol.clone()));
+
+                let mut event = sync.on_update(update.data.into()).map_err(backoff::Error::permanent)?;
+                if event == DepthEvent::Pending {
+                    let snapshot = self
+                        .fetch_depth_snapshot(venue_symbol)
+                        .await
+                        .map_err(backoff::Error::transient)?;
+                    event = sync.on_snapshot(snapshot).map_err(backoff::Error::permanent)?;
+                }
+
+                match event {
+                    DepthEvent::Updated => {
+                        let book = sync.book.truncated(depth);
+                        if let Err(e) = sender.send(book.clone()).await {
+                            error!("Failed to send order book: {}", e);
+                            return Ok(());
+                        }
+                        let _ = self.dashboard_tx.send(DashboardEvent::OrderBook(book));
+                    }
+                    DepthEvent::Gap { expected, received } => {
+                        warn!(
+                            symbol = %symbol,
+                            expected = expected,
+                            received = received,
+                            "Binance depth sequence gap, resyncing"
+                        );
+                        counter!("orderbook_resyncs", "source" => "Binance", "symbol" => symbol.clone()).increment(1);
+                    }
+                    DepthEvent::Pending | DepthEvent::Ignored => {}
+                }
+            }
+        };
+
+        retry(backoff, operation).await
+    }
+}
+
+#[async_trait]
+impl QuoteConnector for BinanceConnector {
+    async fn subscribe_quotes(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<Quote>,
+    ) -> Result<()> {
+        self.subscriptions.reset(symbols);
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let (symbols, mut changes) = self.subscriptions.connect();
+            let url = self.streams_url(&symbols, "bookTicker").map_err(backoff::Error::transient)?;
+
+            let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+
+            connection.up();
+            info!("Connected to Binance bookTicker WebSocket");
+
+            let (mut write, mut read) = ws_stream.split();
+            self.watchdog.on_connect(FeedStream::Quotes, &symbols);
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
+            let mut pending = PendingRequests::default();
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::Quotes, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Binance bookTicker stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let command = self.subscription_command(&request, "bookTicker");
+                        write.send(Message::Text(command.to_string())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        match &request.change {
+                            SubscriptionChange::Add(symbols) => self.watchdog.on_connect(FeedStream::Quotes, symbols),
+                            SubscriptionChange::Remove(symbols) => self.watchdog.forget(FeedStream::Quotes, symbols),
+                        }
+                        pending.insert(request, 1);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Binance bookTicker stream closed")));
+                };
+                let data = message.map_err(|e| backoff::Error::transient(e.into()))?;
+                last_frame = Instant::now();
+                let Message::Text(msg_str) = data else {
+                    continue;
+                };
+                let ticker: BinanceStreamData<BinanceBookTicker> = match serde_json::from_str(&msg_str) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        match serde_json::from_str::<BinanceCommandReply>(&msg_str) {
+                            Ok(reply) => reply.answer(&mut pending),
+                            Err(_) => warn!("Failed to parse bookTicker message: {:?}, error: {}", msg_str, e),
+                        }
+                        continue;
+                    }
+                };
+                let symbol = self.instruments.canonical(MarketDataSource::Binance, &ticker.data.symbol);
+                self.watchdog.on_message(FeedStream::Quotes, &symbol);
+                let quote = ticker.data.quote(symbol).map_err(backoff::Error::permanent)?;
+
+                if let Err(e) = sender.send(quote.clone()).await {
+                    error!("Failed to send quote: {}", e);
+                    return Ok(());
+                }
+                let _ = self.dashboard_tx.send(DashboardEvent::Quote(quote));
+            }
+        };
+
+        retry(backoff, operation).await
+    }
 }
 
 #[async_trait]
 impl ExecutionGateway for BinanceConnector {
-    async fn send_order(&self, order: Order) -> Result<String, Box<dyn Error + Send + Sync>> {
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError> {
         let executed_at = Utc::now();
 
         if let Some(tick) = &order.triggering_tick {
@@ -151,7 +1408,7 @@ impl ExecutionGateway for BinanceConnector {
                 "Tick-to-trade latency"
             );
             let _ = self.dashboard_tx.send(DashboardEvent::LatencyUpdate {
-                order_id: order.id.clone(),
+                order_id: order.id,
                 latency_us: latency_us as u64,
             });
         }
@@ -165,71 +1422,321 @@ impl ExecutionGateway for BinanceConnector {
             "Executing order"
         );
 
-        let endpoint = "/api/v3/order";
-        let url = format!("{}{}", BINANCE_API_URL, endpoint);
-
-        let mut params = format!(
-            "symbol={}&side={:?}&type={:?}&quantity={}",
-            order.symbol, order.side, order.order_type, order.amount
+        let mut params = self.order_params(&order)?;
+        params.push(("newOrderRespType", "FULL".to_string()));
+        let response_text = self.signed_request(reqwest::Method::POST, BINANCE_ORDER_PATH, &params).await?;
+        let response: BinanceOrder = serde_json::from_str(&response_text)?;
+        info!(
+            order_id = %order.id,
+            exchange_order_id = response.order_id,
+            status = %response.status,
+            executed_qty = response.executed_qty,
+            "Binance accepted order"
         );
-
-        if let Some(price) = order.price {
-            params.push_str(&format!("&price={}", price));
+        // Fills that matched straight away are in the response; the user data
+        // stream repeats them, and any later ones, keyed by the client order id.
+        for fill in response.fills(order.id)? {
+            self.publish_fill_once(fill).await;
         }
 
-        let timestamp = chrono::Utc::now().timestamp_millis();
-        params.push_str(&format!("&timestamp={}", timestamp));
+        Ok(response.order_id.to_string())
+    }
 
-        let signature = self.sign_request(&params);
-        params.push_str(&format!("&signature={}", signature));
-        
-        info!("Sending order to Binance: {}?{}", url, params);
+    async fn cancel_order(&self, symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let params = [
+            ("symbol", self.instruments.venue_symbol(MarketDataSource::Binance, symbol)),
+            ("origClientOrderId", order_id.to_string()),
+        ];
+        let response_text = self.signed_request(reqwest::Method::DELETE, BINANCE_ORDER_PATH, &params).await?;
+        let order: BinanceOrder = serde_json::from_str(&response_text)?;
+        Ok(self.order_report(&order)?)
+    }
 
-        let res = self.http_client
-            .post(&url)
-            .header("X-MBX-APIKEY", BINANCE_API_KEY)
-            .body(params)
-            .send()
-            .await;
-        
-        info!("Pretending to send order, response: {:?}", res);
-
-        // In a real implementation, you would parse the exchange's response
-        // to create the Fill object. Here we'll just create a mock fill.
-        let price = order.price.unwrap_or(1.0);
-        let fill = Fill {
-            order_id: order.id,
-            symbol: order.symbol.clone(),
-            side: order.side,
-            price, // Mock price
-            quantity: order.amount,
-            source: MarketDataSource::Binance,
-            executed_at,
+    async fn cancel_all_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        // Binance only cancels one symbol at a time.
+        let venue_symbols = match symbol {
+            Some(symbol) => vec![self.instruments.venue_symbol(MarketDataSource::Binance, &symbol)],
+            None => {
+                let response_text = self.signed_request(reqwest::Method::GET, BINANCE_OPEN_ORDERS_PATH, &[]).await?;
+                let mut venue_symbols: Vec<String> = serde_json::from_str::<Vec<BinanceOrder>>(&response_text)?
+                    .into_iter()
+                    .map(|order| order.symbol)
+                    .collect();
+                venue_symbols.sort();
+                venue_symbols.dedup();
+                venue_symbols
+            }
         };
 
-        if let Some(sender) = &self.fill_sender {
-            if let Err(e) = sender.send(fill.clone()).await {
-                error!("Failed to send fill: {}", e);
-            }
+        let mut canceled = Vec::new();
+        for venue_symbol in venue_symbols {
+            let params = [("symbol", venue_symbol)];
+            let response_text = self.signed_request(reqwest::Method::DELETE, BINANCE_OPEN_ORDERS_PATH, &params).await?;
+            canceled.extend(self.order_reports(&response_text)?);
         }
+        Ok(canceled)
+    }
+
+    async fn amend_order(&self, order_id: Uuid, replacement: Order) -> Result<Amendment, ExecutionError> {
+        info!(order_id = %order_id, replacement_id = %replacement.id, price = ?replacement.price, amount = %replacement.amount, "Amending order");
+        let mut params = self.order_params(&replacement)?;
+        params.extend([
+            ("cancelReplaceMode", "STOP_ON_FAILURE".to_string()),
+            ("cancelOrigClientOrderId", order_id.to_string()),
+            ("newOrderRespType", "RESULT".to_string()),
+        ]);
+        let response_text = self.signed_request(reqwest::Method::POST, BINANCE_CANCEL_REPLACE_PATH, &params).await?;
+        let response: BinanceCancelReplace = serde_json::from_str(&response_text)?;
+        Ok(Amendment {
+            canceled: self.order_report(&response.cancel_response)?,
+            replacement: self.order_report(&response.new_order_response)?,
+        })
+    }
 
-        let trade = crate::models::Trade {
-            id: Uuid::new_v4(),
-            order_id: fill.order_id,
-            symbol: fill.symbol.clone(),
-            side: fill.side,
-            amount: fill.quantity,
-            price: fill.price,
-            source: fill.source,
-            executed_at: fill.executed_at,
+    async fn query_order(&self, symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let params = [
+            ("symbol", self.instruments.venue_symbol(MarketDataSource::Binance, symbol)),
+            ("origClientOrderId", order_id.to_string()),
+        ];
+        let response_text = self.signed_request(reqwest::Method::GET, BINANCE_ORDER_PATH, &params).await?;
+        let order: BinanceOrder = serde_json::from_str(&response_text)?;
+        Ok(self.order_report(&order)?)
+    }
+
+    async fn open_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let params: Vec<(&str, String)> = symbol
+            .map(|symbol| ("symbol", self.instruments.venue_symbol(MarketDataSource::Binance, &symbol)))
+            .into_iter()
+            .collect();
+        let response_text = self.signed_request(reqwest::Method::GET, BINANCE_OPEN_ORDERS_PATH, &params).await?;
+        self.order_reports(&response_text)
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PriceLevel;
+    use serde_json::json;
+
+    fn update(first: u64, last: u64, bids: &[(&str, &str)], asks: &[(&str, &str)]) -> DepthDiff {
+        let levels = |levels: &[(&str, &str)]| -> Vec<[Decimal; 2]> {
+            levels.iter().map(|(p, q)| [Decimal(p.parse().unwrap()), Decimal(q.parse().unwrap())]).collect()
         };
-        if let Err(e) = self.db_manager.save_trade(&trade).await {
-            error!("Failed to save trade to DB: {}", e);
+        DepthDiff {
+            first_update_id: first,
+            final_update_id: last,
+            bids: levels(bids),
+            asks: levels(asks),
+        }
+    }
+
+    fn snapshot(last_update_id: u64) -> BinanceDepthSnapshot {
+        BinanceDepthSnapshot {
+            last_update_id,
+            bids: vec![[Decimal(100.0), Decimal(1.0)], [Decimal(99.0), Decimal(2.0)]],
+            asks: vec![[Decimal(101.0), Decimal(1.5)]],
         }
-        let _ = self.dashboard_tx.send(DashboardEvent::Trade(trade));
+    }
+
+    #[test]
+    fn test_depth_sync_replays_buffer_after_snapshot() {
+        let mut sync = DepthSync::new("BTCUSDT".to_string());
+
+        // Already covered by the snapshot, must be dropped.
+        assert_eq!(sync.on_update(update(5, 9, &[("100.0", "7.0")], &[])).unwrap(), DepthEvent::Pending);
+        // Straddles the snapshot id.
+        assert_eq!(sync.on_update(update(10, 12, &[("99.0", "0")], &[("100.5", "3.0")])).unwrap(), DepthEvent::Pending);
+
+        assert_eq!(sync.on_snapshot(snapshot(10)).unwrap(), DepthEvent::Updated);
+        assert_eq!(sync.book.last_update_id, 12);
+        assert_eq!(sync.book.bids, vec![PriceLevel { price: 100.0, quantity: 1.0 }]);
+        assert_eq!(sync.book.best_ask().unwrap().price, 100.5);
+
+        assert_eq!(sync.on_update(update(13, 13, &[("100.2", "4.0")], &[])).unwrap(), DepthEvent::Updated);
+        assert_eq!(sync.book.best_bid().unwrap().price, 100.2);
+        assert_eq!(sync.on_update(update(11, 13, &[], &[])).unwrap(), DepthEvent::Ignored);
+    }
+
+    #[test]
+    fn test_depth_sync_detects_gap() {
+        let mut sync = DepthSync::new("BTCUSDT".to_string());
+        sync.on_update(update(11, 11, &[], &[])).unwrap();
+        assert_eq!(sync.on_snapshot(snapshot(10)).unwrap(), DepthEvent::Updated);
+
+        let event = sync.on_update(update(15, 16, &[], &[])).unwrap();
+        assert_eq!(event, DepthEvent::Gap { expected: 12, received: 15 });
+        assert!(sync.book.bids.is_empty());
+        assert_eq!(sync.on_update(update(17, 17, &[], &[])).unwrap(), DepthEvent::Pending);
+    }
+
+    #[test]
+    fn test_depth_sync_rejects_stale_snapshot() {
+        let mut sync = DepthSync::new("BTCUSDT".to_string());
+        sync.on_update(update(20, 21, &[], &[])).unwrap();
+
+        let event = sync.on_snapshot(snapshot(10)).unwrap();
+        assert_eq!(event, DepthEvent::Gap { expected: 11, received: 20 });
+        assert_eq!(sync.buffer.len(), 1);
+        assert_eq!(sync.on_snapshot(snapshot(20)).unwrap(), DepthEvent::Updated);
+        assert_eq!(sync.book.last_update_id, 21);
+    }
+
+    fn execution_report(execution_type: &str, status: &str, client_order_id: &str, orig_client_order_id: &str) -> String {
+        json!({
+            "e": "executionReport", "E": 1499405658658u64, "s": "ETHBTC",
+            "c": client_order_id, "S": "BUY", "o": "LIMIT", "f": "GTC",
+            "q": "1.00000000", "p": "0.10264410", "P": "0.00000000", "F": "0.00000000",
+            "g": -1, "C": orig_client_order_id, "x": execution_type, "X": status,
+            "r": "NONE", "i": 4293153, "l": "0.40000000", "z": "0.40000000",
+            "L": "0.10264000", "n": "0.00040000", "N": "ETH", "T": 1499405658657u64,
+            "t": 1234, "I": 8641984, "w": false, "m": false, "M": true,
+            "O": 1499405658657u64, "Z": "0.04105600", "Y": "0.04105600", "Q": "0.00000000"
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_execution_report_trade_becomes_fill() {
+        let order_id = Uuid::new_v4();
+        let text = execution_report("TRADE", "PARTIALLY_FILLED", &order_id.to_string(), "");
+        let BinanceUserEvent::ExecutionReport(report) = serde_json::from_str(&text).unwrap() else {
+            panic!("expected an execution report");
+        };
+
+        assert_eq!(report.order_id(), Some(order_id));
+        assert_eq!(report.status(), Some(OrderStatus::PartiallyFilled));
+        let fill = report.fill(order_id).unwrap().unwrap();
+        assert_eq!(fill.side, OrderSide::Buy);
+        assert_eq!(fill.price, 0.10264);
+        assert_eq!(fill.quantity, 0.4);
+        assert_eq!(fill.commission, 0.0004);
+        assert_eq!(fill.commission_asset.as_deref(), Some("ETH"));
+        assert_eq!(fill.trade_id.as_deref(), Some("1234"));
+        assert_eq!(fill.executed_at.timestamp_millis(), 1499405658657);
+    }
+
+    #[test]
+    fn test_execution_report_cancel_uses_original_client_id() {
+        let order_id = Uuid::new_v4();
+        let text = execution_report("CANCELED", "CANCELED", "web_cancel_request", &order_id.to_string());
+        let BinanceUserEvent::ExecutionReport(report) = serde_json::from_str(&text).unwrap() else {
+            panic!("expected an execution report");
+        };
+
+        assert_eq!(report.order_id(), Some(order_id));
+        assert_eq!(report.status(), Some(OrderStatus::Canceled));
+        assert!(report.fill(order_id).unwrap().is_none());
+
+        let expired: BinanceUserEvent = serde_json::from_str(r#"{"e":"listenKeyExpired","E":1576653824250,"listenKey":"abc"}"#).unwrap();
+        assert!(matches!(expired, BinanceUserEvent::ListenKeyExpired));
+        let balance: BinanceUserEvent = serde_json::from_str(r#"{"e":"balanceUpdate","E":1573200697110,"a":"BTC","d":"100.00000000"}"#).unwrap();
+        assert!(matches!(balance, BinanceUserEvent::Other));
+    }
 
-        self.settlement.send_order(&order).await?;
+    #[test]
+    fn test_order_responses_become_reports() {
+        let order_id = Uuid::new_v4();
+        let text = json!({
+            "symbol": "LTCBTC", "orderId": 1, "orderListId": -1, "clientOrderId": order_id.to_string(),
+            "price": "0.10000000", "origQty": "1.00000000", "executedQty": "0.40000000",
+            "cummulativeQuoteQty": "0.03960000", "status": "PARTIALLY_FILLED", "timeInForce": "GTC",
+            "type": "LIMIT", "side": "BUY", "stopPrice": "0.00000000", "icebergQty": "0.00000000",
+            "time": 1499827319559u64, "updateTime": 1499827319559u64, "isWorking": true
+        })
+        .to_string();
+        let report = serde_json::from_str::<BinanceOrder>(&text).unwrap().report().unwrap();
+        assert_eq!((report.order_id, report.status.clone()), (Some(order_id), OrderStatus::PartiallyFilled));
+        assert_eq!((report.amount, report.filled, report.remaining()), (1.0, 0.4, 0.6));
+        assert_eq!(report.price, Some(0.1));
+        assert!((report.avg_fill_price.unwrap() - 0.099).abs() < 1e-12);
+        assert_eq!(report.updated_at.timestamp_millis(), 1499827319559);
 
-        Ok("mock_binance_order_id".to_string())
+        // Cancels name the cancel request in `clientOrderId`.
+        let text = json!({
+            "symbol": "LTCBTC", "origClientOrderId": order_id.to_string(), "orderId": 4, "orderListId": -1,
+            "clientOrderId": "cancelMyOrder1", "transactTime": 1684804350068u64, "price": "2.00000000",
+            "origQty": "1.00000000", "executedQty": "0.00000000", "cummulativeQuoteQty": "0.00000000",
+            "status": "CANCELED", "timeInForce": "GTC", "type": "LIMIT", "side": "SELL"
+        })
+        .to_string();
+        let report = serde_json::from_str::<BinanceOrder>(&text).unwrap().report().unwrap();
+        assert_eq!((report.order_id, report.side, report.status.clone()), (Some(order_id), OrderSide::Sell, OrderStatus::Canceled));
+        assert_eq!(report.avg_fill_price, None);
+
+        assert_eq!(order_status("REJECTED", 0.0), Some(OrderStatus::Rejected));
+        assert_eq!(order_status("EXPIRED_IN_MATCH", 0.0), Some(OrderStatus::Expired));
+        assert_eq!(order_status("PENDING_CANCEL", 0.0), Some(OrderStatus::New));
+        assert_eq!(order_status("PENDING_CANCEL", 0.4), Some(OrderStatus::PartiallyFilled));
+    }
+
+    #[test]
+    fn test_exchange_info_filters_become_instrument_rules() {
+        let text = r#"{"timezone": "UTC", "symbols": [
+            {"symbol": "ETHBTC", "status": "TRADING", "baseAsset": "ETH", "quoteAsset": "BTC",
+             "filters": [
+                {"filterType": "PRICE_FILTER", "minPrice": "0.00001000", "maxPrice": "922327.00000000", "tickSize": "0.00001000"},
+                {"filterType": "LOT_SIZE", "minQty": "0.00010000", "maxQty": "100000.00000000", "stepSize": "0.00010000"},
+                {"filterType": "ICEBERG_PARTS", "limit": 10},
+                {"filterType": "NOTIONAL", "minNotional": "0.00010000", "applyMinToMarket": true, "maxNotional": "9000000.00000000"}
+             ]},
+            {"symbol": "LUNABTC", "status": "BREAK", "baseAsset": "LUNA", "quoteAsset": "BTC", "filters": []}
+        ]}"#;
+
+        let instruments = parse_exchange_info(text, MarketDataSource::Binance).unwrap();
+        assert_eq!(instruments.len(), 1);
+        let ethbtc = &instruments[0];
+        assert_eq!(ethbtc.symbol, "ETH/BTC");
+        assert_eq!(ethbtc.venue_symbol, "ETHBTC");
+        assert_eq!(ethbtc.tick_size, 0.00001);
+        assert_eq!(ethbtc.lot_size, 0.0001);
+        assert_eq!(ethbtc.min_notional, 0.0001);
+    }
+
+    #[test]
+    fn test_full_order_response_carries_its_fills() {
+        let order_id = Uuid::new_v4();
+        let text = json!({
+            "symbol": "BTCUSDT", "orderId": 28, "clientOrderId": order_id.to_string(),
+            "transactTime": 1507725176595i64, "price": "0.00000000", "origQty": "0.30000000",
+            "executedQty": "0.30000000", "cummulativeQuoteQty": "15000.00000000", "status": "FILLED",
+            "type": "MARKET", "side": "SELL",
+            "fills": [
+                {"price": "50000.00000000", "qty": "0.20000000", "commission": "10.00000000", "commissionAsset": "USDT", "tradeId": 56},
+                {"price": "49999.00000000", "qty": "0.10000000", "commission": "4.99990000", "commissionAsset": "USDT", "tradeId": 57}
+            ]
+        })
+        .to_string();
+
+        let response: BinanceOrder = serde_json::from_str(&text).unwrap();
+        assert_eq!(response.order_id, 28);
+        let fills = response.fills(order_id).unwrap();
+        assert_eq!(fills.len(), 2);
+        assert_eq!(fills[0].order_id, order_id);
+        assert_eq!((fills[0].side, fills[0].price, fills[0].quantity, fills[0].commission), (OrderSide::Sell, 50_000.0, 0.2, 10.0));
+        assert_eq!(fills[1].trade_id.as_deref(), Some("57"));
+        // ACK and RESULT responses have no fills.
+        let text = json!({"symbol": "BTCUSDT", "orderId": 29, "clientOrderId": "x", "transactTime": 1, "price": "1", "origQty": "1",
+            "executedQty": "0", "cummulativeQuoteQty": "0", "status": "NEW", "type": "LIMIT", "side": "BUY"}).to_string();
+        assert!(serde_json::from_str::<BinanceOrder>(&text).unwrap().fills(order_id).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_api_error_codes_are_classified() {
+        let kind = |status: u16, body: &str| BinanceApiError::from_response(status, body).kind();
+        assert_eq!(kind(400, r#"{"code":-1013,"msg":"Filter failure: LOT_SIZE"}"#), BinanceErrorKind::FilterFailure);
+        assert_eq!(kind(400, r#"{"code":-2010,"msg":"Account has insufficient balance for requested action."}"#), BinanceErrorKind::InsufficientBalance);
+        assert_eq!(kind(400, r#"{"code":-2010,"msg":"Order would immediately match and take."}"#), BinanceErrorKind::OrderRejected);
+        assert_eq!(kind(400, r#"{"code":-2011,"msg":"Unknown order sent."}"#), BinanceErrorKind::UnknownOrder);
+        assert_eq!(kind(400, r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#), BinanceErrorKind::Timestamp);
+        assert_eq!(kind(401, r#"{"code":-2015,"msg":"Invalid API-key, IP, or permissions for action."}"#), BinanceErrorKind::Unauthorized);
+        assert_eq!(kind(429, r#"{"code":-1003,"msg":"Too many requests."}"#), BinanceErrorKind::RateLimited);
+        assert_eq!(kind(400, r#"{"code":-1102,"msg":"Mandatory parameter 'price' was not sent."}"#), BinanceErrorKind::BadRequest);
+
+        let error = BinanceApiError::from_response(403, "<html>Forbidden</html>");
+        assert_eq!((error.code, error.msg.as_str(), error.kind()), (0, "<html>Forbidden</html>", BinanceErrorKind::Unauthorized));
+        assert_eq!(
+            BinanceApiError::from_response(400, r#"{"code":-1013,"msg":"Filter failure: LOT_SIZE"}"#).to_string(),
+            "Binance order outside symbol filters (-1013): Filter failure: LOT_SIZE"
+        );
     }
-} 
\ No newline at end of file
+}
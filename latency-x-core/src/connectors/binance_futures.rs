@@ -0,0 +1,548 @@
+//! Binance USD-M perpetual futures.
+//!
+//! Streams aggregate trades and the `markPrice@1s` mark price and funding
+//! feed, and places signed orders on `/fapi/v1/order`. Futures share the
+//! spot wire formats wherever they can, so the spot connector's stream and
+//! exchange info types are reused.
+
+use crate::config::{ExchangeConfig, FeedConfig};
+use crate::connectors::binance::{parse_exchange_info, BinanceApiError, BinanceCommandReply, BinanceEndpoints, BinanceStreamData};
+use crate::connectors::decimal;
+use crate::connectors::subscriptions::{PendingRequests, SubscriptionChange, SubscriptionRequest, Subscriptions};
+use crate::connectors::watchdog::FeedWatchdog;
+use crate::connectors::{record_feed_latency, Connector, FeedBackoff, MarkPriceConnector};
+use crate::dashboard::events::DashboardEvent;
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::instruments::{Instrument, InstrumentRegistry};
+use crate::models::{FeedStream, MarkPrice, MarketDataSource, Order, OrderSide, OrderType, PositionSide, Tick};
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use backoff::future::retry;
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{broadcast, mpsc};
+use tokio_tungstenite::connect_async;
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{error, info, warn};
+use url::Url;
+
+const BINANCE_FUTURES_API_URL: &str = "https://fapi.binance.com";
+const BINANCE_FUTURES_WS_URL: &str = "wss://fstream.binance.com";
+const BINANCE_FUTURES_ORDER_PATH: &str = "/fapi/v1/order";
+
+impl BinanceEndpoints {
+    /// The production USD-M futures hosts.
+    pub fn futures() -> Self {
+        Self {
+            api_url: BINANCE_FUTURES_API_URL.to_string(),
+            ws_url: BINANCE_FUTURES_WS_URL.to_string(),
+        }
+    }
+}
+
+pub struct BinanceFuturesConnector {
+    http_client: Client,
+    endpoints: BinanceEndpoints,
+    api_key: String,
+    api_secret: String,
+    dashboard_tx: broadcast::Sender<DashboardEvent>,
+    watchdog: FeedWatchdog,
+    instruments: Arc<InstrumentRegistry>,
+    subscriptions: Subscriptions,
+}
+
+impl BinanceFuturesConnector {
+    pub fn new(config: &ExchangeConfig, dashboard_tx: broadcast::Sender<DashboardEvent>) -> Self {
+        let watchdog = FeedWatchdog::new(MarketDataSource::BinanceFutures, &FeedConfig::default(), dashboard_tx.clone(), None);
+        Self {
+            http_client: Client::new(),
+            endpoints: BinanceEndpoints::futures(),
+            api_key: config.api_key.clone(),
+            api_secret: config.api_secret.clone(),
+            dashboard_tx,
+            watchdog,
+            instruments: Arc::new(InstrumentRegistry::new()),
+            subscriptions: Subscriptions::new(),
+        }
+    }
+
+    pub fn with_watchdog(mut self, watchdog: FeedWatchdog) -> Self {
+        self.watchdog = watchdog;
+        self
+    }
+
+    pub fn with_instruments(mut self, instruments: Arc<InstrumentRegistry>) -> Self {
+        self.instruments = instruments;
+        self
+    }
+
+    pub fn with_endpoints(mut self, endpoints: BinanceEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
+    /// Stream name prefix for a canonical symbol, e.g. `btcusdt`.
+    fn stream_symbol(&self, symbol: &str) -> String {
+        self.instruments.ws_symbol(MarketDataSource::BinanceFutures, symbol).to_lowercase()
+    }
+
+    /// A combined stream connection for one kind of stream, e.g. `aggTrade`.
+    fn streams_url(&self, symbols: &[String], stream: &str) -> Result<Url> {
+        let streams = symbols
+            .iter()
+            .map(|s| format!("{}@{}", self.stream_symbol(s), stream))
+            .collect::<Vec<_>>()
+            .join("/");
+        Ok(Url::parse(&format!("{}/stream?streams={}", self.endpoints.ws_url, streams))?)
+    }
+
+    /// A `SUBSCRIBE` or `UNSUBSCRIBE` for one kind of stream on an open
+    /// connection, answered under the request's id.
+    fn subscription_command(&self, request: &SubscriptionRequest, stream: &str) -> serde_json::Value {
+        let (method, symbols) = match &request.change {
+            SubscriptionChange::Add(symbols) => ("SUBSCRIBE", symbols),
+            SubscriptionChange::Remove(symbols) => ("UNSUBSCRIBE", symbols),
+        };
+        let streams: Vec<String> = symbols.iter().map(|s| format!("{}@{}", self.stream_symbol(s), stream)).collect();
+        json!({
+            "method": method,
+            "params": streams,
+            "id": request.id,
+        })
+    }
+
+    /// The unsigned form of an order. In hedge mode the position side already
+    /// says whether an order opens or closes, and Binance rejects `reduceOnly`
+    /// next to it, so a reduce-only order there must be the closing side.
+    fn order_params(&self, order: &Order, timestamp: i64) -> Result<String> {
+        let mut params = format!(
+            "symbol={}&side={}&type={}&quantity={}&newClientOrderId={}",
+            self.instruments.venue_symbol(MarketDataSource::BinanceFutures, &order.symbol),
+            order.side.to_string().to_uppercase(),
+            order.order_type.to_string().to_uppercase(),
+            decimal::format_decimal(order.amount),
+            order.id
+        );
+        if order.order_type == OrderType::Limit {
+            let price = order.price.ok_or_else(|| anyhow!("Limit order {} has no price", order.id))?;
+            params.push_str(&format!("&price={}&timeInForce=GTC", decimal::format_decimal(price)));
+        }
+
+        match (order.position_side, order.reduce_only) {
+            (None, false) => {}
+            (None | Some(PositionSide::Both), true) => params.push_str("&reduceOnly=true"),
+            (Some(PositionSide::Long), true) if order.side == OrderSide::Buy => {
+                bail!("Reduce-only order {} would add to the long position", order.id)
+            }
+            (Some(PositionSide::Short), true) if order.side == OrderSide::Sell => {
+                bail!("Reduce-only order {} would add to the short position", order.id)
+            }
+            (Some(_), _) => {}
+        }
+        if let Some(position_side) = order.position_side {
+            params.push_str(&format!("&positionSide={}", position_side.to_string().to_uppercase()));
+        }
+
+        params.push_str(&format!("&timestamp={}", timestamp));
+        Ok(params)
+    }
+
+    fn sign_request(&self, params: &str) -> String {
+        let signature = hmac_sha256::HMAC::mac(params.as_bytes(), self.api_secret.as_bytes());
+        hex::encode(signature)
+    }
+}
+
+/// Trading rules for every perpetual currently trading on Binance USD-M futures.
+pub(crate) async fn fetch_instruments(http_client: &Client) -> Result<Vec<Instrument>> {
+    let response_text = http_client
+        .get(format!("{}/fapi/v1/exchangeInfo", BINANCE_FUTURES_API_URL))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_exchange_info(&response_text, MarketDataSource::BinanceFutures)
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceAggTrade<'a> {
+    #[serde(rename = "s")]
+    symbol: &'a str,
+    #[serde(rename = "a")]
+    agg_trade_id: u64,
+    #[serde(rename = "T")]
+    timestamp: i64,
+    #[serde(rename = "p", deserialize_with = "decimal::deserialize")]
+    price: f64,
+    #[serde(rename = "q", deserialize_with = "decimal::deserialize")]
+    quantity: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct BinanceMarkPriceUpdate {
+    #[serde(rename = "E")]
+    event_time: i64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "p")]
+    mark_price: String,
+    #[serde(rename = "i")]
+    index_price: String,
+    #[serde(rename = "r")]
+    funding_rate: String,
+    #[serde(rename = "T")]
+    next_funding_time: i64,
+}
+
+/// The `POST /fapi/v1/order` acknowledgement.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceFuturesOrder {
+    order_id: u64,
+    status: String,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    executed_qty: f64,
+}
+
+impl BinanceMarkPriceUpdate {
+    fn mark_price(&self, symbol: String) -> Result<MarkPrice> {
+        Ok(MarkPrice {
+            source: MarketDataSource::BinanceFutures,
+            symbol,
+            mark_price: self.mark_price.parse()?,
+            index_price: self.index_price.parse()?,
+            funding_rate: self.funding_rate.parse()?,
+            next_funding_time: DateTime::from_timestamp_millis(self.next_funding_time)
+                .ok_or_else(|| anyhow!("Invalid funding time {}", self.next_funding_time))?,
+            received_at: Utc::now(),
+            exchange_ts: DateTime::from_timestamp_millis(self.event_time),
+        })
+    }
+}
+
+#[async_trait]
+impl Connector for BinanceFuturesConnector {
+    async fn subscribe(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<Tick>,
+    ) -> Result<()> {
+        self.subscriptions.reset(symbols);
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let (symbols, mut changes) = self.subscriptions.connect();
+            let url = self.streams_url(&symbols, "aggTrade").map_err(backoff::Error::transient)?;
+
+            let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+
+            connection.up();
+            info!("Connected to Binance Futures WebSocket");
+
+            let (mut write, mut read) = ws_stream.split();
+            self.watchdog.on_connect(FeedStream::Trades, &symbols);
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
+            let mut pending = PendingRequests::default();
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::Trades, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Binance Futures trade stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        // A missed change is recovered by reconnecting with the current set.
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let command = self.subscription_command(&request, "aggTrade");
+                        write.send(Message::Text(command.to_string())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        match &request.change {
+                            SubscriptionChange::Add(symbols) => self.watchdog.on_connect(FeedStream::Trades, symbols),
+                            SubscriptionChange::Remove(symbols) => self.watchdog.forget(FeedStream::Trades, symbols),
+                        }
+                        pending.insert(request, 1);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Binance Futures trade stream closed")));
+                };
+                let data = message.map_err(|e| backoff::Error::transient(e.into()))?;
+                last_frame = Instant::now();
+                let Message::Text(msg_str) = data else {
+                    continue;
+                };
+                let trade: BinanceStreamData<BinanceAggTrade> = match serde_json::from_str(&msg_str) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        match serde_json::from_str::<BinanceCommandReply>(&msg_str) {
+                            Ok(reply) => reply.answer(&mut pending),
+                            Err(_) => warn!("Failed to parse message: {:?}, error: {}", msg_str, e),
+                        }
+                        continue;
+                    }
+                };
+                let symbol = self.instruments.canonical(MarketDataSource::BinanceFutures, trade.data.symbol);
+                self.watchdog.on_message(FeedStream::Trades, &symbol);
+
+                let tick = Tick {
+                    source: MarketDataSource::BinanceFutures,
+                    symbol,
+                    price: trade.data.price,
+                    volume: trade.data.quantity,
+                    received_at: Utc::now(),
+                    exchange_ts: DateTime::from_timestamp_millis(trade.data.timestamp),
+                    trade_id: Some(trade.data.agg_trade_id),
+                };
+
+                if let Err(e) = sender.send(tick.clone()).await {
+                    error!("Failed to send tick: {}", e);
+                    return Ok(());
+                }
+                record_feed_latency(&tick, &self.dashboard_tx);
+                let _ = self.dashboard_tx.send(DashboardEvent::Tick(tick));
+            }
+        };
+
+        retry(backoff, operation).await
+    }
+
+    fn get_source(&self) -> MarketDataSource {
+        MarketDataSource::BinanceFutures
+    }
+
+    async fn add_symbols(&self, symbols: &[String]) -> Result<()> {
+        self.subscriptions.add(symbols).await?;
+        Ok(())
+    }
+
+    async fn remove_symbols(&self, symbols: &[String]) -> Result<()> {
+        self.subscriptions.remove(symbols).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl MarkPriceConnector for BinanceFuturesConnector {
+    async fn subscribe_mark_prices(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<MarkPrice>,
+    ) -> Result<()> {
+        self.subscriptions.reset(symbols);
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let (symbols, mut changes) = self.subscriptions.connect();
+            let url = self.streams_url(&symbols, "markPrice@1s").map_err(backoff::Error::transient)?;
+
+            let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+
+            connection.up();
+            info!("Connected to Binance Futures mark price WebSocket");
+
+            let (mut write, mut read) = ws_stream.split();
+            self.watchdog.on_connect(FeedStream::MarkPrice, &symbols);
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
+            let mut pending = PendingRequests::default();
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::MarkPrice, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Binance Futures mark price stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let command = self.subscription_command(&request, "markPrice@1s");
+                        write.send(Message::Text(command.to_string())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        match &request.change {
+                            SubscriptionChange::Add(symbols) => self.watchdog.on_connect(FeedStream::MarkPrice, symbols),
+                            SubscriptionChange::Remove(symbols) => self.watchdog.forget(FeedStream::MarkPrice, symbols),
+                        }
+                        pending.insert(request, 1);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Binance Futures mark price stream closed")));
+                };
+                let data = message.map_err(|e| backoff::Error::transient(e.into()))?;
+                last_frame = Instant::now();
+                let Message::Text(msg_str) = data else {
+                    continue;
+                };
+                let update: BinanceStreamData<BinanceMarkPriceUpdate> = match serde_json::from_str(&msg_str) {
+                    Ok(data) => data,
+                    Err(e) => {
+                        match serde_json::from_str::<BinanceCommandReply>(&msg_str) {
+                            Ok(reply) => reply.answer(&mut pending),
+                            Err(_) => warn!("Failed to parse mark price message: {:?}, error: {}", msg_str, e),
+                        }
+                        continue;
+                    }
+                };
+                let symbol = self.instruments.canonical(MarketDataSource::BinanceFutures, &update.data.symbol);
+                self.watchdog.on_message(FeedStream::MarkPrice, &symbol);
+                let mark_price = update.data.mark_price(symbol).map_err(backoff::Error::permanent)?;
+
+                if let Err(e) = sender.send(mark_price.clone()).await {
+                    error!("Failed to send mark price: {}", e);
+                    return Ok(());
+                }
+                let _ = self.dashboard_tx.send(DashboardEvent::MarkPrice(mark_price));
+            }
+        };
+
+        retry(backoff, operation).await
+    }
+}
+
+#[async_trait]
+impl ExecutionGateway for BinanceFuturesConnector {
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError> {
+        if let Some(tick) = &order.triggering_tick {
+            let latency_us = Utc::now().signed_duration_since(tick.received_at).num_microseconds().unwrap_or(-1);
+            info!(latency_us = latency_us, order_id = %order.id, "Tick-to-trade latency");
+            let _ = self.dashboard_tx.send(DashboardEvent::LatencyUpdate {
+                order_id: order.id,
+                latency_us: latency_us as u64,
+            });
+        }
+
+        info!(
+            order_id = %order.id,
+            symbol = %order.symbol,
+            side = ?order.side,
+            amount = %order.amount,
+            price = ?order.price,
+            reduce_only = order.reduce_only,
+            position_side = ?order.position_side,
+            "Executing futures order"
+        );
+
+        let mut params = self.order_params(&order, Utc::now().timestamp_millis())?;
+        let signature = self.sign_request(&params);
+        params.push_str(&format!("&signature={}", signature));
+
+        let res = self
+            .http_client
+            .post(format!("{}{}", self.endpoints.api_url, BINANCE_FUTURES_ORDER_PATH))
+            .header("X-MBX-APIKEY", &self.api_key)
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .body(params)
+            .send()
+            .await?;
+
+        let status = res.status();
+        let response_text = res.text().await?;
+        if !status.is_success() {
+            let error = BinanceApiError::from_response(status.as_u16(), &response_text);
+            warn!(order_id = %order.id, code = error.code, "Binance Futures rejected order: {}", error);
+            return Err(error.into());
+        }
+        let response: BinanceFuturesOrder = serde_json::from_str(&response_text)?;
+        info!(
+            order_id = %order.id,
+            exchange_order_id = response.order_id,
+            status = %response.status,
+            executed_qty = response.executed_qty,
+            "Binance Futures accepted order"
+        );
+
+        Ok(response.order_id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn connector() -> BinanceFuturesConnector {
+        let config = ExchangeConfig {
+            api_key: "key".to_string(),
+            api_secret: "secret".to_string(),
+            book_depth: 20,
+        };
+        BinanceFuturesConnector::new(&config, broadcast::channel(16).0)
+    }
+
+    fn order(side: OrderSide, reduce_only: bool, position_side: Option<PositionSide>) -> Order {
+        Order {
+            reduce_only,
+            position_side,
+            ..Order::market("BTCUSDT".to_string(), side, 0.01, MarketDataSource::Strategy, None)
+        }
+    }
+
+    #[test]
+    fn test_mark_price_update_parses() {
+        let text = r#"{"stream":"btcusdt@markPrice@1s","data":{"e":"markPriceUpdate","E":1562305380000,"s":"BTCUSDT",
+            "p":"11794.15000000","i":"11784.62659091","P":"11784.25641265","r":"0.00038167","T":1562306400000}}"#;
+        let update: BinanceStreamData<BinanceMarkPriceUpdate> = serde_json::from_str(text).unwrap();
+        let mark = update.data.mark_price("BTC/USDT".to_string()).unwrap();
+        assert_eq!(mark.source, MarketDataSource::BinanceFutures);
+        assert_eq!(mark.funding_rate, 0.00038167);
+        assert!((mark.basis() - 9.52340909).abs() < 1e-9);
+        assert_eq!(mark.next_funding_time.timestamp_millis(), 1562306400000);
+        assert_eq!(mark.exchange_ts.unwrap().timestamp_millis(), 1562305380000);
+    }
+
+    #[test]
+    fn test_reduce_only_and_position_side_params() {
+        let connector = connector();
+        let params = |order: &Order| connector.order_params(order, 1).unwrap();
+
+        let one_way = params(&order(OrderSide::Sell, true, None));
+        assert!(one_way.contains("&reduceOnly=true") && !one_way.contains("positionSide"));
+
+        // Hedge mode: selling the long leg closes it; reduceOnly is not sent.
+        let close_long = params(&order(OrderSide::Sell, true, Some(PositionSide::Long)));
+        assert!(close_long.contains("&positionSide=LONG") && !close_long.contains("reduceOnly"));
+        assert!(connector.order_params(&order(OrderSide::Buy, true, Some(PositionSide::Long)), 1).is_err());
+        assert!(connector.order_params(&order(OrderSide::Sell, true, Some(PositionSide::Short)), 1).is_err());
+
+        let limit = Order { order_type: OrderType::Limit, price: Some(50_000.0), ..order(OrderSide::Buy, false, None) };
+        assert!(params(&limit).contains("&price=50000&timeInForce=GTC"));
+        // Sizes and prices go out as plain decimals, never f64 noise or exponents.
+        let small = Order { amount: 0.1 + 0.2, price: Some(1e-7), ..limit };
+        let small = params(&small);
+        assert!(small.contains("&quantity=0.3&") && small.contains("&price=0.0000001&"), "{}", small);
+    }
+
+    #[test]
+    fn test_exchange_info_keeps_perpetuals() {
+        let text = r#"{"symbols": [
+            {"symbol": "BTCUSDT", "status": "TRADING", "contractType": "PERPETUAL", "baseAsset": "BTC", "quoteAsset": "USDT",
+             "filters": [
+                {"filterType": "PRICE_FILTER", "minPrice": "556.80", "maxPrice": "4529764", "tickSize": "0.10"},
+                {"filterType": "LOT_SIZE", "minQty": "0.001", "maxQty": "1000", "stepSize": "0.001"},
+                {"filterType": "MIN_NOTIONAL", "notional": "100"}
+             ]},
+            {"symbol": "BTCUSDT_250926", "status": "TRADING", "contractType": "CURRENT_QUARTER", "baseAsset": "BTC", "quoteAsset": "USDT", "filters": []}
+        ]}"#;
+
+        let instruments = parse_exchange_info(text, MarketDataSource::BinanceFutures).unwrap();
+        assert_eq!(instruments.len(), 1);
+        let btcusdt = &instruments[0];
+        assert_eq!(btcusdt.symbol, "BTC/USDT");
+        assert_eq!(btcusdt.venue, MarketDataSource::BinanceFutures);
+        assert_eq!(btcusdt.tick_size, 0.1);
+        assert_eq!(btcusdt.min_notional, 100.0);
+    }
+}
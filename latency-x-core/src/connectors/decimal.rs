@@ -0,0 +1,148 @@
+//! Decimal parsing for exchange messages, and formatting for requests.
+//!
+//! Venues send prices and quantities as JSON strings such as `"27123.45000000"`.
+//! Deserializing those into a `String` and then calling `str::parse` costs an
+//! allocation per field; the helpers here read the borrowed text straight into
+//! an `f64` instead.
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::Deserialize;
+use std::fmt;
+
+/// Integers up to 2^53 convert to `f64` exactly.
+const MAX_EXACT_MANTISSA: u64 = 1 << 53;
+
+/// Powers of ten that are exact in an `f64`.
+const POW10: [f64; 23] = [
+    1e0, 1e1, 1e2, 1e3, 1e4, 1e5, 1e6, 1e7, 1e8, 1e9, 1e10, 1e11, 1e12, 1e13, 1e14, 1e15, 1e16,
+    1e17, 1e18, 1e19, 1e20, 1e21, 1e22,
+];
+
+/// Parses a plain decimal like `-0.00123400`, rounding exactly as `str::parse::<f64>` does.
+///
+/// When the digits fit in 53 bits and there are at most 22 of them after the
+/// point, both the mantissa and the power of ten are exact and a single
+/// division gives the correctly rounded result. Anything else (exponents, very
+/// long fractions, `inf`) falls back to the standard library.
+pub fn parse_decimal(s: &str) -> Option<f64> {
+    let bytes = s.as_bytes();
+    let (negative, digits) = match bytes.first() {
+        Some(b'-') => (true, &bytes[1..]),
+        Some(b'+') => (false, &bytes[1..]),
+        _ => (false, bytes),
+    };
+
+    let mut mantissa: u64 = 0;
+    let mut seen_digit = false;
+    let mut fraction_digits: Option<usize> = None;
+    for &b in digits {
+        match b {
+            b'0'..=b'9' => {
+                mantissa = match mantissa.checked_mul(10).and_then(|m| m.checked_add(u64::from(b - b'0'))) {
+                    Some(m) => m,
+                    None => return s.parse().ok(),
+                };
+                seen_digit = true;
+                if let Some(count) = fraction_digits.as_mut() {
+                    *count += 1;
+                }
+            }
+            b'.' if fraction_digits.is_none() => fraction_digits = Some(0),
+            _ => return s.parse().ok(),
+        }
+    }
+    if !seen_digit {
+        return s.parse().ok();
+    }
+
+    let scale = fraction_digits.unwrap_or(0);
+    if mantissa > MAX_EXACT_MANTISSA || scale >= POW10.len() {
+        return s.parse().ok();
+    }
+    let value = mantissa as f64 / POW10[scale];
+    Some(if negative { -value } else { value })
+}
+
+struct DecimalVisitor;
+
+impl<'de> Visitor<'de> for DecimalVisitor {
+    type Value = f64;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a decimal number or a string holding one")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<f64, E> {
+        parse_decimal(v).ok_or_else(|| E::invalid_value(de::Unexpected::Str(v), &self))
+    }
+
+    fn visit_f64<E: de::Error>(self, v: f64) -> Result<f64, E> {
+        Ok(v)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<f64, E> {
+        Ok(v as f64)
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<f64, E> {
+        Ok(v as f64)
+    }
+}
+
+/// `deserialize_with` helper for `f64` fields sent as decimal strings.
+pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    deserializer.deserialize_any(DecimalVisitor)
+}
+
+/// A decimal read without allocating, for use inside containers such as the
+/// `[price, quantity]` pairs of a depth update.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Decimal(pub f64);
+
+impl<'de> Deserialize<'de> for Decimal {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserialize(deserializer).map(Decimal)
+    }
+}
+
+/// A price or quantity as order entry takes it: plain notation, at most
+/// eight decimals, no trailing zeros.
+pub fn format_decimal(value: f64) -> String {
+    let formatted = format!("{:.8}", value);
+    formatted.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_decimal_matches_std() {
+        let cases = [
+            "0", "0.0", "1", "-1", "27123.45000000", "0.00000001", "0.1", "0.3", "-0.00123400",
+            "123456789.12345678", "9007199254740993", "0.1234567890123456789012345", "1e-7",
+            "1.5E3", ".5", "5.", "+2.25", "18446744073709551616",
+        ];
+        for case in cases {
+            assert_eq!(parse_decimal(case), case.parse::<f64>().ok(), "{}", case);
+        }
+        for case in ["", "-", ".", "1.2.3", "abc", "1,5"] {
+            assert_eq!(parse_decimal(case), None, "{}", case);
+        }
+    }
+
+    #[test]
+    fn test_deserialize_accepts_strings_and_numbers() {
+        let levels: Vec<[Decimal; 2]> = serde_json::from_str(r#"[["100.5","2"],[99.25,3]]"#).unwrap();
+        assert_eq!(levels, vec![[Decimal(100.5), Decimal(2.0)], [Decimal(99.25), Decimal(3.0)]]);
+        assert!(serde_json::from_str::<Decimal>(r#""1.2.3""#).is_err());
+    }
+
+    #[test]
+    fn test_formatted_decimals_are_plain_and_trimmed() {
+        assert_eq!(format_decimal(0.1 + 0.2), "0.3");
+        assert_eq!(format_decimal(50_000.0), "50000");
+        assert_eq!(format_decimal(0.00000001), "0.00000001");
+        assert_eq!(format_decimal(1e-9), "0");
+    }
+}
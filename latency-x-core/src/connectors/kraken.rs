@@ -1,62 +1,157 @@
-use crate::connectors::Connector;
-use crate::execution::ExecutionGateway;
-use crate::models::{Fill, MarketDataSource, Order, Tick};
+use crate::connectors::decimal::{self, Decimal};
+use crate::connectors::sequence::{record_backfill, SequenceCheck, TradeSequence};
+use crate::connectors::subscriptions::{PendingRequests, SubscriptionChange, Subscriptions};
+use crate::connectors::watchdog::FeedWatchdog;
+use crate::connectors::{publish_fill, record_feed_latency, Connector, FeedBackoff, PublishedTrades, OrderBookConnector, QuoteConnector};
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::instruments::{canonical_asset, canonical_symbol, Instrument, InstrumentRegistry};
+use crate::models::{Amendment, FeedStream, Fill, MarketDataSource, Order, OrderBook, OrderReport, OrderSide, OrderStatus, OrderType, Quote, Tick};
 use crate::settlement::Settlement;
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
 use base64::{engine::general_purpose, Engine as _};
 use futures_util::{SinkExt, StreamExt};
 use hmac::{Hmac, Mac};
 use reqwest;
+use serde::de::{self, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fmt;
 use serde_json::json;
 use sha2::{Digest, Sha256, Sha512};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::error::Error;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 use tokio::sync::mpsc;
 use tokio::sync::mpsc::Sender;
 use tokio_tungstenite::connect_async;
 use url::Url;
-use crate::config::ExchangeConfig;
-use backoff::ExponentialBackoff;
+use crate::config::{ExchangeConfig, FeedConfig};
 use backoff::future::retry;
-use chrono::Utc;
-use tracing::{error, info};
+use chrono::{DateTime, Utc};
+use tracing::{debug, error, info};
 use crate::dashboard::events::DashboardEvent;
 use tokio::sync::broadcast;
 use tracing::warn;
-use uuid::Uuid;
 use crate::persistence::db::DatabaseManager;
+use metrics::counter;
+use uuid::Uuid;
+use tokio_tungstenite::tungstenite::Message;
 
 
 const KRAKEN_WSS_URL: &str = "wss://ws.kraken.com/";
+const KRAKEN_WSS_V2_URL: &str = "wss://ws.kraken.com/v2";
+const KRAKEN_WSS_AUTH_URL: &str = "wss://ws-auth.kraken.com/";
+const KRAKEN_API_URL: &str = "https://api.kraken.com";
+/// `Trades` returns up to 1000 trades per call; longer gaps are left partly unfilled.
+const KRAKEN_BACKFILL_PAGES: usize = 5;
+/// Book depths accepted by the v2 `book` channel.
+const KRAKEN_BOOK_DEPTHS: [usize; 5] = [10, 25, 100, 500, 1000];
+/// Number of levels per side covered by the book checksum.
+const KRAKEN_CHECKSUM_DEPTH: usize = 10;
+
+/// Where the connector reaches Kraken. Tests point this at the local simulator.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KrakenEndpoints {
+    pub api_url: String,
+    pub ws_url: String,
+    pub ws_v2_url: String,
+    pub ws_auth_url: String,
+}
+
+impl Default for KrakenEndpoints {
+    fn default() -> Self {
+        Self {
+            api_url: KRAKEN_API_URL.to_string(),
+            ws_url: KRAKEN_WSS_URL.to_string(),
+            ws_v2_url: KRAKEN_WSS_V2_URL.to_string(),
+            ws_auth_url: KRAKEN_WSS_AUTH_URL.to_string(),
+        }
+    }
+}
 
 pub struct KrakenConnector {
+    endpoints: KrakenEndpoints,
     api_key: String,
     api_secret: String,
-    settlement: Arc<dyn Settlement>,
     fill_sender: Option<Sender<Fill>>,
     dashboard_tx: broadcast::Sender<DashboardEvent>,
     db_manager: Arc<DatabaseManager>,
+    watchdog: FeedWatchdog,
+    order_ids: Mutex<KrakenOrderIds>,
+    seen_trades: Mutex<PublishedTrades>,
+    instruments: Arc<InstrumentRegistry>,
+    subscriptions: Subscriptions,
 }
 
 impl KrakenConnector {
+    /// Orders settle on the exchange, so `_settlement` is not used here; it
+    /// is kept so every connector is built the same way.
     pub fn new(
         kraken_config: &ExchangeConfig,
-        settlement: Arc<dyn Settlement>,
+        _settlement: Arc<dyn Settlement>,
         fill_sender: Option<Sender<Fill>>,
         dashboard_tx: broadcast::Sender<DashboardEvent>,
         db_manager: Arc<DatabaseManager>,
     ) -> Self {
+        let watchdog = FeedWatchdog::new(MarketDataSource::Kraken, &FeedConfig::default(), dashboard_tx.clone(), None);
         Self {
+            endpoints: KrakenEndpoints::default(),
             api_key: kraken_config.api_key.clone(),
             api_secret: kraken_config.api_secret.clone(),
-            settlement,
             fill_sender,
             dashboard_tx,
             db_manager,
+            watchdog,
+            order_ids: Mutex::new(KrakenOrderIds::default()),
+            seen_trades: Mutex::new(PublishedTrades::new(KRAKEN_SEEN_TRADES)),
+            instruments: Arc::new(InstrumentRegistry::new()),
+            subscriptions: Subscriptions::new(),
         }
     }
 
+    pub fn with_watchdog(mut self, watchdog: FeedWatchdog) -> Self {
+        self.watchdog = watchdog;
+        self
+    }
+
+    pub fn with_instruments(mut self, instruments: Arc<InstrumentRegistry>) -> Self {
+        self.instruments = instruments;
+        self
+    }
+
+    /// A v1 `subscribe` or `unsubscribe` to the `name` channel. v1 still names
+    /// bitcoin XBT, so pairs go out under the venue's names. Kraken echoes
+    /// `reqid` in the status it sends for each pair.
+    fn v1_subscription(&self, name: &str, change: &SubscriptionChange, reqid: Option<u64>) -> serde_json::Value {
+        let (event, symbols) = match change {
+            SubscriptionChange::Add(symbols) => ("subscribe", symbols),
+            SubscriptionChange::Remove(symbols) => ("unsubscribe", symbols),
+        };
+        let pairs: Vec<String> = symbols
+            .iter()
+            .map(|s| self.instruments.ws_symbol(MarketDataSource::Kraken, s))
+            .collect();
+        let mut msg = json!({
+            "event": event,
+            "pair": pairs,
+            "subscription": {
+                "name": name
+            }
+        });
+        if let Some(reqid) = reqid {
+            msg["reqid"] = json!(reqid);
+        }
+        msg
+    }
+
+    pub fn with_endpoints(mut self, endpoints: KrakenEndpoints) -> Self {
+        self.endpoints = endpoints;
+        self
+    }
+
     fn get_kraken_signature(
         &self,
         path: &str,
@@ -76,6 +171,820 @@ impl KrakenConnector {
         let signature = mac.finalize().into_bytes();
         Ok(general_purpose::STANDARD.encode(signature))
     }
+
+    /// Signs and posts a private REST call, returning the `result` object.
+    async fn private_request(
+        &self,
+        path: &str,
+        mut params: Vec<(&str, String)>,
+    ) -> Result<serde_json::Value, ExecutionError> {
+        let nonce = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_err(anyhow::Error::from)?
+            .as_millis()
+            .to_string();
+        params.insert(0, ("nonce", nonce.clone()));
+
+        // The signature covers the body exactly as sent, so encode it once.
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(&params)
+            .finish();
+        let signature = self.get_kraken_signature(path, &nonce, &body)?;
+
+        let res = reqwest::Client::new()
+            .post(format!("{}{}", self.endpoints.api_url, path))
+            .header("API-Key", &self.api_key)
+            .header("API-Sign", signature)
+            .header(reqwest::header::CONTENT_TYPE, "application/x-www-form-urlencoded")
+            .body(body)
+            .send()
+            .await?;
+
+        let response_text = res.text().await?;
+        let mut response_json: serde_json::Value = serde_json::from_str(&response_text)?;
+
+        if let Some(error) = response_json["error"].as_array() {
+            if !error.is_empty() {
+                return Err(kraken_error(error));
+            }
+        }
+        Ok(response_json["result"].take())
+    }
+
+    /// Recovers the trades timestamped strictly between `after` and `before`
+    /// (epoch microseconds), oldest first.
+    async fn backfill_trades(&self, symbol: &str, after: u64, before: u64) -> Result<Vec<Tick>> {
+        let pair = self.instruments.venue_symbol(MarketDataSource::Kraken, symbol);
+        // `since` is in nanoseconds; each page continues from the previous `last`.
+        let mut since = (after * 1_000).to_string();
+        let mut ticks = Vec::new();
+        for _ in 0..KRAKEN_BACKFILL_PAGES {
+            let response_text = reqwest::Client::new()
+                .get(format!("{}/0/public/Trades", self.endpoints.api_url))
+                .query(&[("pair", pair.as_str()), ("since", since.as_str())])
+                .send()
+                .await?
+                .text()
+                .await?;
+            let response: serde_json::Value = serde_json::from_str(&response_text)?;
+            if let Some(error) = response["error"].as_array().filter(|error| !error.is_empty()) {
+                return Err(anyhow!("Kraken API Error: {:?}", error));
+            }
+            // Trades are keyed by Kraken's own pair name, next to `last`.
+            let result = response["result"].as_object().ok_or_else(|| anyhow!("Kraken Trades returned no result"))?;
+            let trades = result
+                .iter()
+                .find(|(key, _)| key.as_str() != "last")
+                .and_then(|(_, trades)| trades.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            let mut reached_before = trades.is_empty();
+            for trade in &trades {
+                let field = |i: usize| trade[i].as_str().unwrap_or("0").parse::<f64>();
+                let timestamp = trade[2].as_f64().unwrap_or_default();
+                let micros = (timestamp * 1_000_000.0).round() as u64;
+                if micros >= before {
+                    reached_before = true;
+                    break;
+                }
+                if micros <= after {
+                    continue;
+                }
+                ticks.push(Tick {
+                    source: MarketDataSource::Kraken,
+                    symbol: symbol.to_string(),
+                    price: field(0)?,
+                    volume: field(1)?,
+                    received_at: Utc::now(),
+                    exchange_ts: DateTime::from_timestamp_micros(micros as i64),
+                    trade_id: None,
+                });
+            }
+            match result.get("last").and_then(|last| last.as_str()) {
+                Some(last) if !reached_before => since = last.to_string(),
+                _ => break,
+            }
+        }
+        Ok(ticks)
+    }
+
+    /// Follows our own executions on the authenticated feed. Every trade
+    /// becomes a `Fill` and every order status change is written through to
+    /// the orders table. Each connection starts with a snapshot of recent
+    /// trades, so fills made while disconnected are still published; the
+    /// first snapshot predates this process and is only remembered.
+    pub async fn subscribe_private(self: Arc<Self>) -> Result<()> {
+        let seeded = AtomicBool::new(false);
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            // Tokens are only needed to subscribe, so a fresh one is fetched per connection.
+            let result = self
+                .private_request("/0/private/GetWebSocketsToken", Vec::new())
+                .await
+                .map_err(|e| backoff::Error::transient(anyhow!("{}", e)))?;
+            let token = result["token"]
+                .as_str()
+                .ok_or_else(|| backoff::Error::permanent(anyhow!("GetWebSocketsToken returned no token")))?
+                .to_string();
+
+            let (ws_stream, _) = connect_async(self.endpoints.ws_auth_url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+
+            connection.up();
+            let (mut write, mut read) = ws_stream.split();
+
+            for subscription in [
+                json!({ "name": "ownTrades", "token": token }),
+                json!({ "name": "openOrders", "token": token }),
+            ] {
+                let subscribe_msg = json!({ "event": "subscribe", "subscription": subscription });
+                write.send(Message::Text(subscribe_msg.to_string()))
+                    .await.map_err(|e| backoff::Error::transient(e.into()))?;
+            }
+            info!("[KRAKEN] Subscribed to ownTrades and openOrders");
+            let mut snapshot = true;
+
+            while let Some(message) = read.next().await {
+                let Message::Text(text) = message.map_err(|e| backoff::Error::transient(e.into()))? else {
+                    continue;
+                };
+                let v: serde_json::Value = match serde_json::from_str(&text) {
+                    Ok(v) => v,
+                    Err(_) => continue,
+                };
+                if let Some(status) = v.get("status").and_then(|s| s.as_str()) {
+                    if status == "error" {
+                        warn!("[KRAKEN] Private feed error: {}", v["errorMessage"]);
+                    }
+                    continue;
+                }
+
+                match v.get(1).and_then(|c| c.as_str()) {
+                    Some("ownTrades") => {
+                        let seed = std::mem::take(&mut snapshot) && !seeded.swap(true, Ordering::Relaxed);
+                        for fill in self.own_trade_fills(&v[0], seed) {
+                            publish_fill(fill, self.fill_sender.as_ref(), &self.db_manager, &self.dashboard_tx).await;
+                        }
+                    }
+                    Some("openOrders") => {
+                        for (order_id, status) in self.open_order_statuses(&v[0]) {
+                            if let Err(e) = self.db_manager.update_order_status(order_id, &status).await {
+                                error!("Failed to update order status: {}", e);
+                            }
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            Err(backoff::Error::transient(anyhow!("Kraken private feed closed")))
+        };
+
+        retry(backoff, operation).await
+    }
+
+    /// Fills for trades not seen before. With `seed` the trades are only
+    /// remembered.
+    fn own_trade_fills(&self, data: &serde_json::Value, seed: bool) -> Vec<Fill> {
+        let mut fills = Vec::new();
+        for (trade_id, trade) in data.as_array().into_iter().flatten().filter_map(|t| t.as_object()).flatten() {
+            if !self.seen_trades.lock().unwrap().insert(trade_id.clone()) || seed {
+                continue;
+            }
+            let trade: KrakenOwnTrade = match serde_json::from_value(trade.clone()) {
+                Ok(trade) => trade,
+                Err(e) => {
+                    warn!("Failed to parse Kraken own trade {}: {}", trade_id, e);
+                    continue;
+                }
+            };
+            let mut fill = match trade.fill(trade_id) {
+                Ok(fill) => fill,
+                Err(e) => {
+                    warn!("Failed to read Kraken own trade {}: {}", trade_id, e);
+                    continue;
+                }
+            };
+            fill.symbol = self.instruments.canonical(MarketDataSource::Kraken, &fill.symbol);
+            // A market order can fill before AddOrder has returned its txid.
+            fills.extend(self.order_ids.lock().unwrap().resolve(&trade.ordertxid, fill));
+        }
+        fills
+    }
+
+    fn open_order_statuses(&self, data: &serde_json::Value) -> Vec<(Uuid, OrderStatus)> {
+        let mut order_ids = self.order_ids.lock().unwrap();
+        let mut statuses = Vec::new();
+        for (txid, update) in data.as_array().into_iter().flatten().filter_map(|o| o.as_object()).flatten() {
+            let Ok(update) = serde_json::from_value::<KrakenOpenOrder>(update.clone()) else {
+                continue;
+            };
+            if let Some(order_id) = update.cl_ord_id.as_deref().and_then(|id| Uuid::parse_str(id).ok()) {
+                order_ids.register(txid, order_id);
+            }
+            let (Some(order_id), Some(status)) = (order_ids.get(txid), update.status()) else {
+                continue;
+            };
+            if !status.is_open() {
+                order_ids.close(txid);
+            }
+            statuses.push((order_id, status));
+        }
+        statuses
+    }
+
+    /// Maps a txid to our order id and publishes any fills that beat it.
+    async fn register_txid(&self, txid: &str, order_id: Uuid) {
+        let early_fills = self.order_ids.lock().unwrap().register(txid, order_id);
+        for fill in early_fills {
+            publish_fill(fill, self.fill_sender.as_ref(), &self.db_manager, &self.dashboard_tx).await;
+        }
+    }
+
+    /// Kraken addresses orders by txid. Orders placed before a restart are
+    /// looked up among the open ones by `cl_ord_id`.
+    async fn txid(&self, order_id: Uuid) -> Result<String, ExecutionError> {
+        if let Some(txid) = self.order_ids.lock().unwrap().txid(order_id) {
+            return Ok(txid);
+        }
+        let result = self
+            .private_request("/0/private/OpenOrders", vec![("cl_ord_id", order_id.to_string())])
+            .await?;
+        let txid = result["open"]
+            .as_object()
+            .and_then(|open| open.keys().next().cloned())
+            .ok_or_else(|| ExecutionError::UnknownOrder(format!("Unknown Kraken order {}", order_id)))?;
+        self.register_txid(&txid, order_id).await;
+        Ok(txid)
+    }
+
+    async fn query_orders(&self, txids: &[String]) -> Result<Vec<OrderReport>, ExecutionError> {
+        let result = self
+            .private_request("/0/private/QueryOrders", vec![("txid", txids.join(","))])
+            .await?;
+        Ok(self.order_reports(&result))
+    }
+
+    /// Reports for a `{txid: order}` object, skipping any kind we do not
+    /// trade (stop orders placed by hand, say).
+    fn order_reports(&self, orders: &serde_json::Value) -> Vec<OrderReport> {
+        let mut order_ids = self.order_ids.lock().unwrap();
+        let mut reports = Vec::new();
+        for (txid, order) in orders.as_object().into_iter().flatten() {
+            let report = serde_json::from_value::<KrakenOrderInfo>(order.clone())
+                .map_err(anyhow::Error::from)
+                .and_then(|order| order.report(txid));
+            match report {
+                Ok(mut report) => {
+                    report.order_id = report.order_id.or_else(|| order_ids.get(txid));
+                    if !report.status.is_open() {
+                        order_ids.close(txid);
+                    }
+                    report.symbol = self.instruments.canonical(MarketDataSource::Kraken, &report.symbol);
+                    reports.push(report);
+                }
+                Err(e) => warn!("Skipping Kraken order {}: {}", txid, e),
+            }
+        }
+        reports
+    }
+}
+
+/// Own trade ids remembered so the snapshot sent on every reconnect only
+/// publishes the trades missed while disconnected.
+const KRAKEN_SEEN_TRADES: usize = 4096;
+/// How long a fill for an unknown txid waits for `AddOrder` to name it.
+const KRAKEN_UNMATCHED_FILL_TTL: Duration = Duration::from_secs(30);
+/// Most txids with fills waiting at once; the oldest go first.
+const KRAKEN_MAX_UNMATCHED: usize = 1024;
+/// How long a closed order stays addressable, long enough for late fills
+/// and for the OMS to reconcile it.
+const KRAKEN_CLOSED_ORDER_RETENTION: Duration = Duration::from_secs(600);
+
+/// Maps Kraken order txids back to our order ids.
+struct KrakenOrderIds {
+    by_txid: HashMap<String, Uuid>,
+    by_order_id: HashMap<Uuid, String>,
+    /// Fills for txids we have not been told about yet, with when the
+    /// first arrived. Fills for orders placed elsewhere never get claimed.
+    unmatched: HashMap<String, (Instant, Vec<Fill>)>,
+    /// Txids of closed orders, oldest first, to forget once retained long enough.
+    closed: VecDeque<(Instant, String)>,
+    unmatched_ttl: Duration,
+    retention: Duration,
+}
+
+impl Default for KrakenOrderIds {
+    fn default() -> Self {
+        Self {
+            by_txid: HashMap::new(),
+            by_order_id: HashMap::new(),
+            unmatched: HashMap::new(),
+            closed: VecDeque::new(),
+            unmatched_ttl: KRAKEN_UNMATCHED_FILL_TTL,
+            retention: KRAKEN_CLOSED_ORDER_RETENTION,
+        }
+    }
+}
+
+impl KrakenOrderIds {
+    /// Records a txid and returns any fills that were waiting for it.
+    fn register(&mut self, txid: &str, order_id: Uuid) -> Vec<Fill> {
+        self.expire(Instant::now());
+        self.by_txid.insert(txid.to_string(), order_id);
+        self.by_order_id.insert(order_id, txid.to_string());
+        let mut fills = self.unmatched.remove(txid).map(|(_, fills)| fills).unwrap_or_default();
+        for fill in &mut fills {
+            fill.order_id = order_id;
+        }
+        fills
+    }
+
+    fn get(&self, txid: &str) -> Option<Uuid> {
+        self.by_txid.get(txid).copied()
+    }
+
+    fn txid(&self, order_id: Uuid) -> Option<String> {
+        self.by_order_id.get(&order_id).cloned()
+    }
+
+    /// Returns the fill with its order id set, or holds it until the txid is registered.
+    fn resolve(&mut self, txid: &str, mut fill: Fill) -> Option<Fill> {
+        match self.get(txid) {
+            Some(order_id) => {
+                fill.order_id = order_id;
+                Some(fill)
+            }
+            None => {
+                let now = Instant::now();
+                self.expire(now);
+                if self.unmatched.len() >= KRAKEN_MAX_UNMATCHED && !self.unmatched.contains_key(txid) {
+                    let oldest = self.unmatched.iter().min_by_key(|(_, (since, _))| *since).map(|(txid, _)| txid.clone());
+                    if let Some(oldest) = oldest {
+                        self.unmatched.remove(&oldest);
+                        warn!("[KRAKEN] Dropped fills for unknown order {}", oldest);
+                    }
+                }
+                self.unmatched.entry(txid.to_string()).or_insert_with(|| (now, Vec::new())).1.push(fill);
+                None
+            }
+        }
+    }
+
+    /// Notes that `txid` will not trade again; its mapping is dropped after
+    /// the retention period.
+    fn close(&mut self, txid: &str) {
+        if self.by_txid.contains_key(txid) && !self.closed.iter().any(|(_, closed)| closed == txid) {
+            self.closed.push_back((Instant::now(), txid.to_string()));
+        }
+    }
+
+    fn expire(&mut self, now: Instant) {
+        let ttl = self.unmatched_ttl;
+        self.unmatched.retain(|txid, (since, _)| {
+            let keep = now.duration_since(*since) < ttl;
+            if !keep {
+                debug!("[KRAKEN] Dropped fills for unknown order {}", txid);
+            }
+            keep
+        });
+        while let Some((closed_at, _)) = self.closed.front() {
+            if now.duration_since(*closed_at) < self.retention {
+                break;
+            }
+            let (_, txid) = self.closed.pop_front().unwrap();
+            if let Some(order_id) = self.by_txid.remove(&txid) {
+                if self.by_order_id.get(&order_id) == Some(&txid) {
+                    self.by_order_id.remove(&order_id);
+                }
+            }
+        }
+    }
+}
+
+/// A v1 trade message, `[channelID, [trade, ...], "trade", pair]`.
+#[derive(Debug, Deserialize)]
+pub struct KrakenTradeMessage<'a>(pub u64, pub Vec<KrakenTrade>, pub &'a str, #[serde(borrow)] pub Cow<'a, str>);
+
+/// One `[price, volume, time, side, orderType, misc]` entry of a v1 trade
+/// message. Only the numbers are read; the rest are skipped without copying.
+#[derive(Debug, PartialEq)]
+pub struct KrakenTrade {
+    pub price: f64,
+    pub volume: f64,
+    /// Fractional epoch seconds.
+    pub time: f64,
+}
+
+impl<'de> Deserialize<'de> for KrakenTrade {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct TradeVisitor;
+
+        impl<'de> Visitor<'de> for TradeVisitor {
+            type Value = KrakenTrade;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a Kraken trade array")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<KrakenTrade, A::Error> {
+                let mut next = |index| -> std::result::Result<f64, A::Error> {
+                    seq.next_element::<Decimal>()?
+                        .map(|d| d.0)
+                        .ok_or_else(|| de::Error::invalid_length(index, &self))
+                };
+                let trade = KrakenTrade { price: next(0)?, volume: next(1)?, time: next(2)? };
+                while seq.next_element::<IgnoredAny>()?.is_some() {}
+                Ok(trade)
+            }
+        }
+
+        deserializer.deserialize_seq(TradeVisitor)
+    }
+}
+
+/// The status v1 sends for each pair of a `subscribe` or `unsubscribe`.
+#[derive(Debug, Deserialize)]
+struct KrakenSubscriptionStatus {
+    event: String,
+    status: String,
+    #[serde(default)]
+    reqid: u64,
+    #[serde(rename = "errorMessage", default)]
+    error_message: String,
+}
+
+impl KrakenSubscriptionStatus {
+    fn answer(self, pending: &mut PendingRequests) {
+        if self.event != "subscriptionStatus" {
+            return;
+        }
+        let result = if self.status == "error" { Err(self.error_message) } else { Ok(()) };
+        pending.reply(self.reqid, result);
+    }
+}
+
+/// A v2 message with `data` read straight into the type its `channel`
+/// calls for, so book updates never pass through a `serde_json::Value`.
+/// Kraken sends `channel` and `type` ahead of `data`; method replies come out
+/// as `Ack`, heartbeats and other channels as `Other`.
+#[derive(Debug)]
+pub enum KrakenV2Message<'a> {
+    Book { snapshot: bool, updates: Vec<KrakenBookData<'a>> },
+    Instrument(KrakenInstrumentData),
+    Ack { req_id: u64, result: Result<(), String> },
+    Other,
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for KrakenV2Message<'a> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct MessageVisitor<'a>(std::marker::PhantomData<&'a ()>);
+
+        impl<'de: 'a, 'a> Visitor<'de> for MessageVisitor<'a> {
+            type Value = KrakenV2Message<'a>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a Kraken v2 message")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<KrakenV2Message<'a>, A::Error> {
+                let mut channel: Option<Cow<'de, str>> = None;
+                let mut snapshot = false;
+                let mut req_id = 0;
+                let mut success = None;
+                let mut error = None;
+                let mut message = KrakenV2Message::Other;
+                while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+                    match (key.as_ref(), channel.as_deref()) {
+                        ("channel", _) => channel = Some(map.next_value()?),
+                        ("type", _) => snapshot = map.next_value::<Cow<'de, str>>()? == "snapshot",
+                        ("req_id", _) => req_id = map.next_value()?,
+                        ("success", _) => success = Some(map.next_value::<bool>()?),
+                        ("error", _) => error = Some(map.next_value::<String>()?),
+                        ("data", Some("book")) => message = KrakenV2Message::Book { snapshot, updates: map.next_value()? },
+                        ("data", Some("instrument")) => message = KrakenV2Message::Instrument(map.next_value()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+                if let (KrakenV2Message::Other, Some(success)) = (&message, success) {
+                    let result = if success { Ok(()) } else { Err(error.unwrap_or_default()) };
+                    message = KrakenV2Message::Ack { req_id, result };
+                }
+                Ok(message)
+            }
+        }
+
+        deserializer.deserialize_map(MessageVisitor(std::marker::PhantomData))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KrakenInstrumentData {
+    pairs: Vec<KrakenPairInfo>,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenPairInfo {
+    symbol: String,
+    price_precision: usize,
+    qty_precision: usize,
+}
+
+/// One symbol's entry in a v2 book message.
+#[derive(Debug, Deserialize)]
+pub struct KrakenBookData<'a> {
+    #[serde(borrow)]
+    pub symbol: Cow<'a, str>,
+    #[serde(default)]
+    pub bids: Vec<KrakenBookLevel>,
+    #[serde(default)]
+    pub asks: Vec<KrakenBookLevel>,
+    pub checksum: u32,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KrakenBookLevel {
+    pub price: f64,
+    pub qty: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenOwnTrade {
+    ordertxid: String,
+    pair: String,
+    #[serde(rename = "type")]
+    side: String,
+    price: String,
+    vol: String,
+    fee: String,
+    time: String,
+}
+
+impl KrakenOwnTrade {
+    /// Builds a fill with a nil order id; the caller maps `ordertxid` to ours.
+    fn fill(&self, trade_id: &str) -> Result<Fill> {
+        let side = match self.side.as_str() {
+            "buy" => OrderSide::Buy,
+            "sell" => OrderSide::Sell,
+            other => anyhow::bail!("Unknown Kraken trade side: {}", other),
+        };
+        let time: f64 = self.time.parse()?;
+        Ok(Fill {
+            order_id: Uuid::nil(),
+            symbol: self.pair.clone(),
+            side,
+            price: self.price.parse()?,
+            quantity: self.vol.parse()?,
+            source: MarketDataSource::Kraken,
+            executed_at: DateTime::from_timestamp_micros((time * 1_000_000.0).round() as i64).unwrap_or_else(Utc::now),
+            commission: self.fee.parse()?,
+            // Kraken charges fees in the quote currency by default.
+            commission_asset: self.pair.split('/').nth(1).map(canonical_asset),
+            trade_id: Some(trade_id.to_string()),
+        })
+    }
+}
+
+/// An openOrders entry. Updates after the first only carry the changed fields.
+#[derive(Debug, Deserialize)]
+struct KrakenOpenOrder {
+    status: Option<String>,
+    vol_exec: Option<String>,
+    cl_ord_id: Option<String>,
+}
+
+impl KrakenOpenOrder {
+    fn status(&self) -> Option<OrderStatus> {
+        let partially_filled = self
+            .vol_exec
+            .as_deref()
+            .and_then(|v| v.parse::<f64>().ok())
+            .is_some_and(|v| v > 0.0);
+        order_status(self.status.as_deref()?, partially_filled)
+    }
+}
+
+fn order_status(status: &str, partially_filled: bool) -> Option<OrderStatus> {
+    match status {
+        "pending" => Some(OrderStatus::New),
+        "open" if partially_filled => Some(OrderStatus::PartiallyFilled),
+        "open" => Some(OrderStatus::New),
+        "closed" => Some(OrderStatus::Filled),
+        "canceled" => Some(OrderStatus::Canceled),
+        "expired" => Some(OrderStatus::Expired),
+        _ => None,
+    }
+}
+
+/// Classifies the `error` array of a private response by its first entry.
+fn kraken_error(errors: &[serde_json::Value]) -> ExecutionError {
+    let message = format!("Kraken API Error: {:?}", errors);
+    let first = errors.first().and_then(|error| error.as_str()).unwrap_or_default();
+    match first.split(':').nth(1).map(str::trim).unwrap_or_default() {
+        "Insufficient funds" | "Insufficient margin" => ExecutionError::InsufficientFunds(message),
+        "Unknown order" => ExecutionError::UnknownOrder(message),
+        "Rate limit exceeded" | "Orders limit exceeded" | "Too many requests" | "Throttled" | "Temporary lockout" => {
+            ExecutionError::RateLimited(message)
+        }
+        "Invalid key" | "Invalid signature" | "Invalid nonce" | "Permission denied" => ExecutionError::Unauthorized(message),
+        // Busy and Unavailable turn requests away before they reach the engine.
+        "Busy" | "Unavailable" => ExecutionError::Network(message),
+        "Deadline elapsed" => ExecutionError::Timeout(message),
+        _ => ExecutionError::Rejected(message),
+    }
+}
+
+/// An order as `QueryOrders` and `OpenOrders` describe it.
+#[derive(Debug, Deserialize)]
+struct KrakenOrderInfo {
+    status: String,
+    #[serde(default)]
+    cl_ord_id: Option<String>,
+    opentm: f64,
+    #[serde(default)]
+    closetm: Option<f64>,
+    descr: KrakenOrderDescr,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    vol: f64,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    vol_exec: f64,
+    /// Average price of the executions.
+    #[serde(deserialize_with = "decimal::deserialize")]
+    price: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenOrderDescr {
+    pair: String,
+    #[serde(rename = "type")]
+    side: String,
+    ordertype: String,
+    #[serde(deserialize_with = "decimal::deserialize")]
+    price: f64,
+}
+
+impl KrakenOrderInfo {
+    /// A report under the venue pair; the caller maps it, and the order id
+    /// if there is no `cl_ord_id`, to ours.
+    fn report(&self, txid: &str) -> Result<OrderReport> {
+        let side = match self.descr.side.as_str() {
+            "buy" => OrderSide::Buy,
+            "sell" => OrderSide::Sell,
+            other => anyhow::bail!("Unknown Kraken order side: {}", other),
+        };
+        let order_type = match self.descr.ordertype.as_str() {
+            "market" => OrderType::Market,
+            "limit" => OrderType::Limit,
+            other => anyhow::bail!("Unsupported Kraken order type: {}", other),
+        };
+        let status = order_status(&self.status, self.vol_exec > 0.0)
+            .ok_or_else(|| anyhow!("Unknown Kraken order status: {}", self.status))?;
+        let updated_at = self.closetm.unwrap_or(self.opentm);
+        Ok(OrderReport {
+            order_id: self.cl_ord_id.as_deref().and_then(|id| Uuid::parse_str(id).ok()),
+            exchange_order_id: txid.to_string(),
+            symbol: self.descr.pair.clone(),
+            side,
+            order_type,
+            status,
+            amount: self.vol,
+            filled: self.vol_exec,
+            price: Some(self.descr.price).filter(|price| *price > 0.0),
+            avg_fill_price: Some(self.price).filter(|_| self.vol_exec > 0.0),
+            source: MarketDataSource::Kraken,
+            updated_at: DateTime::from_timestamp_micros((updated_at * 1_000_000.0).round() as i64).unwrap_or_else(Utc::now),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct KrakenAssetPair {
+    altname: String,
+    /// Absent on dark-pool pairs, which have no WebSocket feed.
+    wsname: Option<String>,
+    pair_decimals: i32,
+    lot_decimals: i32,
+    tick_size: Option<String>,
+    costmin: Option<String>,
+    status: Option<String>,
+}
+
+impl KrakenAssetPair {
+    fn instrument(&self) -> Result<Option<Instrument>> {
+        let Some(wsname) = &self.wsname else {
+            return Ok(None);
+        };
+        if self.status.as_deref().is_some_and(|status| status != "online") {
+            return Ok(None);
+        }
+        let Some((base, quote)) = wsname.split_once('/') else {
+            return Ok(None);
+        };
+        let tick_size = match &self.tick_size {
+            Some(tick_size) => tick_size.parse()?,
+            None => 10f64.powi(-self.pair_decimals),
+        };
+        Ok(Some(Instrument {
+            symbol: canonical_symbol(base, quote),
+            base: canonical_asset(base),
+            quote: canonical_asset(quote),
+            venue: MarketDataSource::Kraken,
+            venue_symbol: self.altname.clone(),
+            ws_symbol: wsname.clone(),
+            tick_size,
+            lot_size: 10f64.powi(-self.lot_decimals),
+            min_notional: self.costmin.as_deref().map(str::parse).transpose()?.unwrap_or(0.0),
+        }))
+    }
+}
+
+/// Trading rules for every online Kraken spot pair.
+pub(crate) async fn fetch_instruments(http_client: &reqwest::Client) -> Result<Vec<Instrument>> {
+    let response_text = http_client
+        .get(format!("{}/0/public/AssetPairs", KRAKEN_API_URL))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+    parse_asset_pairs(&response_text)
+}
+
+fn parse_asset_pairs(text: &str) -> Result<Vec<Instrument>> {
+    let response: serde_json::Value = serde_json::from_str(text)?;
+    if let Some(error) = response["error"].as_array().filter(|e| !e.is_empty()) {
+        anyhow::bail!("Kraken API Error: {:?}", error);
+    }
+    let pairs: HashMap<String, KrakenAssetPair> = serde_json::from_value(response["result"].clone())?;
+    let mut instruments = Vec::new();
+    for pair in pairs.values() {
+        instruments.extend(pair.instrument()?);
+    }
+    Ok(instruments)
+}
+
+/// Reads a v1 `spread` payload: `[bid, ask, timestamp, bidVolume, askVolume]`.
+fn spread_quote(spread: &serde_json::Value, symbol: String) -> Result<Quote> {
+    let field = |i: usize| -> Result<f64> {
+        let value = spread[i].as_str().ok_or_else(|| anyhow!("Kraken spread field {} missing", i))?;
+        Ok(value.parse()?)
+    };
+    Ok(Quote {
+        source: MarketDataSource::Kraken,
+        symbol,
+        bid: field(0)?,
+        ask: field(1)?,
+        bid_size: field(3)?,
+        ask_size: field(4)?,
+        received_at: Utc::now(),
+        exchange_ts: DateTime::from_timestamp_micros((field(2)? * 1_000_000.0).round() as i64),
+    })
+}
+
+/// Rounds a requested depth up to the nearest depth Kraken accepts.
+fn kraken_book_depth(depth: usize) -> usize {
+    KRAKEN_BOOK_DEPTHS
+        .iter()
+        .copied()
+        .find(|&d| d >= depth)
+        .unwrap_or(KRAKEN_BOOK_DEPTHS[KRAKEN_BOOK_DEPTHS.len() - 1])
+}
+
+/// Kraken answers each symbol apart, echoing `req_id`.
+fn book_subscription(method: &str, symbols: &[String], depth: usize, req_id: Option<u64>) -> serde_json::Value {
+    let mut msg = json!({
+        "method": method,
+        "params": {
+            "channel": "book",
+            "symbol": symbols,
+            "depth": depth,
+            "snapshot": true
+        }
+    });
+    if let Some(req_id) = req_id {
+        msg["req_id"] = json!(req_id);
+    }
+    msg
+}
+
+/// Formats a value at the instrument's precision with the decimal point and
+/// leading zeros removed, as Kraken does when building the checksum string.
+fn checksum_field(value: f64, precision: usize) -> String {
+    let formatted = format!("{:.*}", precision, value).replace('.', "");
+    let trimmed = formatted.trim_start_matches('0');
+    if trimmed.is_empty() { "0".to_string() } else { trimmed.to_string() }
+}
+
+/// CRC32 over the top ten asks (best first) followed by the top ten bids.
+pub(crate) fn kraken_book_checksum(book: &OrderBook, price_precision: usize, qty_precision: usize) -> u32 {
+    let mut input = String::new();
+    for level in book.asks.iter().take(KRAKEN_CHECKSUM_DEPTH).chain(book.bids.iter().take(KRAKEN_CHECKSUM_DEPTH)) {
+        input.push_str(&checksum_field(level.price, price_precision));
+        input.push_str(&checksum_field(level.quantity, qty_precision));
+    }
+    crc32fast::hash(input.as_bytes())
 }
 
 #[async_trait]
@@ -85,70 +994,423 @@ impl Connector for KrakenConnector {
         symbols: &[String],
         sender: mpsc::Sender<Tick>,
     ) -> Result<()> {
+        self.subscriptions.reset(symbols);
+        // Kept across reconnects, so trades missed while disconnected are backfilled.
+        let sequence = std::sync::Mutex::new(TradeSequence::new(MarketDataSource::Kraken));
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
         let operation = || async {
-            let url = Url::parse(KRAKEN_WSS_URL).map_err(|e| backoff::Error::transient(e.into()))?;
+            sequence.lock().unwrap().on_reconnect();
+            let (symbols, mut changes) = self.subscriptions.connect();
+            let url = Url::parse(&self.endpoints.ws_url).map_err(|e| backoff::Error::transient(e.into()))?;
             let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+            connection.up();
             let (mut write, mut read) = ws_stream.split();
 
-            let subscribe_msg = json!({
-                "event": "subscribe",
-                "pair": symbols,
-                "subscription": {
-                    "name": "trade"
-                }
-            });
-
-            write.send(tokio_tungstenite::tungstenite::Message::Text(
-                subscribe_msg.to_string(),
-            ))
+            let subscribe_msg = self.v1_subscription("trade", &SubscriptionChange::Add(symbols.clone()), None);
+            write.send(Message::Text(subscribe_msg.to_string()))
             .await.map_err(|e| backoff::Error::transient(e.into()))?;
             println!("[KRAKEN] Subscribed to trades for {:?}", symbols);
 
-            while let Some(Ok(msg)) = read.next().await {
-                if let tokio_tungstenite::tungstenite::Message::Text(text) = msg {
-                    let v: serde_json::Value = match serde_json::from_str(&text) {
-                        Ok(v) => v,
-                        Err(_) => continue,
-                    };
+            self.watchdog.on_connect(FeedStream::Trades, &symbols);
+            let mut pending = PendingRequests::default();
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
 
-                    if let Some(trades) = v.get(1).and_then(|d| d.as_array()) {
-                        for trade in trades {
-                            let price = trade[0].as_str().unwrap_or("0").parse::<f64>().map_err(|e| backoff::Error::permanent(e.into()))?;
-                            let volume = trade[1].as_str().unwrap_or("0").parse::<f64>().map_err(|e| backoff::Error::permanent(e.into()))?;
-                            let _timestamp = trade[2].as_str().unwrap_or("0").parse::<f64>().map_err(|e| backoff::Error::permanent(e.into()))?;
-                            let symbol = v[3].as_str().unwrap_or_default().to_string();
-
-                            let tick = Tick {
-                                source: MarketDataSource::Kraken,
-                                symbol,
-                                price,
-                                volume,
-                                received_at: Utc::now(),
-                            };
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::Trades, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Kraken trade stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        // A missed change is recovered by reconnecting with the current set.
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        write.send(Message::Text(self.v1_subscription("trade", &request.change, Some(request.id)).to_string()))
+                            .await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let replies = match &request.change {
+                            SubscriptionChange::Add(symbols) => {
+                                self.watchdog.on_connect(FeedStream::Trades, symbols);
+                                symbols.len()
+                            }
+                            SubscriptionChange::Remove(symbols) => {
+                                self.watchdog.forget(FeedStream::Trades, symbols);
+                                let mut sequence = sequence.lock().unwrap();
+                                for symbol in symbols {
+                                    sequence.forget(symbol);
+                                }
+                                symbols.len()
+                            }
+                        };
+                        pending.insert(request, replies);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Kraken trade stream closed")));
+                };
+                last_frame = Instant::now();
+                let Message::Text(text) = message.map_err(|e| backoff::Error::transient(e.into()))? else {
+                    continue;
+                };
+                // Heartbeats and subscription events are objects and fail this parse.
+                let Ok(KrakenTradeMessage(_, trades, _, pair)) = serde_json::from_str(&text) else {
+                    if let Ok(status) = serde_json::from_str::<KrakenSubscriptionStatus>(&text) {
+                        status.answer(&mut pending);
+                    }
+                    continue;
+                };
 
-                            if let Err(e) = sender.send(tick.clone()).await {
-                                eprintln!("[KRAKEN] Failed to send tick: {}", e);
-                                break;
+                let symbol = self.instruments.canonical(MarketDataSource::Kraken, &pair);
+                self.watchdog.on_message(FeedStream::Trades, &symbol);
+                for KrakenTrade { price, volume, time } in trades {
+                    let micros = (time * 1_000_000.0).round() as u64;
+
+                    let check = sequence.lock().unwrap().check_time(&symbol, micros);
+                    match check {
+                        SequenceCheck::Duplicate => continue,
+                        SequenceCheck::Gap { after, before } => {
+                            match self.backfill_trades(&symbol, after, before).await {
+                                Ok(ticks) => {
+                                    record_backfill(MarketDataSource::Kraken, &symbol, ticks.len());
+                                    for tick in ticks {
+                                        if let Err(e) = sender.send(tick.clone()).await {
+                                            error!("[KRAKEN] Failed to send backfilled tick: {}", e);
+                                            return Ok(());
+                                        }
+                                        let _ = self.dashboard_tx.send(DashboardEvent::Tick(tick));
+                                    }
+                                }
+                                Err(e) => warn!("Failed to backfill Kraken {} trades: {}", symbol, e),
                             }
-                            let _ = self.dashboard_tx.send(DashboardEvent::Tick(tick));
                         }
+                        SequenceCheck::InOrder => (),
                     }
+
+                    let tick = Tick {
+                        source: MarketDataSource::Kraken,
+                        symbol: symbol.clone(),
+                        price,
+                        volume,
+                        received_at: Utc::now(),
+                        // Kraken v1 trades carry no id, only fractional epoch seconds.
+                        exchange_ts: DateTime::from_timestamp_micros(micros as i64),
+                        trade_id: None,
+                    };
+
+                    if let Err(e) = sender.send(tick.clone()).await {
+                        eprintln!("[KRAKEN] Failed to send tick: {}", e);
+                        return Ok(());
+                    }
+                    record_feed_latency(&tick, &self.dashboard_tx);
+                    let _ = self.dashboard_tx.send(DashboardEvent::Tick(tick));
                 }
             }
-            Ok(())
         };
 
-        retry(ExponentialBackoff::default(), operation).await
+        retry(backoff, operation).await
     }
 
     fn get_source(&self) -> MarketDataSource {
         MarketDataSource::Kraken
     }
+
+    async fn add_symbols(&self, symbols: &[String]) -> Result<()> {
+        self.subscriptions.add(symbols).await?;
+        Ok(())
+    }
+
+    async fn remove_symbols(&self, symbols: &[String]) -> Result<()> {
+        self.subscriptions.remove(symbols).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl OrderBookConnector for KrakenConnector {
+    async fn subscribe_order_book(
+        self: Arc<Self>,
+        symbols: &[String],
+        depth: usize,
+        sender: mpsc::Sender<OrderBook>,
+    ) -> Result<()> {
+        let book_depth = kraken_book_depth(depth);
+
+        self.subscriptions.reset(symbols);
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let (mut symbols, mut changes) = self.subscriptions.connect();
+            let url = Url::parse(&self.endpoints.ws_v2_url).map_err(|e| backoff::Error::transient(e.into()))?;
+            let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+            connection.up();
+            let (mut write, mut read) = ws_stream.split();
+
+            // v2 names pairs like our canonical symbols (BTC rather than XBT),
+            // so no translation is needed on this feed.
+            // Price and quantity precision are needed for the checksum, so the
+            // book is only requested once the instrument snapshot has arrived.
+            let instrument_msg = json!({
+                "method": "subscribe",
+                "params": { "channel": "instrument", "snapshot": true }
+            });
+            write.send(Message::Text(instrument_msg.to_string()))
+                .await.map_err(|e| backoff::Error::transient(e.into()))?;
+
+            let mut precisions: HashMap<String, (usize, usize)> = HashMap::new();
+            let mut books: HashMap<String, OrderBook> = HashMap::new();
+            // Symbols waiting for a fresh snapshot after a checksum mismatch.
+            let mut resyncing: HashSet<String> = HashSet::new();
+            let mut book_subscribed = false;
+
+            self.watchdog.on_connect(FeedStream::Book, &symbols);
+            let mut pending = PendingRequests::default();
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::Book, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Kraken book stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let (method, changed) = match &request.change {
+                            SubscriptionChange::Add(added) => {
+                                symbols.extend(added.iter().cloned());
+                                self.watchdog.on_connect(FeedStream::Book, added);
+                                ("subscribe", added)
+                            }
+                            SubscriptionChange::Remove(removed) => {
+                                symbols.retain(|symbol| !removed.contains(symbol));
+                                self.watchdog.forget(FeedStream::Book, removed);
+                                books.retain(|symbol, _| !removed.contains(symbol));
+                                resyncing.retain(|symbol| !removed.contains(symbol));
+                                ("unsubscribe", removed)
+                            }
+                        };
+                        // Until the instrument snapshot is in, the book has not been
+                        // requested, and the request it waits to send covers the change.
+                        if !book_subscribed {
+                            request.answer(Ok(()));
+                            continue;
+                        }
+                        let msg = book_subscription(method, changed, book_depth, Some(request.id));
+                        let replies = changed.len();
+                        write.send(Message::Text(msg.to_string()))
+                            .await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        pending.insert(request, replies);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Kraken book stream closed")));
+                };
+                last_frame = Instant::now();
+                let Message::Text(text) = message.map_err(|e| backoff::Error::transient(e.into()))? else {
+                    continue;
+                };
+                let msg: KrakenV2Message = match serde_json::from_str(&text) {
+                    Ok(msg) => msg,
+                    Err(e) => {
+                        warn!("Failed to parse Kraken v2 message: {:?}, error: {}", text, e);
+                        continue;
+                    }
+                };
+
+                match msg {
+                    KrakenV2Message::Instrument(instruments) => {
+                        for pair in instruments.pairs {
+                            precisions.insert(pair.symbol, (pair.price_precision, pair.qty_precision));
+                        }
+                        if !book_subscribed {
+                            let subscribe_msg = book_subscription("subscribe", &symbols, book_depth, None);
+                            write.send(Message::Text(subscribe_msg.to_string()))
+                                .await.map_err(|e| backoff::Error::transient(e.into()))?;
+                            info!("[KRAKEN] Subscribed to book depth {} for {:?}", book_depth, symbols);
+                            book_subscribed = true;
+                        }
+                    }
+                    KrakenV2Message::Book { snapshot, updates } => {
+                        for update in updates {
+                            let symbol = update.symbol.as_ref();
+                            self.watchdog.on_message(FeedStream::Book, symbol);
+                            if snapshot {
+                                resyncing.remove(symbol);
+                                books.remove(symbol);
+                            } else if resyncing.contains(symbol) {
+                                continue;
+                            }
+                            let Some(&(price_precision, qty_precision)) = precisions.get(symbol) else {
+                                warn!(symbol = %symbol, "No Kraken instrument precision, skipping book update");
+                                continue;
+                            };
+
+                            if !books.contains_key(symbol) {
+                                books.insert(symbol.to_string(), OrderBook::new(MarketDataSource::Kraken, symbol.to_string()));
+                            }
+                            let Some(book) = books.get_mut(symbol) else {
+                                continue;
+                            };
+                            for level in &update.bids {
+                                book.update_bid(level.price, level.qty);
+                            }
+                            for level in &update.asks {
+                                book.update_ask(level.price, level.qty);
+                            }
+                            book.bids.truncate(book_depth);
+                            book.asks.truncate(book_depth);
+                            book.last_update_id += 1;
+                            book.received_at = Utc::now();
+
+                            let checksum = kraken_book_checksum(book, price_precision, qty_precision);
+                            if checksum != update.checksum {
+                                warn!(
+                                    symbol = %symbol,
+                                    expected = update.checksum,
+                                    computed = checksum,
+                                    "Kraken book checksum mismatch, resubscribing"
+                                );
+                                counter!("orderbook_resyncs", "source" => "Kraken", "symbol" => symbol.to_string()).increment(1);
+                                books.remove(symbol);
+                                resyncing.insert(symbol.to_string());
+                                let symbol = [symbol.to_string()];
+                                for method in ["unsubscribe", "subscribe"] {
+                                    let msg = book_subscription(method, &symbol, book_depth, None);
+                                    write.send(Message::Text(msg.to_string()))
+                                        .await.map_err(|e| backoff::Error::transient(e.into()))?;
+                                }
+                                continue;
+                            }
+
+                            let published = book.truncated(depth);
+                            if let Err(e) = sender.send(published.clone()).await {
+                                error!("[KRAKEN] Failed to send order book: {}", e);
+                                return Ok(());
+                            }
+                            let _ = self.dashboard_tx.send(DashboardEvent::OrderBook(published));
+                        }
+                    }
+                    KrakenV2Message::Ack { req_id, result } => pending.reply(req_id, result),
+                    _ => {}
+                }
+            }
+        };
+
+        retry(backoff, operation).await
+    }
+}
+
+#[async_trait]
+impl QuoteConnector for KrakenConnector {
+    async fn subscribe_quotes(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<Quote>,
+    ) -> Result<()> {
+        self.subscriptions.reset(symbols);
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let (symbols, mut changes) = self.subscriptions.connect();
+            let url = Url::parse(&self.endpoints.ws_url).map_err(|e| backoff::Error::transient(e.into()))?;
+            let (ws_stream, _) = connect_async(url.as_str()).await.map_err(|e| backoff::Error::transient(e.into()))?;
+            connection.up();
+            let (mut write, mut read) = ws_stream.split();
+
+            let subscribe_msg = self.v1_subscription("spread", &SubscriptionChange::Add(symbols.clone()), None);
+            write.send(Message::Text(subscribe_msg.to_string()))
+                .await.map_err(|e| backoff::Error::transient(e.into()))?;
+            info!("[KRAKEN] Subscribed to spread for {:?}", symbols);
+
+            self.watchdog.on_connect(FeedStream::Quotes, &symbols);
+            let mut pending = PendingRequests::default();
+            let mut ping = tokio::time::interval(self.watchdog.ping_interval());
+            let mut last_frame = Instant::now();
+
+            loop {
+                let message = tokio::select! {
+                    message = read.next() => message,
+                    _ = ping.tick() => {
+                        if self.watchdog.needs_reconnect(FeedStream::Quotes, last_frame) {
+                            return Err(backoff::Error::transient(anyhow!("Kraken spread stream silent")));
+                        }
+                        write.send(Message::Ping(Vec::new())).await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        continue;
+                    }
+                    request = changes.recv() => {
+                        let request = request.map_err(|e| backoff::Error::transient(e.into()))?;
+                        write.send(Message::Text(self.v1_subscription("spread", &request.change, Some(request.id)).to_string()))
+                            .await.map_err(|e| backoff::Error::transient(e.into()))?;
+                        let replies = match &request.change {
+                            SubscriptionChange::Add(symbols) => {
+                                self.watchdog.on_connect(FeedStream::Quotes, symbols);
+                                symbols.len()
+                            }
+                            SubscriptionChange::Remove(symbols) => {
+                                self.watchdog.forget(FeedStream::Quotes, symbols);
+                                symbols.len()
+                            }
+                        };
+                        pending.insert(request, replies);
+                        continue;
+                    }
+                };
+                let Some(message) = message else {
+                    return Err(backoff::Error::transient(anyhow!("Kraken spread stream closed")));
+                };
+                last_frame = Instant::now();
+                let Message::Text(text) = message.map_err(|e| backoff::Error::transient(e.into()))? else {
+                    continue;
+                };
+                let v: serde_json::Value = match serde_json::from_str(&text) {
+                    Ok(v) => v,
+                    Err(_) => continue,
+                };
+                if v.is_object() {
+                    if let Ok(status) = serde_json::from_value::<KrakenSubscriptionStatus>(v) {
+                        status.answer(&mut pending);
+                    }
+                    continue;
+                }
+                if v.get(2).and_then(|c| c.as_str()) != Some("spread") {
+                    continue;
+                }
+
+                let symbol = self.instruments.canonical(MarketDataSource::Kraken, v[3].as_str().unwrap_or_default());
+                self.watchdog.on_message(FeedStream::Quotes, &symbol);
+                let quote = match spread_quote(&v[1], symbol) {
+                    Ok(quote) => quote,
+                    Err(e) => {
+                        warn!("Failed to parse Kraken spread message: {:?}, error: {}", text, e);
+                        continue;
+                    }
+                };
+
+                if let Err(e) = sender.send(quote.clone()).await {
+                    error!("[KRAKEN] Failed to send quote: {}", e);
+                    return Ok(());
+                }
+                let _ = self.dashboard_tx.send(DashboardEvent::Quote(quote));
+            }
+        };
+
+        retry(backoff, operation).await
+    }
 }
 
 #[async_trait]
 impl ExecutionGateway for KrakenConnector {
-    async fn send_order(&self, order: Order) -> Result<String, Box<dyn Error + Send + Sync>> {
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError> {
         let executed_at = Utc::now();
 
         if let Some(tick) = &order.triggering_tick {
@@ -160,7 +1422,7 @@ impl ExecutionGateway for KrakenConnector {
                 "Tick-to-trade latency"
             );
             let _ = self.dashboard_tx.send(DashboardEvent::LatencyUpdate {
-                order_id: order.id.clone(),
+                order_id: order.id,
                 latency_us: latency_us as u64,
             });
         }
@@ -174,95 +1436,318 @@ impl ExecutionGateway for KrakenConnector {
             "Executing order"
         );
 
-        let client = reqwest::Client::new();
-        let nonce = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)?
-            .as_millis()
-            .to_string();
-
         let mut params = vec![
-            ("nonce", nonce.clone()),
-            ("ordertype", order.order_type.to_string()),
+            ("ordertype", order.order_type.to_string().to_lowercase()),
             ("type", order.side.to_string().to_lowercase()),
             ("volume", order.amount.to_string()),
-            ("pair", order.symbol.clone()),
+            ("pair", self.instruments.venue_symbol(MarketDataSource::Kraken, &order.symbol)),
+            ("cl_ord_id", order.id.to_string()),
         ];
         if let Some(price) = order.price {
             params.push(("price", price.to_string()));
         }
 
-        let body = params
-            .iter()
-            .map(|(k, v)| format!("{}={}", k, v))
-            .collect::<Vec<String>>()
-            .join("&");
-
-        let path = "/0/private/AddOrder".to_string();
+        let result = self.private_request("/0/private/AddOrder", params).await?;
+        let txid = result["txid"]
+            .as_array()
+            .and_then(|txids| txids.first())
+            .and_then(|txid| txid.as_str())
+            .map(str::to_string);
 
-        let signature = self.get_kraken_signature(&path, &nonce, &body)?;
+        // Fills arrive on the private feed; release any that beat this response.
+        if let Some(txid) = &txid {
+            self.register_txid(txid, order.id).await;
+        }
 
-        let res = client
-            .post("https://api.kraken.com/0/private/AddOrder")
-            .header("API-Key", &self.api_key)
-            .header("API-Sign", signature)
-            .form(&params)
-            .send()
-            .await?;
+        txid.ok_or_else(|| anyhow!("Could not extract transaction ID from Kraken response").into())
+    }
 
-        let response_text = res.text().await?;
-        let response_json: serde_json::Value = serde_json::from_str(&response_text)?;
+    async fn cancel_order(&self, _symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let txid = self.txid(order_id).await?;
+        self.private_request("/0/private/CancelOrder", vec![("txid", txid.clone())]).await?;
+        self.query_orders(&[txid])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow!("Kraken did not report canceled order {}", order_id).into())
+    }
 
-        if let Some(error) = response_json["error"].as_array() {
-            if !error.is_empty() {
-                return Err(format!("Kraken API Error: {:?}", error).into());
+    async fn cancel_all_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let txids: Vec<String> = self
+            .open_orders(symbol.clone())
+            .await?
+            .into_iter()
+            .map(|report| report.exchange_order_id)
+            .collect();
+        if txids.is_empty() {
+            return Ok(Vec::new());
+        }
+        match symbol {
+            None => {
+                self.private_request("/0/private/CancelAll", Vec::new()).await?;
+            }
+            Some(_) => {
+                for txid in &txids {
+                    self.private_request("/0/private/CancelOrder", vec![("txid", txid.clone())]).await?;
+                }
             }
         }
+        self.query_orders(&txids).await
+    }
 
-        let price = order.price.unwrap_or_else(|| {
-            warn!("Order price is None, using 1.0 as a mock price");
-            1.0
-        });
-
-        let fill = Fill {
-            order_id: order.id,
-            symbol: order.symbol.clone(),
-            side: order.side,
-            price,
-            quantity: order.amount,
-            source: MarketDataSource::Kraken,
-            executed_at,
+    /// Uses `EditOrder`, which keeps the side and order type of the
+    /// original; only the volume and price of `replacement` apply.
+    async fn amend_order(&self, order_id: Uuid, replacement: Order) -> Result<Amendment, ExecutionError> {
+        info!(order_id = %order_id, replacement_id = %replacement.id, price = ?replacement.price, amount = %replacement.amount, "Amending order");
+        let original_txid = self.txid(order_id).await?;
+        let mut params = vec![
+            ("txid", original_txid.clone()),
+            ("pair", self.instruments.venue_symbol(MarketDataSource::Kraken, &replacement.symbol)),
+            ("volume", replacement.amount.to_string()),
+        ];
+        if let Some(price) = replacement.price {
+            params.push(("price", price.to_string()));
+        }
+        let result = self.private_request("/0/private/EditOrder", params).await?;
+        let txid = result["txid"]
+            .as_str()
+            .ok_or_else(|| anyhow!("Could not extract transaction ID from Kraken response"))?
+            .to_string();
+        self.register_txid(&txid, replacement.id).await;
+        let mut reports = self.query_orders(&[original_txid.clone(), txid.clone()]).await?;
+        let mut take = |txid: &str, order_id: Uuid| {
+            reports
+                .iter()
+                .position(|report| report.exchange_order_id == txid)
+                .map(|index| reports.swap_remove(index))
+                .ok_or_else(|| ExecutionError::Other(anyhow!("Kraken did not report amended order {}", order_id)))
         };
+        Ok(Amendment { canceled: take(&original_txid, order_id)?, replacement: take(&txid, replacement.id)? })
+    }
 
-        if let Some(sender) = &self.fill_sender {
-            if let Err(e) = sender.send(fill.clone()).await {
-                error!("Failed to send fill: {}", e);
-            }
+    async fn query_order(&self, _symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let txid = self.txid(order_id).await?;
+        self.query_orders(&[txid])
+            .await?
+            .pop()
+            .ok_or_else(|| ExecutionError::UnknownOrder(format!("Unknown Kraken order {}", order_id)))
+    }
+
+    async fn open_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let result = self.private_request("/0/private/OpenOrders", Vec::new()).await?;
+        let mut reports = self.order_reports(&result["open"]);
+        if let Some(symbol) = symbol {
+            let pair = self.instruments.venue_symbol(MarketDataSource::Kraken, &symbol);
+            let symbol = self.instruments.canonical(MarketDataSource::Kraken, &pair);
+            reports.retain(|report| report.symbol == symbol);
         }
+        Ok(reports)
+    }
+} 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_private_errors_are_classified() {
+        let kind = |error: &str| kraken_error(&[json!(error)]);
+        assert!(matches!(kind("EOrder:Insufficient funds"), ExecutionError::InsufficientFunds(_)));
+        assert!(matches!(kind("EOrder:Unknown order"), ExecutionError::UnknownOrder(_)));
+        assert!(matches!(kind("EAPI:Rate limit exceeded"), ExecutionError::RateLimited(_)));
+        assert!(matches!(kind("EAPI:Invalid signature"), ExecutionError::Unauthorized(_)));
+        assert!(matches!(kind("EService:Unavailable"), ExecutionError::Network(_)));
+        assert!(matches!(kind("EService:Deadline elapsed"), ExecutionError::Timeout(_)));
+        let error = kind("EOrder:Order minimum not met");
+        assert!(matches!(error, ExecutionError::Rejected(_)));
+        assert!(error.to_string().contains("EOrder:Order minimum not met"), "{}", error);
+    }
+
+    #[test]
+    fn test_checksum_field_strips_point_and_leading_zeros() {
+        assert_eq!(checksum_field(45285.2, 1), "452852");
+        assert_eq!(checksum_field(0.001, 8), "100000");
+        assert_eq!(checksum_field(0.5, 3), "500");
+    }
+
+    #[test]
+    fn test_book_checksum_orders_asks_before_bids() {
+        let mut book = OrderBook::new(MarketDataSource::Kraken, "BTC/USD".to_string());
+        book.update_bid(100.0, 1.0);
+        book.update_bid(99.5, 2.0);
+        book.update_ask(100.5, 0.25);
+
+        let expected = crc32fast::hash(b"1005250100010009952000");
+        assert_eq!(kraken_book_checksum(&book, 1, 3), expected);
+
+        book.update_bid(99.5, 0.0);
+        assert_ne!(kraken_book_checksum(&book, 1, 3), expected);
+    }
+
+    #[test]
+    fn test_spread_message_becomes_quote() {
+        let message: serde_json::Value = serde_json::from_str(
+            r#"[0, ["5698.40000", "5700.00000", "1542057299.545897", "1.01234567", "0.98765432"], "spread", "XBT/USD"]"#,
+        ).unwrap();
+        let quote = spread_quote(&message[1], "BTC/USD".to_string()).unwrap();
+        assert_eq!((quote.bid, quote.bid_size), (5698.4, 1.01234567));
+        assert_eq!((quote.ask, quote.ask_size), (5700.0, 0.98765432));
+        assert_eq!(quote.exchange_ts.unwrap().timestamp_micros(), 1542057299545897);
+        assert!((quote.spread() - 1.6).abs() < 1e-9);
+    }
 
-        let trade = crate::models::Trade {
-            id: Uuid::new_v4(),
-            order_id: fill.order_id,
-            symbol: fill.symbol,
-            side: fill.side,
-            amount: fill.quantity,
-            price: fill.price,
-            source: fill.source,
-            executed_at: fill.executed_at,
+    #[test]
+    fn test_trade_message_parses_without_value_tree() {
+        let text = r#"[0, [["5541.20000", "0.15850568", "1534614057.321597", "s", "l", ""], ["6060.00000", "0.02455000", "1534614057.324998", "b", "l", "", 12]], "trade", "XBT/USD"]"#;
+        let KrakenTradeMessage(_, trades, _, pair) = serde_json::from_str(text).unwrap();
+        assert_eq!(pair, "XBT/USD");
+        assert_eq!(trades[0], KrakenTrade { price: 5541.2, volume: 0.15850568, time: 1534614057.321597 });
+        assert_eq!(trades[1].price, 6060.0);
+        assert!(serde_json::from_str::<KrakenTradeMessage>(r#"{"event":"heartbeat"}"#).is_err());
+    }
+
+    #[test]
+    fn test_v2_messages_are_read_by_channel() {
+        let text = r#"{"channel":"book","type":"snapshot","data":[{"symbol":"BTC/USD","bids":[{"price":45283.5,"qty":0.1}],"asks":[{"price":45285.2,"qty":0.001}],"checksum":2439117997}]}"#;
+        let KrakenV2Message::Book { snapshot, updates } = serde_json::from_str(text).unwrap() else {
+            panic!("not a book message");
         };
+        assert!(snapshot);
+        assert!(matches!(updates[0].symbol, Cow::Borrowed("BTC/USD")));
+        assert_eq!((updates[0].bids[0].price, updates[0].asks[0].qty, updates[0].checksum), (45283.5, 0.001, 2439117997));
 
-        if let Err(e) = self.db_manager.save_trade(&trade).await {
-            error!("Failed to save trade to DB: {}", e);
-        }
-        let _ = self.dashboard_tx.send(DashboardEvent::Trade(trade));
+        let text = r#"{"channel":"book","type":"update","data":[{"symbol":"BTC/USD","bids":[],"asks":[{"price":45285.2,"qty":0.0}],"checksum":1}]}"#;
+        assert!(matches!(serde_json::from_str(text).unwrap(), KrakenV2Message::Book { snapshot: false, .. }));
+        let text = r#"{"channel":"instrument","type":"snapshot","data":{"assets":[],"pairs":[{"symbol":"BTC/USD","price_precision":1,"qty_precision":8}]}}"#;
+        assert!(matches!(serde_json::from_str(text).unwrap(), KrakenV2Message::Instrument(data) if data.pairs[0].qty_precision == 8));
+        let text = r#"{"method":"subscribe","req_id":7,"result":{"channel":"book"},"success":true}"#;
+        assert!(matches!(serde_json::from_str(text).unwrap(), KrakenV2Message::Ack { req_id: 7, result: Ok(()) }));
+        let text = r#"{"error":"Currency pair not supported","method":"subscribe","req_id":8,"success":false}"#;
+        assert!(matches!(serde_json::from_str(text).unwrap(), KrakenV2Message::Ack { req_id: 8, result: Err(e) } if e == "Currency pair not supported"));
+        assert!(matches!(serde_json::from_str(r#"{"channel":"heartbeat"}"#).unwrap(), KrakenV2Message::Other));
+    }
 
-        self.settlement.send_order(&order).await?;
+    #[test]
+    fn test_book_depth_rounds_up_to_supported_value() {
+        assert_eq!(kraken_book_depth(5), 10);
+        assert_eq!(kraken_book_depth(20), 25);
+        assert_eq!(kraken_book_depth(5000), 1000);
+    }
 
-        if let Some(txid) = response_json["result"]["txid"].as_array() {
-            if !txid.is_empty() {
-                return Ok(txid[0].as_str().unwrap_or_default().to_string());
-            }
+    #[test]
+    fn test_own_trade_waits_for_its_txid() {
+        let message: serde_json::Value = serde_json::from_str(r#"[
+            [{"TDLH43-DVQXD-2KHVYY": {
+                "cost": "1000.00000", "fee": "1.60000", "margin": "0.00000",
+                "ordertxid": "OGTT3Y-C6I3P-XRI6HX", "ordertype": "market", "pair": "XBT/EUR",
+                "postxid": "OGTT3Y-C6I3P-XRI6HX", "price": "100000.00000",
+                "time": "1560516023.070651", "type": "sell", "vol": "0.01000000"
+            }}],
+            "ownTrades",
+            {"sequence": 2}
+        ]"#).unwrap();
+        let (trade_id, trade) = message[0][0].as_object().unwrap().iter().next().unwrap();
+        let trade: KrakenOwnTrade = serde_json::from_value(trade.clone()).unwrap();
+        let fill = trade.fill(trade_id).unwrap();
+        assert_eq!(fill.side, OrderSide::Sell);
+        assert_eq!(fill.price, 100000.0);
+        assert_eq!(fill.quantity, 0.01);
+        assert_eq!(fill.commission, 1.6);
+        assert_eq!(fill.commission_asset.as_deref(), Some("EUR"));
+        assert_eq!(fill.trade_id.as_deref(), Some("TDLH43-DVQXD-2KHVYY"));
+
+        let mut order_ids = KrakenOrderIds::default();
+        assert!(order_ids.resolve(&trade.ordertxid, fill.clone()).is_none());
+        let order_id = Uuid::new_v4();
+        let released = order_ids.register(&trade.ordertxid, order_id);
+        assert_eq!(released.len(), 1);
+        assert_eq!(released[0].order_id, order_id);
+        assert_eq!(order_ids.resolve(&trade.ordertxid, fill).unwrap().order_id, order_id);
+    }
+
+    #[test]
+    fn test_unclaimed_fills_and_closed_orders_are_forgotten() {
+        let fill = Fill {
+            order_id: Uuid::nil(),
+            symbol: "BTC/EUR".to_string(),
+            side: OrderSide::Buy,
+            price: 100000.0,
+            quantity: 0.01,
+            source: MarketDataSource::Kraken,
+            executed_at: Utc::now(),
+            commission: 0.0,
+            commission_asset: None,
+            trade_id: Some("TDLH43-DVQXD-2KHVYY".to_string()),
+        };
+        let mut order_ids = KrakenOrderIds { unmatched_ttl: Duration::ZERO, retention: Duration::ZERO, ..Default::default() };
+        // A fill from another session, or from before a restart, is never claimed.
+        assert!(order_ids.resolve("OTHER-SESSION", fill.clone()).is_none());
+        let order_id = Uuid::new_v4();
+        assert!(order_ids.register("OGTT3Y-C6I3P-XRI6HX", order_id).is_empty());
+        assert!(order_ids.unmatched.is_empty());
+
+        order_ids.close("OGTT3Y-C6I3P-XRI6HX");
+        order_ids.expire(Instant::now());
+        assert_eq!((order_ids.get("OGTT3Y-C6I3P-XRI6HX"), order_ids.txid(order_id)), (None, None));
+        assert!(order_ids.closed.is_empty());
+
+        let mut order_ids = KrakenOrderIds::default();
+        for n in 0..KRAKEN_MAX_UNMATCHED + 10 {
+            order_ids.resolve(&format!("O{}", n), fill.clone());
         }
+        assert_eq!(order_ids.unmatched.len(), KRAKEN_MAX_UNMATCHED);
+    }
 
-        Err("Could not extract transaction ID from Kraken response".into())
+    #[test]
+    fn test_open_order_status_mapping() {
+        let status = |json: &str| serde_json::from_str::<KrakenOpenOrder>(json).unwrap().status();
+        assert_eq!(status(r#"{"status": "pending", "vol": "1.0", "vol_exec": "0.00000000"}"#), Some(OrderStatus::New));
+        assert_eq!(status(r#"{"status": "open", "vol_exec": "0.50000000"}"#), Some(OrderStatus::PartiallyFilled));
+        assert_eq!(status(r#"{"status": "closed", "userref": 0}"#), Some(OrderStatus::Filled));
+        assert_eq!(status(r#"{"status": "canceled", "reason": "User requested"}"#), Some(OrderStatus::Canceled));
+        assert_eq!(status(r#"{"status": "expired"}"#), Some(OrderStatus::Expired));
+        assert_eq!(status(r#"{"vol_exec": "0.50000000", "avg_price": "100.0"}"#), None);
     }
-} 
\ No newline at end of file
+
+    #[test]
+    fn test_order_info_becomes_report() {
+        let order_id = Uuid::new_v4();
+        let text = format!(r#"{{
+            "refid": null, "userref": 0, "cl_ord_id": "{}", "status": "open", "opentm": 1688666559.8974,
+            "starttm": 0, "expiretm": 0,
+            "descr": {{"pair": "XBTUSD", "type": "buy", "ordertype": "limit", "price": "30010.0",
+                       "price2": "0", "leverage": "none", "order": "buy 1.25000000 XBTUSD @ limit 30010.0", "close": ""}},
+            "vol": "1.25000000", "vol_exec": "0.37500000", "cost": "11253.7", "fee": "0.00000",
+            "price": "30010.0", "stopprice": "0.00000", "limitprice": "0.00000", "misc": "", "oflags": "fciq"
+        }}"#, order_id);
+        let info: KrakenOrderInfo = serde_json::from_str(&text).unwrap();
+        let report = info.report("OHYO67-6LP66-HMQ437").unwrap();
+        assert_eq!(report.order_id, Some(order_id));
+        assert_eq!(report.exchange_order_id, "OHYO67-6LP66-HMQ437");
+        assert_eq!((report.symbol.as_str(), report.side, report.order_type.clone()), ("XBTUSD", OrderSide::Buy, OrderType::Limit));
+        assert_eq!(report.status, OrderStatus::PartiallyFilled);
+        assert_eq!((report.amount, report.filled), (1.25, 0.375));
+        assert_eq!((report.price, report.avg_fill_price), (Some(30010.0), Some(30010.0)));
+        assert_eq!(report.updated_at.timestamp_micros(), 1688666559897400);
+    }
+
+    #[test]
+    fn test_asset_pairs_map_xbt_to_btc() {
+        let text = r#"{"error": [], "result": {
+            "XXBTZUSD": {"altname": "XBTUSD", "wsname": "XBT/USD", "base": "XXBT", "quote": "ZUSD",
+                         "pair_decimals": 1, "lot_decimals": 8, "ordermin": "0.0001", "costmin": "0.5",
+                         "tick_size": "0.1", "status": "online"},
+            "XXBTZUSD.d": {"altname": "XBTUSD.d", "base": "XXBT", "quote": "ZUSD",
+                           "pair_decimals": 1, "lot_decimals": 8}
+        }}"#;
+
+        let instruments = parse_asset_pairs(text).unwrap();
+        assert_eq!(instruments.len(), 1);
+        let btc_usd = &instruments[0];
+        assert_eq!(btc_usd.symbol, "BTC/USD");
+        assert_eq!(btc_usd.base, "BTC");
+        assert_eq!(btc_usd.venue_symbol, "XBTUSD");
+        assert_eq!(btc_usd.ws_symbol, "XBT/USD");
+        assert_eq!(btc_usd.tick_size, 0.1);
+        assert_eq!(btc_usd.lot_size, 0.00000001);
+        assert_eq!(btc_usd.min_notional, 0.5);
+    }
+}
@@ -1,22 +1,314 @@
+use crate::connectors::replay::{ReplayClock, ReplaySpeed};
 use crate::connectors::Connector;
 use crate::models::{MarketDataSource, Tick};
 use anyhow::Result;
 use async_trait::async_trait;
-use chrono::Utc;
-use rand::{Rng, SeedableRng};
+use chrono::{DateTime, Utc};
 use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Deserialize;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::sync::Arc;
 use tokio::sync::mpsc;
-use tokio::time::{interval, Duration};
+use tokio::time::{sleep_until, Duration};
+
+/// How a symbol's fair price evolves. Rates and volatilities are per second,
+/// so a volatility of `1e-4` moves the price roughly 1bp per √s.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(tag = "model", rename_all = "snake_case")]
+pub enum PriceModel {
+    /// Geometric Brownian motion.
+    Gbm { drift: f64, volatility: f64 },
+    /// GBM plus Poisson-arriving jumps whose log size is normal.
+    JumpDiffusion {
+        drift: f64,
+        volatility: f64,
+        /// Expected jumps per second.
+        jump_intensity: f64,
+        jump_mean: f64,
+        jump_std: f64,
+    },
+    /// Ornstein-Uhlenbeck on log price, pulled towards `mean`.
+    MeanReversion { mean: f64, speed: f64, volatility: f64 },
+}
+
+impl Default for PriceModel {
+    fn default() -> Self {
+        PriceModel::Gbm { drift: 0.0, volatility: 1e-4 }
+    }
+}
+
+impl PriceModel {
+    /// Advances `log_price` by `dt` seconds.
+    fn step(&self, log_price: f64, dt: f64, rng: &mut StdRng) -> f64 {
+        match *self {
+            PriceModel::Gbm { drift, volatility } => {
+                log_price + (drift - volatility * volatility / 2.0) * dt + volatility * dt.sqrt() * standard_normal(rng)
+            }
+            PriceModel::JumpDiffusion { drift, volatility, jump_intensity, jump_mean, jump_std } => {
+                let diffusion = PriceModel::Gbm { drift, volatility }.step(log_price, dt, rng);
+                if rng.gen::<f64>() < 1.0 - (-jump_intensity * dt).exp() {
+                    diffusion + jump_mean + jump_std * standard_normal(rng)
+                } else {
+                    diffusion
+                }
+            }
+            PriceModel::MeanReversion { mean, speed, volatility } => {
+                log_price + speed * (mean.ln() - log_price) * dt + volatility * dt.sqrt() * standard_normal(rng)
+            }
+        }
+    }
+}
+
+/// Box-Muller; one draw per call keeps the stream easy to reason about.
+fn standard_normal(rng: &mut StdRng) -> f64 {
+    let u1: f64 = 1.0 - rng.gen::<f64>();
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+/// Generation parameters for one symbol.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct SymbolModel {
+    pub symbol: String,
+    pub initial_price: f64,
+    #[serde(flatten, default)]
+    pub model: PriceModel,
+    /// Must be positive and finite; anything else is rejected on load.
+    #[serde(default = "default_ticks_per_sec", deserialize_with = "positive_rate")]
+    pub ticks_per_sec: f64,
+    /// Trade sizes are exponentially distributed around this.
+    #[serde(default = "default_mean_volume")]
+    pub mean_volume: f64,
+}
+
+fn default_ticks_per_sec() -> f64 {
+    10.0
+}
+
+fn default_mean_volume() -> f64 {
+    1.0
+}
+
+fn positive_rate<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<f64, D::Error> {
+    let rate = f64::deserialize(deserializer)?;
+    if rate > 0.0 && rate.is_finite() {
+        Ok(rate)
+    } else {
+        Err(serde::de::Error::custom(format!("ticks_per_sec must be positive, got {}", rate)))
+    }
+}
+
+impl SymbolModel {
+    pub fn new(symbol: &str, initial_price: f64, model: PriceModel) -> Self {
+        Self {
+            symbol: symbol.to_string(),
+            initial_price,
+            model,
+            ticks_per_sec: default_ticks_per_sec(),
+            mean_volume: default_mean_volume(),
+        }
+    }
+}
+
+/// One venue's view of the shared fair price.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct VenueView {
+    pub source: MarketDataSource,
+    /// How far this venue trails the fair price.
+    #[serde(default)]
+    pub lag_ms: u64,
+    /// Persistent premium over the fair price; negative for a discount.
+    #[serde(default)]
+    pub premium_bps: f64,
+}
+
+impl VenueView {
+    pub fn new(source: MarketDataSource) -> Self {
+        Self { source, lag_ms: 0, premium_bps: 0.0 }
+    }
+}
+
+struct SymbolState {
+    model: SymbolModel,
+    log_price: f64,
+    next_at: Duration,
+}
+
+/// A venue tick waiting for its (possibly lagged) publication time.
+struct Pending {
+    at: Duration,
+    seq: u64,
+    tick: Tick,
+}
+
+impl PartialEq for Pending {
+    fn eq(&self, other: &Self) -> bool {
+        (self.at, self.seq) == (other.at, other.seq)
+    }
+}
+
+impl Eq for Pending {}
+
+impl PartialOrd for Pending {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Pending {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        (self.at, self.seq).cmp(&(other.at, other.seq))
+    }
+}
+
+/// Deterministic tick stream on a simulated clock. Every symbol follows its
+/// own fair price; each venue publishes that price after its lag, shifted by
+/// its premium, so the same seed always yields the same sequence.
+pub struct MarketSimulator {
+    rng: StdRng,
+    symbols: Vec<SymbolState>,
+    venues: Vec<VenueView>,
+    start: DateTime<Utc>,
+    pending: BinaryHeap<Reverse<Pending>>,
+    seq: u64,
+}
 
+impl MarketSimulator {
+    /// Panics if a model's `ticks_per_sec` is not positive and finite.
+    pub fn new(seed: u64, symbols: Vec<SymbolModel>, venues: Vec<VenueView>, start: DateTime<Utc>) -> Self {
+        for model in &symbols {
+            assert!(
+                model.ticks_per_sec > 0.0 && model.ticks_per_sec.is_finite(),
+                "{}: ticks_per_sec must be positive, got {}",
+                model.symbol,
+                model.ticks_per_sec
+            );
+        }
+        let symbols = symbols
+            .into_iter()
+            .map(|model| SymbolState { log_price: model.initial_price.ln(), model, next_at: Duration::ZERO })
+            .collect();
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+            symbols,
+            venues,
+            start,
+            pending: BinaryHeap::new(),
+            seq: 0,
+        }
+    }
+
+    /// Next tick in publication order, with `received_at` on the simulated clock.
+    pub fn next_tick(&mut self) -> Option<Tick> {
+        loop {
+            let next_step = self.symbols.iter().map(|s| s.next_at).min()?;
+            match self.pending.peek() {
+                Some(Reverse(pending)) if pending.at <= next_step => {
+                    return self.pending.pop().map(|Reverse(pending)| pending.tick);
+                }
+                _ => self.step(next_step),
+            }
+        }
+    }
+
+    /// Moves every symbol due at `at` and queues a tick per venue.
+    fn step(&mut self, at: Duration) {
+        for index in 0..self.symbols.len() {
+            if self.symbols[index].next_at != at {
+                continue;
+            }
+            let state = &mut self.symbols[index];
+            let dt = 1.0 / state.model.ticks_per_sec;
+            state.log_price = state.model.model.step(state.log_price, dt, &mut self.rng);
+            state.next_at = at + Duration::from_secs_f64(dt);
+
+            let fair = state.log_price.exp();
+            let symbol = state.model.symbol.clone();
+            let volume = -state.model.mean_volume * (1.0 - self.rng.gen::<f64>()).ln();
+            for venue in &self.venues {
+                let published = at + Duration::from_millis(venue.lag_ms);
+                self.seq += 1;
+                self.pending.push(Reverse(Pending {
+                    at: published,
+                    seq: self.seq,
+                    tick: Tick {
+                        source: venue.source,
+                        symbol: symbol.clone(),
+                        price: fair * (1.0 + venue.premium_bps / 10_000.0),
+                        volume,
+                        received_at: self.start + published,
+                        exchange_ts: None,
+                        trade_id: None,
+                    },
+                }));
+            }
+        }
+    }
+}
+
+/// Synthetic feed for running strategies without an exchange. Defaults to a
+/// single venue at 10 ticks per second; configure models, venues and pacing
+/// with the builder methods.
 pub struct MockDataConnector {
     source: MarketDataSource,
     base_price: f64,
+    seed: u64,
+    models: Vec<SymbolModel>,
+    venues: Vec<VenueView>,
+    speed: ReplaySpeed,
 }
 
 impl MockDataConnector {
     pub fn new(source: MarketDataSource, base_price: f64) -> Self {
-        Self { source, base_price }
+        Self {
+            source,
+            base_price,
+            seed: 0,
+            models: Vec::new(),
+            venues: vec![VenueView::new(source)],
+            speed: ReplaySpeed::Original,
+        }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    /// Parameters for one symbol; subscribed symbols without a model get a
+    /// default GBM starting at the base price.
+    pub fn with_symbol(mut self, model: SymbolModel) -> Self {
+        self.models.retain(|m| m.symbol != model.symbol);
+        self.models.push(model);
+        self
+    }
+
+    /// Replaces the single default venue, e.g. with a leading Binance and a
+    /// lagging Kraken view of the same asset.
+    pub fn with_venues(mut self, venues: Vec<VenueView>) -> Self {
+        self.venues = venues;
+        self
+    }
+
+    pub fn with_speed(mut self, speed: ReplaySpeed) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    fn simulator(&self, symbols: &[String], start: DateTime<Utc>) -> MarketSimulator {
+        let models = symbols
+            .iter()
+            .map(|symbol| {
+                self.models
+                    .iter()
+                    .find(|m| &m.symbol == symbol)
+                    .cloned()
+                    .unwrap_or_else(|| SymbolModel::new(symbol, self.base_price, PriceModel::default()))
+            })
+            .collect();
+        MarketSimulator::new(self.seed, models, self.venues.clone(), start)
     }
 }
 
@@ -27,34 +319,108 @@ impl Connector for MockDataConnector {
         symbols: &[String],
         sender: mpsc::Sender<Tick>,
     ) -> Result<()> {
-        let mut interval = interval(Duration::from_millis(100)); // 10 ticks per second
-        let mut rng = StdRng::from_entropy();
-        let mut current_price = self.base_price;
+        let mut simulator = self.simulator(symbols, Utc::now());
+        let mut clock = ReplayClock::new(self.speed);
 
-        loop {
-            interval.tick().await;
-            
-            // Simulate price movement with random walk
-            let change_percent = rng.gen_range(-0.001..0.001); // ±0.1% change
-            current_price *= 1.0 + change_percent;
-            
-            for symbol in symbols {
-                let tick = Tick {
-                    source: self.source,
-                    symbol: symbol.clone(),
-                    price: current_price,
-                    volume: rng.gen_range(0.1..10.0),
-                    received_at: Utc::now(),
-                };
-
-                if sender.send(tick).await.is_err() {
-                    return Ok(()); // Channel closed, exit gracefully
-                }
+        while let Some(tick) = simulator.next_tick() {
+            if let Some(due) = clock.due(tick.received_at) {
+                sleep_until(due).await;
+            }
+            if sender.send(tick).await.is_err() {
+                return Ok(()); // Channel closed, exit gracefully
             }
         }
+        Ok(())
     }
 
     fn get_source(&self) -> MarketDataSource {
         self.source
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn start() -> DateTime<Utc> {
+        DateTime::from_timestamp_micros(1_700_000_000_000_000).unwrap()
+    }
+
+    fn ticks(simulator: &mut MarketSimulator, n: usize) -> Vec<Tick> {
+        (0..n).map(|_| simulator.next_tick().unwrap()).collect()
+    }
+
+    #[test]
+    fn test_same_seed_reproduces_the_market() {
+        let models = vec![
+            SymbolModel::new("BTC/USDT", 50_000.0, PriceModel::JumpDiffusion {
+                drift: 0.0,
+                volatility: 1e-3,
+                jump_intensity: 0.5,
+                jump_mean: 0.0,
+                jump_std: 0.01,
+            }),
+            SymbolModel { ticks_per_sec: 4.0, ..SymbolModel::new("ETH/USDT", 3_000.0, PriceModel::default()) },
+        ];
+        let venues = vec![VenueView::new(MarketDataSource::Binance)];
+
+        let a = ticks(&mut MarketSimulator::new(7, models.clone(), venues.clone(), start()), 200);
+        let b = ticks(&mut MarketSimulator::new(7, models.clone(), venues.clone(), start()), 200);
+        let c = ticks(&mut MarketSimulator::new(8, models, venues, start()), 200);
+
+        let key = |t: &Tick| (t.symbol.clone(), t.price.to_bits(), t.volume.to_bits(), t.received_at);
+        assert_eq!(a.iter().map(key).collect::<Vec<_>>(), b.iter().map(key).collect::<Vec<_>>());
+        assert_ne!(a.iter().map(key).collect::<Vec<_>>(), c.iter().map(key).collect::<Vec<_>>());
+        // Each symbol keeps its own tick rate.
+        let eth = a.iter().filter(|t| t.symbol == "ETH/USDT").count();
+        assert!((55..=59).contains(&eth), "{} ETH ticks", eth);
+    }
+
+    #[test]
+    fn test_lagging_venue_trails_the_leader() {
+        let mut simulator = MarketSimulator::new(
+            1,
+            vec![SymbolModel::new("BTC/USDT", 50_000.0, PriceModel::default())],
+            vec![
+                VenueView::new(MarketDataSource::Binance),
+                VenueView { lag_ms: 250, premium_bps: 10.0, ..VenueView::new(MarketDataSource::Kraken) },
+            ],
+            start(),
+        );
+        let all = ticks(&mut simulator, 100);
+        let (binance, kraken): (Vec<&Tick>, Vec<&Tick>) = all.iter().partition(|t| t.source == MarketDataSource::Binance);
+
+        // Kraken publishes Binance's price from 250ms earlier, 10bp richer.
+        for k in &kraken {
+            let lead = binance
+                .iter()
+                .find(|b| b.received_at == k.received_at - chrono::Duration::milliseconds(250))
+                .unwrap();
+            assert!((k.price / lead.price - 1.001).abs() < 1e-12);
+        }
+        assert!(all.windows(2).all(|w| w[0].received_at <= w[1].received_at));
+    }
+
+    #[test]
+    fn test_mean_reversion_pulls_towards_mean() {
+        let mut simulator = MarketSimulator::new(
+            3,
+            vec![SymbolModel::new("SOL/USDT", 150.0, PriceModel::MeanReversion { mean: 100.0, speed: 1.0, volatility: 1e-3 })],
+            vec![VenueView::new(MarketDataSource::Binance)],
+            start(),
+        );
+        let last = ticks(&mut simulator, 1_000).pop().unwrap();
+        assert!((last.price - 100.0).abs() < 1.0, "ended at {}", last.price);
+    }
+
+    #[test]
+    fn test_tick_rate_must_be_positive() {
+        let model: SymbolModel = serde_json::from_str(r#"{"symbol": "BTC/USDT", "initial_price": 50000.0, "model": "gbm", "drift": 0.0, "volatility": 1e-4}"#).unwrap();
+        assert_eq!(model.ticks_per_sec, 10.0);
+        for rate in ["0", "-2.5"] {
+            let text = format!(r#"{{"symbol": "BTC/USDT", "initial_price": 50000.0, "model": "gbm", "drift": 0.0, "volatility": 1e-4, "ticks_per_sec": {}}}"#, rate);
+            let err = serde_json::from_str::<SymbolModel>(&text).unwrap_err();
+            assert!(err.to_string().contains("ticks_per_sec must be positive"), "{}", err);
+        }
+    }
+}
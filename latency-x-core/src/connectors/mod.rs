@@ -1,13 +1,30 @@
 pub mod binance;
-// pub mod coinbase;
+pub mod binance_futures;
+pub mod coinbase;
+pub mod decimal;
 pub mod kraken;
+pub mod mock_data;
 pub mod pump;
+pub mod replay;
+pub mod sequence;
+pub mod subscriptions;
+pub mod watchdog;
 
-use crate::models::{Tick, MarketDataSource};
-use anyhow::Result;
+use crate::dashboard::events::DashboardEvent;
+use crate::models::{Fill, Tick, MarkPrice, MarketDataSource, OrderBook, Quote, Trade};
+use crate::persistence::db::DatabaseManager;
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
+use metrics::histogram;
+use std::collections::{HashSet, VecDeque};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::mpsc;
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::error;
+use uuid::Uuid;
 
 #[async_trait]
 pub trait Connector: Send + Sync {
@@ -18,4 +35,180 @@ pub trait Connector: Send + Sync {
     ) -> Result<()>;
 
     fn get_source(&self) -> MarketDataSource;
-} 
\ No newline at end of file
+
+    /// Starts streaming trades for more symbols on the running feed. They
+    /// stay subscribed across reconnects.
+    async fn add_symbols(&self, _symbols: &[String]) -> Result<()> {
+        Err(anyhow!("{} does not support runtime subscriptions", self.get_source()))
+    }
+
+    /// Stops streaming trades for symbols on the running feed.
+    async fn remove_symbols(&self, _symbols: &[String]) -> Result<()> {
+        Err(anyhow!("{} does not support runtime subscriptions", self.get_source()))
+    }
+}
+
+/// A venue that can maintain a local L2 book and publish it after every update.
+#[async_trait]
+pub trait OrderBookConnector: Send + Sync {
+    /// Streams books for `symbols`, each truncated to `depth` levels per side.
+    async fn subscribe_order_book(
+        self: Arc<Self>,
+        symbols: &[String],
+        depth: usize,
+        sender: mpsc::Sender<OrderBook>,
+    ) -> Result<()>;
+}
+
+/// A venue that streams its best bid and offer.
+#[async_trait]
+pub trait QuoteConnector: Send + Sync {
+    /// Streams a `Quote` for `symbols` whenever the top of book changes.
+    async fn subscribe_quotes(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<Quote>,
+    ) -> Result<()>;
+}
+
+/// A perpetual futures venue that streams mark price and funding.
+#[async_trait]
+pub trait MarkPriceConnector: Send + Sync {
+    async fn subscribe_mark_prices(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<MarkPrice>,
+    ) -> Result<()>;
+}
+
+/// Recently published trade ids, oldest dropped first.
+pub(crate) struct PublishedTrades {
+    ids: HashSet<String>,
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl PublishedTrades {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self { ids: HashSet::new(), order: VecDeque::new(), capacity }
+    }
+
+    /// Whether `id` is new.
+    pub(crate) fn insert(&mut self, id: String) -> bool {
+        if !self.ids.insert(id.clone()) {
+            return false;
+        }
+        self.order.push_back(id);
+        if self.order.len() > self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.ids.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Reconnect policy for feeds, which run for the life of the process. It
+/// never gives up, and starts again from the shortest delay after an attempt
+/// that got connected, so a feed that dropped after hours comes straight back.
+pub(crate) struct FeedBackoff {
+    backoff: ExponentialBackoff,
+    connected: Arc<AtomicBool>,
+}
+
+/// Lets a connect loop tell its `FeedBackoff` it got through.
+pub(crate) struct FeedConnection(Arc<AtomicBool>);
+
+impl FeedBackoff {
+    pub(crate) fn new() -> Self {
+        Self {
+            backoff: ExponentialBackoff { max_elapsed_time: None, ..ExponentialBackoff::default() },
+            connected: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub(crate) fn connection(&self) -> FeedConnection {
+        FeedConnection(self.connected.clone())
+    }
+}
+
+impl FeedConnection {
+    /// Called once the connection is up.
+    pub(crate) fn up(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Backoff for FeedBackoff {
+    fn reset(&mut self) {
+        self.backoff.reset();
+    }
+
+    fn next_backoff(&mut self) -> Option<Duration> {
+        if self.connected.swap(false, Ordering::Relaxed) {
+            self.backoff.reset();
+        }
+        self.backoff.next_backoff()
+    }
+}
+
+/// Exports exchange-to-receive latency for ticks that carry a venue timestamp.
+pub(crate) fn record_feed_latency(tick: &Tick, dashboard_tx: &broadcast::Sender<DashboardEvent>) {
+    let Some(latency_us) = tick.feed_latency_us() else {
+        return;
+    };
+    histogram!("feed_latency_us", "source" => tick.source.to_string()).record(latency_us as f64);
+    let _ = dashboard_tx.send(DashboardEvent::FeedLatency {
+        source: tick.source,
+        symbol: tick.symbol.clone(),
+        latency_us,
+    });
+}
+
+/// Hands an exchange fill to the risk manager and records it as a trade.
+pub(crate) async fn publish_fill(
+    fill: Fill,
+    fill_sender: Option<&mpsc::Sender<Fill>>,
+    db_manager: &DatabaseManager,
+    dashboard_tx: &broadcast::Sender<DashboardEvent>,
+) {
+    if let Some(sender) = fill_sender {
+        if let Err(e) = sender.send(fill.clone()).await {
+            error!("Failed to send fill: {}", e);
+        }
+    }
+
+    let trade = Trade {
+        id: Uuid::new_v4(),
+        order_id: fill.order_id,
+        symbol: fill.symbol,
+        side: fill.side,
+        amount: fill.quantity,
+        price: fill.price,
+        source: fill.source,
+        executed_at: fill.executed_at,
+    };
+    if let Err(e) = db_manager.save_trade(&trade).await {
+        error!("Failed to save trade to DB: {}", e);
+    }
+    let _ = dashboard_tx.send(DashboardEvent::Trade(trade));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feed_backoff_never_gives_up_and_restarts_after_a_connection() {
+        let mut backoff = FeedBackoff::new();
+        backoff.backoff.start_time -= Duration::from_secs(24 * 3600);
+        let first = backoff.next_backoff().unwrap();
+        for _ in 0..50 {
+            assert!(backoff.next_backoff().is_some());
+        }
+        assert!(backoff.next_backoff().unwrap() > first * 10);
+
+        backoff.connection().up();
+        assert!(backoff.next_backoff().unwrap() <= backoff.backoff.initial_interval.mul_f64(1.5));
+    }
+}
@@ -7,23 +7,87 @@ use solana_sdk::{
     system_program,
     sysvar::rent,
 };
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
+use solana_rpc_client_api::config::{RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_sdk::commitment_config::CommitmentConfig;
 use std::{error::Error, str::FromStr, sync::Arc};
-use crate::models::{Order, OrderSide};
-use crate::execution::ExecutionGateway;
+use crate::connectors::{Connector, FeedBackoff};
+use crate::dashboard::events::DashboardEvent;
+use crate::models::{MarketDataSource, Order, OrderSide, Tick};
+use crate::execution::{ExecutionError, ExecutionGateway};
+use anyhow::{anyhow, Result};
 use async_trait::async_trait;
+use backoff::future::retry;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
 use crate::config::SolanaConfig;
+use futures_util::StreamExt;
 use spl_associated_token_account::get_associated_token_address;
 use borsh::{BorshSerialize, BorshDeserialize};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{error, info};
 
 const PUMP_PROGRAM_ID: &str = "6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P";
 const TOKEN_PROGRAM_ID: &str = "TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA";
 const GLOBAL_ACCOUNT_SEED: &[u8] = b"global";
 const FEE_RECIPIENT_SEED: &[u8] = b"fee_recipient";
 const BONDING_CURVE_SEED: &[u8] = b"bonding-curve";
+const TRADE_EVENT_DISCRIMINATOR: [u8; 8] = [189, 219, 127, 211, 78, 230, 97, 238];
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+/// Pump.fun mints every token with six decimals.
+const TOKEN_UNITS: f64 = 1_000_000.0;
 
 pub struct PumpConnector {
     _rpc_client: Arc<RpcClient>,
     _signer: Keypair,
+    ws_url: String,
+    dashboard_tx: Option<broadcast::Sender<DashboardEvent>>,
+}
+
+/// The event the pump program logs for every buy and sell on a bonding curve.
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, PartialEq)]
+pub struct TradeEvent {
+    pub mint: Pubkey,
+    /// Lamports paid or received.
+    pub sol_amount: u64,
+    /// Token base units bought or sold.
+    pub token_amount: u64,
+    pub is_buy: bool,
+    pub user: Pubkey,
+    /// Unix seconds.
+    pub timestamp: i64,
+    pub virtual_sol_reserves: u64,
+    pub virtual_token_reserves: u64,
+}
+
+impl TradeEvent {
+    /// Decodes a `Program data:` log line. Any other line or event gives
+    /// `None`; fields newer program versions append are ignored.
+    pub fn from_log(line: &str) -> Option<Self> {
+        let data = general_purpose::STANDARD.decode(line.strip_prefix("Program data: ")?).ok()?;
+        let mut payload = data.strip_prefix(&TRADE_EVENT_DISCRIMINATOR[..])?;
+        Self::deserialize(&mut payload).ok()
+    }
+
+    /// SOL paid per whole token, or `None` for an empty trade.
+    pub fn price(&self) -> Option<f64> {
+        (self.token_amount > 0).then(|| {
+            (self.sol_amount as f64 / LAMPORTS_PER_SOL) / (self.token_amount as f64 / TOKEN_UNITS)
+        })
+    }
+
+    /// The trade as a tick on the mint, sized in whole tokens.
+    pub fn tick(&self) -> Option<Tick> {
+        Some(Tick {
+            source: MarketDataSource::PumpFun,
+            symbol: self.mint.to_string(),
+            price: self.price()?,
+            volume: self.token_amount as f64 / TOKEN_UNITS,
+            received_at: Utc::now(),
+            exchange_ts: DateTime::from_timestamp(self.timestamp, 0),
+            trade_id: None,
+        })
+    }
 }
 
 #[derive(BorshSerialize, BorshDeserialize, Debug)]
@@ -41,9 +105,16 @@ impl PumpConnector {
         Self {
             _rpc_client: rpc_client,
             _signer: signer,
+            ws_url: config.ws_url.clone(),
+            dashboard_tx: None,
         }
     }
 
+    pub fn with_dashboard(mut self, dashboard_tx: broadcast::Sender<DashboardEvent>) -> Self {
+        self.dashboard_tx = Some(dashboard_tx);
+        self
+    }
+
     fn create_buy_instruction(
         &self,
         mint_str: &str,
@@ -107,9 +178,9 @@ impl PumpConnector {
 
 #[async_trait]
 impl ExecutionGateway for PumpConnector {
-    async fn send_order(&self, order: Order) -> Result<String, Box<dyn Error + Send + Sync>> {
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError> {
         if order.side != OrderSide::Buy {
-            return Err("Only buy orders are supported for Pump.fun".into());
+            return Err(ExecutionError::Rejected("Only buy orders are supported for Pump.fun".to_string()));
         }
 
         let mint_str = order.symbol;
@@ -117,12 +188,16 @@ impl ExecutionGateway for PumpConnector {
         let max_sol_cost = (order.price.unwrap_or(0.0) * 1_000_000_000.0) as u64;
 
         if max_sol_cost == 0 {
-            return Err("Price must be set for buy orders to calculate max_sol_cost".into());
+            return Err(ExecutionError::Rejected("Price must be set for buy orders to calculate max_sol_cost".to_string()));
         }
 
         let instruction = self.create_buy_instruction(&mint_str, token_amount, max_sol_cost)?;
 
-        let recent_blockhash = self._rpc_client.get_latest_blockhash().await?;
+        let recent_blockhash = self
+            ._rpc_client
+            .get_latest_blockhash()
+            .await
+            .map_err(|e| ExecutionError::Network(e.to_string()))?;
 
         let mut transaction = Transaction::new_with_payer(
             &[instruction],
@@ -134,8 +209,122 @@ impl ExecutionGateway for PumpConnector {
         let signature = self
             ._rpc_client
             .send_and_confirm_transaction(&transaction)
-            .await?;
+            .await
+            .map_err(|e| ExecutionError::Timeout(e.to_string()))?;
 
         Ok(signature.to_string())
     }
-} 
\ No newline at end of file
+}
+
+#[async_trait]
+impl Connector for PumpConnector {
+    /// Streams trades on the given mints, or on every mint when `symbols` is
+    /// empty, decoded from the pump program's logs.
+    async fn subscribe(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<Tick>,
+    ) -> Result<()> {
+        let backoff = FeedBackoff::new();
+        let connection = backoff.connection();
+        let operation = || async {
+            let client = PubsubClient::new(&self.ws_url).await.map_err(|e| backoff::Error::transient(e.into()))?;
+            let (mut logs, unsubscribe) = client
+                .logs_subscribe(
+                    RpcTransactionLogsFilter::Mentions(vec![PUMP_PROGRAM_ID.to_string()]),
+                    RpcTransactionLogsConfig {
+                        commitment: Some(CommitmentConfig::processed()),
+                    },
+                )
+                .await
+                .map_err(|e| backoff::Error::transient(e.into()))?;
+            info!("[PUMP] Subscribed to logs for program: {}", PUMP_PROGRAM_ID);
+            connection.up();
+
+            while let Some(response) = logs.next().await {
+                // A failed transaction still logs the events it emitted before failing.
+                if response.value.err.is_some() {
+                    continue;
+                }
+                let ticks = response
+                    .value
+                    .logs
+                    .iter()
+                    .filter_map(|line| TradeEvent::from_log(line))
+                    .filter_map(|event| event.tick())
+                    .filter(|tick| symbols.is_empty() || symbols.contains(&tick.symbol));
+                for tick in ticks {
+                    // Block times are whole seconds, too coarse for feed latency.
+                    if let Err(e) = sender.send(tick.clone()).await {
+                        error!("[PUMP] Failed to send tick: {}", e);
+                        unsubscribe().await;
+                        return Ok(());
+                    }
+                    if let Some(dashboard_tx) = &self.dashboard_tx {
+                        let _ = dashboard_tx.send(DashboardEvent::Tick(tick));
+                    }
+                }
+            }
+
+            unsubscribe().await;
+            Err(backoff::Error::transient(anyhow!("Pump.fun log stream closed")))
+        };
+
+        retry(backoff, operation).await
+    }
+
+    fn get_source(&self) -> MarketDataSource {
+        MarketDataSource::PumpFun
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn trade() -> TradeEvent {
+        TradeEvent {
+            mint: Pubkey::new_unique(),
+            sol_amount: 500_000_000,
+            token_amount: 17_500_000_000_000,
+            is_buy: true,
+            user: Pubkey::new_unique(),
+            timestamp: 1_718_000_000,
+            virtual_sol_reserves: 30_500_000_000,
+            virtual_token_reserves: 1_055_000_000_000_000,
+        }
+    }
+
+    fn log_line(discriminator: [u8; 8], payload: &[u8]) -> String {
+        let mut data = discriminator.to_vec();
+        data.extend_from_slice(payload);
+        format!("Program data: {}", general_purpose::STANDARD.encode(data))
+    }
+
+    #[test]
+    fn test_trade_event_decodes_into_tick() {
+        let event = trade();
+        let mut payload = event.try_to_vec().unwrap();
+        // Fields appended by later program versions.
+        payload.extend_from_slice(&[7; 40]);
+
+        let decoded = TradeEvent::from_log(&log_line(TRADE_EVENT_DISCRIMINATOR, &payload)).unwrap();
+        assert_eq!(decoded, event);
+
+        let tick = decoded.tick().unwrap();
+        assert_eq!(tick.source, MarketDataSource::PumpFun);
+        assert_eq!(tick.symbol, event.mint.to_string());
+        assert_eq!(tick.volume, 17_500_000.0);
+        assert!((tick.price - 0.5 / 17_500_000.0).abs() < 1e-15);
+        assert_eq!(tick.exchange_ts.unwrap().timestamp(), 1_718_000_000);
+    }
+
+    #[test]
+    fn test_other_log_lines_are_skipped() {
+        let payload = trade().try_to_vec().unwrap();
+        let create_event = [84, 97, 40, 193, 72, 143, 120, 163];
+        assert!(TradeEvent::from_log(&log_line(create_event, &payload)).is_none());
+        assert!(TradeEvent::from_log("Program log: Instruction: Buy").is_none());
+        assert!(TradeEvent::from_log(&log_line(TRADE_EVENT_DISCRIMINATOR, &payload[..20])).is_none());
+    }
+}
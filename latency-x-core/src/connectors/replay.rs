@@ -0,0 +1,304 @@
+use crate::connectors::{Connector, OrderBookConnector};
+use crate::models::{MarketDataSource, OrderBook, Tick};
+use crate::persistence::capture::{capture_files, CaptureReader, CaptureRecord};
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use tokio::sync::mpsc;
+use tokio::time::{sleep_until, Instant};
+use tracing::info;
+
+/// How fast recorded data is played back.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keep the recorded gaps between records.
+    Original,
+    /// Shrink the recorded gaps by this factor.
+    Accelerated(f64),
+    /// Emit records as fast as the consumer takes them.
+    AsFastAsPossible,
+}
+
+impl FromStr for ReplaySpeed {
+    type Err = String;
+
+    /// Accepts `original`, `max`, or a speed-up factor such as `10`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "original" => Ok(ReplaySpeed::Original),
+            "max" => Ok(ReplaySpeed::AsFastAsPossible),
+            factor => match factor.parse::<f64>() {
+                Ok(factor) if factor > 0.0 && factor.is_finite() => Ok(if factor == 1.0 {
+                    ReplaySpeed::Original
+                } else {
+                    ReplaySpeed::Accelerated(factor)
+                }),
+                _ => Err(format!("invalid replay speed {:?}, expected original, max or a positive factor", s)),
+            },
+        }
+    }
+}
+
+/// Anchors the capture timeline to the wall clock when playback starts.
+pub(crate) struct ReplayClock {
+    speed: ReplaySpeed,
+    started: Instant,
+    first: Option<DateTime<Utc>>,
+}
+
+impl ReplayClock {
+    pub(crate) fn new(speed: ReplaySpeed) -> Self {
+        Self::starting_at(speed, Instant::now())
+    }
+
+    /// A clock whose first record is due at `started`.
+    pub(crate) fn starting_at(speed: ReplaySpeed, started: Instant) -> Self {
+        Self { speed, started, first: None }
+    }
+
+    /// When a record captured at `at` is due.
+    pub(crate) fn due(&mut self, at: DateTime<Utc>) -> Option<Instant> {
+        let first = *self.first.get_or_insert(at);
+        let offset = (at - first).to_std().unwrap_or_default();
+        match self.speed {
+            ReplaySpeed::Original => Some(self.started + offset),
+            ReplaySpeed::Accelerated(factor) => Some(self.started + offset.div_f64(factor)),
+            ReplaySpeed::AsFastAsPossible => None,
+        }
+    }
+}
+
+/// Plays capture files back as live feeds. Records keep their recorded source
+/// and timestamps, so strategies see yesterday's market exactly as it arrived.
+/// Ticks and books share one timeline: both start when the first stream
+/// subscribes and are paced from the first record in the capture.
+pub struct ReplayConnector {
+    files: Vec<PathBuf>,
+    speed: ReplaySpeed,
+    started: OnceLock<Instant>,
+}
+
+impl ReplayConnector {
+    pub fn new(files: Vec<PathBuf>, speed: ReplaySpeed) -> Self {
+        Self { files, speed, started: OnceLock::new() }
+    }
+
+    /// Replays every capture file in `dir`, oldest first.
+    pub fn from_dir(dir: impl AsRef<Path>, speed: ReplaySpeed) -> Result<Self> {
+        let files = capture_files(&dir)?;
+        if files.is_empty() {
+            return Err(anyhow!("no capture files in {}", dir.as_ref().display()));
+        }
+        Ok(Self::new(files, speed))
+    }
+
+    /// Decompresses on a blocking thread so the reader never stalls the runtime.
+    fn read_records(&self) -> mpsc::Receiver<Result<CaptureRecord>> {
+        let (tx, rx) = mpsc::channel(1024);
+        let files = self.files.clone();
+        tokio::task::spawn_blocking(move || {
+            for file in files {
+                let reader = match CaptureReader::open(&file) {
+                    Ok(reader) => reader,
+                    Err(e) => {
+                        let _ = tx.blocking_send(Err(e));
+                        return;
+                    }
+                };
+                for record in reader {
+                    if tx.blocking_send(record).is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        rx
+    }
+
+    /// Feeds every record accepted by `emit` through the shared clock; stops at
+    /// the end of the capture or when `emit` reports the consumer is gone.
+    /// Skipped records still pass through the clock so every stream is anchored
+    /// to the same first record.
+    async fn replay<F, Fut>(&self, mut emit: F) -> Result<u64>
+    where
+        F: FnMut(CaptureRecord) -> Option<Fut>,
+        Fut: std::future::Future<Output = bool>,
+    {
+        let mut records = self.read_records();
+        let started = *self.started.get_or_init(Instant::now);
+        let mut clock = ReplayClock::starting_at(self.speed, started);
+        let mut replayed = 0;
+        while let Some(record) = records.recv().await {
+            let record = record?;
+            let due = clock.due(record.received_at());
+            let Some(send) = emit(record) else {
+                continue;
+            };
+            if let Some(due) = due {
+                sleep_until(due).await;
+            }
+            if !send.await {
+                break;
+            }
+            replayed += 1;
+        }
+        Ok(replayed)
+    }
+}
+
+fn wanted(symbols: &[String], symbol: &str) -> bool {
+    symbols.is_empty() || symbols.iter().any(|s| s == symbol)
+}
+
+#[async_trait]
+impl Connector for ReplayConnector {
+    /// Emits recorded ticks for `symbols`, or for every symbol when empty.
+    async fn subscribe(
+        self: Arc<Self>,
+        symbols: &[String],
+        sender: mpsc::Sender<Tick>,
+    ) -> Result<()> {
+        let replayed = self
+            .replay(|record| match record {
+                CaptureRecord::Tick(tick) if wanted(symbols, &tick.symbol) => {
+                    let sender = sender.clone();
+                    Some(async move { sender.send(tick).await.is_ok() })
+                }
+                _ => None,
+            })
+            .await?;
+        info!(ticks = replayed, "Tick replay finished");
+        Ok(())
+    }
+
+    fn get_source(&self) -> MarketDataSource {
+        MarketDataSource::Replay
+    }
+}
+
+#[async_trait]
+impl OrderBookConnector for ReplayConnector {
+    async fn subscribe_order_book(
+        self: Arc<Self>,
+        symbols: &[String],
+        depth: usize,
+        sender: mpsc::Sender<OrderBook>,
+    ) -> Result<()> {
+        let replayed = self
+            .replay(|record| match record {
+                CaptureRecord::Book(book) if wanted(symbols, &book.symbol) => {
+                    let sender = sender.clone();
+                    Some(async move { sender.send(book.truncated(depth)).await.is_ok() })
+                }
+                _ => None,
+            })
+            .await?;
+        info!(books = replayed, "Order book replay finished");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::persistence::capture::CaptureWriter;
+    use tokio::time::Duration;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_replay_speed_parsing() {
+        assert_eq!("original".parse::<ReplaySpeed>(), Ok(ReplaySpeed::Original));
+        assert_eq!("1".parse::<ReplaySpeed>(), Ok(ReplaySpeed::Original));
+        assert_eq!("max".parse::<ReplaySpeed>(), Ok(ReplaySpeed::AsFastAsPossible));
+        assert_eq!("25".parse::<ReplaySpeed>(), Ok(ReplaySpeed::Accelerated(25.0)));
+        assert!("0".parse::<ReplaySpeed>().is_err());
+        assert!("fast".parse::<ReplaySpeed>().is_err());
+    }
+
+    #[test]
+    fn test_replay_clock_scales_recorded_gaps() {
+        let t0 = DateTime::from_timestamp_micros(1_700_000_000_000_000).unwrap();
+        let t1 = t0 + chrono::Duration::milliseconds(500);
+
+        let mut clock = ReplayClock::new(ReplaySpeed::Accelerated(10.0));
+        let start = clock.due(t0).unwrap();
+        assert_eq!(clock.due(t1).unwrap() - start, Duration::from_millis(50));
+        // Out-of-order records are due immediately rather than in the past.
+        assert_eq!(clock.due(t0 - chrono::Duration::seconds(1)).unwrap(), start);
+
+        let mut clock = ReplayClock::new(ReplaySpeed::AsFastAsPossible);
+        assert_eq!(clock.due(t1), None);
+    }
+
+    #[tokio::test]
+    async fn test_replay_emits_recorded_ticks_in_order() {
+        let dir = std::env::temp_dir().join(format!("latency-x-replay-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let start = DateTime::from_timestamp_micros(1_700_000_000_000_000).unwrap();
+        let tick = |source, symbol: &str, price, ms| Tick {
+            source,
+            symbol: symbol.to_string(),
+            price,
+            volume: 1.0,
+            received_at: start + chrono::Duration::milliseconds(ms),
+            exchange_ts: None,
+            trade_id: None,
+        };
+
+        let mut writer = CaptureWriter::new(&dir, std::time::Duration::from_secs(3600));
+        writer.write(&CaptureRecord::Tick(tick(MarketDataSource::Binance, "BTC/USDT", 100.0, 0))).unwrap();
+        writer.write(&CaptureRecord::Book(OrderBook::new(MarketDataSource::Binance, "BTC/USDT".to_string()))).unwrap();
+        writer.write(&CaptureRecord::Tick(tick(MarketDataSource::Kraken, "ETH/USDT", 5.0, 5))).unwrap();
+        writer.write(&CaptureRecord::Tick(tick(MarketDataSource::Kraken, "BTC/USDT", 101.0, 10))).unwrap();
+        writer.finish().unwrap();
+
+        let connector = Arc::new(ReplayConnector::from_dir(&dir, ReplaySpeed::AsFastAsPossible).unwrap());
+        let (tx, mut rx) = mpsc::channel(16);
+        connector.subscribe(&["BTC/USDT".to_string()], tx).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        let first = rx.recv().await.unwrap();
+        let second = rx.recv().await.unwrap();
+        assert!(rx.recv().await.is_none());
+        assert_eq!((first.source, first.price), (MarketDataSource::Binance, 100.0));
+        assert_eq!((second.source, second.price), (MarketDataSource::Kraken, 101.0));
+        assert_eq!(second.received_at, start + chrono::Duration::milliseconds(10));
+    }
+
+    #[tokio::test]
+    async fn test_books_and_ticks_share_one_timeline() {
+        let dir = std::env::temp_dir().join(format!("latency-x-replay-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let start = DateTime::from_timestamp_micros(1_700_000_000_000_000).unwrap();
+        let tick = Tick {
+            source: MarketDataSource::Binance,
+            symbol: "BTC/USDT".to_string(),
+            price: 100.0,
+            volume: 1.0,
+            received_at: start,
+            exchange_ts: None,
+            trade_id: None,
+        };
+        let mut book = OrderBook::new(MarketDataSource::Binance, "BTC/USDT".to_string());
+        book.received_at = start + chrono::Duration::milliseconds(300);
+
+        let mut writer = CaptureWriter::new(&dir, std::time::Duration::from_secs(3600));
+        writer.write(&CaptureRecord::Tick(tick)).unwrap();
+        writer.write(&CaptureRecord::Book(book)).unwrap();
+        writer.finish().unwrap();
+
+        let connector = Arc::new(ReplayConnector::from_dir(&dir, ReplaySpeed::Original).unwrap());
+        let (tx, mut rx) = mpsc::channel(16);
+        let began = Instant::now();
+        connector.subscribe_order_book(&[], 10, tx).await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // The book is due 300ms after the tick that opens the capture, even
+        // though the book stream never emits that tick.
+        assert!(rx.recv().await.is_some());
+        assert!(began.elapsed() >= Duration::from_millis(300));
+    }
+}
@@ -0,0 +1,138 @@
+//! Per-symbol position in a venue's trade stream.
+//!
+//! Binance numbers trades consecutively per symbol, so a skipped id is a lost
+//! message. Kraken v1 trades only carry a timestamp: a gap can't be seen
+//! while connected, but trades between the last one before a reconnect and
+//! the first one after it are presumed missed. Either way the caller
+//! backfills the reported range from REST.
+
+use crate::models::MarketDataSource;
+use metrics::counter;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SequenceCheck {
+    InOrder,
+    /// Already delivered; drop it.
+    Duplicate,
+    /// Trades strictly between `after` and `before` were missed.
+    Gap { after: u64, before: u64 },
+}
+
+#[derive(Debug)]
+pub struct TradeSequence {
+    source: MarketDataSource,
+    last: HashMap<String, u64>,
+    /// Symbols whose next trade is the first after a reconnect.
+    reconnected: HashSet<String>,
+}
+
+impl TradeSequence {
+    pub fn new(source: MarketDataSource) -> Self {
+        Self {
+            source,
+            last: HashMap::new(),
+            reconnected: HashSet::new(),
+        }
+    }
+
+    /// Checks a trade id from a venue that numbers trades consecutively.
+    pub fn check_id(&mut self, symbol: &str, id: u64) -> SequenceCheck {
+        let check = match self.last.get(symbol) {
+            Some(&last) if id <= last => SequenceCheck::Duplicate,
+            Some(&last) if id > last + 1 => SequenceCheck::Gap { after: last, before: id },
+            _ => SequenceCheck::InOrder,
+        };
+        self.record(symbol, id, check)
+    }
+
+    /// Checks a trade timestamp from a venue without trade ids. Trades that
+    /// share a timestamp are only told apart while connected.
+    pub fn check_time(&mut self, symbol: &str, micros: u64) -> SequenceCheck {
+        let reconnected = self.reconnected.contains(symbol);
+        let check = match self.last.get(symbol) {
+            Some(&last) if micros < last || (reconnected && micros == last) => SequenceCheck::Duplicate,
+            Some(&last) if reconnected && micros > last => SequenceCheck::Gap { after: last, before: micros },
+            _ => SequenceCheck::InOrder,
+        };
+        // Replayed trades don't end the reconnect; the first new one does.
+        if check != SequenceCheck::Duplicate {
+            self.reconnected.remove(symbol);
+        }
+        self.record(symbol, micros, check)
+    }
+
+    /// Drops an unsubscribed symbol, so subscribing to it again starts
+    /// afresh rather than backfilling everything since it was removed.
+    pub fn forget(&mut self, symbol: &str) {
+        self.last.remove(symbol);
+        self.reconnected.remove(symbol);
+    }
+
+    /// Marks every symbol seen so far as resuming on a new connection.
+    pub fn on_reconnect(&mut self) {
+        self.reconnected.extend(self.last.keys().cloned());
+    }
+
+    fn record(&mut self, symbol: &str, position: u64, check: SequenceCheck) -> SequenceCheck {
+        let source = self.source.to_string();
+        match check {
+            SequenceCheck::Duplicate => {
+                counter!("trade_duplicates", "source" => source, "symbol" => symbol.to_string()).increment(1);
+                return check;
+            }
+            SequenceCheck::Gap { .. } => {
+                counter!("trade_gaps", "source" => source, "symbol" => symbol.to_string()).increment(1);
+            }
+            SequenceCheck::InOrder => (),
+        }
+        self.last.insert(symbol.to_string(), position);
+        check
+    }
+}
+
+/// Counts trades recovered from REST after a gap.
+pub(crate) fn record_backfill(source: MarketDataSource, symbol: &str, trades: usize) {
+    counter!("trades_backfilled", "source" => source.to_string(), "symbol" => symbol.to_string()).increment(trades as u64);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_consecutive_ids() {
+        let mut sequence = TradeSequence::new(MarketDataSource::Binance);
+        assert_eq!(sequence.check_id("BTC/USDT", 10), SequenceCheck::InOrder);
+        assert_eq!(sequence.check_id("BTC/USDT", 11), SequenceCheck::InOrder);
+        assert_eq!(sequence.check_id("BTC/USDT", 11), SequenceCheck::Duplicate);
+        assert_eq!(sequence.check_id("BTC/USDT", 14), SequenceCheck::Gap { after: 11, before: 14 });
+        assert_eq!(sequence.check_id("BTC/USDT", 12), SequenceCheck::Duplicate);
+        // Symbols are numbered independently.
+        assert_eq!(sequence.check_id("ETH/USDT", 3), SequenceCheck::InOrder);
+    }
+
+    #[test]
+    fn test_forgotten_symbols_start_afresh() {
+        let mut sequence = TradeSequence::new(MarketDataSource::Kraken);
+        assert_eq!(sequence.check_time("BTC/USD", 1_000), SequenceCheck::InOrder);
+        sequence.on_reconnect();
+        sequence.forget("BTC/USD");
+        assert_eq!(sequence.check_time("BTC/USD", 9_000), SequenceCheck::InOrder);
+    }
+
+    #[test]
+    fn test_timestamps_only_gap_across_reconnects() {
+        let mut sequence = TradeSequence::new(MarketDataSource::Kraken);
+        assert_eq!(sequence.check_time("BTC/USD", 1_000), SequenceCheck::InOrder);
+        assert_eq!(sequence.check_time("BTC/USD", 1_000), SequenceCheck::InOrder);
+        assert_eq!(sequence.check_time("BTC/USD", 5_000), SequenceCheck::InOrder);
+        assert_eq!(sequence.check_time("BTC/USD", 4_000), SequenceCheck::Duplicate);
+
+        sequence.on_reconnect();
+        // The venue may replay the last trade before the new ones.
+        assert_eq!(sequence.check_time("BTC/USD", 5_000), SequenceCheck::Duplicate);
+        assert_eq!(sequence.check_time("BTC/USD", 9_000), SequenceCheck::Gap { after: 5_000, before: 9_000 });
+        assert_eq!(sequence.check_time("BTC/USD", 9_000), SequenceCheck::InOrder);
+    }
+}
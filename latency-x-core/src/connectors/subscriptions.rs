@@ -0,0 +1,445 @@
+//! Symbols a connector's live feeds follow, changeable while they run.
+//!
+//! A connector keeps one [`Subscriptions`] for all of its feeds: trades,
+//! books, quotes and mark prices. Each feed task subscribes to whatever the
+//! set holds when it (re)connects and forwards every later
+//! [`SubscriptionRequest`] over its open socket, so a symbol added at runtime
+//! gets every stream and survives reconnects. A change only succeeds once
+//! each connected feed has the venue's ack; if the venue refuses, the feeds
+//! that took it drop it again and the caller gets the venue's error.
+//! [`SubscriptionManager`] fans changes out to every registered connector;
+//! the dashboard API and config reloads go through it.
+
+use crate::connectors::Connector;
+use crate::instruments::InstrumentRegistry;
+use anyhow::{anyhow, Result};
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+/// How long a change waits for the venue to answer on every connected feed.
+const SUBSCRIPTION_ANSWER_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A change to the symbols a live feed is subscribed to.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SubscriptionChange {
+    Add(Vec<String>),
+    Remove(Vec<String>),
+}
+
+/// A change as a connected feed receives it. The feed sends it to the venue
+/// under `id` and answers once the venue has accepted or refused it. A request
+/// dropped unanswered, as when the connection goes down, counts as accepted:
+/// the feed subscribes to the current set when it reconnects.
+#[derive(Debug, Clone)]
+pub struct SubscriptionRequest {
+    pub id: u64,
+    pub change: SubscriptionChange,
+    answer: mpsc::UnboundedSender<Result<(), String>>,
+}
+
+impl SubscriptionRequest {
+    pub fn answer(self, result: Result<(), String>) {
+        let _ = self.answer.send(result);
+    }
+}
+
+/// Requests a feed has sent to its venue and not yet heard back about.
+/// Venues that answer each symbol apart are counted down.
+#[derive(Debug, Default)]
+pub struct PendingRequests(BTreeMap<u64, (SubscriptionRequest, usize)>);
+
+impl PendingRequests {
+    /// Waits for `replies` venue replies to `request`.
+    pub fn insert(&mut self, request: SubscriptionRequest, replies: usize) {
+        if replies == 0 {
+            request.answer(Ok(()));
+            return;
+        }
+        self.0.insert(request.id, (request, replies));
+    }
+
+    /// Records a venue reply to request `id`. The request is answered at the
+    /// first refusal, or once every reply is in.
+    pub fn reply(&mut self, id: u64, result: Result<(), String>) {
+        let Some((_, remaining)) = self.0.get_mut(&id) else {
+            return;
+        };
+        *remaining -= 1;
+        if result.is_err() || *remaining == 0 {
+            if let Some((request, _)) = self.0.remove(&id) {
+                request.answer(result);
+            }
+        }
+    }
+
+    /// Records a reply that carries no id, for venues that answer in order.
+    pub fn reply_oldest(&mut self, result: Result<(), String>) {
+        if let Some(&id) = self.0.keys().next() {
+            self.reply(id, result);
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Subscriptions {
+    symbols: Mutex<Vec<String>>,
+    changes: broadcast::Sender<SubscriptionRequest>,
+    next_id: AtomicU64,
+}
+
+impl Default for Subscriptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Subscriptions {
+    pub fn new() -> Self {
+        Self {
+            symbols: Mutex::new(Vec::new()),
+            changes: broadcast::channel(64).0,
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Replaces the whole set, as a feed does when it is started. Every feed
+    /// of a connector starts with the same symbols.
+    pub fn reset(&self, symbols: &[String]) {
+        *self.symbols.lock().unwrap() = symbols.to_vec();
+    }
+
+    /// The set to subscribe to on a fresh connection, and the changes made
+    /// after it was read. A change racing the read may show up in both.
+    pub fn connect(&self) -> (Vec<String>, broadcast::Receiver<SubscriptionRequest>) {
+        let changes = self.changes.subscribe();
+        (self.symbols.lock().unwrap().clone(), changes)
+    }
+
+    /// Adds the symbols not already followed and returns them once the venue
+    /// has taken them on every connected feed.
+    pub async fn add(&self, symbols: &[String]) -> Result<Vec<String>> {
+        let added: Vec<String> = {
+            let mut current = self.symbols.lock().unwrap();
+            let added: Vec<String> = symbols
+                .iter()
+                .filter(|symbol| !current.contains(symbol))
+                .cloned()
+                .collect::<BTreeSet<_>>()
+                .into_iter()
+                .collect();
+            current.extend(added.iter().cloned());
+            added
+        };
+        if added.is_empty() {
+            return Ok(added);
+        }
+        if let Err(e) = self.apply(SubscriptionChange::Add(added.clone())).await {
+            self.symbols.lock().unwrap().retain(|symbol| !added.contains(symbol));
+            self.send(SubscriptionChange::Remove(added));
+            return Err(e);
+        }
+        Ok(added)
+    }
+
+    /// Removes the symbols currently followed and returns them once the venue
+    /// has dropped them on every connected feed.
+    pub async fn remove(&self, symbols: &[String]) -> Result<Vec<String>> {
+        let removed: Vec<String> = {
+            let mut current = self.symbols.lock().unwrap();
+            let removed: Vec<String> = current.iter().filter(|symbol| symbols.contains(symbol)).cloned().collect();
+            current.retain(|symbol| !symbols.contains(symbol));
+            removed
+        };
+        if removed.is_empty() {
+            return Ok(removed);
+        }
+        if let Err(e) = self.apply(SubscriptionChange::Remove(removed.clone())).await {
+            self.symbols.lock().unwrap().extend(removed.iter().cloned());
+            self.send(SubscriptionChange::Add(removed));
+            return Err(e);
+        }
+        Ok(removed)
+    }
+
+    /// Sends `change` to every connected feed without waiting for answers,
+    /// returning how many feeds it went to and where their answers arrive.
+    fn send(&self, change: SubscriptionChange) -> (usize, mpsc::UnboundedReceiver<Result<(), String>>) {
+        let (answer, answers) = mpsc::unbounded_channel();
+        let request = SubscriptionRequest {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            change,
+            answer,
+        };
+        // With no feed connected, each picks the change up when it connects.
+        (self.changes.send(request).unwrap_or(0), answers)
+    }
+
+    /// Sends `change` to every connected feed and waits for the venue's answers.
+    async fn apply(&self, change: SubscriptionChange) -> Result<()> {
+        let (feeds, mut answers) = self.send(change);
+        let answered = async {
+            for _ in 0..feeds {
+                match answers.recv().await {
+                    Some(Ok(())) => {}
+                    Some(Err(e)) => return Err(anyhow!("venue refused the change: {}", e)),
+                    // Every feed still holding the request has disconnected.
+                    None => break,
+                }
+            }
+            Ok(())
+        };
+        tokio::time::timeout(SUBSCRIPTION_ANSWER_TIMEOUT, answered)
+            .await
+            .map_err(|_| anyhow!("venue did not answer the change within {:?}", SUBSCRIPTION_ANSWER_TIMEOUT))?
+    }
+}
+
+/// Adds and removes canonical symbols across every registered venue, each
+/// venue taking only the symbols it lists.
+#[derive(Default)]
+pub struct SubscriptionManager {
+    connectors: Mutex<Vec<Arc<dyn Connector>>>,
+    symbols: Mutex<BTreeSet<String>>,
+    instruments: Arc<InstrumentRegistry>,
+}
+
+impl SubscriptionManager {
+    pub fn new(instruments: Arc<InstrumentRegistry>, symbols: &[String]) -> Self {
+        Self {
+            connectors: Mutex::new(Vec::new()),
+            symbols: Mutex::new(symbols.iter().cloned().collect()),
+            instruments,
+        }
+    }
+
+    pub fn register(&self, connector: Arc<dyn Connector>) {
+        self.connectors.lock().unwrap().push(connector);
+    }
+
+    pub fn symbols(&self) -> Vec<String> {
+        self.symbols.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Adds `symbols` on every venue listing them. If a venue refuses, the
+    /// venues that already took them drop them again and nothing changes.
+    pub async fn add(&self, symbols: &[String]) -> Result<()> {
+        let current = self.symbols();
+        let added: Vec<String> = symbols.iter().filter(|symbol| !current.contains(symbol)).cloned().collect();
+        let mut applied = Vec::new();
+        for connector in self.connectors() {
+            let listed = self.instruments.listed(connector.get_source(), &added);
+            if listed.is_empty() {
+                continue;
+            }
+            if let Err(e) = connector.add_symbols(&listed).await {
+                for (connector, listed) in applied {
+                    Self::undo(&connector, SubscriptionChange::Remove(listed)).await;
+                }
+                return Err(e.context(format!("{} could not subscribe to {:?}", connector.get_source(), listed)));
+            }
+            applied.push((connector, listed));
+        }
+        self.symbols.lock().unwrap().extend(added);
+        info!("Subscribed to {:?}", symbols);
+        Ok(())
+    }
+
+    /// Removes `symbols` everywhere, or, if a venue refuses, nowhere.
+    pub async fn remove(&self, symbols: &[String]) -> Result<()> {
+        let current = self.symbols();
+        let removed: Vec<String> = symbols.iter().filter(|symbol| current.contains(symbol)).cloned().collect();
+        if removed.is_empty() {
+            return Ok(());
+        }
+        let mut applied: Vec<Arc<dyn Connector>> = Vec::new();
+        for connector in self.connectors() {
+            if let Err(e) = connector.remove_symbols(&removed).await {
+                for connector in applied {
+                    let listed = self.instruments.listed(connector.get_source(), &removed);
+                    Self::undo(&connector, SubscriptionChange::Add(listed)).await;
+                }
+                return Err(e.context(format!("{} could not unsubscribe from {:?}", connector.get_source(), removed)));
+            }
+            applied.push(connector);
+        }
+        self.symbols.lock().unwrap().retain(|symbol| !removed.contains(symbol));
+        info!("Unsubscribed from {:?}", symbols);
+        Ok(())
+    }
+
+    /// Brings the subscriptions in line with `symbols`, e.g. after the config
+    /// file was edited.
+    pub async fn sync(&self, symbols: &[String]) -> Result<()> {
+        let current = self.symbols();
+        let removed: Vec<String> = current.iter().filter(|s| !symbols.contains(s)).cloned().collect();
+        let added: Vec<String> = symbols.iter().filter(|s| !current.contains(s)).cloned().collect();
+        if !removed.is_empty() {
+            self.remove(&removed).await?;
+        }
+        if !added.is_empty() {
+            self.add(&added).await?;
+        }
+        Ok(())
+    }
+
+    /// Reverts a change a connector already made. A failure here leaves that
+    /// venue out of step until the next change or restart.
+    async fn undo(connector: &Arc<dyn Connector>, change: SubscriptionChange) {
+        let result = match &change {
+            SubscriptionChange::Add(symbols) if !symbols.is_empty() => connector.add_symbols(symbols).await,
+            SubscriptionChange::Remove(symbols) if !symbols.is_empty() => connector.remove_symbols(symbols).await,
+            _ => Ok(()),
+        };
+        if let Err(e) = result {
+            warn!("{} could not revert {:?}: {}", connector.get_source(), change, e);
+        }
+    }
+
+    fn connectors(&self) -> Vec<Arc<dyn Connector>> {
+        self.connectors.lock().unwrap().clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::instruments::Instrument;
+    use crate::models::{MarketDataSource, Tick};
+    use anyhow::anyhow;
+    use async_trait::async_trait;
+    use tokio::sync::mpsc;
+
+    /// Follows symbols like a live feed, or refuses every change.
+    struct Feed {
+        source: MarketDataSource,
+        subscriptions: Subscriptions,
+        refuses: bool,
+    }
+
+    #[async_trait]
+    impl Connector for Feed {
+        async fn subscribe(self: Arc<Self>, _symbols: &[String], _sender: mpsc::Sender<Tick>) -> Result<()> {
+            Ok(())
+        }
+
+        fn get_source(&self) -> MarketDataSource {
+            self.source
+        }
+
+        async fn add_symbols(&self, symbols: &[String]) -> Result<()> {
+            if self.refuses {
+                return Err(anyhow!("socket closed"));
+            }
+            self.subscriptions.add(symbols).await?;
+            Ok(())
+        }
+
+        async fn remove_symbols(&self, symbols: &[String]) -> Result<()> {
+            if self.refuses {
+                return Err(anyhow!("socket closed"));
+            }
+            self.subscriptions.remove(symbols).await?;
+            Ok(())
+        }
+    }
+
+    fn symbols(names: &[&str]) -> Vec<String> {
+        names.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[tokio::test]
+    async fn test_changes_only_carry_new_state() {
+        let subscriptions = Subscriptions::new();
+        subscriptions.reset(&symbols(&["BTC/USDT"]));
+        let (current, mut changes) = subscriptions.connect();
+        assert_eq!(current, symbols(&["BTC/USDT"]));
+        let feed = tokio::spawn(async move {
+            let mut seen = Vec::new();
+            while let Ok(request) = changes.recv().await {
+                seen.push(request.change.clone());
+                request.answer(Ok(()));
+            }
+            seen
+        });
+
+        assert_eq!(subscriptions.add(&symbols(&["BTC/USDT", "ETH/USDT", "ETH/USDT"])).await.unwrap(), symbols(&["ETH/USDT"]));
+        assert!(subscriptions.add(&symbols(&["ETH/USDT"])).await.unwrap().is_empty());
+        assert_eq!(subscriptions.remove(&symbols(&["BTC/USDT", "SOL/USDT"])).await.unwrap(), symbols(&["BTC/USDT"]));
+        assert_eq!(subscriptions.connect().0, symbols(&["ETH/USDT"]));
+
+        drop(subscriptions);
+        assert_eq!(
+            feed.await.unwrap(),
+            vec![SubscriptionChange::Add(symbols(&["ETH/USDT"])), SubscriptionChange::Remove(symbols(&["BTC/USDT"]))]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_a_change_the_venue_refuses_is_undone() {
+        let subscriptions = Subscriptions::new();
+        subscriptions.reset(&symbols(&["BTC/USDT"]));
+        let (_, mut accepting) = subscriptions.connect();
+        let (_, mut refusing) = subscriptions.connect();
+        let feeds = tokio::spawn(async move {
+            let accepted = accepting.recv().await.unwrap();
+            let mut pending = PendingRequests::default();
+            pending.insert(refusing.recv().await.unwrap(), 2);
+            pending.reply(1, Ok(()));
+            pending.reply_oldest(Err("Invalid symbol".to_string()));
+            accepted.answer(Ok(()));
+            accepting.recv().await.unwrap().change
+        });
+
+        let error = subscriptions.add(&symbols(&["ETH/USDT"])).await.unwrap_err();
+        assert!(error.to_string().contains("Invalid symbol"), "{}", error);
+        assert_eq!(subscriptions.connect().0, symbols(&["BTC/USDT"]));
+        // The feed that took the symbol is told to drop it again.
+        assert_eq!(feeds.await.unwrap(), SubscriptionChange::Remove(symbols(&["ETH/USDT"])));
+
+        // A feed that disconnects without answering holds nothing up.
+        let (_, dropped) = subscriptions.connect();
+        let dropping = tokio::spawn(async move { drop(dropped) });
+        assert_eq!(subscriptions.add(&symbols(&["SOL/USDT"])).await.unwrap(), symbols(&["SOL/USDT"]));
+        dropping.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_a_refused_change_is_rolled_back_everywhere() {
+        let mut instruments = InstrumentRegistry::new();
+        for venue in [MarketDataSource::Binance, MarketDataSource::Kraken] {
+            for symbol in ["BTC/USDT", "ETH/USDT"] {
+                instruments.insert(Instrument {
+                    symbol: symbol.to_string(),
+                    base: symbol[..3].to_string(),
+                    quote: "USDT".to_string(),
+                    venue,
+                    venue_symbol: symbol.replace('/', ""),
+                    ws_symbol: symbol.replace('/', ""),
+                    tick_size: 0.01,
+                    lot_size: 0.0001,
+                    min_notional: 5.0,
+                });
+            }
+        }
+        let manager = SubscriptionManager::new(Arc::new(instruments), &symbols(&["BTC/USDT"]));
+        let feed = |source, refuses| {
+            let feed = Arc::new(Feed { source, subscriptions: Subscriptions::new(), refuses });
+            feed.subscriptions.reset(&symbols(&["BTC/USDT"]));
+            feed
+        };
+        let binance = feed(MarketDataSource::Binance, false);
+        let kraken = feed(MarketDataSource::Kraken, true);
+        manager.register(binance.clone());
+        manager.register(kraken);
+
+        assert!(manager.add(&symbols(&["BTC/USDT", "ETH/USDT"])).await.is_err());
+        assert_eq!(binance.subscriptions.connect().0, symbols(&["BTC/USDT"]));
+        assert!(manager.remove(&symbols(&["BTC/USDT"])).await.is_err());
+        assert_eq!(binance.subscriptions.connect().0, symbols(&["BTC/USDT"]));
+        assert_eq!(manager.symbols(), symbols(&["BTC/USDT"]));
+    }
+}
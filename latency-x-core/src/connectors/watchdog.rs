@@ -0,0 +1,242 @@
+use crate::config::FeedConfig;
+use crate::dashboard::events::DashboardEvent;
+use crate::models::{FeedState, FeedStatus, FeedStream, MarketDataSource};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, mpsc};
+use tracing::{info, warn};
+
+/// Tracks when each symbol of each of a connector's streams last produced
+/// data and publishes `FeedStatus` transitions. Streams are checked apart,
+/// so a silent book connection does not take the trade feed down with it.
+/// A quiet symbol is only reported stale, since a pair can go minutes without
+/// a trade; the connection is dropped when it sends nothing at all for
+/// `stale_after`, using pings to keep quiet connections busy.
+pub struct FeedWatchdog {
+    source: MarketDataSource,
+    stale_after: Duration,
+    ping_interval: Duration,
+    symbols: Mutex<HashMap<(FeedStream, String), (Instant, FeedState)>>,
+    dashboard_tx: broadcast::Sender<DashboardEvent>,
+    status_tx: Option<mpsc::Sender<FeedStatus>>,
+}
+
+impl FeedWatchdog {
+    pub fn new(
+        source: MarketDataSource,
+        config: &FeedConfig,
+        dashboard_tx: broadcast::Sender<DashboardEvent>,
+        status_tx: Option<mpsc::Sender<FeedStatus>>,
+    ) -> Self {
+        Self {
+            source,
+            stale_after: Duration::from_millis(config.stale_after_ms),
+            ping_interval: Duration::from_millis(config.ping_interval_ms),
+            symbols: Mutex::new(HashMap::new()),
+            dashboard_tx,
+            status_tx,
+        }
+    }
+
+    pub fn stale_after(&self) -> Duration {
+        self.stale_after
+    }
+
+    pub fn ping_interval(&self) -> Duration {
+        self.ping_interval
+    }
+
+    /// Gives every subscribed symbol a fresh silence window after a (re)connect
+    /// and reports the ones that were down as up again.
+    pub fn on_connect(&self, stream: FeedStream, symbols: &[String]) {
+        let now = Instant::now();
+        let recovered: Vec<&String> = {
+            let mut tracked = self.symbols.lock().unwrap();
+            symbols
+                .iter()
+                .filter(|symbol| {
+                    let previous = tracked.insert((stream, symbol.to_string()), (now, FeedState::Up));
+                    previous.is_some_and(|(_, state)| state != FeedState::Up)
+                })
+                .collect()
+        };
+        for symbol in recovered {
+            info!(source = %self.source, stream = %stream, symbol = %symbol, "Feed is up");
+            self.publish(stream, Some(symbol.clone()), FeedState::Up);
+        }
+    }
+
+    /// Stops tracking unsubscribed symbols so they are never reported stale.
+    pub fn forget(&self, stream: FeedStream, symbols: &[String]) {
+        let mut tracked = self.symbols.lock().unwrap();
+        for symbol in symbols {
+            tracked.remove(&(stream, symbol.clone()));
+        }
+    }
+
+    pub fn on_message(&self, stream: FeedStream, symbol: &str) {
+        let recovered = {
+            let mut tracked = self.symbols.lock().unwrap();
+            let entry = tracked
+                .entry((stream, symbol.to_string()))
+                .or_insert((Instant::now(), FeedState::Up));
+            entry.0 = Instant::now();
+            std::mem::replace(&mut entry.1, FeedState::Up) != FeedState::Up
+        };
+        if recovered {
+            info!(source = %self.source, stream = %stream, symbol = %symbol, "Feed is up");
+            self.publish(stream, Some(symbol.to_string()), FeedState::Up);
+        }
+    }
+
+    /// Marks symbols of `stream` silent for longer than `stale_after` as
+    /// stale and returns the ones that just became stale.
+    pub fn check(&self, stream: FeedStream) -> Vec<String> {
+        let newly_stale: Vec<String> = {
+            let mut tracked = self.symbols.lock().unwrap();
+            tracked
+                .iter_mut()
+                .filter(|((tracked_stream, _), (seen, state))| {
+                    *tracked_stream == stream && *state == FeedState::Up && seen.elapsed() > self.stale_after
+                })
+                .map(|((_, symbol), (_, state))| {
+                    *state = FeedState::Stale;
+                    symbol.clone()
+                })
+                .collect()
+        };
+        for symbol in &newly_stale {
+            warn!(source = %self.source, stream = %stream, symbol = %symbol, "Feed is stale");
+            self.publish(stream, Some(symbol.clone()), FeedState::Stale);
+        }
+        newly_stale
+    }
+
+    /// Runs on every ping of `stream`: reports symbols that went quiet as
+    /// stale, and returns true, with the stream's symbols marked reconnecting,
+    /// when the connection has sent nothing since `last_frame` for too long.
+    pub fn needs_reconnect(&self, stream: FeedStream, last_frame: Instant) -> bool {
+        self.check(stream);
+        if last_frame.elapsed() <= self.stale_after {
+            return false;
+        }
+        warn!(source = %self.source, stream = %stream, "Feed connection silent, reconnecting");
+        self.on_reconnect(stream);
+        true
+    }
+
+    /// Called when the connector drops a silent connection; only the
+    /// symbols of that stream are affected.
+    pub fn on_reconnect(&self, stream: FeedStream) {
+        let affected: Vec<String> = {
+            let mut tracked = self.symbols.lock().unwrap();
+            tracked
+                .iter_mut()
+                .filter(|((tracked_stream, _), _)| *tracked_stream == stream)
+                .map(|((_, symbol), (_, state))| {
+                    *state = FeedState::Reconnecting;
+                    symbol.clone()
+                })
+                .collect()
+        };
+        if affected.is_empty() {
+            self.publish(stream, None, FeedState::Reconnecting);
+        }
+        for symbol in affected {
+            self.publish(stream, Some(symbol), FeedState::Reconnecting);
+        }
+    }
+
+    fn publish(&self, stream: FeedStream, symbol: Option<String>, state: FeedState) {
+        let status = FeedStatus {
+            source: self.source,
+            stream,
+            symbol,
+            state,
+            at: Utc::now(),
+        };
+        if let Some(status_tx) = &self.status_tx {
+            // Never block a feed on a slow consumer.
+            let _ = status_tx.try_send(status.clone());
+        }
+        let _ = self.dashboard_tx.send(DashboardEvent::FeedStatus(status));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn watchdog(stale_after_ms: u64) -> (FeedWatchdog, mpsc::Receiver<FeedStatus>) {
+        let (dashboard_tx, _) = broadcast::channel(16);
+        let (status_tx, status_rx) = mpsc::channel(16);
+        let config = FeedConfig { stale_after_ms, ping_interval_ms: 1 };
+        (FeedWatchdog::new(MarketDataSource::Binance, &config, dashboard_tx, Some(status_tx)), status_rx)
+    }
+
+    #[test]
+    fn test_silent_symbol_goes_stale_then_recovers() {
+        let (watchdog, mut status_rx) = watchdog(5);
+        watchdog.on_connect(FeedStream::Trades, &["BTCUSDT".to_string(), "ETHUSDT".to_string()]);
+        assert!(watchdog.check(FeedStream::Trades).is_empty());
+
+        std::thread::sleep(Duration::from_millis(10));
+        watchdog.on_message(FeedStream::Trades, "ETHUSDT");
+        assert_eq!(watchdog.check(FeedStream::Trades), vec!["BTCUSDT".to_string()]);
+        // Only the transition is reported.
+        assert!(watchdog.check(FeedStream::Trades).is_empty());
+
+        let status = status_rx.try_recv().unwrap();
+        assert_eq!((status.stream, status.state), (FeedStream::Trades, FeedState::Stale));
+        assert_eq!(status.symbol.as_deref(), Some("BTCUSDT"));
+
+        watchdog.on_message(FeedStream::Trades, "BTCUSDT");
+        assert_eq!(status_rx.try_recv().unwrap().state, FeedState::Up);
+    }
+
+    #[test]
+    fn test_reconnect_only_affects_its_stream() {
+        let (watchdog, mut status_rx) = watchdog(50);
+        watchdog.on_connect(FeedStream::Trades, &["BTCUSDT".to_string()]);
+        watchdog.on_connect(FeedStream::Book, &["BTCUSDT".to_string()]);
+        watchdog.on_reconnect(FeedStream::Book);
+
+        let status = status_rx.try_recv().unwrap();
+        assert_eq!((status.stream, status.state), (FeedStream::Book, FeedState::Reconnecting));
+        assert_eq!(status.symbol.as_deref(), Some("BTCUSDT"));
+        assert!(status_rx.try_recv().is_err());
+        // The trade stream's copy of the symbol is untouched.
+        watchdog.on_message(FeedStream::Trades, "BTCUSDT");
+        assert!(status_rx.try_recv().is_err());
+
+        watchdog.on_message(FeedStream::Book, "BTCUSDT");
+        assert_eq!(status_rx.try_recv().unwrap().state, FeedState::Up);
+    }
+
+    #[test]
+    fn test_only_a_silent_connection_forces_a_reconnect() {
+        let (watchdog, mut status_rx) = watchdog(5);
+        watchdog.on_connect(FeedStream::Quotes, &["BTCUSDT".to_string(), "ETHUSDT".to_string()]);
+        let connected = Instant::now();
+        assert!(!watchdog.needs_reconnect(FeedStream::Quotes, connected));
+
+        std::thread::sleep(Duration::from_millis(10));
+        watchdog.on_message(FeedStream::Quotes, "ETHUSDT");
+        // BTCUSDT has gone quiet, but the connection is still delivering.
+        assert!(!watchdog.needs_reconnect(FeedStream::Quotes, Instant::now()));
+        let status = status_rx.try_recv().unwrap();
+        assert_eq!((status.symbol.as_deref(), status.state), (Some("BTCUSDT"), FeedState::Stale));
+
+        assert!(watchdog.needs_reconnect(FeedStream::Quotes, connected));
+        assert_eq!(status_rx.try_recv().unwrap().state, FeedState::Reconnecting);
+        assert_eq!(status_rx.try_recv().unwrap().state, FeedState::Reconnecting);
+
+        // Reconnecting brings every symbol back up, quiet or not.
+        watchdog.on_connect(FeedStream::Quotes, &["BTCUSDT".to_string(), "ETHUSDT".to_string()]);
+        assert_eq!(status_rx.try_recv().unwrap().state, FeedState::Up);
+        assert_eq!(status_rx.try_recv().unwrap().state, FeedState::Up);
+        assert!(status_rx.try_recv().is_err());
+    }
+}
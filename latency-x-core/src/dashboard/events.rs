@@ -1,4 +1,4 @@
-use crate::models::{Trade, Tick};
+use crate::models::{Candle, FeedStatus, MarkPrice, MarketDataSource, OrderBook, Quote, Trade, Tick};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::{DateTime, Utc};
@@ -16,6 +16,13 @@ pub struct LogEntry {
 pub enum DashboardEvent {
     Trade(Trade),
     Tick(Tick),
+    Quote(Quote),
+    MarkPrice(MarkPrice),
+    OrderBook(OrderBook),
+    /// A bar that has just closed.
+    Candle(Candle),
     Log(LogEntry),
     LatencyUpdate { order_id: Uuid, latency_us: u64 },
+    FeedLatency { source: MarketDataSource, symbol: String, latency_us: i64 },
+    FeedStatus(FeedStatus),
 } 
\ No newline at end of file
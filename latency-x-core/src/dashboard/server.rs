@@ -1,15 +1,21 @@
+use crate::config::DashboardConfig;
+use crate::connectors::subscriptions::SubscriptionManager;
 use crate::dashboard::events::DashboardEvent;
+use crate::oms::OrderManager;
 use crate::persistence::db::DatabaseManager;
 use axum::{
     extract::{
         ws::{Message, WebSocket, WebSocketUpgrade},
-        State,
+        ConnectInfo, Query, State,
     },
+    http::{header, HeaderMap, Method, StatusCode},
     response::{IntoResponse, Json},
     routing::get,
     Router,
 };
+use serde::Deserialize;
 use futures_util::{SinkExt, StreamExt};
+use std::net::SocketAddr;
 use std::sync::Arc;
 use tokio::sync::broadcast;
 use tower_http::services::ServeDir;
@@ -19,26 +25,91 @@ use tower_http::cors::{Any, CorsLayer};
 struct AppState {
     tx: broadcast::Sender<DashboardEvent>,
     db: Arc<DatabaseManager>,
+    subscriptions: Arc<SubscriptionManager>,
+    oms: Arc<OrderManager>,
+    admin_token: Option<String>,
 }
 
-pub async fn start_dashboard_server(tx: broadcast::Sender<DashboardEvent>, db: Arc<DatabaseManager>) {
+impl AppState {
+    fn authorize(&self, peer: SocketAddr, headers: &HeaderMap) -> Result<(), StatusCode> {
+        if is_admin(self.admin_token.as_deref(), peer, headers) {
+            Ok(())
+        } else {
+            tracing::warn!("Refused a subscription change from {}", peer);
+            Err(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
+
+/// Whether a caller may change what the bot trades: it must present the
+/// admin token, or connect locally when none is configured.
+fn is_admin(admin_token: Option<&str>, peer: SocketAddr, headers: &HeaderMap) -> bool {
+    match admin_token {
+        Some(token) => headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|presented| presented == token),
+        None => peer.ip().is_loopback(),
+    }
+}
+
+#[derive(Deserialize)]
+struct SymbolsRequest {
+    symbols: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct CandlesQuery {
+    source: String,
+    symbol: String,
+    interval_secs: u64,
+    #[serde(default = "default_candle_limit")]
+    limit: u32,
+}
+
+fn default_candle_limit() -> u32 {
+    500
+}
+
+#[derive(Deserialize)]
+struct WorkingOrdersQuery {
+    strategy: Option<String>,
+    symbol: Option<String>,
+}
+
+pub async fn start_dashboard_server(
+    tx: broadcast::Sender<DashboardEvent>,
+    db: Arc<DatabaseManager>,
+    subscriptions: Arc<SubscriptionManager>,
+    oms: Arc<OrderManager>,
+    config: DashboardConfig,
+) {
+    // Other origins may read, but browsers will not let their pages send
+    // the JSON writes below.
     let cors = CorsLayer::new()
         .allow_origin(Any)
-        .allow_methods(Any)
+        .allow_methods([Method::GET])
         .allow_headers(Any);
-    
-    let app_state = AppState { tx, db };
+
+    let app_state = AppState { tx, db, subscriptions, oms, admin_token: config.admin_token };
 
     let app = Router::new()
         .route("/api/health", get(|| async { "OK" }))
         .route("/ws", get(websocket_handler))
         .route("/api/trades", get(get_trades_handler))
+        .route("/api/candles", get(get_candles_handler))
+        .route("/api/orders", get(get_working_orders_handler))
+        .route(
+            "/api/subscriptions",
+            get(get_subscriptions_handler).post(add_subscriptions_handler).delete(remove_subscriptions_handler),
+        )
         .fallback_service(ServeDir::new("latency-x-dashboard/dist"))
         .with_state(Arc::new(app_state))
         .layer(cors);
 
     let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
-    axum::serve(listener, app).await.unwrap();
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
 }
 
 async fn get_trades_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
@@ -51,6 +122,76 @@ async fn get_trades_handler(State(state): State<Arc<AppState>>) -> impl IntoResp
     }
 }
 
+/// Closed bars of one series, oldest first, for charts to start from.
+async fn get_candles_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<CandlesQuery>,
+) -> impl IntoResponse {
+    let source = match query.source.parse() {
+        Ok(source) => source,
+        Err(e) => return (axum::http::StatusCode::BAD_REQUEST, format!("{}", e)).into_response(),
+    };
+    match state.db.get_candles(source, &query.symbol, query.interval_secs, query.limit).await {
+        Ok(candles) => Json(candles).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to get candles: {}", e);
+            (axum::http::StatusCode::INTERNAL_SERVER_ERROR, "Internal Server Error").into_response()
+        }
+    }
+}
+
+/// Orders the OMS has not yet seen to a final state.
+async fn get_working_orders_handler(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<WorkingOrdersQuery>,
+) -> impl IntoResponse {
+    Json(state.oms.working_orders(query.strategy.as_deref(), query.symbol.as_deref()))
+}
+
+async fn get_subscriptions_handler(State(state): State<Arc<AppState>>) -> impl IntoResponse {
+    Json(state.subscriptions.symbols())
+}
+
+/// Subscribes every venue listing them to trades for canonical `symbols`.
+/// Book and quote feeds keep their startup symbols. If any venue refuses,
+/// none of them change.
+async fn add_subscriptions_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<SymbolsRequest>,
+) -> impl IntoResponse {
+    if let Err(status) = state.authorize(peer, &headers) {
+        return status.into_response();
+    }
+    match state.subscriptions.add(&request.symbols).await {
+        Ok(()) => Json(state.subscriptions.symbols()).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to subscribe to {:?}: {}", request.symbols, e);
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
+
+/// Unsubscribes trade feeds from `symbols`, on every venue or none.
+async fn remove_subscriptions_handler(
+    State(state): State<Arc<AppState>>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<SymbolsRequest>,
+) -> impl IntoResponse {
+    if let Err(status) = state.authorize(peer, &headers) {
+        return status.into_response();
+    }
+    match state.subscriptions.remove(&request.symbols).await {
+        Ok(()) => Json(state.subscriptions.symbols()).into_response(),
+        Err(e) => {
+            tracing::error!("Failed to unsubscribe from {:?}: {}", request.symbols, e);
+            (StatusCode::BAD_REQUEST, e.to_string()).into_response()
+        }
+    }
+}
+
 #[axum::debug_handler]
 async fn websocket_handler(
     ws: WebSocketUpgrade,
@@ -85,4 +226,24 @@ async fn websocket(stream: WebSocket, state: Arc<AppState>) {
         _ = (&mut send_task) => recv_task.abort(),
         _ = (&mut recv_task) => send_task.abort(),
     };
-} 
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_subscription_changes_need_the_token_or_a_local_caller() {
+        let local: SocketAddr = "127.0.0.1:50000".parse().unwrap();
+        let remote: SocketAddr = "192.168.1.20:50000".parse().unwrap();
+        let mut headers = HeaderMap::new();
+        assert!(is_admin(None, local, &headers));
+        assert!(!is_admin(None, remote, &headers));
+        assert!(!is_admin(Some("s3cret"), local, &headers));
+
+        headers.insert(header::AUTHORIZATION, "Bearer wrong".parse().unwrap());
+        assert!(!is_admin(Some("s3cret"), remote, &headers));
+        headers.insert(header::AUTHORIZATION, "Bearer s3cret".parse().unwrap());
+        assert!(is_admin(Some("s3cret"), remote, &headers));
+    }
+}
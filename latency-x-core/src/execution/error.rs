@@ -0,0 +1,114 @@
+use std::error::Error;
+use thiserror::Error;
+
+/// Why an order, cancel or query failed, as far as the caller needs to
+/// know to decide what to do next. Messages keep the venue's own wording
+/// and error code.
+#[derive(Debug, Error)]
+pub enum ExecutionError {
+    /// The venue refused the request. Sending it again unchanged fails the
+    /// same way.
+    #[error("rejected: {0}")]
+    Rejected(String),
+    #[error("insufficient funds: {0}")]
+    InsufficientFunds(String),
+    /// The order does not exist on the venue, or is already closed.
+    #[error("unknown order: {0}")]
+    UnknownOrder(String),
+    #[error("rate limited: {0}")]
+    RateLimited(String),
+    #[error("unauthorized: {0}")]
+    Unauthorized(String),
+    /// The request never reached the venue.
+    #[error("network error: {0}")]
+    Network(String),
+    /// The request was sent but no answer came back, so it may or may not
+    /// have taken effect. Query the order before sending it again.
+    #[error("outcome unknown: {0}")]
+    Timeout(String),
+    #[error("this gateway does not support {0}")]
+    Unsupported(&'static str),
+    /// Anything else, such as a response that could not be parsed.
+    #[error(transparent)]
+    Other(#[from] anyhow::Error),
+}
+
+impl ExecutionError {
+    /// Whether the same request can be sent again as is, after a pause.
+    /// Only errors where the venue certainly did not act on it qualify.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, ExecutionError::RateLimited(_) | ExecutionError::Network(_))
+    }
+
+    /// Whether the venue may have acted on the request despite the error.
+    pub fn is_uncertain(&self) -> bool {
+        matches!(self, ExecutionError::Timeout(_))
+    }
+}
+
+impl From<reqwest::Error> for ExecutionError {
+    fn from(error: reqwest::Error) -> Self {
+        if error.is_connect() {
+            ExecutionError::Network(error.to_string())
+        } else if error.is_builder() {
+            ExecutionError::Other(error.into())
+        } else {
+            // Timed out, or the connection dropped after the request went out.
+            ExecutionError::Timeout(error.to_string())
+        }
+    }
+}
+
+impl From<serde_json::Error> for ExecutionError {
+    fn from(error: serde_json::Error) -> Self {
+        ExecutionError::Other(error.into())
+    }
+}
+
+/// For helpers that still return boxed errors; typed errors inside the box
+/// keep their kind.
+impl From<Box<dyn Error + Send + Sync>> for ExecutionError {
+    fn from(error: Box<dyn Error + Send + Sync>) -> Self {
+        let error = match error.downcast::<ExecutionError>() {
+            Ok(error) => return *error,
+            Err(error) => error,
+        };
+        match error.downcast::<reqwest::Error>() {
+            Ok(error) => (*error).into(),
+            Err(error) => ExecutionError::Other(anyhow::anyhow!(error)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_only_errors_the_venue_did_not_act_on_are_retryable() {
+        assert!(ExecutionError::RateLimited("-1003".to_string()).is_retryable());
+        assert!(ExecutionError::Network("connection refused".to_string()).is_retryable());
+        for error in [
+            ExecutionError::Rejected("-2010".to_string()),
+            ExecutionError::InsufficientFunds("-2010".to_string()),
+            ExecutionError::UnknownOrder("-2011".to_string()),
+            ExecutionError::Unauthorized("-2015".to_string()),
+            ExecutionError::Timeout("deadline elapsed".to_string()),
+            ExecutionError::Unsupported("amend_order"),
+        ] {
+            assert!(!error.is_retryable(), "{}", error);
+        }
+        assert!(ExecutionError::Timeout("deadline elapsed".to_string()).is_uncertain());
+        assert!(!ExecutionError::Network("connection refused".to_string()).is_uncertain());
+    }
+
+    #[test]
+    fn test_boxed_errors_keep_their_kind() {
+        let boxed: Box<dyn Error + Send + Sync> = Box::new(ExecutionError::RateLimited("-1015".to_string()));
+        assert!(matches!(ExecutionError::from(boxed), ExecutionError::RateLimited(_)));
+        let boxed: Box<dyn Error + Send + Sync> = "Invalid EC private key".into();
+        let error = ExecutionError::from(boxed);
+        assert!(matches!(error, ExecutionError::Other(_)));
+        assert_eq!(error.to_string(), "Invalid EC private key");
+    }
+}
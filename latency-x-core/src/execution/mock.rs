@@ -1,14 +1,19 @@
-use crate::models::Order;
+use crate::models::{Amendment, Order, OrderReport};
 use async_trait::async_trait;
-use std::error::Error;
-use super::ExecutionGateway;
+use super::{ExecutionError, ExecutionGateway};
 use mockall::mock;
+use uuid::Uuid;
 
 mock! {
     pub ExecutionGateway {}
 
     #[async_trait]
     impl ExecutionGateway for ExecutionGateway {
-        async fn send_order(&self, order: Order) -> Result<String, Box<dyn Error + Send + Sync>>;
+        async fn send_order(&self, order: Order) -> Result<String, ExecutionError>;
+        async fn cancel_order(&self, symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError>;
+        async fn cancel_all_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError>;
+        async fn amend_order(&self, order_id: Uuid, replacement: Order) -> Result<Amendment, ExecutionError>;
+        async fn query_order(&self, symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError>;
+        async fn open_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError>;
     }
-} 
\ No newline at end of file
+}
@@ -1,12 +1,47 @@
+pub mod error;
 #[cfg(test)]
 pub mod mock;
+pub mod paper;
 
-use crate::models::Order;
+use crate::models::{Amendment, Order, OrderReport};
 use anyhow::Result;
 use async_trait::async_trait;
-use std::error::Error;
+use uuid::Uuid;
 
+pub use error::ExecutionError;
+
+/// Places and manages orders on one venue. Orders are addressed by the
+/// `Uuid` they were sent with. Gateways that only fire orders keep the
+/// default lifecycle methods, which fail.
 #[async_trait]
 pub trait ExecutionGateway: Send + Sync {
-    async fn send_order(&self, order: Order) -> Result<String, Box<dyn Error + Send + Sync>>;
-} 
\ No newline at end of file
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError>;
+
+    /// Cancels a resting order and returns its final state.
+    async fn cancel_order(&self, _symbol: &str, _order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        Err(ExecutionError::Unsupported("cancel_order"))
+    }
+
+    /// Cancels every resting order on `symbol`, or on all symbols, and
+    /// returns the orders it canceled.
+    async fn cancel_all_orders(&self, _symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        Err(ExecutionError::Unsupported("cancel_all_orders"))
+    }
+
+    /// Cancels `order_id` and places `replacement` in its place, returning
+    /// both. Nothing is placed if the cancel fails, so an order that filled
+    /// in the meantime is not doubled.
+    async fn amend_order(&self, _order_id: Uuid, _replacement: Order) -> Result<Amendment, ExecutionError> {
+        Err(ExecutionError::Unsupported("amend_order"))
+    }
+
+    async fn query_order(&self, _symbol: &str, _order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        Err(ExecutionError::Unsupported("query_order"))
+    }
+
+    /// Resting orders on `symbol`, or on all symbols, including ones placed
+    /// outside this process.
+    async fn open_orders(&self, _symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        Err(ExecutionError::Unsupported("open_orders"))
+    }
+}
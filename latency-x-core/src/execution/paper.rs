@@ -0,0 +1,564 @@
+use crate::config::{FeeSchedule, PaperConfig};
+use crate::connectors::publish_fill;
+use crate::dashboard::events::DashboardEvent;
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::models::{
+    Amendment, Fill, MarketDataSource, Order, OrderBook, OrderReport, OrderSide, OrderStatus, OrderType, PriceLevel, Quote, Tick,
+};
+use crate::persistence::db::DatabaseManager;
+use async_trait::async_trait;
+use chrono::Utc;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, mpsc};
+use tracing::info;
+use uuid::Uuid;
+
+/// How many finished orders are kept for queries before the oldest go.
+const PAPER_CLOSED_ORDERS: usize = 10_000;
+
+/// Trades one venue against its live feed without sending anything. Market
+/// orders fill at the touch plus slippage as a taker; limit orders fill on
+/// arrival if they cross, and otherwise rest until a quote or trade reaches
+/// their price, filling there as a maker. No fill is larger than the quoted
+/// size or traded volume that triggered it; the rest of the order stays open
+/// as `PartiallyFilled`. Fills go out like a connector's.
+pub struct PaperExecutionGateway {
+    venue: MarketDataSource,
+    slippage_bps: f64,
+    fees: FeeSchedule,
+    market: Mutex<PaperMarket>,
+    next_id: AtomicU64,
+    fill_sender: Option<mpsc::Sender<Fill>>,
+    dashboard_tx: broadcast::Sender<DashboardEvent>,
+    db_manager: Arc<DatabaseManager>,
+}
+
+/// What the feed has shown us, and the orders sent. Sizes are what is left
+/// after our own fills against them.
+#[derive(Default)]
+struct PaperMarket {
+    /// Best bid and ask by symbol.
+    touch: HashMap<String, (PriceLevel, PriceLevel)>,
+    /// Price and volume of the last trade by symbol.
+    last_trade: HashMap<String, PriceLevel>,
+    /// Open orders by symbol, in the order they arrived.
+    open: HashMap<String, VecDeque<OrderReport>>,
+    /// The latest finished orders, oldest first in `closed_order`.
+    closed: HashMap<Uuid, OrderReport>,
+    closed_order: VecDeque<Uuid>,
+}
+
+impl PaperMarket {
+    fn get(&self, order_id: Uuid) -> Option<&OrderReport> {
+        self.open
+            .values()
+            .flatten()
+            .find(|report| report.order_id == Some(order_id))
+            .or_else(|| self.closed.get(&order_id))
+    }
+
+    /// Queues an open order behind the others on its symbol, or files a
+    /// finished one.
+    fn insert(&mut self, report: OrderReport) {
+        if report.status.is_open() {
+            self.open.entry(report.symbol.clone()).or_default().push_back(report);
+        } else {
+            self.close(report);
+        }
+    }
+
+    /// Takes an open order out of its queue.
+    fn take_open(&mut self, order_id: Uuid) -> Option<OrderReport> {
+        self.open.values_mut().find_map(|queue| {
+            let index = queue.iter().position(|report| report.order_id == Some(order_id))?;
+            queue.remove(index)
+        })
+    }
+
+    /// Moves the orders on `symbol` that have finished out of its queue.
+    fn close_finished(&mut self, symbol: &str) {
+        let Some(queue) = self.open.get_mut(symbol) else {
+            return;
+        };
+        let (open, finished): (VecDeque<_>, VecDeque<_>) = queue.drain(..).partition(|report| report.status.is_open());
+        *queue = open;
+        for report in finished {
+            self.close(report);
+        }
+    }
+
+    fn close(&mut self, report: OrderReport) {
+        let Some(order_id) = report.order_id else {
+            return;
+        };
+        if self.closed.insert(order_id, report).is_none() {
+            self.closed_order.push_back(order_id);
+        }
+        while self.closed_order.len() > PAPER_CLOSED_ORDERS {
+            if let Some(oldest) = self.closed_order.pop_front() {
+                self.closed.remove(&oldest);
+            }
+        }
+    }
+}
+
+/// How much our buy and sell orders may still take from one market event.
+struct Liquidity {
+    buy: f64,
+    sell: f64,
+}
+
+impl PaperExecutionGateway {
+    pub fn new(
+        venue: MarketDataSource,
+        config: &PaperConfig,
+        fill_sender: Option<mpsc::Sender<Fill>>,
+        dashboard_tx: broadcast::Sender<DashboardEvent>,
+        db_manager: Arc<DatabaseManager>,
+    ) -> Self {
+        Self {
+            venue,
+            slippage_bps: config.slippage_bps,
+            fees: config.fees(venue),
+            market: Mutex::new(PaperMarket::default()),
+            next_id: AtomicU64::new(1),
+            fill_sender,
+            dashboard_tx,
+            db_manager,
+        }
+    }
+
+    /// Fills resting limit orders the trade went through, up to its volume.
+    /// A trade at exactly our price may have been someone ahead of us in the
+    /// queue, so it does not.
+    pub fn on_tick(&self, tick: &Tick) {
+        if tick.source != self.venue {
+            return;
+        }
+        let fee_bps = self.fees.maker_bps;
+        let fills = {
+            let mut market = self.market.lock().unwrap();
+            let mut liquidity = Liquidity { buy: tick.volume, sell: tick.volume };
+            let fills = self.cross(&mut market, &tick.symbol, &mut liquidity, |report| {
+                let price = report.price?;
+                let crossed = match report.side {
+                    OrderSide::Buy => tick.price < price,
+                    OrderSide::Sell => tick.price > price,
+                };
+                crossed.then_some((price, fee_bps))
+            });
+            let volume = liquidity.buy.min(liquidity.sell);
+            market.last_trade.insert(tick.symbol.clone(), PriceLevel { price: tick.price, quantity: volume });
+            fills
+        };
+        self.publish(fills);
+    }
+
+    pub fn on_quote(&self, quote: &Quote) {
+        if quote.source == self.venue {
+            let bid = PriceLevel { price: quote.bid, quantity: quote.bid_size };
+            let ask = PriceLevel { price: quote.ask, quantity: quote.ask_size };
+            self.on_touch(&quote.symbol, bid, ask);
+        }
+    }
+
+    /// For feeds, such as replays, that carry books but no quotes.
+    pub fn on_order_book(&self, book: &OrderBook) {
+        if book.source != self.venue {
+            return;
+        }
+        if let (Some(bid), Some(ask)) = (book.best_bid(), book.best_ask()) {
+            self.on_touch(&book.symbol, *bid, *ask);
+        }
+    }
+
+    /// Fills resting limit orders the other side of the book has reached, and
+    /// what is left of market orders, up to the quoted sizes.
+    fn on_touch(&self, symbol: &str, bid: PriceLevel, ask: PriceLevel) {
+        let slippage = self.slippage_bps / 10_000.0;
+        let (maker_bps, taker_bps) = (self.fees.maker_bps, self.fees.taker_bps);
+        let fills = {
+            let mut market = self.market.lock().unwrap();
+            let mut liquidity = Liquidity { buy: ask.quantity, sell: bid.quantity };
+            let fills = self.cross(&mut market, symbol, &mut liquidity, |report| match (report.side, report.price) {
+                (OrderSide::Buy, Some(price)) => (ask.price <= price).then_some((price, maker_bps)),
+                (OrderSide::Sell, Some(price)) => (bid.price >= price).then_some((price, maker_bps)),
+                (OrderSide::Buy, None) => Some((ask.price * (1.0 + slippage), taker_bps)),
+                (OrderSide::Sell, None) => Some((bid.price * (1.0 - slippage), taker_bps)),
+            });
+            let bid = PriceLevel { quantity: liquidity.sell, ..bid };
+            let ask = PriceLevel { quantity: liquidity.buy, ..ask };
+            market.touch.insert(symbol.to_string(), (bid, ask));
+            fills
+        };
+        self.publish(fills);
+    }
+
+    /// Fills open orders on `symbol`, oldest first, at the price and fee
+    /// `fill_at` gives them, until `liquidity` on their side runs out.
+    fn cross(
+        &self,
+        market: &mut PaperMarket,
+        symbol: &str,
+        liquidity: &mut Liquidity,
+        fill_at: impl Fn(&OrderReport) -> Option<(f64, f64)>,
+    ) -> Vec<Fill> {
+        let Some(queue) = market.open.get_mut(symbol) else {
+            return Vec::new();
+        };
+        let mut fills = Vec::new();
+        for report in queue.iter_mut() {
+            let Some((price, fee_bps)) = fill_at(report) else {
+                continue;
+            };
+            let available = match report.side {
+                OrderSide::Buy => &mut liquidity.buy,
+                OrderSide::Sell => &mut liquidity.sell,
+            };
+            let quantity = report.remaining().min(*available);
+            if quantity > 0.0 {
+                *available -= quantity;
+                fills.push(self.fill(report, price, quantity, fee_bps));
+            }
+        }
+        if !fills.is_empty() {
+            market.close_finished(symbol);
+        }
+        fills
+    }
+
+    /// Fills `quantity` of `report` at `price`, which stays `PartiallyFilled`
+    /// until nothing remains.
+    fn fill(&self, report: &mut OrderReport, price: f64, quantity: f64, fee_bps: f64) -> Fill {
+        let filled_before = report.filled;
+        if quantity >= report.remaining() {
+            report.status = OrderStatus::Filled;
+            report.filled = report.amount;
+        } else {
+            report.status = OrderStatus::PartiallyFilled;
+            report.filled += quantity;
+        }
+        let avg = report.avg_fill_price.unwrap_or(price);
+        report.avg_fill_price = Some((avg * filled_before + price * quantity) / (filled_before + quantity));
+        report.updated_at = Utc::now();
+        Fill {
+            order_id: report.order_id.unwrap_or_default(),
+            symbol: report.symbol.clone(),
+            side: report.side,
+            price,
+            quantity,
+            source: self.venue,
+            executed_at: report.updated_at,
+            commission: price * quantity * fee_bps / 10_000.0,
+            commission_asset: report.symbol.split_once('/').map(|(_, quote)| quote.to_string()),
+            trade_id: Some(format!("paper-{}", self.next_id.fetch_add(1, Ordering::Relaxed))),
+        }
+    }
+
+    /// Publishes off the feed's path, in order.
+    fn publish(&self, fills: Vec<Fill>) {
+        if fills.is_empty() {
+            return;
+        }
+        let fill_sender = self.fill_sender.clone();
+        let dashboard_tx = self.dashboard_tx.clone();
+        let db_manager = self.db_manager.clone();
+        tokio::spawn(async move {
+            for fill in fills {
+                publish_fill(fill, fill_sender.as_ref(), &db_manager, &dashboard_tx).await;
+            }
+        });
+    }
+
+    /// The level an order taking liquidity on arrival fills against, if any:
+    /// the far touch plus slippage, or the last trade before any quote, with
+    /// the size still shown there. Limit orders never fill beyond their price.
+    fn taker_level<'a>(&self, market: &'a mut PaperMarket, order: &Order) -> Option<(f64, &'a mut f64)> {
+        let slippage = self.slippage_bps / 10_000.0;
+        let level = match market.touch.get_mut(&order.symbol) {
+            Some((bid, ask)) => match order.side {
+                OrderSide::Buy => ask,
+                OrderSide::Sell => bid,
+            },
+            None => market.last_trade.get_mut(&order.symbol)?,
+        };
+        let price = match (order.side, order.price) {
+            (OrderSide::Buy, _) if order.order_type == OrderType::Market => level.price * (1.0 + slippage),
+            (OrderSide::Sell, _) if order.order_type == OrderType::Market => level.price * (1.0 - slippage),
+            (OrderSide::Buy, Some(limit)) if level.price <= limit => (level.price * (1.0 + slippage)).min(limit),
+            (OrderSide::Sell, Some(limit)) if level.price >= limit => (level.price * (1.0 - slippage)).max(limit),
+            _ => return None,
+        };
+        Some((price, &mut level.quantity))
+    }
+
+    fn cancel(report: &mut OrderReport) -> OrderReport {
+        report.status = OrderStatus::Canceled;
+        report.updated_at = Utc::now();
+        report.clone()
+    }
+}
+
+#[async_trait]
+impl ExecutionGateway for PaperExecutionGateway {
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError> {
+        if order.amount <= 0.0 {
+            return Err(ExecutionError::Rejected(format!("Order {} has no quantity", order.id)));
+        }
+        if order.order_type == OrderType::Limit && order.price.is_none() {
+            return Err(ExecutionError::Rejected(format!("Limit order {} has no price", order.id)));
+        }
+        let exchange_order_id = format!("paper-{}", self.next_id.fetch_add(1, Ordering::Relaxed));
+        let fill = {
+            let mut market = self.market.lock().unwrap();
+            let mut report = OrderReport {
+                order_id: Some(order.id),
+                exchange_order_id: exchange_order_id.clone(),
+                symbol: order.symbol.clone(),
+                side: order.side,
+                order_type: order.order_type.clone(),
+                status: OrderStatus::New,
+                amount: order.amount,
+                filled: 0.0,
+                price: order.price,
+                avg_fill_price: None,
+                source: self.venue,
+                updated_at: Utc::now(),
+            };
+            let fill = match self.taker_level(&mut market, &order) {
+                Some((price, available)) => {
+                    let quantity = report.remaining().min(*available);
+                    *available -= quantity;
+                    (quantity > 0.0).then(|| self.fill(&mut report, price, quantity, self.fees.taker_bps))
+                }
+                None if order.order_type == OrderType::Market => {
+                    return Err(ExecutionError::Rejected(format!("No {} price for {} yet", self.venue, order.symbol)));
+                }
+                None => None,
+            };
+            market.insert(report);
+            fill
+        };
+        info!(order_id = %order.id, symbol = %order.symbol, side = ?order.side, amount = %order.amount, price = ?order.price, "Paper order {}", exchange_order_id);
+        if let Some(fill) = fill {
+            publish_fill(fill, self.fill_sender.as_ref(), &self.db_manager, &self.dashboard_tx).await;
+        }
+        Ok(exchange_order_id)
+    }
+
+    async fn cancel_order(&self, _symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let mut market = self.market.lock().unwrap();
+        if let Some(mut report) = market.take_open(order_id) {
+            let canceled = Self::cancel(&mut report);
+            market.close(report);
+            return Ok(canceled);
+        }
+        match market.get(order_id) {
+            Some(report) => Err(ExecutionError::UnknownOrder(format!("Order {} is already {}", order_id, report.status))),
+            None => Err(ExecutionError::UnknownOrder(format!("Unknown order {}", order_id))),
+        }
+    }
+
+    async fn cancel_all_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let mut market = self.market.lock().unwrap();
+        let mut canceled: Vec<OrderReport> = market
+            .open
+            .iter_mut()
+            .filter(|(queued, _)| symbol.as_ref().is_none_or(|symbol| *queued == symbol))
+            .flat_map(|(_, queue)| queue.drain(..))
+            .collect();
+        for report in &mut canceled {
+            Self::cancel(report);
+        }
+        for report in &canceled {
+            market.close(report.clone());
+        }
+        Ok(canceled)
+    }
+
+    async fn amend_order(&self, order_id: Uuid, replacement: Order) -> Result<Amendment, ExecutionError> {
+        let canceled = self.cancel_order(&replacement.symbol, order_id).await?;
+        let replacement_id = replacement.id;
+        self.send_order(replacement).await?;
+        let replacement = self.query_order("", replacement_id).await?;
+        Ok(Amendment { canceled, replacement })
+    }
+
+    async fn query_order(&self, _symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let market = self.market.lock().unwrap();
+        market
+            .get(order_id)
+            .cloned()
+            .ok_or_else(|| ExecutionError::UnknownOrder(format!("Unknown order {}", order_id)))
+    }
+
+    async fn open_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let market = self.market.lock().unwrap();
+        let mut open: Vec<_> = market
+            .open
+            .iter()
+            .filter(|(queued, _)| symbol.as_ref().is_none_or(|symbol| *queued == symbol))
+            .flat_map(|(_, queue)| queue.iter().cloned())
+            .collect();
+        open.sort_by_key(|report| report.updated_at);
+        Ok(open)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn gateway() -> (PaperExecutionGateway, mpsc::Receiver<Fill>) {
+        let db_manager = Arc::new(DatabaseManager::new(":memory:").await.unwrap());
+        db_manager.init().await.unwrap();
+        let (fill_tx, fill_rx) = mpsc::channel(8);
+        let config = PaperConfig { slippage_bps: 10.0, ..PaperConfig::default() };
+        let gateway = PaperExecutionGateway::new(MarketDataSource::Binance, &config, Some(fill_tx), broadcast::channel(16).0, db_manager);
+        (gateway, fill_rx)
+    }
+
+    fn quote(source: MarketDataSource, bid: f64, ask: f64) -> Quote {
+        Quote {
+            source,
+            symbol: "BTC/USDT".to_string(),
+            bid,
+            bid_size: 5.0,
+            ask,
+            ask_size: 5.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+        }
+    }
+
+    fn tick(price: f64) -> Tick {
+        Tick {
+            source: MarketDataSource::Binance,
+            symbol: "BTC/USDT".to_string(),
+            price,
+            volume: 5.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
+        }
+    }
+
+    fn limit(side: OrderSide, price: f64) -> Order {
+        Order {
+            order_type: OrderType::Limit,
+            price: Some(price),
+            ..Order::market("BTC/USDT".to_string(), side, 2.0, MarketDataSource::Strategy, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_market_orders_fill_past_the_touch_and_pay_taker_fees() {
+        let (gateway, mut fill_rx) = gateway().await;
+        let buy = Order::market("BTC/USDT".to_string(), OrderSide::Buy, 2.0, MarketDataSource::Strategy, None);
+        assert!(matches!(gateway.send_order(buy.clone()).await, Err(ExecutionError::Rejected(_))));
+
+        // Other venues' quotes are not ours.
+        gateway.on_quote(&quote(MarketDataSource::Kraken, 90.0, 91.0));
+        gateway.on_quote(&quote(MarketDataSource::Binance, 99.0, 100.0));
+        gateway.send_order(buy.clone()).await.unwrap();
+        let fill = fill_rx.recv().await.unwrap();
+        assert_eq!((fill.order_id, fill.side, fill.quantity, fill.source), (buy.id, OrderSide::Buy, 2.0, MarketDataSource::Binance));
+        assert!((fill.price - 100.1).abs() < 1e-9);
+        assert!((fill.commission - 100.1 * 2.0 * 0.001).abs() < 1e-9);
+        assert_eq!(fill.commission_asset.as_deref(), Some("USDT"));
+        assert_eq!(gateway.query_order("BTC/USDT", buy.id).await.unwrap().status, OrderStatus::Filled);
+    }
+
+    #[tokio::test]
+    async fn test_limit_orders_rest_until_the_feed_crosses_them() {
+        let (gateway, mut fill_rx) = gateway().await;
+        gateway.on_quote(&quote(MarketDataSource::Binance, 99.0, 100.0));
+        let bid = limit(OrderSide::Buy, 98.0);
+        let ask = limit(OrderSide::Sell, 102.0);
+        gateway.send_order(bid.clone()).await.unwrap();
+        gateway.send_order(ask.clone()).await.unwrap();
+        assert_eq!(gateway.open_orders(Some("BTC/USDT".to_string())).await.unwrap().len(), 2);
+
+        // A print at our price may have been ahead of us in the queue.
+        gateway.on_tick(&tick(98.0));
+        gateway.on_tick(&tick(97.5));
+        let fill = fill_rx.recv().await.unwrap();
+        assert_eq!((fill.order_id, fill.price), (bid.id, 98.0));
+        assert!((fill.commission - 98.0 * 2.0 * 0.001).abs() < 1e-9);
+
+        gateway.on_quote(&quote(MarketDataSource::Binance, 102.5, 103.0));
+        let fill = fill_rx.recv().await.unwrap();
+        assert_eq!((fill.order_id, fill.side, fill.price), (ask.id, OrderSide::Sell, 102.0));
+        assert!(gateway.open_orders(None).await.unwrap().is_empty());
+        assert!(matches!(gateway.cancel_order("BTC/USDT", ask.id).await, Err(ExecutionError::UnknownOrder(_))));
+    }
+
+    #[tokio::test]
+    async fn test_marketable_limit_orders_fill_on_arrival_and_amends_replace() {
+        let (gateway, mut fill_rx) = gateway().await;
+        gateway.on_quote(&quote(MarketDataSource::Binance, 99.0, 100.0));
+        let crossing = limit(OrderSide::Buy, 100.05);
+        gateway.send_order(crossing.clone()).await.unwrap();
+        // Slippage never takes a limit order past its price.
+        assert_eq!(fill_rx.recv().await.unwrap().price, 100.05);
+
+        let resting = limit(OrderSide::Sell, 105.0);
+        gateway.send_order(resting.clone()).await.unwrap();
+        let replacement = limit(OrderSide::Sell, 104.0);
+        let amended = gateway.amend_order(resting.id, replacement.clone()).await.unwrap().replacement;
+        assert_eq!((amended.order_id, amended.status, amended.price), (Some(replacement.id), OrderStatus::New, Some(104.0)));
+        assert_eq!(gateway.query_order("BTC/USDT", resting.id).await.unwrap().status, OrderStatus::Canceled);
+        assert_eq!(gateway.cancel_all_orders(None).await.unwrap().len(), 1);
+        assert!(fill_rx.try_recv().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_fills_never_exceed_the_size_shown() {
+        let (gateway, mut fill_rx) = gateway().await;
+        gateway.on_quote(&Quote { ask_size: 0.5, ..quote(MarketDataSource::Binance, 99.0, 100.0) });
+        let buy = Order::market("BTC/USDT".to_string(), OrderSide::Buy, 2.0, MarketDataSource::Strategy, None);
+        gateway.send_order(buy.clone()).await.unwrap();
+        assert_eq!(fill_rx.recv().await.unwrap().quantity, 0.5);
+        let report = gateway.query_order("BTC/USDT", buy.id).await.unwrap();
+        assert_eq!((report.status, report.filled), (OrderStatus::PartiallyFilled, 0.5));
+
+        // The rest of the market order takes the next ask.
+        gateway.on_quote(&quote(MarketDataSource::Binance, 100.0, 101.0));
+        let fill = fill_rx.recv().await.unwrap();
+        assert_eq!((fill.order_id, fill.quantity), (buy.id, 1.5));
+        assert!((fill.price - 101.101).abs() < 1e-9);
+        let report = gateway.query_order("BTC/USDT", buy.id).await.unwrap();
+        assert_eq!(report.status, OrderStatus::Filled);
+        assert!((report.avg_fill_price.unwrap() - (100.1 * 0.5 + 101.101 * 1.5) / 2.0).abs() < 1e-9);
+
+        // A print through a resting bid fills no more than it traded.
+        let bid = limit(OrderSide::Buy, 98.0);
+        gateway.send_order(bid.clone()).await.unwrap();
+        gateway.on_tick(&Tick { volume: 0.25, ..tick(97.0) });
+        assert_eq!(fill_rx.recv().await.unwrap().quantity, 0.25);
+        let report = gateway.query_order("BTC/USDT", bid.id).await.unwrap();
+        assert_eq!((report.status, report.filled), (OrderStatus::PartiallyFilled, 0.25));
+        assert_eq!(gateway.open_orders(None).await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_resting_orders_fill_in_arrival_order_and_leave_the_queue() {
+        let (gateway, mut fill_rx) = gateway().await;
+        gateway.on_quote(&quote(MarketDataSource::Binance, 99.0, 100.0));
+        let first = limit(OrderSide::Buy, 97.0);
+        let second = limit(OrderSide::Buy, 98.0);
+        gateway.send_order(first.clone()).await.unwrap();
+        gateway.send_order(second.clone()).await.unwrap();
+
+        // The older order goes first even at a worse price.
+        gateway.on_tick(&Tick { volume: 2.0, ..tick(96.0) });
+        assert_eq!(fill_rx.recv().await.unwrap().order_id, first.id);
+        let open = gateway.open_orders(None).await.unwrap();
+        assert_eq!(open.iter().map(|report| report.order_id).collect::<Vec<_>>(), vec![Some(second.id)]);
+        assert_eq!(gateway.query_order("BTC/USDT", first.id).await.unwrap().status, OrderStatus::Filled);
+        assert!(matches!(gateway.cancel_order("BTC/USDT", first.id).await, Err(ExecutionError::UnknownOrder(e)) if e.contains("Filled")));
+    }
+
+}
@@ -0,0 +1,323 @@
+use crate::config::InstrumentsConfig;
+use crate::connectors::{binance, binance_futures, coinbase, kraken};
+use crate::models::MarketDataSource;
+use anyhow::Result;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+/// A tradable pair on one venue, keyed by a venue-independent symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Instrument {
+    /// Canonical `BASE/QUOTE` symbol, e.g. `BTC/USDT`.
+    pub symbol: String,
+    pub base: String,
+    pub quote: String,
+    pub venue: MarketDataSource,
+    /// Name used by the venue's REST API and order entry.
+    pub venue_symbol: String,
+    /// Name used on the venue's WebSocket feeds.
+    pub ws_symbol: String,
+    pub tick_size: f64,
+    pub lot_size: f64,
+    pub min_notional: f64,
+}
+
+impl Instrument {
+    pub fn round_price(&self, price: f64) -> f64 {
+        round_to_increment(price, self.tick_size, f64::round)
+    }
+
+    /// Rounds down so an order never exceeds the intended size.
+    pub fn round_quantity(&self, quantity: f64) -> f64 {
+        round_to_increment(quantity, self.lot_size, f64::floor)
+    }
+
+    pub fn meets_min_notional(&self, price: f64, quantity: f64) -> bool {
+        price * quantity >= self.min_notional
+    }
+
+    /// A price as order entry takes it, with exactly the tick size's decimals.
+    pub fn format_price(&self, price: f64) -> String {
+        format!("{:.*}", increment_decimals(self.tick_size), self.round_price(price))
+    }
+
+    /// A quantity as order entry takes it, rounded down to the lot size and
+    /// written with its decimals.
+    pub fn format_quantity(&self, quantity: f64) -> String {
+        format!("{:.*}", increment_decimals(self.lot_size), self.round_quantity(quantity))
+    }
+}
+
+/// Decimal places of an increment such as `0.25` or `0.00000001`.
+fn increment_decimals(increment: f64) -> usize {
+    increment.to_string().split_once('.').map_or(0, |(_, fraction)| fraction.len())
+}
+
+fn round_to_increment(value: f64, increment: f64, round: fn(f64) -> f64) -> f64 {
+    if increment <= 0.0 {
+        return value;
+    }
+    // Snap to the increment's decimal places to shed float noise like 0.30000000000000004.
+    let decimals = (-increment.log10()).ceil().max(0.0) as i32;
+    let scale = 10f64.powi(decimals);
+    ((round(value / increment + 1e-9) * increment) * scale).round() / scale
+}
+
+/// Maps venue asset codes to the names used everywhere else, e.g. Kraken's
+/// `XBT` and `XXBT` to `BTC`.
+pub fn canonical_asset(asset: &str) -> String {
+    let asset = asset.to_uppercase();
+    match asset.as_str() {
+        "XBT" | "XXBT" => "BTC".to_string(),
+        "XDG" | "XXDG" => "DOGE".to_string(),
+        // Kraken prefixes legacy crypto codes with X and fiat with Z.
+        "XETH" | "XLTC" | "XXRP" | "XXLM" | "XETC" | "XMLN" | "XREP" | "XZEC" | "XXMR" => asset[1..].to_string(),
+        "ZUSD" | "ZEUR" | "ZGBP" | "ZCAD" | "ZJPY" | "ZAUD" | "ZCHF" => asset[1..].to_string(),
+        _ => asset,
+    }
+}
+
+pub fn canonical_symbol(base: &str, quote: &str) -> String {
+    format!("{}/{}", canonical_asset(base), canonical_asset(quote))
+}
+
+/// Every known instrument, looked up by canonical symbol or by any name a
+/// venue uses for it. Unknown symbols pass through unchanged.
+#[derive(Debug, Default)]
+pub struct InstrumentRegistry {
+    instruments: HashMap<(MarketDataSource, String), Instrument>,
+    by_venue_name: HashMap<(MarketDataSource, String), String>,
+}
+
+impl InstrumentRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(&mut self, instrument: Instrument) {
+        for name in [&instrument.venue_symbol, &instrument.ws_symbol, &instrument.symbol] {
+            self.by_venue_name
+                .insert((instrument.venue, name.to_uppercase()), instrument.symbol.clone());
+        }
+        self.instruments
+            .insert((instrument.venue, instrument.symbol.clone()), instrument);
+    }
+
+    pub fn get(&self, venue: MarketDataSource, symbol: &str) -> Option<&Instrument> {
+        self.instruments.get(&(venue, symbol.to_string()))
+    }
+
+    pub fn by_venue_symbol(&self, venue: MarketDataSource, venue_symbol: &str) -> Option<&Instrument> {
+        let symbol = self.by_venue_name.get(&(venue, venue_symbol.to_uppercase()))?;
+        self.get(venue, symbol)
+    }
+
+    /// Canonical symbol for a name seen on a venue feed or REST response.
+    pub fn canonical(&self, venue: MarketDataSource, venue_symbol: &str) -> String {
+        self.by_venue_symbol(venue, venue_symbol)
+            .map(|i| i.symbol.clone())
+            .unwrap_or_else(|| venue_symbol.to_string())
+    }
+
+    /// Venue name to place orders for a canonical symbol.
+    pub fn venue_symbol(&self, venue: MarketDataSource, symbol: &str) -> String {
+        self.get(venue, symbol)
+            .map(|i| i.venue_symbol.clone())
+            .unwrap_or_else(|| symbol.to_string())
+    }
+
+    /// Venue name to subscribe to for a canonical symbol.
+    pub fn ws_symbol(&self, venue: MarketDataSource, symbol: &str) -> String {
+        self.get(venue, symbol)
+            .map(|i| i.ws_symbol.clone())
+            .unwrap_or_else(|| symbol.to_string())
+    }
+
+    /// The subset of `symbols` listed on `venue`.
+    pub fn listed(&self, venue: MarketDataSource, symbols: &[String]) -> Vec<String> {
+        symbols
+            .iter()
+            .filter(|symbol| self.get(venue, symbol).is_some())
+            .cloned()
+            .collect()
+    }
+
+    pub fn len(&self) -> usize {
+        self.instruments.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.instruments.is_empty()
+    }
+
+    pub fn from_file(path: &str) -> Result<Self> {
+        let instruments: Vec<Instrument> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+        let mut registry = Self::new();
+        for instrument in instruments {
+            registry.insert(instrument);
+        }
+        Ok(registry)
+    }
+
+    pub fn save(&self, path: &str) -> Result<()> {
+        let mut instruments: Vec<&Instrument> = self.instruments.values().collect();
+        instruments.sort_by(|a, b| (a.venue.to_string(), &a.symbol).cmp(&(b.venue.to_string(), &b.symbol)));
+        std::fs::write(path, serde_json::to_string_pretty(&instruments)?)?;
+        Ok(())
+    }
+
+    /// Fetches trading rules from each of `venues` and refreshes the cache
+    /// file. A venue that cannot be reached keeps its rules from the cache, so
+    /// one outage never discards the fresh rules of the others.
+    pub async fn load(config: &InstrumentsConfig, venues: &[MarketDataSource]) -> Result<Self> {
+        let client = Client::new();
+        let fetched = futures::future::join_all(venues.iter().map(|venue| fetch_instruments(&client, *venue))).await;
+        let fresh = fetched.iter().any(Result::is_ok);
+        let registry = Self::assemble(venues.iter().copied().zip(fetched), &config.cache_path)?;
+        if fresh {
+            if let Err(e) = registry.save(&config.cache_path) {
+                warn!("Failed to write instrument cache {}: {}", config.cache_path, e);
+            }
+        }
+        Ok(registry)
+    }
+
+    /// Builds the registry from each venue's fetch, taking the rules of the
+    /// venues that failed from the cache at `cache_path`.
+    fn assemble(
+        fetched: impl IntoIterator<Item = (MarketDataSource, Result<Vec<Instrument>>)>,
+        cache_path: &str,
+    ) -> Result<Self> {
+        let mut registry = Self::new();
+        let mut failed = Vec::new();
+        for (venue, instruments) in fetched {
+            match instruments {
+                Ok(instruments) => {
+                    info!(venue = %venue, instruments = instruments.len(), "Loaded instruments from exchange");
+                    for instrument in instruments {
+                        registry.insert(instrument);
+                    }
+                }
+                Err(e) => {
+                    warn!("Failed to fetch {} instruments, using cache {}: {}", venue, cache_path, e);
+                    failed.push(venue);
+                }
+            }
+        }
+        if failed.is_empty() {
+            return Ok(registry);
+        }
+
+        match Self::from_file(cache_path) {
+            Ok(cache) => {
+                let cached = cache.instruments.into_values().filter(|instrument| failed.contains(&instrument.venue));
+                for instrument in cached {
+                    registry.insert(instrument);
+                }
+                info!(instruments = registry.len(), "Loaded missing venues from cache");
+            }
+            Err(e) if registry.is_empty() => return Err(e.context(format!("no instruments fetched and no cache at {}", cache_path))),
+            Err(e) => warn!("Instrument cache {} unavailable, {:?} will have no instruments: {}", cache_path, failed, e),
+        }
+        Ok(registry)
+    }
+}
+
+async fn fetch_instruments(client: &Client, venue: MarketDataSource) -> Result<Vec<Instrument>> {
+    match venue {
+        MarketDataSource::Binance => binance::fetch_instruments(client).await,
+        MarketDataSource::BinanceFutures => binance_futures::fetch_instruments(client).await,
+        MarketDataSource::Kraken => kraken::fetch_instruments(client).await,
+        MarketDataSource::Coinbase => coinbase::fetch_instruments(client).await,
+        other => anyhow::bail!("{} has no instrument list", other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kraken_btc_usdt() -> Instrument {
+        Instrument {
+            symbol: "BTC/USDT".to_string(),
+            base: "BTC".to_string(),
+            quote: "USDT".to_string(),
+            venue: MarketDataSource::Kraken,
+            venue_symbol: "XBTUSDT".to_string(),
+            ws_symbol: "XBT/USDT".to_string(),
+            tick_size: 0.1,
+            lot_size: 0.00000001,
+            min_notional: 0.5,
+        }
+    }
+
+    #[test]
+    fn test_kraken_aliases_resolve_to_canonical_symbol() {
+        assert_eq!(canonical_symbol("XXBT", "ZUSD"), "BTC/USD");
+        assert_eq!(canonical_symbol("XDG", "USD"), "DOGE/USD");
+        assert_eq!(canonical_symbol("SOL", "USDT"), "SOL/USDT");
+
+        let mut registry = InstrumentRegistry::new();
+        registry.insert(kraken_btc_usdt());
+        assert_eq!(registry.canonical(MarketDataSource::Kraken, "XBT/USDT"), "BTC/USDT");
+        assert_eq!(registry.canonical(MarketDataSource::Kraken, "xbtusdt"), "BTC/USDT");
+        assert_eq!(registry.canonical(MarketDataSource::Kraken, "BTC/USDT"), "BTC/USDT");
+        assert_eq!(registry.venue_symbol(MarketDataSource::Kraken, "BTC/USDT"), "XBTUSDT");
+        assert_eq!(registry.ws_symbol(MarketDataSource::Kraken, "BTC/USDT"), "XBT/USDT");
+        // Not listed on Binance, so names pass through.
+        assert_eq!(registry.canonical(MarketDataSource::Binance, "BTCUSDT"), "BTCUSDT");
+        assert!(registry.listed(MarketDataSource::Binance, &["BTC/USDT".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_a_failed_venue_falls_back_to_the_cache_alone() {
+        let path = std::env::temp_dir().join(format!("latency-x-instruments-{}.json", uuid::Uuid::new_v4()));
+        let path = path.to_str().unwrap();
+        let stale = Instrument { tick_size: 1.0, ..kraken_btc_usdt() };
+        let cached_binance = Instrument { venue: MarketDataSource::Binance, venue_symbol: "BTCUSDT".to_string(), ..stale.clone() };
+        let mut cache = InstrumentRegistry::new();
+        cache.insert(stale);
+        cache.insert(cached_binance.clone());
+        cache.save(path).unwrap();
+
+        let fetched = vec![
+            (MarketDataSource::Kraken, Ok(vec![kraken_btc_usdt()])),
+            (MarketDataSource::Binance, Err(anyhow::anyhow!("geo-blocked"))),
+        ];
+        let registry = InstrumentRegistry::assemble(fetched, path).unwrap();
+        assert_eq!(registry.get(MarketDataSource::Kraken, "BTC/USDT"), Some(&kraken_btc_usdt()));
+        assert_eq!(registry.get(MarketDataSource::Binance, "BTC/USDT"), Some(&cached_binance));
+        std::fs::remove_file(path).unwrap();
+
+        // Without a cache, whatever was fetched still loads.
+        let fetched = vec![
+            (MarketDataSource::Kraken, Ok(vec![kraken_btc_usdt()])),
+            (MarketDataSource::Binance, Err(anyhow::anyhow!("geo-blocked"))),
+        ];
+        assert_eq!(InstrumentRegistry::assemble(fetched, path).unwrap().len(), 1);
+        let fetched = vec![(MarketDataSource::Binance, Err(anyhow::anyhow!("geo-blocked")))];
+        assert!(InstrumentRegistry::assemble(fetched, path).is_err());
+    }
+
+    #[test]
+    fn test_instrument_rounding() {
+        let instrument = Instrument {
+            tick_size: 0.01,
+            lot_size: 0.001,
+            min_notional: 5.0,
+            ..kraken_btc_usdt()
+        };
+        assert_eq!(instrument.round_price(100.126), 100.13);
+        assert_eq!(instrument.round_price(0.3), 0.3);
+        assert_eq!(instrument.round_quantity(0.0129), 0.012);
+        assert_eq!(instrument.round_quantity(0.003), 0.003);
+        assert!(instrument.meets_min_notional(100.0, 0.05));
+        assert!(!instrument.meets_min_notional(100.0, 0.049));
+        assert_eq!(instrument.format_price(0.1 + 0.2), "0.30");
+        assert_eq!(instrument.format_quantity(0.0129), "0.012");
+        assert_eq!(Instrument { lot_size: 1.0, ..instrument.clone() }.format_quantity(3.7), "3");
+        assert_eq!(Instrument { tick_size: 0.00000001, ..instrument }.format_price(0.000012345678), "0.00001235");
+    }
+}
@@ -1,12 +1,18 @@
 
+pub mod backtest;
 pub mod connectors;
 pub mod execution;
+pub mod instruments;
 pub mod models;
+pub mod oms;
 pub mod settlement;
+#[cfg(any(test, feature = "simulator"))]
+pub mod simulator;
 pub mod strategies;
 pub mod config;
 pub mod risk;
 pub mod persistence;
 pub mod dashboard;
+pub mod candles;
 
 pub use models::{Tick, Order}; 
\ No newline at end of file
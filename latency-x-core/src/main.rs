@@ -1,33 +1,34 @@
-mod config;
-mod connectors;
-mod dashboard;
-mod execution;
-mod models;
-mod persistence;
-mod risk;
-mod settlement;
-mod strategies;
-
-use crate::connectors::{binance::BinanceConnector, kraken::KrakenConnector};
-use crate::connectors::Connector;
-use crate::execution::ExecutionGateway;
-use crate::models::Fill;
-use crate::persistence::db::DatabaseManager;
-use crate::risk::RiskManager;
-use crate::settlement::{helius::HeliusSettlement, Settlement};
-use crate::strategies::arbitrage::Arbitrage;
-use crate::strategies::market_maker::MarketMaker;
-use crate::strategies::Strategy;
-use crate::config::Config;
-use crate::dashboard::server::start_dashboard_server;
-use crate::dashboard::broadcaster_layer::DashboardBroadcastLayer;
-use crate::strategies::mev::MevStrategy;
-use crate::dashboard::events::DashboardEvent;
+use latency_x_core::connectors::{binance::BinanceConnector, binance_futures::BinanceFuturesConnector, coinbase::CoinbaseConnector, kraken::KrakenConnector};
+use latency_x_core::connectors::{watchdog::FeedWatchdog, Connector, MarkPriceConnector, OrderBookConnector, QuoteConnector};
+use latency_x_core::connectors::replay::{ReplayConnector, ReplaySpeed};
+use latency_x_core::connectors::subscriptions::SubscriptionManager;
+use latency_x_core::backtest::gateway::BacktestExecutionGateway;
+use latency_x_core::candles::CandleService;
+use latency_x_core::execution::{paper::PaperExecutionGateway, ExecutionGateway};
+use latency_x_core::instruments::InstrumentRegistry;
+use latency_x_core::models::{FeedStatus, Fill, MarkPrice, MarketDataSource, OrderBook, Quote};
+use latency_x_core::oms::OrderManager;
+use latency_x_core::persistence::capture::{CaptureRecord, CaptureRecorder};
+use latency_x_core::persistence::db::DatabaseManager;
+use latency_x_core::risk::RiskManager;
+use latency_x_core::settlement::{helius::HeliusSettlement, Settlement};
+use latency_x_core::strategies::arbitrage::Arbitrage;
+use latency_x_core::strategies::market_maker::MarketMaker;
+use latency_x_core::strategies::Strategy;
+use latency_x_core::config::Config;
+use latency_x_core::dashboard::server::start_dashboard_server;
+use latency_x_core::dashboard::broadcaster_layer::DashboardBroadcastLayer;
+use latency_x_core::strategies::mev::MevStrategy;
+use latency_x_core::dashboard::events::DashboardEvent;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 use tokio::sync::mpsc;
+#[cfg(unix)]
+use tokio::signal::unix::{signal, SignalKind};
 use clap::Parser;
 use tracing_subscriber::{self, EnvFilter};
-use metrics_exporter_prometheus::PrometheusBuilder;
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder};
 use std::net::SocketAddr;
 use tracing_subscriber::prelude::__tracing_subscriber_SubscriberExt;
 use tokio::sync::broadcast;
@@ -40,6 +41,18 @@ struct Cli {
     /// The trading strategy to use
     #[arg(short, long, value_enum)]
     strategy: StrategyChoice,
+
+    /// Replay capture files from this directory instead of connecting to exchanges
+    #[arg(long)]
+    replay: Option<PathBuf>,
+
+    /// Replay pacing: `original`, `max`, or a speed-up factor such as `10`
+    #[arg(long, default_value = "original")]
+    replay_speed: ReplaySpeed,
+
+    /// Fill orders against the market data feed instead of sending them
+    #[arg(long)]
+    paper: bool,
 }
 
 #[derive(clap::ValueEnum, Clone, Debug)]
@@ -69,6 +82,10 @@ async fn main() -> anyhow::Result<()> {
     let addr: SocketAddr = ([127, 0, 0, 1], 9000).into();
     builder
         .with_http_listener(addr)
+        .set_buckets_for_metric(
+            Matcher::Full("feed_latency_us".to_string()),
+            &[100.0, 250.0, 500.0, 1_000.0, 2_500.0, 5_000.0, 10_000.0, 25_000.0, 50_000.0, 100_000.0, 250_000.0, 1_000_000.0],
+        )?
         .install()
         .expect("failed to install Prometheus exporter");
 
@@ -77,81 +94,372 @@ async fn main() -> anyhow::Result<()> {
         anyhow::bail!("This application requires at least 4 CPU cores to run effectively.");
     }
 
-    let dashboard_tx_clone = dashboard_tx.clone();
-    let dashboard_core = core_ids[3];
     let db_manager = Arc::new(DatabaseManager::new("sqlite:latency_x.db").await?);
     db_manager.init().await?;
-    let db_manager_for_dashboard = db_manager.clone();
-    tokio::spawn(async move {
-        core_affinity::set_for_current(dashboard_core);
-        start_dashboard_server(dashboard_tx_clone, db_manager_for_dashboard).await;
-    });
     let risk_manager = Arc::new(RiskManager::new(db_manager.clone()).await?);
+    let oms = Arc::new(OrderManager::new(db_manager.clone()));
 
     let (tx, mut rx) = mpsc::channel(1024);
     let (fill_tx, mut fill_rx) = mpsc::channel::<Fill>(1024);
+    let (book_tx, mut book_rx) = mpsc::channel::<OrderBook>(1024);
+    let (quote_tx, mut quote_rx) = mpsc::channel::<Quote>(1024);
+    let (mark_price_tx, mut mark_price_rx) = mpsc::channel::<MarkPrice>(1024);
+    let (feed_status_tx, mut feed_status_rx) = mpsc::channel::<FeedStatus>(64);
 
     let settlement: Arc<dyn Settlement> = Arc::new(HeliusSettlement::new(&config.helius, &config.solana)?);
 
-    let binance_connector = Arc::new(BinanceConnector::new(settlement.clone(), Some(fill_tx.clone()), dashboard_tx.clone(), db_manager.clone()));
-    let kraken_connector = Arc::new(KrakenConnector::new(&config.kraken, settlement.clone(), Some(fill_tx.clone()), dashboard_tx.clone(), db_manager.clone()));
-    let binance_symbols = vec!["btcusdt".to_string()];
-    let kraken_symbols = vec!["BTC/USD".to_string()];
+    let mut venues = vec![MarketDataSource::Binance, MarketDataSource::Kraken, MarketDataSource::Coinbase];
+    if config.binance_futures.is_some() {
+        venues.push(MarketDataSource::BinanceFutures);
+    }
+    let instruments = Arc::new(InstrumentRegistry::load(&config.instruments, &venues).await?);
+    let symbols = &config.instruments.symbols;
+    let subscriptions = Arc::new(SubscriptionManager::new(instruments.clone(), symbols));
 
-    let binance_tx = tx.clone();
-    let binance_connector_clone = binance_connector.clone();
-    let binance_core = core_ids[0];
+    let dashboard_tx_clone = dashboard_tx.clone();
+    let dashboard_core = core_ids[3];
+    let db_manager_for_dashboard = db_manager.clone();
+    let subscriptions_for_dashboard = subscriptions.clone();
+    let oms_for_dashboard = oms.clone();
+    let dashboard_config = config.dashboard.clone();
     tokio::spawn(async move {
-        core_affinity::set_for_current(binance_core);
-        if let Err(e) = binance_connector_clone.subscribe(&binance_symbols, binance_tx).await {
-            tracing::error!("Binance connector error: {}", e);
-        }
+        core_affinity::set_for_current(dashboard_core);
+        start_dashboard_server(dashboard_tx_clone, db_manager_for_dashboard, subscriptions_for_dashboard, oms_for_dashboard, dashboard_config).await;
     });
 
-    let kraken_tx = tx.clone();
-    let kraken_connector_clone = kraken_connector.clone();
-    let kraken_core = core_ids[1];
-    tokio::spawn(async move {
-        core_affinity::set_for_current(kraken_core);
-        if let Err(e) = kraken_connector_clone.subscribe(&kraken_symbols, kraken_tx).await {
-            tracing::error!("Kraken connector error: {}", e);
+    let feed_watchdog = |source| FeedWatchdog::new(source, &config.feed, dashboard_tx.clone(), Some(feed_status_tx.clone()));
+    let (binance_execution, kraken_execution): (Arc<dyn ExecutionGateway>, Arc<dyn ExecutionGateway>) = if let Some(dir) = &cli.replay {
+        // Orders from a replayed session never leave the process.
+        let replay = Arc::new(ReplayConnector::from_dir(dir, cli.replay_speed)?);
+        tracing::info!("Replaying captures from {}", dir.display());
+
+        let replay_tx = tx.clone();
+        let replay_trade_connector = replay.clone();
+        let replay_trade_symbols = symbols.clone();
+        tokio::spawn(async move {
+            if let Err(e) = replay_trade_connector.subscribe(&replay_trade_symbols, replay_tx).await {
+                tracing::error!("Replay error: {}", e);
+            }
+        });
+
+        let replay_book_tx = book_tx.clone();
+        let replay_book_symbols = symbols.clone();
+        let replay_book_depth = config.binance.book_depth.max(config.kraken.book_depth);
+        tokio::spawn(async move {
+            if let Err(e) = replay.subscribe_order_book(&replay_book_symbols, replay_book_depth, replay_book_tx).await {
+                tracing::error!("Order book replay error: {}", e);
+            }
+        });
+
+        (Arc::new(BacktestExecutionGateway::new()), Arc::new(BacktestExecutionGateway::new()))
+    } else {
+        let binance_connector = Arc::new(
+            BinanceConnector::new(&config.binance, settlement.clone(), Some(fill_tx.clone()), dashboard_tx.clone(), db_manager.clone())
+                .with_watchdog(feed_watchdog(MarketDataSource::Binance))
+                .with_instruments(instruments.clone()),
+        );
+        let kraken_connector = Arc::new(
+            KrakenConnector::new(&config.kraken, settlement.clone(), Some(fill_tx.clone()), dashboard_tx.clone(), db_manager.clone())
+                .with_watchdog(feed_watchdog(MarketDataSource::Kraken))
+                .with_instruments(instruments.clone()),
+        );
+        let coinbase_connector = Arc::new(
+            CoinbaseConnector::new(&config.coinbase, Some(fill_tx.clone()), dashboard_tx.clone(), db_manager.clone())
+                .with_watchdog(feed_watchdog(MarketDataSource::Coinbase))
+                .with_instruments(instruments.clone()),
+        );
+        subscriptions.register(binance_connector.clone());
+        subscriptions.register(kraken_connector.clone());
+        subscriptions.register(coinbase_connector.clone());
+        let binance_symbols = instruments.listed(MarketDataSource::Binance, symbols);
+        let kraken_symbols = instruments.listed(MarketDataSource::Kraken, symbols);
+        let coinbase_symbols = instruments.listed(MarketDataSource::Coinbase, symbols);
+
+        let binance_tx = tx.clone();
+        let binance_connector_clone = binance_connector.clone();
+        let binance_core = core_ids[0];
+        let binance_trade_symbols = binance_symbols.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(binance_core);
+            if let Err(e) = binance_connector_clone.subscribe(&binance_trade_symbols, binance_tx).await {
+                tracing::error!("Binance connector error: {}", e);
+            }
+        });
+
+        let binance_user_data_connector = binance_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(binance_core);
+            if let Err(e) = binance_user_data_connector.subscribe_user_data().await {
+                tracing::error!("Binance user data stream error: {}", e);
+            }
+        });
+
+        let binance_book_symbols = binance_symbols.clone();
+        let binance_book_depth = config.binance.book_depth;
+        let binance_book_tx = book_tx.clone();
+        let binance_book_connector = binance_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(binance_core);
+            if let Err(e) = binance_book_connector.subscribe_order_book(&binance_book_symbols, binance_book_depth, binance_book_tx).await {
+                tracing::error!("Binance order book error: {}", e);
+            }
+        });
+
+        let binance_quote_symbols = binance_symbols.clone();
+        let binance_quote_tx = quote_tx.clone();
+        let binance_quote_connector = binance_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(binance_core);
+            if let Err(e) = binance_quote_connector.subscribe_quotes(&binance_quote_symbols, binance_quote_tx).await {
+                tracing::error!("Binance quote stream error: {}", e);
+            }
+        });
+
+        // Perpetuals share the Binance core; they only run when configured.
+        if let Some(futures_config) = &config.binance_futures {
+            let futures_connector = Arc::new(
+                BinanceFuturesConnector::new(futures_config, dashboard_tx.clone())
+                    .with_watchdog(feed_watchdog(MarketDataSource::BinanceFutures))
+                    .with_instruments(instruments.clone()),
+            );
+            subscriptions.register(futures_connector.clone());
+            let futures_symbols = instruments.listed(MarketDataSource::BinanceFutures, symbols);
+
+            let futures_tx = tx.clone();
+            let futures_trade_connector = futures_connector.clone();
+            let futures_trade_symbols = futures_symbols.clone();
+            tokio::spawn(async move {
+                core_affinity::set_for_current(binance_core);
+                if let Err(e) = futures_trade_connector.subscribe(&futures_trade_symbols, futures_tx).await {
+                    tracing::error!("Binance Futures connector error: {}", e);
+                }
+            });
+
+            let futures_mark_price_tx = mark_price_tx.clone();
+            tokio::spawn(async move {
+                core_affinity::set_for_current(binance_core);
+                if let Err(e) = futures_connector.subscribe_mark_prices(&futures_symbols, futures_mark_price_tx).await {
+                    tracing::error!("Binance Futures mark price stream error: {}", e);
+                }
+            });
         }
-    });
 
-    let binance_execution: Arc<dyn ExecutionGateway> = binance_connector.clone();
-    let kraken_execution: Arc<dyn ExecutionGateway> = kraken_connector.clone();
+        let kraken_tx = tx.clone();
+        let kraken_connector_clone = kraken_connector.clone();
+        let kraken_core = core_ids[1];
+        let kraken_trade_symbols = kraken_symbols.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(kraken_core);
+            if let Err(e) = kraken_connector_clone.subscribe(&kraken_trade_symbols, kraken_tx).await {
+                tracing::error!("Kraken connector error: {}", e);
+            }
+        });
+
+        let kraken_private_connector = kraken_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(kraken_core);
+            if let Err(e) = kraken_private_connector.subscribe_private().await {
+                tracing::error!("Kraken private feed error: {}", e);
+            }
+        });
+
+        let kraken_book_symbols = kraken_symbols.clone();
+        let kraken_book_depth = config.kraken.book_depth;
+        let kraken_book_tx = book_tx.clone();
+        let kraken_book_connector = kraken_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(kraken_core);
+            if let Err(e) = kraken_book_connector.subscribe_order_book(&kraken_book_symbols, kraken_book_depth, kraken_book_tx).await {
+                tracing::error!("Kraken order book error: {}", e);
+            }
+        });
+
+        let kraken_quote_symbols = kraken_symbols.clone();
+        let kraken_quote_tx = quote_tx.clone();
+        let kraken_quote_connector = kraken_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(kraken_core);
+            if let Err(e) = kraken_quote_connector.subscribe_quotes(&kraken_quote_symbols, kraken_quote_tx).await {
+                tracing::error!("Kraken quote stream error: {}", e);
+            }
+        });
+
+        // Coinbase shares the Kraken core; both feeds are comparatively slow.
+        let coinbase_tx = tx.clone();
+        let coinbase_trade_symbols = coinbase_symbols.clone();
+        let coinbase_connector_clone = coinbase_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(kraken_core);
+            if let Err(e) = coinbase_connector_clone.subscribe(&coinbase_trade_symbols, coinbase_tx).await {
+                tracing::error!("Coinbase connector error: {}", e);
+            }
+        });
+
+        let coinbase_user_connector = coinbase_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(kraken_core);
+            if let Err(e) = coinbase_user_connector.subscribe_user().await {
+                tracing::error!("Coinbase user feed error: {}", e);
+            }
+        });
+
+        let coinbase_book_depth = config.coinbase.book_depth;
+        let coinbase_book_tx = book_tx.clone();
+        let coinbase_book_connector = coinbase_connector.clone();
+        tokio::spawn(async move {
+            core_affinity::set_for_current(kraken_core);
+            if let Err(e) = coinbase_book_connector.subscribe_order_book(&coinbase_symbols, coinbase_book_depth, coinbase_book_tx).await {
+                tracing::error!("Coinbase order book error: {}", e);
+            }
+        });
+
+        (binance_connector, kraken_connector)
+    };
+    // Paper gateways follow the feeds from the main loop below.
+    let mut paper = Vec::new();
+    let (binance_execution, kraken_execution) = if cli.paper {
+        tracing::info!("Paper trading; orders fill against the feed with [paper] slippage and fees");
+        let gateway = |venue| {
+            Arc::new(PaperExecutionGateway::new(venue, &config.paper, Some(fill_tx.clone()), dashboard_tx.clone(), db_manager.clone()))
+        };
+        let (binance, kraken) = (gateway(MarketDataSource::Binance), gateway(MarketDataSource::Kraken));
+        paper = vec![binance.clone(), kraken.clone()];
+        (binance as Arc<dyn ExecutionGateway>, kraken as Arc<dyn ExecutionGateway>)
+    } else {
+        (binance_execution, kraken_execution)
+    };
+    // Only the spawned feeds hold senders now, so the main loop ends once a replay runs dry.
+    drop((tx, book_tx, quote_tx, mark_price_tx, feed_status_tx));
+
+    // Edit [instruments] symbols in Config.toml and send SIGHUP to follow the new list.
+    #[cfg(unix)]
+    {
+        let subscriptions = subscriptions.clone();
+        tokio::spawn(async move {
+            let mut hangups = match signal(SignalKind::hangup()) {
+                Ok(hangups) => hangups,
+                Err(e) => {
+                    tracing::error!("Config reload on SIGHUP unavailable: {}", e);
+                    return;
+                }
+            };
+            while hangups.recv().await.is_some() {
+                let reloaded = match Config::from_file("Config.toml") {
+                    Ok(config) => subscriptions.sync(&config.instruments.symbols).await,
+                    Err(e) => Err(e),
+                };
+                match reloaded {
+                    Ok(()) => tracing::info!("Reloaded symbols: {:?}", subscriptions.symbols()),
+                    Err(e) => tracing::error!("Failed to reload Config.toml: {}", e),
+                }
+            }
+        });
+    }
     
+    let strategy_symbol = symbols
+        .first()
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("No symbols configured under [instruments]"))?;
+    // Strategies trade through the OMS, which tracks every order they send.
     let mut strategy: Box<dyn Strategy> = match cli.strategy {
         StrategyChoice::Arbitrage => {
-            Box::new(Arbitrage::new(binance_execution, kraken_execution, 0.0001, 1.0, db_manager.clone()))
+            Box::new(
+                Arbitrage::new(
+                    oms.gateway("arbitrage", "Binance", binance_execution),
+                    oms.gateway("arbitrage", "Kraken", kraken_execution),
+                    strategy_symbol,
+                    0.0001,
+                    1.0,
+                )
+                .with_instruments(instruments.clone()),
+            )
         }
         StrategyChoice::MarketMaker => {
-            Box::new(MarketMaker::new(binance_execution, 0.01, 0.01, "BTCUSDT".to_string(), db_manager.clone()))
+            Box::new(
+                MarketMaker::new(oms.gateway("market_maker", "Binance", binance_execution), 0.01, 0.01, strategy_symbol)
+                    .with_instruments(instruments.clone()),
+            )
         }
         StrategyChoice::Mev => {
             // For now, we'll only use the binance connector for triangular arbitrage.
-            let mev_execution: Arc<dyn ExecutionGateway> = Arc::new(BinanceConnector::new(settlement.clone(), Some(fill_tx.clone()), dashboard_tx.clone(), db_manager.clone()));
             Box::new(MevStrategy::new(
-                mev_execution,
+                oms.gateway("mev", "Binance", binance_execution),
                 &config.mev_strategy,
-                db_manager.clone(),
             ))
         }
     };
+    oms.spawn_reconciler(Duration::from_secs(config.oms.reconcile_interval_secs));
 
     // This is a placeholder for the risk manager loop
     let rm_clone = risk_manager.clone();
+    let oms_for_fills = oms.clone();
     let risk_manager_core = core_ids[2];
     tokio::spawn(async move {
         core_affinity::set_for_current(risk_manager_core);
         while let Some(fill) = fill_rx.recv().await {
             rm_clone.on_fill(&fill).await;
+            oms_for_fills.on_fill(&fill).await;
         }
     });
 
-    while let Some(tick) = rx.recv().await {
-        if let Err(e) = strategy.on_tick(&tick).await {
-            tracing::error!("Strategy error: {}", e);
+    let capture = if config.capture.enabled {
+        Some(CaptureRecorder::spawn(&config.capture)?)
+    } else {
+        None
+    };
+    let candles = if config.candles.enabled {
+        Some(CandleService::spawn(&config.candles, db_manager.clone(), dashboard_tx.clone())?)
+    } else {
+        None
+    };
+
+    loop {
+        tokio::select! {
+            Some(tick) = rx.recv() => {
+                for gateway in &paper {
+                    gateway.on_tick(&tick);
+                }
+                if let Err(e) = strategy.on_tick(&tick).await {
+                    tracing::error!("Strategy error: {}", e);
+                }
+                // Recorded after the strategy has seen it, off the hot path.
+                if let Some(candles) = &candles {
+                    candles.record(&tick);
+                }
+                if let Some(capture) = &capture {
+                    capture.record(CaptureRecord::Tick(tick));
+                }
+            }
+            Some(quote) = quote_rx.recv() => {
+                for gateway in &paper {
+                    gateway.on_quote(&quote);
+                }
+                if let Err(e) = strategy.on_quote(&quote).await {
+                    tracing::error!("Strategy error: {}", e);
+                }
+            }
+            Some(mark_price) = mark_price_rx.recv() => {
+                if let Err(e) = strategy.on_mark_price(&mark_price).await {
+                    tracing::error!("Strategy error: {}", e);
+                }
+            }
+            Some(book) = book_rx.recv() => {
+                for gateway in &paper {
+                    gateway.on_order_book(&book);
+                }
+                if let Err(e) = strategy.on_order_book(&book).await {
+                    tracing::error!("Strategy error: {}", e);
+                }
+                if let Some(capture) = &capture {
+                    capture.record(CaptureRecord::Book(book));
+                }
+            }
+            Some(status) = feed_status_rx.recv() => {
+                if let Err(e) = strategy.on_feed_status(&status).await {
+                    tracing::error!("Strategy error: {}", e);
+                }
+            }
+            else => break,
         }
     }
 
@@ -6,20 +6,44 @@ use strum_macros::Display;
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
 pub enum MarketDataSource {
     Binance,
+    /// Binance USD-M perpetual futures.
+    BinanceFutures,
     Kraken,
     Coinbase,
     PumpFun,
     Strategy,
+    /// Recorded data played back by the replay connector.
+    Replay,
 }
 
 impl std::fmt::Display for MarketDataSource {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             MarketDataSource::Binance => write!(f, "Binance"),
+            MarketDataSource::BinanceFutures => write!(f, "Binance Futures"),
             MarketDataSource::Kraken => write!(f, "Kraken"),
             MarketDataSource::Coinbase => write!(f, "Coinbase"),
             MarketDataSource::PumpFun => write!(f, "Pump.fun"),
             MarketDataSource::Strategy => write!(f, "Strategy"),
+            MarketDataSource::Replay => write!(f, "Replay"),
+        }
+    }
+}
+
+impl std::str::FromStr for MarketDataSource {
+    type Err = anyhow::Error;
+
+    /// Parses the `Display` form back, as written to capture files and the database.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "Binance" => Ok(MarketDataSource::Binance),
+            "Binance Futures" => Ok(MarketDataSource::BinanceFutures),
+            "Kraken" => Ok(MarketDataSource::Kraken),
+            "Coinbase" => Ok(MarketDataSource::Coinbase),
+            "Pump.fun" => Ok(MarketDataSource::PumpFun),
+            "Strategy" => Ok(MarketDataSource::Strategy),
+            "Replay" => Ok(MarketDataSource::Replay),
+            other => anyhow::bail!("Unknown market data source: {}", other),
         }
     }
 }
@@ -31,16 +55,228 @@ pub struct Tick {
     pub price: f64,
     pub volume: f64,
     pub received_at: DateTime<Utc>,
+    /// When the venue says the trade happened, if it reports it.
+    #[serde(default)]
+    pub exchange_ts: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub trade_id: Option<u64>,
+}
+
+impl Tick {
+    /// Exchange-to-receive latency. Can be negative when the local clock
+    /// lags the venue's.
+    pub fn feed_latency_us(&self) -> Option<i64> {
+        self.exchange_ts
+            .and_then(|ts| self.received_at.signed_duration_since(ts).num_microseconds())
+    }
+}
+
+/// Best bid and offer on one venue: the prices an order can actually trade at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub source: MarketDataSource,
+    pub symbol: String,
+    pub bid: f64,
+    pub bid_size: f64,
+    pub ask: f64,
+    pub ask_size: f64,
+    pub received_at: DateTime<Utc>,
+    #[serde(default)]
+    pub exchange_ts: Option<DateTime<Utc>>,
+}
+
+impl Quote {
+    pub fn mid(&self) -> f64 {
+        (self.bid + self.ask) / 2.0
+    }
+
+    pub fn spread(&self) -> f64 {
+        self.ask - self.bid
+    }
+}
+
+/// Mark price and funding of a perpetual future.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarkPrice {
+    pub source: MarketDataSource,
+    pub symbol: String,
+    /// Price positions are marked and liquidated at.
+    pub mark_price: f64,
+    /// Spot index the contract tracks.
+    pub index_price: f64,
+    /// Rate paid by longs to shorts (negative: shorts pay) at `next_funding_time`.
+    pub funding_rate: f64,
+    pub next_funding_time: DateTime<Utc>,
+    pub received_at: DateTime<Utc>,
+    #[serde(default)]
+    pub exchange_ts: Option<DateTime<Utc>>,
+}
+
+impl MarkPrice {
+    /// Premium of the contract over spot.
+    pub fn basis(&self) -> f64 {
+        self.mark_price - self.index_price
+    }
+}
+
+/// An OHLCV bar of one venue's trades in a symbol.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Candle {
+    pub source: MarketDataSource,
+    pub symbol: String,
+    pub interval_secs: u64,
+    /// Start of the bar, a multiple of the interval since the Unix epoch.
+    pub open_time: DateTime<Utc>,
+    pub open: f64,
+    pub high: f64,
+    pub low: f64,
+    pub close: f64,
+    pub volume: f64,
+    /// Number of trades in the bar.
+    pub trades: u64,
+}
+
+impl Candle {
+    /// End of the bar, exclusive.
+    pub fn close_time(&self) -> DateTime<Utc> {
+        self.open_time + chrono::Duration::seconds(self.interval_secs as i64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display)]
+pub enum FeedState {
+    Up,
+    Stale,
+    Reconnecting,
+}
+
+/// One of a venue's market data connections.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash, Display)]
+pub enum FeedStream {
+    Trades,
+    Book,
+    Quotes,
+    MarkPrice,
+}
+
+/// Health of one stream of a market data feed. `symbol` is `None` when the
+/// whole connection is affected and it follows no symbols.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FeedStatus {
+    pub source: MarketDataSource,
+    pub stream: FeedStream,
+    pub symbol: Option<String>,
+    pub state: FeedState,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct PriceLevel {
+    pub price: f64,
+    pub quantity: f64,
+}
+
+/// A locally maintained L2 order book. Bids are kept best (highest) first and
+/// asks best (lowest) first; a level with zero quantity is removed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderBook {
+    pub source: MarketDataSource,
+    pub symbol: String,
+    pub bids: Vec<PriceLevel>,
+    pub asks: Vec<PriceLevel>,
+    pub last_update_id: u64,
+    pub received_at: DateTime<Utc>,
+}
+
+impl OrderBook {
+    pub fn new(source: MarketDataSource, symbol: String) -> Self {
+        Self {
+            source,
+            symbol,
+            bids: Vec::new(),
+            asks: Vec::new(),
+            last_update_id: 0,
+            received_at: Utc::now(),
+        }
+    }
+
+    pub fn update_bid(&mut self, price: f64, quantity: f64) {
+        let idx = self.bids.partition_point(|level| level.price > price);
+        Self::update_level(&mut self.bids, idx, price, quantity);
+    }
+
+    pub fn update_ask(&mut self, price: f64, quantity: f64) {
+        let idx = self.asks.partition_point(|level| level.price < price);
+        Self::update_level(&mut self.asks, idx, price, quantity);
+    }
+
+    fn update_level(levels: &mut Vec<PriceLevel>, idx: usize, price: f64, quantity: f64) {
+        let exists = levels.get(idx).is_some_and(|level| level.price == price);
+        match (exists, quantity > 0.0) {
+            (true, true) => levels[idx].quantity = quantity,
+            (true, false) => {
+                levels.remove(idx);
+            }
+            (false, true) => levels.insert(idx, PriceLevel { price, quantity }),
+            (false, false) => {}
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+        self.last_update_id = 0;
+    }
+
+    pub fn best_bid(&self) -> Option<&PriceLevel> {
+        self.bids.first()
+    }
+
+    pub fn best_ask(&self) -> Option<&PriceLevel> {
+        self.asks.first()
+    }
+
+    pub fn mid_price(&self) -> Option<f64> {
+        match (self.best_bid(), self.best_ask()) {
+            (Some(bid), Some(ask)) => Some((bid.price + ask.price) / 2.0),
+            _ => None,
+        }
+    }
+
+    /// Returns a copy of the book limited to the top `depth` levels per side.
+    pub fn truncated(&self, depth: usize) -> OrderBook {
+        OrderBook {
+            source: self.source,
+            symbol: self.symbol.clone(),
+            bids: self.bids.iter().take(depth).copied().collect(),
+            asks: self.asks.iter().take(depth).copied().collect(),
+            last_update_id: self.last_update_id,
+            received_at: self.received_at,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Display)]
 pub enum OrderStatus {
     New,
+    PartiallyFilled,
     Filled,
     Canceled,
+    /// Refused by the venue.
+    Rejected,
+    /// Withdrawn by the venue when its time in force ran out.
+    Expired,
+    /// Could not be sent to the venue.
     Failed,
 }
 
+impl OrderStatus {
+    /// Whether the order can still trade.
+    pub fn is_open(&self) -> bool {
+        matches!(self, OrderStatus::New | OrderStatus::PartiallyFilled)
+    }
+}
+
 #[derive(Display, Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
 pub enum OrderSide {
     Buy,
@@ -53,6 +289,15 @@ pub enum OrderType {
     Limit,
 }
 
+/// The futures position an order trades against. `Both` is the single
+/// position of one-way mode; `Long` and `Short` are the legs of hedge mode.
+#[derive(Display, Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash, Copy)]
+pub enum PositionSide {
+    Both,
+    Long,
+    Short,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Order {
     pub id: Uuid,
@@ -65,6 +310,12 @@ pub struct Order {
     pub source: MarketDataSource,
     pub created_at: DateTime<Utc>,
     pub triggering_tick: Option<Box<Tick>>,
+    /// Futures only: the order may shrink the position but never grow or flip it.
+    #[serde(default)]
+    pub reduce_only: bool,
+    /// Futures only; `None` leaves it to the venue's default.
+    #[serde(default)]
+    pub position_side: Option<PositionSide>,
 }
 
 impl Order {
@@ -86,10 +337,46 @@ impl Order {
             source,
             created_at: Utc::now(),
             triggering_tick,
+            reduce_only: false,
+            position_side: None,
         }
     }
 }
 
+/// An order as the venue currently sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderReport {
+    /// `None` for orders placed outside this process.
+    pub order_id: Option<Uuid>,
+    pub exchange_order_id: String,
+    pub symbol: String,
+    pub side: OrderSide,
+    pub order_type: OrderType,
+    pub status: OrderStatus,
+    pub amount: f64,
+    /// Quantity executed so far.
+    pub filled: f64,
+    pub price: Option<f64>,
+    /// Volume-weighted price of the executions, once there are any.
+    pub avg_fill_price: Option<f64>,
+    pub source: MarketDataSource,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl OrderReport {
+    pub fn remaining(&self) -> f64 {
+        (self.amount - self.filled).max(0.0)
+    }
+}
+
+/// The result of an amend: the original order as it was canceled, with
+/// whatever it executed before the cancel, and the order placed instead.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Amendment {
+    pub canceled: OrderReport,
+    pub replacement: OrderReport,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Trade {
     pub id: Uuid,
@@ -111,6 +398,14 @@ pub struct Fill {
     pub quantity: f64,
     pub source: MarketDataSource,
     pub executed_at: DateTime<Utc>,
+    /// Fee charged by the venue, in `commission_asset`.
+    #[serde(default)]
+    pub commission: f64,
+    #[serde(default)]
+    pub commission_asset: Option<String>,
+    /// Venue trade id, when the fill came from an exchange execution report.
+    #[serde(default)]
+    pub trade_id: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -0,0 +1,451 @@
+//! Order management between strategies and execution gateways. Every order
+//! sent through a [`ManagedGateway`] gets a fresh client id, is tracked
+//! through the [`OrderState`] machine as acks, fills and venue reports
+//! arrive, and has each transition written to `order_events`.
+
+pub mod state;
+
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::models::{Amendment, Fill, Order, OrderReport};
+use crate::persistence::db::DatabaseManager;
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+
+pub use state::{ManagedOrder, OrderEvent, OrderState, OrderTransition};
+
+pub struct OrderManager {
+    orders: Mutex<HashMap<Uuid, ManagedOrder>>,
+    /// Orders whose send failed in a way that may still have placed them.
+    /// They stay `PendingNew` until reconciliation finds out.
+    unconfirmed: Mutex<HashSet<Uuid>>,
+    /// Gateways by venue name, for reconciliation.
+    gateways: Mutex<Vec<(String, Arc<dyn ExecutionGateway>)>>,
+    db_manager: Arc<DatabaseManager>,
+}
+
+impl OrderManager {
+    pub fn new(db_manager: Arc<DatabaseManager>) -> Self {
+        Self {
+            orders: Mutex::new(HashMap::new()),
+            unconfirmed: Mutex::new(HashSet::new()),
+            gateways: Mutex::new(Vec::new()),
+            db_manager,
+        }
+    }
+
+    /// Wraps `gateway` for `strategy`. Orders it sends are tracked under
+    /// `venue`, which also names the gateway for reconciliation.
+    pub fn gateway(self: &Arc<Self>, strategy: &str, venue: &str, gateway: Arc<dyn ExecutionGateway>) -> Arc<ManagedGateway> {
+        let mut gateways = self.gateways.lock().unwrap();
+        if !gateways.iter().any(|(name, _)| name == venue) {
+            gateways.push((venue.to_string(), gateway.clone()));
+        }
+        Arc::new(ManagedGateway {
+            oms: self.clone(),
+            strategy: strategy.to_string(),
+            venue: venue.to_string(),
+            inner: gateway,
+        })
+    }
+
+    pub fn order(&self, order_id: Uuid) -> Option<ManagedOrder> {
+        self.orders.lock().unwrap().get(&order_id).cloned()
+    }
+
+    /// Orders that may still trade, optionally only one strategy's or one
+    /// symbol's, oldest first.
+    pub fn working_orders(&self, strategy: Option<&str>, symbol: Option<&str>) -> Vec<ManagedOrder> {
+        let orders = self.orders.lock().unwrap();
+        let mut working: Vec<_> = orders
+            .values()
+            .filter(|order| order.state.is_working())
+            .filter(|order| strategy.is_none_or(|strategy| order.strategy == strategy))
+            .filter(|order| symbol.is_none_or(|symbol| order.order.symbol == symbol))
+            .cloned()
+            .collect();
+        working.sort_by_key(|order| order.order.created_at);
+        working
+    }
+
+    /// Fills for orders sent elsewhere are ignored.
+    pub async fn on_fill(&self, fill: &Fill) {
+        self.apply(fill.order_id, OrderEvent::Fill(fill.clone())).await;
+    }
+
+    /// Gives a new order its client id and starts tracking it.
+    async fn submit(&self, mut order: Order, strategy: &str, venue: &str) -> Order {
+        order.id = Uuid::new_v4();
+        let managed = ManagedOrder::new(order.clone(), strategy, venue);
+        if let Err(e) = self.db_manager.save_managed_order(&managed).await {
+            tracing::error!("Failed to save order {}: {}", order.id, e);
+        }
+        self.orders.lock().unwrap().insert(order.id, managed);
+        order
+    }
+
+    async fn apply(&self, order_id: Uuid, event: OrderEvent) {
+        let transition = {
+            let mut orders = self.orders.lock().unwrap();
+            let Some(order) = orders.get_mut(&order_id) else {
+                return;
+            };
+            match order.apply(event) {
+                Ok(transition) => transition,
+                Err(e) => {
+                    tracing::warn!("{}", e);
+                    None
+                }
+            }
+        };
+        if let Some(transition) = transition {
+            tracing::debug!("Order {} {} -> {} on {}", order_id, transition.from, transition.to, transition.event);
+            if let Err(e) = self.db_manager.record_order_transition(&transition).await {
+                tracing::error!("Failed to record order {} transition: {}", order_id, e);
+            }
+        }
+    }
+
+    async fn apply_report(&self, report: &OrderReport) {
+        if let Some(order_id) = report.order_id {
+            self.apply(order_id, OrderEvent::Report(report.clone())).await;
+        }
+    }
+
+    /// Records why a new order was not acknowledged. Orders the venue may
+    /// have placed anyway are left for reconciliation to settle.
+    async fn send_failed(&self, order_id: Uuid, error: &ExecutionError) {
+        let reason = error.to_string();
+        match error {
+            ExecutionError::Rejected(_) | ExecutionError::InsufficientFunds(_) => {
+                self.apply(order_id, OrderEvent::Rejected { reason }).await
+            }
+            error if error.is_uncertain() => {
+                tracing::warn!("Order {} may have been placed: {}", order_id, reason);
+                self.unconfirmed.lock().unwrap().insert(order_id);
+            }
+            _ => self.apply(order_id, OrderEvent::Failed { reason }).await,
+        }
+    }
+
+    /// Brings working orders in line with each venue. Orders a venue still
+    /// lists take its status and fills; ones it no longer lists are queried
+    /// for their final state, and unconfirmed ones the venue does not know
+    /// fail. Open orders we did not send are logged.
+    pub async fn reconcile(&self) {
+        let gateways = self.gateways.lock().unwrap().clone();
+        for (venue, gateway) in gateways {
+            let open = match gateway.open_orders(None).await {
+                Ok(open) => open,
+                Err(e) => {
+                    tracing::debug!("Skipping {} reconciliation: {}", venue, e);
+                    continue;
+                }
+            };
+            let mut listed = Vec::with_capacity(open.len());
+            for report in &open {
+                match report.order_id.filter(|order_id| self.orders.lock().unwrap().contains_key(order_id)) {
+                    Some(order_id) => {
+                        listed.push(order_id);
+                        self.apply_report(report).await;
+                    }
+                    None => tracing::warn!(
+                        "Untracked open order {} on {}: {} {} {}",
+                        report.exchange_order_id, venue, report.side, report.remaining(), report.symbol
+                    ),
+                }
+            }
+
+            let unconfirmed = self.unconfirmed.lock().unwrap().clone();
+            let missing: Vec<_> = self
+                .working_orders(None, None)
+                .into_iter()
+                // Still in flight; the venue may not list them yet.
+                .filter(|order| order.state != OrderState::PendingNew || unconfirmed.contains(&order.order.id))
+                .filter(|order| order.venue == venue && !listed.contains(&order.order.id))
+                .collect();
+            for order in missing {
+                let order_id = order.order.id;
+                match gateway.query_order(&order.order.symbol, order_id).await {
+                    Ok(report) => self.apply_report(&report).await,
+                    Err(e @ ExecutionError::UnknownOrder(_)) if unconfirmed.contains(&order_id) => {
+                        self.apply(order_id, OrderEvent::Failed { reason: e.to_string() }).await
+                    }
+                    Err(e) => {
+                        tracing::warn!("Could not reconcile order {} on {}: {}", order_id, venue, e);
+                        continue;
+                    }
+                }
+                self.unconfirmed.lock().unwrap().remove(&order_id);
+            }
+        }
+    }
+
+    /// Reconciles every `interval` until the task is aborted.
+    pub fn spawn_reconciler(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let oms = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                oms.reconcile().await;
+            }
+        })
+    }
+}
+
+/// One strategy's view of a gateway. Order ids passed to `send_order` are
+/// replaced; the assigned id is returned instead of the venue's.
+pub struct ManagedGateway {
+    oms: Arc<OrderManager>,
+    strategy: String,
+    venue: String,
+    inner: Arc<dyn ExecutionGateway>,
+}
+
+impl ManagedGateway {
+    /// This strategy's working orders on this venue.
+    pub fn working_orders(&self, symbol: Option<&str>) -> Vec<ManagedOrder> {
+        self.oms
+            .working_orders(Some(&self.strategy), symbol)
+            .into_iter()
+            .filter(|order| order.venue == self.venue)
+            .collect()
+    }
+
+    async fn apply_reports(&self, reports: &[OrderReport]) {
+        for report in reports {
+            self.oms.apply_report(report).await;
+        }
+    }
+}
+
+#[async_trait]
+impl ExecutionGateway for ManagedGateway {
+    async fn send_order(&self, order: Order) -> Result<String, ExecutionError> {
+        let order = self.oms.submit(order, &self.strategy, &self.venue).await;
+        let order_id = order.id;
+        match self.inner.send_order(order).await {
+            Ok(exchange_order_id) => {
+                self.oms.apply(order_id, OrderEvent::Acknowledged { exchange_order_id }).await;
+                Ok(order_id.to_string())
+            }
+            Err(e) => {
+                self.oms.send_failed(order_id, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn cancel_order(&self, symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        self.oms.apply(order_id, OrderEvent::CancelRequested).await;
+        match self.inner.cancel_order(symbol, order_id).await {
+            Ok(report) => {
+                self.oms.apply_report(&report).await;
+                Ok(report)
+            }
+            Err(e) => {
+                self.oms.apply(order_id, OrderEvent::CancelRefused { reason: e.to_string() }).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn cancel_all_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let reports = self.inner.cancel_all_orders(symbol).await?;
+        self.apply_reports(&reports).await;
+        Ok(reports)
+    }
+
+    /// The replacement gets a client id of its own and is tracked from
+    /// `PendingNew` like any other order. The original keeps what it
+    /// executed before the cancel, as the cancel leg reports it.
+    async fn amend_order(&self, order_id: Uuid, replacement: Order) -> Result<Amendment, ExecutionError> {
+        self.oms.apply(order_id, OrderEvent::CancelRequested).await;
+        let replacement = self.oms.submit(replacement, &self.strategy, &self.venue).await;
+        let replacement_id = replacement.id;
+        match self.inner.amend_order(order_id, replacement).await {
+            Ok(amendment) => {
+                self.oms.apply(order_id, OrderEvent::Replaced { replacement_id }).await;
+                self.oms.apply_report(&amendment.canceled).await;
+                let exchange_order_id = amendment.replacement.exchange_order_id.clone();
+                self.oms.apply(replacement_id, OrderEvent::Acknowledged { exchange_order_id }).await;
+                self.oms.apply_report(&amendment.replacement).await;
+                Ok(amendment)
+            }
+            Err(e) => {
+                self.oms.apply(order_id, OrderEvent::CancelRefused { reason: e.to_string() }).await;
+                self.oms.send_failed(replacement_id, &e).await;
+                Err(e)
+            }
+        }
+    }
+
+    async fn query_order(&self, symbol: &str, order_id: Uuid) -> Result<OrderReport, ExecutionError> {
+        let report = self.inner.query_order(symbol, order_id).await?;
+        self.oms.apply_report(&report).await;
+        Ok(report)
+    }
+
+    async fn open_orders(&self, symbol: Option<String>) -> Result<Vec<OrderReport>, ExecutionError> {
+        let reports = self.inner.open_orders(symbol).await?;
+        self.apply_reports(&reports).await;
+        Ok(reports)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backtest::gateway::BacktestExecutionGateway;
+    use crate::execution::mock::MockExecutionGateway;
+    use crate::models::{MarketDataSource, OrderSide, OrderStatus, OrderType};
+    use chrono::Utc;
+
+    fn limit(price: f64) -> Order {
+        Order {
+            order_type: OrderType::Limit,
+            price: Some(price),
+            ..Order::market("BTC/USDT".to_string(), OrderSide::Buy, 1.0, MarketDataSource::Strategy, None)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orders_are_tracked_persisted_and_reconciled() {
+        let db_manager = Arc::new(DatabaseManager::new(":memory:").await.unwrap());
+        db_manager.init().await.unwrap();
+        let oms = Arc::new(OrderManager::new(db_manager.clone()));
+        let gateway = oms.gateway("market_maker", "Binance", Arc::new(BacktestExecutionGateway::new()));
+
+        let market = Order::market("BTC/USDT".to_string(), OrderSide::Sell, 1.0, MarketDataSource::Strategy, None);
+        let strategy_id = market.id;
+        let market_id: Uuid = gateway.send_order(market).await.unwrap().parse().unwrap();
+        assert_ne!(market_id, strategy_id);
+        let resting_id: Uuid = gateway.send_order(limit(100.0)).await.unwrap().parse().unwrap();
+        assert_eq!(oms.working_orders(Some("market_maker"), Some("BTC/USDT")).len(), 2);
+        assert!(oms.working_orders(Some("arbitrage"), None).is_empty());
+
+        // The backtest gateway reports no fills; only reconciliation learns the market order filled.
+        assert_eq!(oms.order(market_id).unwrap().state, OrderState::New);
+        oms.reconcile().await;
+        assert_eq!(oms.order(market_id).unwrap().state, OrderState::Filled);
+        assert_eq!(oms.order(market_id).unwrap().filled, 1.0);
+
+        let amended = gateway.amend_order(resting_id, limit(101.0)).await.unwrap();
+        let replacement_id = amended.replacement.order_id.unwrap();
+        assert_eq!(oms.order(resting_id).unwrap().state, OrderState::Canceled);
+        let working = gateway.working_orders(Some("BTC/USDT"));
+        assert_eq!(working.iter().map(|order| (order.order.id, order.state)).collect::<Vec<_>>(), vec![(replacement_id, OrderState::New)]);
+
+        gateway.cancel_order("BTC/USDT", replacement_id).await.unwrap();
+        assert!(oms.working_orders(None, None).is_empty());
+        assert!(gateway.cancel_order("BTC/USDT", replacement_id).await.is_err());
+        assert_eq!(oms.order(replacement_id).unwrap().state, OrderState::Canceled);
+
+        let events = db_manager.get_order_events(resting_id).await.unwrap();
+        let steps: Vec<_> = events.iter().map(|event| (event.event.as_str(), event.to)).collect();
+        assert_eq!(steps, vec![
+            ("Acknowledged", OrderState::New),
+            ("CancelRequested", OrderState::PendingCancel),
+            ("Replaced", OrderState::Canceled),
+        ]);
+        let canceled = db_manager.get_order_events(replacement_id).await.unwrap();
+        assert_eq!(canceled.last().map(|event| event.to), Some(OrderState::Canceled));
+    }
+
+    #[tokio::test]
+    async fn test_failed_sends_are_classified_and_unconfirmed_ones_reconciled() {
+        let db_manager = Arc::new(DatabaseManager::new(":memory:").await.unwrap());
+        db_manager.init().await.unwrap();
+        let oms = Arc::new(OrderManager::new(db_manager));
+        let mut inner = MockExecutionGateway::new();
+        inner
+            .expect_send_order()
+            .withf(|order| order.price == Some(100.0))
+            .returning(|_| Err(ExecutionError::InsufficientFunds("-2010".to_string())));
+        inner
+            .expect_send_order()
+            .withf(|order| order.price == Some(101.0))
+            .returning(|_| Err(ExecutionError::Network("connection refused".to_string())));
+        inner
+            .expect_send_order()
+            .withf(|order| order.price == Some(102.0))
+            .returning(|_| Err(ExecutionError::Timeout("operation timed out".to_string())));
+        inner.expect_open_orders().returning(|_| Ok(Vec::new()));
+        inner
+            .expect_query_order()
+            .returning(|_, order_id| Err(ExecutionError::UnknownOrder(format!("Unknown order {}", order_id))));
+        let gateway = oms.gateway("market_maker", "Binance", Arc::new(inner));
+
+        for price in [100.0, 101.0, 102.0] {
+            assert!(gateway.send_order(limit(price)).await.is_err());
+        }
+        let state = |price: f64| {
+            let orders = oms.orders.lock().unwrap();
+            orders.values().find(|order| order.order.price == Some(price)).unwrap().state
+        };
+        assert_eq!((state(100.0), state(101.0)), (OrderState::Rejected, OrderState::Failed));
+        // It may have been placed, so it stays working until the venue says otherwise.
+        assert_eq!(state(102.0), OrderState::PendingNew);
+        oms.reconcile().await;
+        assert_eq!(state(102.0), OrderState::Failed);
+        assert!(oms.unconfirmed.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_amended_orders_keep_fills_from_before_the_cancel() {
+        let db_manager = Arc::new(DatabaseManager::new(":memory:").await.unwrap());
+        db_manager.init().await.unwrap();
+        let oms = Arc::new(OrderManager::new(db_manager));
+        let report = |order: &Order, status: OrderStatus, filled: f64, exchange_order_id: &str| OrderReport {
+            order_id: Some(order.id),
+            exchange_order_id: exchange_order_id.to_string(),
+            symbol: order.symbol.clone(),
+            side: order.side,
+            order_type: order.order_type.clone(),
+            status,
+            amount: order.amount,
+            filled,
+            price: order.price,
+            avg_fill_price: (filled > 0.0).then_some(100.0),
+            source: MarketDataSource::Binance,
+            updated_at: Utc::now(),
+        };
+        let mut inner = MockExecutionGateway::new();
+        inner.expect_send_order().returning(|_| Ok("1".to_string()));
+        inner.expect_amend_order().returning(move |order_id, replacement| {
+            let original = Order { id: order_id, ..limit(100.0) };
+            Ok(Amendment {
+                canceled: report(&original, OrderStatus::Canceled, 0.3, "1"),
+                replacement: report(&replacement, OrderStatus::New, 0.0, "2"),
+            })
+        });
+        let gateway = oms.gateway("market_maker", "Binance", Arc::new(inner));
+
+        let resting_id: Uuid = gateway.send_order(limit(100.0)).await.unwrap().parse().unwrap();
+        gateway.amend_order(resting_id, limit(101.0)).await.unwrap();
+        let resting = oms.order(resting_id).unwrap();
+        assert_eq!((resting.state, resting.filled), (OrderState::Canceled, 0.3));
+
+        // The rest of the partial fill only shows up on the stream afterwards.
+        let fill = Fill {
+            order_id: resting_id,
+            symbol: "BTC/USDT".to_string(),
+            side: OrderSide::Buy,
+            price: 100.0,
+            quantity: 0.4,
+            source: MarketDataSource::Binance,
+            executed_at: Utc::now(),
+            commission: 0.0,
+            commission_asset: None,
+            trade_id: Some("7".to_string()),
+        };
+        oms.on_fill(&fill).await;
+        let resting = oms.order(resting_id).unwrap();
+        assert_eq!((resting.state, resting.filled), (OrderState::Canceled, 0.4));
+    }
+}
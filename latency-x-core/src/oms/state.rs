@@ -0,0 +1,393 @@
+use crate::models::{Fill, Order, OrderReport, OrderStatus};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use strum_macros::{Display, EnumString};
+use uuid::Uuid;
+
+/// Where a managed order is in its life. Follows `OrderStatus`, plus the two
+/// states in which we are waiting on the venue.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Display, EnumString)]
+pub enum OrderState {
+    /// Handed to the gateway, not yet acknowledged.
+    PendingNew,
+    New,
+    PartiallyFilled,
+    /// A cancel has been sent; the order can still trade until it lands.
+    PendingCancel,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+    Failed,
+}
+
+impl OrderState {
+    pub fn is_terminal(self) -> bool {
+        matches!(
+            self,
+            OrderState::Filled | OrderState::Canceled | OrderState::Rejected | OrderState::Expired | OrderState::Failed
+        )
+    }
+
+    /// Whether the order may still trade, or is about to.
+    pub fn is_working(self) -> bool {
+        !self.is_terminal()
+    }
+
+    /// Terminal states that can still learn of fills matched before the
+    /// order closed, which the venue may report after the close.
+    pub fn takes_late_fills(self) -> bool {
+        matches!(self, OrderState::Canceled | OrderState::Expired)
+    }
+
+    pub fn can_transition_to(self, next: OrderState) -> bool {
+        use OrderState::*;
+        if self == next {
+            return !self.is_terminal();
+        }
+        match self {
+            PendingNew => true,
+            New => matches!(next, PartiallyFilled | PendingCancel | Filled | Canceled | Expired),
+            PartiallyFilled => matches!(next, PendingCancel | Filled | Canceled | Expired),
+            PendingCancel => matches!(next, New | PartiallyFilled | Filled | Canceled | Expired),
+            Filled | Canceled | Rejected | Expired | Failed => false,
+        }
+    }
+}
+
+impl From<&OrderStatus> for OrderState {
+    fn from(status: &OrderStatus) -> Self {
+        match status {
+            OrderStatus::New => OrderState::New,
+            OrderStatus::PartiallyFilled => OrderState::PartiallyFilled,
+            OrderStatus::Filled => OrderState::Filled,
+            OrderStatus::Canceled => OrderState::Canceled,
+            OrderStatus::Rejected => OrderState::Rejected,
+            OrderStatus::Expired => OrderState::Expired,
+            OrderStatus::Failed => OrderState::Failed,
+        }
+    }
+}
+
+/// Something we learned about an order.
+#[derive(Debug, Clone)]
+pub enum OrderEvent {
+    Acknowledged { exchange_order_id: String },
+    Fill(Fill),
+    CancelRequested,
+    CancelRefused { reason: String },
+    /// Canceled by an amend that placed `replacement_id` instead.
+    Replaced { replacement_id: Uuid },
+    /// The venue's view, from a cancel, query or reconciliation.
+    Report(OrderReport),
+    /// The venue refused the order.
+    Rejected { reason: String },
+    /// The order never reached the venue.
+    Failed { reason: String },
+}
+
+impl OrderEvent {
+    fn name(&self) -> &'static str {
+        match self {
+            OrderEvent::Acknowledged { .. } => "Acknowledged",
+            OrderEvent::Fill(_) => "Fill",
+            OrderEvent::CancelRequested => "CancelRequested",
+            OrderEvent::CancelRefused { .. } => "CancelRefused",
+            OrderEvent::Replaced { .. } => "Replaced",
+            OrderEvent::Report(_) => "Report",
+            OrderEvent::Rejected { .. } => "Rejected",
+            OrderEvent::Failed { .. } => "Failed",
+        }
+    }
+}
+
+/// One applied event, as written to `order_events`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OrderTransition {
+    pub order_id: Uuid,
+    pub event: String,
+    pub from: OrderState,
+    pub to: OrderState,
+    pub filled: f64,
+    pub avg_fill_price: Option<f64>,
+    pub exchange_order_id: Option<String>,
+    pub detail: Option<String>,
+    pub at: DateTime<Utc>,
+}
+
+/// An order sent through the OMS and everything known about it.
+#[derive(Debug, Clone, Serialize)]
+pub struct ManagedOrder {
+    pub order: Order,
+    pub strategy: String,
+    /// Name of the gateway the order was sent through.
+    pub venue: String,
+    pub state: OrderState,
+    pub exchange_order_id: Option<String>,
+    pub filled: f64,
+    pub avg_fill_price: Option<f64>,
+    pub updated_at: DateTime<Utc>,
+    /// Fills and reports overlap, so each is tallied separately and the
+    /// larger taken.
+    #[serde(skip)]
+    fills: FillTally,
+    #[serde(skip)]
+    reported: Option<(f64, Option<f64>)>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct FillTally {
+    quantity: f64,
+    notional: f64,
+    trade_ids: HashSet<String>,
+}
+
+impl ManagedOrder {
+    pub fn new(order: Order, strategy: &str, venue: &str) -> Self {
+        Self {
+            updated_at: order.created_at,
+            order,
+            strategy: strategy.to_string(),
+            venue: venue.to_string(),
+            state: OrderState::PendingNew,
+            exchange_order_id: None,
+            filled: 0.0,
+            avg_fill_price: None,
+            fills: FillTally::default(),
+            reported: None,
+        }
+    }
+
+    pub fn remaining(&self) -> f64 {
+        (self.order.amount - self.filled).max(0.0)
+    }
+
+    /// Where the order stands on fills alone, ignoring any pending cancel.
+    fn fill_state(&self) -> OrderState {
+        if self.filled >= self.order.amount * (1.0 - 1e-9) {
+            OrderState::Filled
+        } else if self.filled > 0.0 {
+            OrderState::PartiallyFilled
+        } else {
+            OrderState::New
+        }
+    }
+
+    fn update_filled(&mut self) {
+        let (reported, reported_price) = self.reported.unwrap_or((0.0, None));
+        if self.fills.quantity >= reported && self.fills.quantity > 0.0 {
+            self.filled = self.fills.quantity;
+            self.avg_fill_price = Some(self.fills.notional / self.fills.quantity);
+        } else if reported > 0.0 {
+            self.filled = reported;
+            self.avg_fill_price = reported_price.or(self.avg_fill_price);
+        }
+    }
+
+    /// Applies `event`, returning the transition to record, or `None` when it
+    /// changed nothing (a fill seen twice, say). Illegal transitions leave
+    /// the order untouched.
+    pub fn apply(&mut self, event: OrderEvent) -> Result<Option<OrderTransition>> {
+        let before = self.clone();
+        let mut detail = None;
+        let next = match &event {
+            OrderEvent::Acknowledged { exchange_order_id } => {
+                self.exchange_order_id = Some(exchange_order_id.clone());
+                match self.state {
+                    OrderState::PendingNew => OrderState::New,
+                    state => state,
+                }
+            }
+            OrderEvent::Fill(fill) => {
+                if self.state.is_terminal() && !self.state.takes_late_fills() {
+                    bail!("{} order {} cannot take a fill", self.state, self.order.id);
+                }
+                if let Some(trade_id) = &fill.trade_id {
+                    if !self.fills.trade_ids.insert(trade_id.clone()) {
+                        return Ok(None);
+                    }
+                }
+                self.fills.quantity += fill.quantity;
+                self.fills.notional += fill.quantity * fill.price;
+                self.update_filled();
+                match (self.state, self.fill_state()) {
+                    // Traded just before the cancel landed; the fill reports later.
+                    (state, _) if state.takes_late_fills() => state,
+                    (OrderState::PendingCancel, state) if state != OrderState::Filled => OrderState::PendingCancel,
+                    (_, state) => state,
+                }
+            }
+            OrderEvent::CancelRequested => OrderState::PendingCancel,
+            OrderEvent::CancelRefused { reason } => {
+                detail = Some(reason.clone());
+                match self.state {
+                    OrderState::PendingCancel => self.fill_state(),
+                    state => state,
+                }
+            }
+            OrderEvent::Replaced { replacement_id } => {
+                detail = Some(format!("Replaced by {}", replacement_id));
+                OrderState::Canceled
+            }
+            OrderEvent::Report(report) => {
+                if report.filled > self.reported.map_or(0.0, |(filled, _)| filled) {
+                    self.reported = Some((report.filled, report.avg_fill_price));
+                    self.update_filled();
+                }
+                if self.exchange_order_id.is_none() {
+                    self.exchange_order_id = Some(report.exchange_order_id.clone());
+                }
+                match (self.state, OrderState::from(&report.status)) {
+                    // The venue has not seen our cancel yet.
+                    (OrderState::PendingCancel, OrderState::New | OrderState::PartiallyFilled) => OrderState::PendingCancel,
+                    (_, OrderState::New | OrderState::PartiallyFilled) => self.fill_state(),
+                    (_, state) => state,
+                }
+            }
+            OrderEvent::Rejected { reason } => {
+                detail = Some(reason.clone());
+                OrderState::Rejected
+            }
+            OrderEvent::Failed { reason } => {
+                detail = Some(reason.clone());
+                OrderState::Failed
+            }
+        };
+
+        let late = self.state == next && self.state.takes_late_fills();
+        if !late && !self.state.can_transition_to(next) {
+            *self = before;
+            bail!("Order {} cannot go from {} to {} on {}", self.order.id, self.state, next, event.name());
+        }
+        if next == before.state && self.filled == before.filled && self.exchange_order_id == before.exchange_order_id {
+            return Ok(None);
+        }
+
+        let from = self.state;
+        self.state = next;
+        self.updated_at = Utc::now();
+        Ok(Some(OrderTransition {
+            order_id: self.order.id,
+            event: event.name().to_string(),
+            from,
+            to: next,
+            filled: self.filled,
+            avg_fill_price: self.avg_fill_price,
+            exchange_order_id: self.exchange_order_id.clone(),
+            detail,
+            at: self.updated_at,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{MarketDataSource, OrderSide, OrderType};
+
+    fn order() -> ManagedOrder {
+        let order = Order {
+            order_type: OrderType::Limit,
+            price: Some(100.0),
+            ..Order::market("BTC/USDT".to_string(), OrderSide::Buy, 1.0, MarketDataSource::Strategy, None)
+        };
+        ManagedOrder::new(order, "market_maker", "Binance")
+    }
+
+    fn fill(order: &ManagedOrder, quantity: f64, trade_id: &str) -> OrderEvent {
+        OrderEvent::Fill(Fill {
+            order_id: order.order.id,
+            symbol: order.order.symbol.clone(),
+            side: order.order.side,
+            price: 100.0,
+            quantity,
+            source: MarketDataSource::Binance,
+            executed_at: Utc::now(),
+            commission: 0.0,
+            commission_asset: None,
+            trade_id: Some(trade_id.to_string()),
+        })
+    }
+
+    fn report(order: &ManagedOrder, status: OrderStatus, filled: f64) -> OrderEvent {
+        OrderEvent::Report(OrderReport {
+            order_id: Some(order.order.id),
+            exchange_order_id: "42".to_string(),
+            symbol: order.order.symbol.clone(),
+            side: order.order.side,
+            order_type: order.order.order_type.clone(),
+            status,
+            amount: order.order.amount,
+            filled,
+            price: order.order.price,
+            avg_fill_price: (filled > 0.0).then_some(100.0),
+            source: MarketDataSource::Binance,
+            updated_at: Utc::now(),
+        })
+    }
+
+    #[test]
+    fn test_fills_and_acks_in_any_order() {
+        let mut order = order();
+        let partial = fill(&order, 0.4, "1");
+        assert_eq!(order.apply(partial.clone()).unwrap().unwrap().to, OrderState::PartiallyFilled);
+        assert!(order.apply(partial).unwrap().is_none());
+
+        // The ack raced the fill and must not send the order back to New.
+        let ack = order.apply(OrderEvent::Acknowledged { exchange_order_id: "42".to_string() }).unwrap().unwrap();
+        assert_eq!((ack.from, ack.to), (OrderState::PartiallyFilled, OrderState::PartiallyFilled));
+
+        // A report covering the fill we already have does not count it twice.
+        assert!(order.apply(report(&order, OrderStatus::PartiallyFilled, 0.4)).unwrap().is_none());
+        let last = fill(&order, 0.6, "2");
+        assert_eq!(order.apply(last).unwrap().unwrap().to, OrderState::Filled);
+        assert_eq!((order.filled, order.remaining()), (1.0, 0.0));
+        assert!(order.apply(fill(&order, 0.1, "3")).is_err());
+    }
+
+    #[test]
+    fn test_cancel_lifecycle() {
+        let mut order = order();
+        order.apply(OrderEvent::Acknowledged { exchange_order_id: "42".to_string() }).unwrap();
+        assert_eq!(order.apply(OrderEvent::CancelRequested).unwrap().unwrap().to, OrderState::PendingCancel);
+
+        // A partial fill racing the cancel leaves it pending; a refusal puts it back to work.
+        order.apply(fill(&order, 0.5, "1")).unwrap();
+        assert_eq!(order.state, OrderState::PendingCancel);
+        assert!(order.apply(report(&order, OrderStatus::New, 0.0)).unwrap().is_none());
+        let refused = order.apply(OrderEvent::CancelRefused { reason: "busy".to_string() }).unwrap().unwrap();
+        assert_eq!((refused.to, refused.detail.as_deref()), (OrderState::PartiallyFilled, Some("busy")));
+
+        order.apply(OrderEvent::CancelRequested).unwrap();
+        assert_eq!(order.apply(report(&order, OrderStatus::Canceled, 0.5)).unwrap().unwrap().to, OrderState::Canceled);
+        assert!(order.apply(OrderEvent::CancelRequested).is_err());
+        assert_eq!(order.state, OrderState::Canceled);
+    }
+
+    #[test]
+    fn test_fills_reported_after_the_cancel_still_count() {
+        let mut order = order();
+        order.apply(OrderEvent::Acknowledged { exchange_order_id: "42".to_string() }).unwrap();
+        order.apply(OrderEvent::Replaced { replacement_id: Uuid::new_v4() }).unwrap();
+
+        // The cancel leg's report, then the fill it covers arriving on the stream.
+        let canceled = order.apply(report(&order, OrderStatus::Canceled, 0.3)).unwrap().unwrap();
+        assert_eq!((canceled.from, canceled.to, canceled.filled), (OrderState::Canceled, OrderState::Canceled, 0.3));
+        assert!(order.apply(fill(&order, 0.3, "1")).unwrap().is_none());
+        let late = order.apply(fill(&order, 0.2, "2")).unwrap().unwrap();
+        assert_eq!((late.to, order.filled), (OrderState::Canceled, 0.5));
+        assert!(order.apply(report(&order, OrderStatus::Filled, 1.0)).is_err());
+        assert_eq!(order.state, OrderState::Canceled);
+    }
+
+    #[test]
+    fn test_transition_table() {
+        assert!(OrderState::PendingNew.can_transition_to(OrderState::Rejected));
+        assert!(!OrderState::New.can_transition_to(OrderState::Rejected));
+        assert!(!OrderState::PartiallyFilled.can_transition_to(OrderState::New));
+        assert!(!OrderState::Filled.can_transition_to(OrderState::Filled));
+        assert!(OrderState::PendingCancel.can_transition_to(OrderState::New));
+    }
+}
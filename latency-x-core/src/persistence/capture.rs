@@ -0,0 +1,331 @@
+//! Market data capture.
+//!
+//! Ticks and book updates are appended to gzip-compressed CSV files that are
+//! rotated on age. Each file starts with a `# latency-x capture v1` line and
+//! then holds one record per line, tagged by its first field:
+//!
+//! ```text
+//! T,<received_at_us>,<source>,<symbol>,<price>,<volume>,<exchange_ts_us>,<trade_id>
+//! B,<received_at_us>,<source>,<symbol>,<last_update_id>,<bids>,<asks>
+//! ```
+//!
+//! Timestamps are microseconds since the Unix epoch, optional fields are left
+//! empty, and book sides are `price:quantity` pairs joined by `;`, best first.
+//! Files are named `capture-<UTC open time>.csv.gz`, so sorting by name sorts
+//! them in time. The encoder is flushed every second, which keeps a file
+//! readable up to the last flush even if the process dies.
+
+use crate::config::CaptureConfig;
+use crate::models::{OrderBook, PriceLevel, Tick};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use flate2::read::MultiGzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use metrics::counter;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError, SyncSender, TrySendError};
+use std::time::{Duration, Instant};
+use tracing::{error, info};
+
+const CAPTURE_HEADER: &[u8] = b"# latency-x capture v1\n";
+const CAPTURE_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone)]
+pub enum CaptureRecord {
+    Tick(Tick),
+    Book(OrderBook),
+}
+
+impl CaptureRecord {
+    pub fn received_at(&self) -> DateTime<Utc> {
+        match self {
+            CaptureRecord::Tick(tick) => tick.received_at,
+            CaptureRecord::Book(book) => book.received_at,
+        }
+    }
+
+    fn to_fields(&self) -> Vec<String> {
+        match self {
+            CaptureRecord::Tick(tick) => vec![
+                "T".to_string(),
+                tick.received_at.timestamp_micros().to_string(),
+                tick.source.to_string(),
+                tick.symbol.clone(),
+                tick.price.to_string(),
+                tick.volume.to_string(),
+                tick.exchange_ts.map(|ts| ts.timestamp_micros().to_string()).unwrap_or_default(),
+                tick.trade_id.map(|id| id.to_string()).unwrap_or_default(),
+            ],
+            CaptureRecord::Book(book) => vec![
+                "B".to_string(),
+                book.received_at.timestamp_micros().to_string(),
+                book.source.to_string(),
+                book.symbol.clone(),
+                book.last_update_id.to_string(),
+                encode_levels(&book.bids),
+                encode_levels(&book.asks),
+            ],
+        }
+    }
+
+    fn from_fields(record: &csv::StringRecord) -> Result<Self> {
+        let field = |i: usize| record.get(i).with_context(|| format!("capture record is missing field {}", i));
+        let received_at = parse_micros(field(1)?)?;
+        match field(0)? {
+            "T" => Ok(CaptureRecord::Tick(Tick {
+                source: field(2)?.parse()?,
+                symbol: field(3)?.to_string(),
+                price: field(4)?.parse()?,
+                volume: field(5)?.parse()?,
+                received_at,
+                exchange_ts: optional(field(6)?).map(parse_micros).transpose()?,
+                trade_id: optional(field(7)?).map(str::parse).transpose()?,
+            })),
+            "B" => {
+                let mut book = OrderBook::new(field(2)?.parse()?, field(3)?.to_string());
+                book.last_update_id = field(4)?.parse()?;
+                book.bids = decode_levels(field(5)?)?;
+                book.asks = decode_levels(field(6)?)?;
+                book.received_at = received_at;
+                Ok(CaptureRecord::Book(book))
+            }
+            other => anyhow::bail!("Unknown capture record kind: {}", other),
+        }
+    }
+}
+
+fn encode_levels(levels: &[PriceLevel]) -> String {
+    levels
+        .iter()
+        .map(|level| format!("{}:{}", level.price, level.quantity))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn decode_levels(field: &str) -> Result<Vec<PriceLevel>> {
+    optional(field)
+        .into_iter()
+        .flat_map(|field| field.split(';'))
+        .map(|level| {
+            let (price, quantity) = level
+                .split_once(':')
+                .with_context(|| format!("malformed book level {:?}", level))?;
+            Ok(PriceLevel { price: price.parse()?, quantity: quantity.parse()? })
+        })
+        .collect()
+}
+
+fn optional(field: &str) -> Option<&str> {
+    (!field.is_empty()).then_some(field)
+}
+
+fn parse_micros(field: &str) -> Result<DateTime<Utc>> {
+    DateTime::from_timestamp_micros(field.parse()?).with_context(|| format!("timestamp out of range: {}", field))
+}
+
+/// Queues records for a background writer thread. Recording never blocks: when
+/// the writer falls behind, records are dropped and counted instead.
+pub struct CaptureRecorder {
+    tx: SyncSender<CaptureRecord>,
+}
+
+impl CaptureRecorder {
+    pub fn spawn(config: &CaptureConfig) -> Result<Self> {
+        std::fs::create_dir_all(&config.dir)?;
+        let (tx, rx) = mpsc::sync_channel(config.buffer);
+        let mut writer = CaptureWriter::new(&config.dir, Duration::from_secs(config.rotate_secs));
+
+        std::thread::Builder::new()
+            .name("capture-writer".to_string())
+            .spawn(move || loop {
+                let result = match rx.recv_timeout(CAPTURE_FLUSH_INTERVAL) {
+                    Ok(record) => writer.write(&record),
+                    Err(RecvTimeoutError::Timeout) => writer.flush(),
+                    Err(RecvTimeoutError::Disconnected) => {
+                        if let Err(e) = writer.finish() {
+                            error!("Failed to close capture file: {}", e);
+                        }
+                        break;
+                    }
+                };
+                if let Err(e) = result {
+                    error!("Capture write failed: {}", e);
+                }
+            })?;
+
+        info!(dir = %config.dir, "Recording market data");
+        Ok(Self { tx })
+    }
+
+    pub fn record(&self, record: CaptureRecord) {
+        if let Err(TrySendError::Full(_)) = self.tx.try_send(record) {
+            counter!("capture_dropped").increment(1);
+        }
+    }
+}
+
+type CaptureFile = csv::Writer<GzEncoder<BufWriter<File>>>;
+
+pub(crate) struct CaptureWriter {
+    dir: PathBuf,
+    rotate_after: Duration,
+    current: Option<(CaptureFile, Instant)>,
+}
+
+impl CaptureWriter {
+    pub(crate) fn new(dir: impl Into<PathBuf>, rotate_after: Duration) -> Self {
+        Self { dir: dir.into(), rotate_after, current: None }
+    }
+
+    pub(crate) fn write(&mut self, record: &CaptureRecord) -> Result<()> {
+        if self.current.as_ref().is_some_and(|(_, opened)| opened.elapsed() >= self.rotate_after) {
+            self.finish()?;
+        }
+        let (file, _) = match &mut self.current {
+            Some(current) => current,
+            None => self.current.insert((self.open()?, Instant::now())),
+        };
+        file.write_record(record.to_fields())?;
+        Ok(())
+    }
+
+    fn open(&self) -> Result<CaptureFile> {
+        let path = self
+            .dir
+            .join(format!("capture-{}.csv.gz", Utc::now().format("%Y%m%dT%H%M%S%.3fZ")));
+        info!(path = %path.display(), "Opening capture file");
+        let mut encoder = GzEncoder::new(BufWriter::new(File::create(&path)?), Compression::fast());
+        encoder.write_all(CAPTURE_HEADER)?;
+        Ok(csv::WriterBuilder::new().flexible(true).from_writer(encoder))
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        if let Some((file, opened)) = &mut self.current {
+            if opened.elapsed() >= self.rotate_after {
+                return self.finish();
+            }
+            file.flush()?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn finish(&mut self) -> Result<()> {
+        if let Some((file, _)) = self.current.take() {
+            let encoder = file.into_inner().map_err(|e| e.into_error())?;
+            encoder.finish()?.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads one capture file record by record.
+pub struct CaptureReader {
+    reader: csv::Reader<MultiGzDecoder<BufReader<File>>>,
+    record: csv::StringRecord,
+}
+
+impl CaptureReader {
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::open(path.as_ref())
+            .with_context(|| format!("opening capture file {}", path.as_ref().display()))?;
+        let reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .comment(Some(b'#'))
+            .from_reader(MultiGzDecoder::new(BufReader::new(file)));
+        Ok(Self { reader, record: csv::StringRecord::new() })
+    }
+}
+
+impl Iterator for CaptureReader {
+    type Item = Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.reader.read_record(&mut self.record) {
+            Ok(true) => Some(CaptureRecord::from_fields(&self.record)),
+            Ok(false) => None,
+            Err(e) => Some(Err(e.into())),
+        }
+    }
+}
+
+/// Capture files in `dir`, oldest first.
+pub fn capture_files(dir: impl AsRef<Path>) -> Result<Vec<PathBuf>> {
+    let mut files: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("capture-") && name.ends_with(".csv.gz"))
+        })
+        .collect();
+    files.sort();
+    Ok(files)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::MarketDataSource;
+    use uuid::Uuid;
+
+    fn tick(price: f64, trade_id: Option<u64>) -> Tick {
+        Tick {
+            source: MarketDataSource::Kraken,
+            symbol: "BTC/USD".to_string(),
+            price,
+            volume: 0.25,
+            received_at: DateTime::from_timestamp_micros(1_700_000_000_123_456).unwrap(),
+            exchange_ts: trade_id.map(|_| DateTime::from_timestamp_micros(1_700_000_000_120_000).unwrap()),
+            trade_id,
+        }
+    }
+
+    #[test]
+    fn test_capture_round_trips_across_rotated_files() {
+        let dir = std::env::temp_dir().join(format!("latency-x-capture-{}", Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let mut book = OrderBook::new(MarketDataSource::Binance, "BTC/USDT".to_string());
+        book.update_bid(100.5, 1.25);
+        book.update_bid(100.0, 2.0);
+        book.update_ask(101.0, 0.5);
+        book.last_update_id = 42;
+
+        let mut writer = CaptureWriter::new(&dir, Duration::from_secs(3600));
+        writer.write(&CaptureRecord::Tick(tick(50_000.5, Some(7)))).unwrap();
+        writer.write(&CaptureRecord::Book(book.clone())).unwrap();
+        writer.finish().unwrap();
+        // File names carry millisecond open times.
+        std::thread::sleep(Duration::from_millis(5));
+        writer.write(&CaptureRecord::Tick(tick(50_001.0, None))).unwrap();
+        writer.finish().unwrap();
+
+        let files = capture_files(&dir).unwrap();
+        assert_eq!(files.len(), 2);
+        let records: Vec<CaptureRecord> = files
+            .iter()
+            .flat_map(|file| CaptureReader::open(file).unwrap())
+            .collect::<Result<_>>()
+            .unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(records.len(), 3);
+        let CaptureRecord::Tick(first) = &records[0] else { panic!("expected a tick") };
+        assert_eq!(first.price, 50_000.5);
+        assert_eq!(first.trade_id, Some(7));
+        assert_eq!(first.received_at, tick(0.0, None).received_at);
+        assert_eq!(first.exchange_ts, tick(0.0, Some(7)).exchange_ts);
+        let CaptureRecord::Book(captured) = &records[1] else { panic!("expected a book") };
+        assert_eq!(captured.source, MarketDataSource::Binance);
+        assert_eq!(captured.bids, book.bids);
+        assert_eq!(captured.asks, book.asks);
+        assert_eq!(captured.last_update_id, 42);
+        let CaptureRecord::Tick(last) = &records[2] else { panic!("expected a tick") };
+        assert_eq!(last.exchange_ts, None);
+        assert_eq!(last.trade_id, None);
+    }
+}
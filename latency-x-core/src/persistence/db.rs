@@ -1,6 +1,9 @@
 use sqlx::{sqlite::SqlitePoolOptions, FromRow, Sqlite, Pool, Row};
 use anyhow::Result;
-use crate::models::{Order, Fill, Trade};
+use crate::models::{Candle, Order, OrderStatus, Fill, MarketDataSource, Trade};
+use crate::oms::{ManagedOrder, OrderTransition};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
 use std::collections::HashMap;
 
 #[derive(FromRow, Clone, Debug, serde::Serialize)]
@@ -32,7 +35,9 @@ impl DatabaseManager {
         self.create_trades_table().await?;
         self.create_fills_table().await?;
         self.create_orders_table().await?;
+        self.create_order_events_table().await?;
         self.create_positions_table().await?;
+        self.create_candles_table().await?;
         Ok(())
     }
 
@@ -66,12 +71,35 @@ impl DatabaseManager {
                 price REAL NOT NULL,
                 quantity REAL NOT NULL,
                 source TEXT NOT NULL,
-                executed_at TEXT NOT NULL
+                executed_at TEXT NOT NULL,
+                commission REAL NOT NULL DEFAULT 0,
+                commission_asset TEXT,
+                trade_id TEXT
             )
             "#,
         )
         .execute(&self.pool)
         .await?;
+        // Databases created before fills carried fees lack these columns.
+        self.add_column_if_missing("fills", "commission", "REAL NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("fills", "commission_asset", "TEXT").await?;
+        self.add_column_if_missing("fills", "trade_id", "TEXT").await?;
+        Ok(())
+    }
+
+    async fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let columns = sqlx::query(&format!("PRAGMA table_info({})", table))
+            .fetch_all(&self.pool)
+            .await?;
+        for row in columns {
+            let name: String = row.try_get("name")?;
+            if name == column {
+                return Ok(());
+            }
+        }
+        sqlx::query(&format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition))
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
@@ -87,12 +115,50 @@ impl DatabaseManager {
                 price REAL,
                 status TEXT NOT NULL,
                 source TEXT NOT NULL,
-                created_at TEXT NOT NULL
+                created_at TEXT NOT NULL,
+                strategy TEXT,
+                venue TEXT,
+                exchange_order_id TEXT,
+                filled REAL NOT NULL DEFAULT 0,
+                avg_fill_price REAL,
+                updated_at TEXT
             )
             "#,
         )
         .execute(&self.pool)
         .await?;
+        // Orders saved before the OMS tracked them lack these columns.
+        self.add_column_if_missing("orders", "strategy", "TEXT").await?;
+        self.add_column_if_missing("orders", "venue", "TEXT").await?;
+        self.add_column_if_missing("orders", "exchange_order_id", "TEXT").await?;
+        self.add_column_if_missing("orders", "filled", "REAL NOT NULL DEFAULT 0").await?;
+        self.add_column_if_missing("orders", "avg_fill_price", "REAL").await?;
+        self.add_column_if_missing("orders", "updated_at", "TEXT").await?;
+        Ok(())
+    }
+
+    async fn create_order_events_table(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS order_events (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                order_id TEXT NOT NULL,
+                event TEXT NOT NULL,
+                from_status TEXT NOT NULL,
+                to_status TEXT NOT NULL,
+                filled REAL NOT NULL,
+                avg_fill_price REAL,
+                exchange_order_id TEXT,
+                detail TEXT,
+                at TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS order_events_order_id ON order_events (order_id)")
+            .execute(&self.pool)
+            .await?;
         Ok(())
     }
 
@@ -110,6 +176,29 @@ impl DatabaseManager {
         Ok(())
     }
 
+    async fn create_candles_table(&self) -> Result<()> {
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS candles (
+                source TEXT NOT NULL,
+                symbol TEXT NOT NULL,
+                interval_secs INTEGER NOT NULL,
+                open_time TEXT NOT NULL,
+                open REAL NOT NULL,
+                high REAL NOT NULL,
+                low REAL NOT NULL,
+                close REAL NOT NULL,
+                volume REAL NOT NULL,
+                trades INTEGER NOT NULL,
+                PRIMARY KEY (source, symbol, interval_secs, open_time)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
     pub async fn get_trades(&self) -> Result<Vec<TradeRow>> {
         let rows = sqlx::query_as::<_, TradeRow>(
             "SELECT id, order_id, symbol, side, amount, price, source, executed_at FROM trades ORDER BY executed_at DESC LIMIT 100"
@@ -161,13 +250,13 @@ impl DatabaseManager {
             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(&order.id.to_string())
+        .bind(order.id.to_string())
         .bind(&order.symbol)
-        .bind(&order.side.to_string())
-        .bind(&order.order_type.to_string())
+        .bind(order.side.to_string())
+        .bind(order.order_type.to_string())
         .bind(order.amount)
         .bind(order.price)
-        .bind(&order.status.to_string())
+        .bind(order.status.to_string())
         .bind(order.source.to_string())
         .bind(order.created_at.to_rfc3339())
         .execute(&mut *conn)
@@ -175,11 +264,107 @@ impl DatabaseManager {
         Ok(())
     }
 
+    /// Saves an order the OMS has just started tracking.
+    pub async fn save_managed_order(&self, order: &ManagedOrder) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO orders (id, symbol, side, order_type, amount, price, status, source, created_at, strategy, venue, filled, updated_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(order.order.id.to_string())
+        .bind(&order.order.symbol)
+        .bind(order.order.side.to_string())
+        .bind(order.order.order_type.to_string())
+        .bind(order.order.amount)
+        .bind(order.order.price)
+        .bind(order.state.to_string())
+        .bind(order.order.source.to_string())
+        .bind(order.order.created_at.to_rfc3339())
+        .bind(&order.strategy)
+        .bind(&order.venue)
+        .bind(order.filled)
+        .bind(order.updated_at.to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Appends a transition to `order_events` and brings the order's row up to date.
+    pub async fn record_order_transition(&self, transition: &OrderTransition) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        sqlx::query(
+            r#"
+            INSERT INTO order_events (order_id, event, from_status, to_status, filled, avg_fill_price, exchange_order_id, detail, at)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(transition.order_id.to_string())
+        .bind(&transition.event)
+        .bind(transition.from.to_string())
+        .bind(transition.to.to_string())
+        .bind(transition.filled)
+        .bind(transition.avg_fill_price)
+        .bind(&transition.exchange_order_id)
+        .bind(&transition.detail)
+        .bind(transition.at.to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE orders SET status = ?, exchange_order_id = ?, filled = ?, avg_fill_price = ?, updated_at = ?
+            WHERE id = ?
+            "#,
+        )
+        .bind(transition.to.to_string())
+        .bind(&transition.exchange_order_id)
+        .bind(transition.filled)
+        .bind(transition.avg_fill_price)
+        .bind(transition.at.to_rfc3339())
+        .bind(transition.order_id.to_string())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Every transition of one order, oldest first.
+    pub async fn get_order_events(&self, order_id: Uuid) -> Result<Vec<OrderTransition>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT event, from_status, to_status, filled, avg_fill_price, exchange_order_id, detail, at
+            FROM order_events WHERE order_id = ? ORDER BY id
+            "#,
+        )
+        .bind(order_id.to_string())
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut events = Vec::with_capacity(rows.len());
+        for row in rows {
+            let from: String = row.try_get("from_status")?;
+            let to: String = row.try_get("to_status")?;
+            let at: String = row.try_get("at")?;
+            events.push(OrderTransition {
+                order_id,
+                event: row.try_get("event")?,
+                from: from.parse()?,
+                to: to.parse()?,
+                filled: row.try_get("filled")?,
+                avg_fill_price: row.try_get("avg_fill_price")?,
+                exchange_order_id: row.try_get("exchange_order_id")?,
+                detail: row.try_get("detail")?,
+                at: DateTime::parse_from_rfc3339(&at)?.with_timezone(&Utc),
+            });
+        }
+        Ok(events)
+    }
+
     pub async fn save_fill(&self, fill: &Fill) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT INTO fills (order_id, symbol, side, price, quantity, source, executed_at)
-            VALUES (?, ?, ?, ?, ?, ?, ?)
+            INSERT INTO fills (order_id, symbol, side, price, quantity, source, executed_at, commission, commission_asset, trade_id)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(fill.order_id.to_string())
@@ -189,11 +374,23 @@ impl DatabaseManager {
         .bind(fill.quantity)
         .bind(fill.source.to_string())
         .bind(fill.executed_at.to_rfc3339())
+        .bind(fill.commission)
+        .bind(&fill.commission_asset)
+        .bind(&fill.trade_id)
         .execute(&self.pool)
         .await?;
         Ok(())
     }
 
+    pub async fn update_order_status(&self, order_id: Uuid, status: &OrderStatus) -> Result<()> {
+        sqlx::query("UPDATE orders SET status = ? WHERE id = ?")
+            .bind(status.to_string())
+            .bind(order_id.to_string())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+
     pub async fn save_trade(&self, trade: &Trade) -> Result<()> {
         sqlx::query(
             r#"
@@ -213,4 +410,72 @@ impl DatabaseManager {
         .await?;
         Ok(())
     }
-} 
\ No newline at end of file
+
+    /// Saves a closed bar, replacing any earlier copy of it.
+    pub async fn save_candle(&self, candle: &Candle) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO candles (source, symbol, interval_secs, open_time, open, high, low, close, volume, trades)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            ON CONFLICT(source, symbol, interval_secs, open_time) DO UPDATE SET
+                open = excluded.open, high = excluded.high, low = excluded.low, close = excluded.close,
+                volume = excluded.volume, trades = excluded.trades
+            "#,
+        )
+        .bind(candle.source.to_string())
+        .bind(&candle.symbol)
+        .bind(candle.interval_secs as i64)
+        .bind(candle.open_time.to_rfc3339())
+        .bind(candle.open)
+        .bind(candle.high)
+        .bind(candle.low)
+        .bind(candle.close)
+        .bind(candle.volume)
+        .bind(candle.trades as i64)
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+
+    /// The latest `limit` bars of one series, oldest first.
+    pub async fn get_candles(
+        &self,
+        source: MarketDataSource,
+        symbol: &str,
+        interval_secs: u64,
+        limit: u32,
+    ) -> Result<Vec<Candle>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT open_time, open, high, low, close, volume, trades FROM candles
+            WHERE source = ? AND symbol = ? AND interval_secs = ?
+            ORDER BY open_time DESC LIMIT ?
+            "#,
+        )
+        .bind(source.to_string())
+        .bind(symbol)
+        .bind(interval_secs as i64)
+        .bind(limit)
+        .fetch_all(&self.pool)
+        .await?;
+
+        let mut candles = Vec::with_capacity(rows.len());
+        for row in rows.into_iter().rev() {
+            let open_time: String = row.try_get("open_time")?;
+            let trades: i64 = row.try_get("trades")?;
+            candles.push(Candle {
+                source,
+                symbol: symbol.to_string(),
+                interval_secs,
+                open_time: DateTime::parse_from_rfc3339(&open_time)?.with_timezone(&Utc),
+                open: row.try_get("open")?,
+                high: row.try_get("high")?,
+                low: row.try_get("low")?,
+                close: row.try_get("close")?,
+                volume: row.try_get("volume")?,
+                trades: trades as u64,
+            });
+        }
+        Ok(candles)
+    }
+}
@@ -1 +1,2 @@
+pub mod capture;
 pub mod db; 
\ No newline at end of file
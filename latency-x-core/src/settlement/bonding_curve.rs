@@ -0,0 +1,212 @@
+//! The pump.fun bonding curve a token trades on until it migrates.
+//!
+//! Each mint has a `bonding-curve` account holding constant-product virtual
+//! reserves. The quote functions reproduce the program's integer arithmetic,
+//! so a quoted cost is what the program will charge at the current state.
+
+use crate::settlement::pump::PUMP_FUN_PROGRAM_ID;
+use anyhow::{anyhow, Result};
+use backoff::{future::retry, ExponentialBackoff};
+use borsh::{BorshDeserialize, BorshSerialize};
+use futures_util::StreamExt;
+use solana_account_decoder::UiAccountEncoding;
+use solana_pubsub_client::nonblocking::pubsub_client::PubsubClient;
+use solana_rpc_client::nonblocking::rpc_client::RpcClient;
+use solana_rpc_client_api::config::RpcAccountInfoConfig;
+use solana_sdk::{commitment_config::CommitmentConfig, pubkey::Pubkey};
+use tokio::sync::watch;
+use tracing::info;
+
+/// sha256("account:BondingCurve")[..8]
+const BONDING_CURVE_DISCRIMINATOR: [u8; 8] = [23, 183, 248, 55, 96, 216, 172, 96];
+/// The program's trading fee, charged in SOL on both sides.
+pub const FEE_BASIS_POINTS: u64 = 100;
+const BASIS_POINTS: u64 = 10_000;
+const LAMPORTS_PER_SOL: f64 = 1_000_000_000.0;
+/// Pump.fun mints every token with six decimals.
+const TOKEN_UNITS: f64 = 1_000_000.0;
+
+#[derive(BorshSerialize, BorshDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BondingCurve {
+    pub virtual_token_reserves: u64,
+    pub virtual_sol_reserves: u64,
+    pub real_token_reserves: u64,
+    pub real_sol_reserves: u64,
+    pub token_total_supply: u64,
+    /// Set once the curve has sold out and the token has migrated; the
+    /// program rejects trades from then on.
+    pub complete: bool,
+}
+
+impl BondingCurve {
+    /// The curve account for `mint`.
+    pub fn address(mint: &Pubkey) -> Pubkey {
+        Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_FUN_PROGRAM_ID).0
+    }
+
+    /// Decodes raw account data. Bytes after the known fields are ignored.
+    pub fn from_account_data(data: &[u8]) -> Result<Self> {
+        let mut fields = data
+            .strip_prefix(&BONDING_CURVE_DISCRIMINATOR[..])
+            .ok_or_else(|| anyhow!("Not a bonding curve account"))?;
+        Ok(Self::deserialize(&mut fields)?)
+    }
+
+    pub async fn fetch(rpc_client: &RpcClient, mint: &Pubkey) -> Result<Self> {
+        let data = rpc_client.get_account_data(&Self::address(mint)).await?;
+        Self::from_account_data(&data)
+    }
+
+    /// SOL per whole token at the margin.
+    pub fn spot_price(&self) -> f64 {
+        (self.virtual_sol_reserves as f64 / LAMPORTS_PER_SOL) / (self.virtual_token_reserves as f64 / TOKEN_UNITS)
+    }
+
+    /// Lamports, fee included, to buy exactly `token_amount` base units, or
+    /// `None` if the curve is complete or holds fewer tokens.
+    pub fn buy_cost(&self, token_amount: u64) -> Option<u64> {
+        if self.complete || token_amount == 0 || token_amount > self.real_token_reserves {
+            return None;
+        }
+        let sol_reserves = self.virtual_sol_reserves as u128;
+        let token_reserves = self.virtual_token_reserves as u128;
+        let remaining = token_reserves.checked_sub(token_amount as u128).filter(|r| *r > 0)?;
+        let cost = (sol_reserves * token_reserves / remaining + 1).checked_sub(sol_reserves)?;
+        let cost = u64::try_from(cost).ok()?;
+        cost.checked_add(fee(cost))
+    }
+
+    /// Base units bought with `lamports`, fee included, capped at what the
+    /// curve still holds.
+    pub fn buy_tokens_for_sol(&self, lamports: u64) -> Option<u64> {
+        if self.complete || lamports == 0 {
+            return None;
+        }
+        let sol_in = lamports as u128 * BASIS_POINTS as u128 / (BASIS_POINTS + FEE_BASIS_POINTS) as u128;
+        let sol_reserves = self.virtual_sol_reserves as u128;
+        let token_reserves = self.virtual_token_reserves as u128;
+        let remaining = sol_reserves * token_reserves / (sol_reserves + sol_in) + 1;
+        let tokens = u64::try_from(token_reserves.saturating_sub(remaining)).ok()?;
+        Some(tokens.min(self.real_token_reserves))
+    }
+
+    /// Lamports received, after the fee, for selling `token_amount` base units.
+    pub fn sell_proceeds(&self, token_amount: u64) -> Option<u64> {
+        if self.complete || token_amount == 0 {
+            return None;
+        }
+        let sol_reserves = self.virtual_sol_reserves as u128;
+        let token_reserves = self.virtual_token_reserves as u128;
+        let proceeds = sol_reserves * token_amount as u128 / (token_reserves + token_amount as u128);
+        let proceeds = u64::try_from(proceeds).ok()?.min(self.real_sol_reserves);
+        Some(proceeds - fee(proceeds))
+    }
+}
+
+fn fee(lamports: u64) -> u64 {
+    (lamports as u128 * FEE_BASIS_POINTS as u128 / BASIS_POINTS as u128) as u64
+}
+
+/// The `max_sol_cost` for a buy quoted at `cost`, allowing `slippage_bps`.
+pub fn max_sol_cost(cost: u64, slippage_bps: u64) -> u64 {
+    cost.saturating_add((cost as u128 * slippage_bps as u128 / BASIS_POINTS as u128) as u64)
+}
+
+/// The `min_sol_output` for a sell quoted at `proceeds`, allowing `slippage_bps`.
+pub fn min_sol_output(proceeds: u64, slippage_bps: u64) -> u64 {
+    proceeds.saturating_sub((proceeds as u128 * slippage_bps as u128 / BASIS_POINTS as u128) as u64)
+}
+
+/// Publishes the curve of `mint` on `sender` every time the account changes.
+pub async fn subscribe(ws_url: &str, mint: &Pubkey, sender: watch::Sender<Option<BondingCurve>>) -> Result<()> {
+    let address = BondingCurve::address(mint);
+    let operation = || async {
+        let client = PubsubClient::new(ws_url).await.map_err(|e| backoff::Error::transient(e.into()))?;
+        let (mut updates, unsubscribe) = client
+            .account_subscribe(
+                &address,
+                Some(RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(CommitmentConfig::processed()),
+                    ..RpcAccountInfoConfig::default()
+                }),
+            )
+            .await
+            .map_err(|e| backoff::Error::transient(e.into()))?;
+        info!("[PUMP] Subscribed to bonding curve {} for {}", address, mint);
+
+        while let Some(update) = updates.next().await {
+            let Some(data) = update.value.data.decode() else {
+                continue;
+            };
+            let curve = BondingCurve::from_account_data(&data).map_err(backoff::Error::permanent)?;
+            if sender.send(Some(curve)).is_err() {
+                unsubscribe().await;
+                return Ok(());
+            }
+        }
+
+        unsubscribe().await;
+        Err(backoff::Error::transient(anyhow!("Bonding curve subscription closed")))
+    };
+
+    retry(ExponentialBackoff::default(), operation).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A freshly launched curve.
+    fn launch() -> BondingCurve {
+        BondingCurve {
+            virtual_token_reserves: 1_073_000_000_000_000,
+            virtual_sol_reserves: 30_000_000_000,
+            real_token_reserves: 793_100_000_000_000,
+            real_sol_reserves: 0,
+            token_total_supply: 1_000_000_000_000_000,
+            complete: false,
+        }
+    }
+
+    #[test]
+    fn test_account_data_round_trips() {
+        let curve = launch();
+        let mut data = BONDING_CURVE_DISCRIMINATOR.to_vec();
+        data.extend_from_slice(&curve.try_to_vec().unwrap());
+        data.extend_from_slice(&[0; 32]);
+        assert_eq!(BondingCurve::from_account_data(&data).unwrap(), curve);
+        assert!(BondingCurve::from_account_data(&data[8..]).is_err());
+    }
+
+    #[test]
+    fn test_buy_quotes_agree_in_both_directions() {
+        let curve = launch();
+        assert!((curve.spot_price() - 30.0 / 1_073_000_000.0).abs() < 1e-15);
+
+        // One SOL on a fresh curve buys roughly 34.3M tokens.
+        let tokens = curve.buy_tokens_for_sol(1_000_000_000).unwrap();
+        assert_eq!(tokens / 1_000_000 / 100_000, 342);
+        let cost = curve.buy_cost(tokens).unwrap();
+        assert!(cost <= 1_000_000_000 && 1_000_000_000 - cost < 10, "cost {}", cost);
+    }
+
+    #[test]
+    fn test_sell_pays_less_than_buy_costs() {
+        let mut curve = launch();
+        curve.real_sol_reserves = 5_000_000_000;
+        let amount = 10_000_000_000_000;
+        assert!(curve.sell_proceeds(amount).unwrap() < curve.buy_cost(amount).unwrap());
+
+        curve.complete = true;
+        assert_eq!(curve.buy_cost(amount), None);
+        assert_eq!(curve.sell_proceeds(amount), None);
+    }
+
+    #[test]
+    fn test_slippage_bounds() {
+        assert_eq!(max_sol_cost(1_000_000, 150), 1_015_000);
+        assert_eq!(min_sol_output(1_000_000, 150), 985_000);
+        assert_eq!(launch().buy_cost(launch().real_token_reserves + 1), None);
+    }
+}
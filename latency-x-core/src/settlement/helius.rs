@@ -1,21 +1,24 @@
 use crate::config::{HeliusConfig, SolanaConfig};
+use crate::execution::ExecutionError;
 use crate::models::{Order, OrderSide};
 use crate::settlement::Settlement;
 use anyhow::Result;
 use async_trait::async_trait;
 use helius_sdk::{Cluster, Helius};
 use solana_sdk::{
-    compute_budget::ComputeBudgetInstruction, instruction::Instruction, signature::Keypair,
+    compute_budget::ComputeBudgetInstruction, signature::Keypair,
     signer::Signer, transaction::Transaction as SolanaTransaction,
 };
 use std::sync::Arc;
 use tracing::info;
 
+use super::bonding_curve::{max_sol_cost, min_sol_output, BondingCurve};
 use super::pump::{get_buy_instruction, get_sell_instruction};
 
 pub struct HeliusSettlement {
     helius: Arc<Helius>,
     wallet: Keypair,
+    slippage_bps: u64,
 }
 
 impl HeliusSettlement {
@@ -27,7 +30,7 @@ impl HeliusSettlement {
         };
         let helius = Arc::new(Helius::new(config.api_key.clone(), cluster));
         let wallet = Keypair::from_base58_string(&solana_config.private_key);
-        Ok(Self { helius, wallet })
+        Ok(Self { helius, wallet, slippage_bps: config.slippage_bps })
     }
 }
 
@@ -36,31 +39,52 @@ impl Settlement for HeliusSettlement {
     async fn send_order(
         &self,
         order: &Order,
-    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    ) -> Result<String, ExecutionError> {
         let blockhash = self
             .helius
             .rpc
             .connection()
             .get_latest_blockhash()
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+            .map_err(|e| ExecutionError::Network(e.to_string()))?;
 
         let mint_address = &order.symbol;
         let token_amount = (order.amount * 1_000_000.0) as u64;
-        let sol_amount = (order.price.unwrap_or(0.0) * order.amount * 1_000_000_000.0) as u64;
+        // The order price, if any, is a limit on the average SOL per token.
+        let limit = order.price.map(|price| (price * order.amount * 1_000_000_000.0) as u64);
+
+        let mint = mint_address.parse().map_err(anyhow::Error::from)?;
+        let curve_data = self
+            .helius
+            .rpc
+            .connection()
+            .get_account_data(&BondingCurve::address(&mint))
+            .map_err(|e| ExecutionError::Network(e.to_string()))?;
+        let curve = BondingCurve::from_account_data(&curve_data)?;
+        if curve.complete {
+            return Err(ExecutionError::Rejected(format!("{} has migrated off its bonding curve", mint_address)));
+        }
 
         let instruction = match order.side {
-            OrderSide::Buy => get_buy_instruction(
-                &self.wallet.pubkey(),
-                mint_address,
-                token_amount,
-                sol_amount,
-            )?,
-            OrderSide::Sell => get_sell_instruction(
-                &self.wallet.pubkey(),
-                mint_address,
-                token_amount,
-                sol_amount,
-            )?,
+            OrderSide::Buy => {
+                let cost = curve
+                    .buy_cost(token_amount)
+                    .ok_or_else(|| ExecutionError::Rejected("Buy exceeds the tokens left on the bonding curve".to_string()))?;
+                if limit.is_some_and(|limit| cost > limit) {
+                    return Err(ExecutionError::Rejected(format!("Curve cost {} lamports is above the limit", cost)));
+                }
+                let max_cost = max_sol_cost(cost, self.slippage_bps).min(limit.unwrap_or(u64::MAX));
+                get_buy_instruction(&self.wallet.pubkey(), mint_address, token_amount, max_cost)?
+            }
+            OrderSide::Sell => {
+                let proceeds = curve
+                    .sell_proceeds(token_amount)
+                    .ok_or_else(|| ExecutionError::Rejected("Sell size is zero".to_string()))?;
+                if limit.is_some_and(|limit| proceeds < limit) {
+                    return Err(ExecutionError::Rejected(format!("Curve proceeds {} lamports are below the limit", proceeds)));
+                }
+                let min_output = min_sol_output(proceeds, self.slippage_bps).max(limit.unwrap_or(0));
+                get_sell_instruction(&self.wallet.pubkey(), mint_address, token_amount, min_output)?
+            }
         };
 
         let instructions = vec![
@@ -77,7 +101,7 @@ impl Settlement for HeliusSettlement {
             .rpc
             .connection()
             .send_and_confirm_transaction(&tx)
-            .map_err(|e| Box::new(e) as Box<dyn std::error::Error + Send + Sync>)?;
+            .map_err(|e| ExecutionError::Timeout(e.to_string()))?;
 
         info!("Transaction sent with signature: {:?}", signature);
 
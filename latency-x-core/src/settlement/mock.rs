@@ -1,8 +1,8 @@
+use crate::execution::ExecutionError;
 use crate::models::Order;
 use crate::settlement::Settlement;
 use anyhow::Result;
 use async_trait::async_trait;
-use std::error::Error;
 
 pub struct MockSettlement;
 
@@ -14,7 +14,7 @@ impl MockSettlement {
 
 #[async_trait]
 impl Settlement for MockSettlement {
-    async fn send_order(&self, order: &Order) -> Result<String, Box<dyn Error + Send + Sync>> {
+    async fn send_order(&self, order: &Order) -> Result<String, ExecutionError> {
         println!("Mock settlement for order: {} ({})", order.id, order.symbol);
         Ok(format!("mock_tx_{}", order.id))
     }
@@ -7,9 +7,12 @@ use solana_sdk::{
 };
 use spl_associated_token_account::get_associated_token_address;
 
+use super::bonding_curve::BondingCurve;
+
 pub const PUMP_FUN_PROGRAM_ID: Pubkey = pubkey!("6EF8rrecthR5Dkzon8Nwu78hRvfCKubJ14M5uBEwF6P");
 const GLOBAL_ACCOUNT: Pubkey = pubkey!("4wTV1YmiEkRvAtNtsSGPtUrqRYQMe5SKy2uB4Jjaxnjf");
 const FEE_RECIPIENT: Pubkey = pubkey!("CebN5WGQ4g2ffEvz7ErmyrssS4U5H2K2sbLqdePs3KNw");
+#[allow(dead_code)]
 const MPL_TOKEN_METADATA_PROGRAM: Pubkey = pubkey!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
 const EVENT_AUTHORITY: Pubkey = pubkey!("Ce6TQqeHC9E8K2eYJikG9gvuGcsgLqpoN1gRrgbAsFGB");
 
@@ -35,8 +38,7 @@ pub fn get_buy_instruction(
 ) -> Result<Instruction, Box<dyn std::error::Error + Send + Sync>> {
     let mint = mint_str.parse::<Pubkey>()?;
 
-    let (bonding_curve, _) =
-        Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_FUN_PROGRAM_ID);
+    let bonding_curve = BondingCurve::address(&mint);
 
     let associated_bonding_curve = get_associated_token_address(&bonding_curve, &mint);
     let associated_user = get_associated_token_address(user, &mint);
@@ -80,8 +82,7 @@ pub fn get_sell_instruction(
 ) -> Result<Instruction, Box<dyn std::error::Error + Send + Sync>> {
     let mint = mint_str.parse::<Pubkey>()?;
 
-    let (bonding_curve, _) =
-        Pubkey::find_program_address(&[b"bonding-curve", mint.as_ref()], &PUMP_FUN_PROGRAM_ID);
+    let bonding_curve = BondingCurve::address(&mint);
 
     let associated_bonding_curve = get_associated_token_address(&bonding_curve, &mint);
     let associated_user = get_associated_token_address(user, &mint);
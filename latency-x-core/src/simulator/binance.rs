@@ -0,0 +1,742 @@
+//! Binance spot: combined trade and depth streams, the user data stream, and
+//! the REST calls the connector makes, including the order lifecycle.
+
+use super::{run_socket, RecordedTrade, SimulatedOrder, SimulatorState};
+use crate::models::{MarketDataSource, OrderBook, OrderSide, OrderStatus};
+use axum::extract::{Path, Query, State, WebSocketUpgrade};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::Utc;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+const USER_CHANNEL: &str = "binance/user";
+/// Commission charged on every fill, in the quote asset.
+const TAKER_FEE: f64 = 0.001;
+/// How old a signed request may be, unless it sends its own `recvWindow`.
+const DEFAULT_RECV_WINDOW_MS: i64 = 5000;
+/// How far ahead of the server clock a request may claim to be.
+const MAX_CLOCK_AHEAD_MS: i64 = 1000;
+/// Binance accepts at most this many decimals in prices and quantities.
+const MAX_DECIMALS: usize = 8;
+
+pub(super) fn router() -> Router<Arc<SimulatorState>> {
+    Router::new()
+        .route("/stream", get(combined_stream))
+        .route("/ws/:listen_key", get(user_data_stream))
+        .route("/api/v3/depth", get(depth_snapshot))
+        .route("/api/v3/historicalTrades", get(historical_trades))
+        .route("/api/v3/userDataStream", post(create_listen_key).put(keepalive_listen_key))
+        .route("/api/v3/order", post(new_order).get(query_order).delete(cancel_order))
+        .route("/api/v3/openOrders", get(open_orders).delete(cancel_open_orders))
+        .route("/api/v3/order/cancelReplace", post(cancel_replace))
+}
+
+fn channel(stream: &str) -> String {
+    format!("binance/{}", stream)
+}
+
+pub(super) fn publish_trade(state: &SimulatorState, symbol: &str, trade: &RecordedTrade) {
+    let stream = format!("{}@trade", symbol.to_lowercase());
+    let message = json!({
+        "stream": stream,
+        "data": {
+            "e": "trade",
+            "E": Utc::now().timestamp_millis(),
+            "s": symbol.to_uppercase(),
+            "t": trade.id,
+            "p": trade.price.to_string(),
+            "q": trade.quantity.to_string(),
+            "T": trade.time.timestamp_millis(),
+            "m": false,
+            "M": true
+        }
+    });
+    state.publish(channel(&stream), message.to_string());
+}
+
+pub(super) fn publish_book_ticker(state: &SimulatorState, symbol: &str, bid: f64, bid_size: f64, ask: f64, ask_size: f64) {
+    let stream = format!("{}@bookTicker", symbol.to_lowercase());
+    let ticker = json!({
+        "stream": stream,
+        "data": {
+            "u": state.next_id(),
+            "s": symbol.to_uppercase(),
+            "b": bid.to_string(),
+            "B": bid_size.to_string(),
+            "a": ask.to_string(),
+            "A": ask_size.to_string()
+        }
+    });
+    state.publish(channel(&stream), ticker.to_string());
+}
+
+/// Applies the changes to the simulated book and sends them as one diff event.
+pub(super) fn publish_depth(state: &SimulatorState, symbol: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+    let symbol = symbol.to_uppercase();
+    let (first_update_id, final_update_id) = {
+        let mut books = state.books.lock().unwrap();
+        let book = books
+            .entry((MarketDataSource::Binance, symbol.clone()))
+            .or_insert_with(|| OrderBook::new(MarketDataSource::Binance, symbol.clone()));
+        for &(price, quantity) in bids {
+            book.update_bid(price, quantity);
+        }
+        for &(price, quantity) in asks {
+            book.update_ask(price, quantity);
+        }
+        let first_update_id = book.last_update_id + 1;
+        book.last_update_id += (bids.len() + asks.len()).max(1) as u64;
+        (first_update_id, book.last_update_id)
+    };
+
+    let levels = |levels: &[(f64, f64)]| -> Vec<[String; 2]> {
+        levels.iter().map(|(p, q)| [p.to_string(), q.to_string()]).collect()
+    };
+    let stream = format!("{}@depth@100ms", symbol.to_lowercase());
+    let update = json!({
+        "stream": stream,
+        "data": {
+            "e": "depthUpdate",
+            "E": Utc::now().timestamp_millis(),
+            "s": symbol,
+            "U": first_update_id,
+            "u": final_update_id,
+            "b": levels(bids),
+            "a": levels(asks)
+        }
+    });
+    state.publish(channel(&stream), update.to_string());
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    streams: String,
+}
+
+async fn combined_stream(
+    ws: WebSocketUpgrade,
+    Query(query): Query<StreamQuery>,
+    State(state): State<Arc<SimulatorState>>,
+) -> impl IntoResponse {
+    serve_combined_stream(ws, state, &query.streams, channel)
+}
+
+/// A combined stream whose stream names map to feed channels through `channel`.
+pub(super) fn serve_combined_stream(
+    ws: WebSocketUpgrade,
+    state: Arc<SimulatorState>,
+    streams: &str,
+    channel: fn(&str) -> String,
+) -> Response {
+    let channels: HashSet<String> = streams.split('/').filter(|s| !s.is_empty()).map(channel).collect();
+    ws.on_upgrade(move |socket| run_socket(socket, state, channels, move |text, channels| stream_command(text, channels, channel)))
+        .into_response()
+}
+
+/// `SUBSCRIBE` and `UNSUBSCRIBE` on an open combined stream.
+fn stream_command(text: &str, channels: &mut HashSet<String>, channel: fn(&str) -> String) -> Vec<String> {
+    let Ok(command) = serde_json::from_str::<serde_json::Value>(text) else {
+        return Vec::new();
+    };
+    let streams = command["params"].as_array().cloned().unwrap_or_default();
+    let streams: Vec<&str> = streams.iter().filter_map(|s| s.as_str()).collect();
+    // Binance refuses the whole command if any stream names no valid symbol.
+    let valid = |stream: &&str| {
+        let symbol = stream.split('@').next().unwrap_or_default();
+        !symbol.is_empty() && symbol.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit())
+    };
+    if let Some(stream) = streams.iter().find(|stream| !valid(stream)) {
+        let msg = format!("Invalid request: invalid stream name {}", stream);
+        return vec![json!({ "error": { "code": 2, "msg": msg }, "id": command["id"] }).to_string()];
+    }
+    let streams = streams.into_iter().map(channel);
+    match command["method"].as_str() {
+        Some("SUBSCRIBE") => channels.extend(streams),
+        Some("UNSUBSCRIBE") => streams.for_each(|stream| {
+            channels.remove(&stream);
+        }),
+        _ => return vec![json!({ "error": { "code": 2, "msg": "Invalid request" }, "id": command["id"] }).to_string()],
+    }
+    vec![json!({ "result": null, "id": command["id"] }).to_string()]
+}
+
+async fn user_data_stream(
+    ws: WebSocketUpgrade,
+    Path(listen_key): Path<String>,
+    State(state): State<Arc<SimulatorState>>,
+) -> Response {
+    if !state.listen_keys.lock().unwrap().contains(&listen_key) {
+        return error(StatusCode::BAD_REQUEST, -1125, "This listenKey does not exist.");
+    }
+    let channels = HashSet::from([USER_CHANNEL.to_string()]);
+    ws.on_upgrade(move |socket| run_socket(socket, state, channels, |_, _| Vec::new()))
+        .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct DepthQuery {
+    symbol: String,
+    limit: Option<usize>,
+}
+
+async fn depth_snapshot(Query(query): Query<DepthQuery>, State(state): State<Arc<SimulatorState>>) -> Response {
+    let books = state.books.lock().unwrap();
+    let Some(book) = books.get(&(MarketDataSource::Binance, query.symbol.to_uppercase())) else {
+        return error(StatusCode::BAD_REQUEST, -1121, "Invalid symbol.");
+    };
+    let book = book.truncated(query.limit.unwrap_or(100));
+    let levels = |levels: &[crate::models::PriceLevel]| -> Vec<[String; 2]> {
+        levels.iter().map(|l| [l.price.to_string(), l.quantity.to_string()]).collect()
+    };
+    Json(json!({
+        "lastUpdateId": book.last_update_id,
+        "bids": levels(&book.bids),
+        "asks": levels(&book.asks)
+    }))
+    .into_response()
+}
+
+#[derive(Debug, Deserialize)]
+struct HistoricalTradesQuery {
+    symbol: String,
+    #[serde(rename = "fromId")]
+    from_id: Option<u64>,
+    limit: Option<usize>,
+}
+
+/// Trades from `fromId` on, or the most recent ones without it.
+async fn historical_trades(
+    Query(query): Query<HistoricalTradesQuery>,
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = reject_api_key(&state, &headers) {
+        return response;
+    }
+    let limit = query.limit.unwrap_or(500).min(1000);
+    let mut trades = state.trades_on(MarketDataSource::Binance, &query.symbol);
+    match query.from_id {
+        Some(from_id) => {
+            trades.retain(|trade| trade.id >= from_id);
+            trades.truncate(limit);
+        }
+        None => {
+            trades.drain(..trades.len().saturating_sub(limit));
+        }
+    }
+    let trades: Vec<_> = trades
+        .iter()
+        .map(|trade| {
+            json!({
+                "id": trade.id,
+                "price": trade.price.to_string(),
+                "qty": trade.quantity.to_string(),
+                "quoteQty": (trade.price * trade.quantity).to_string(),
+                "time": trade.time.timestamp_millis(),
+                "isBuyerMaker": false,
+                "isBestMatch": true
+            })
+        })
+        .collect();
+    Json(trades).into_response()
+}
+
+pub(super) fn error(status: StatusCode, code: i64, msg: &str) -> Response {
+    (status, Json(json!({ "code": code, "msg": msg }))).into_response()
+}
+
+/// The rejection for a missing or unknown `X-MBX-APIKEY`, if any.
+fn reject_api_key(state: &SimulatorState, headers: &HeaderMap) -> Option<Response> {
+    match headers.get("X-MBX-APIKEY").and_then(|key| key.to_str().ok()) {
+        Some(key) if key == state.binance.api_key => None,
+        _ => Some(error(StatusCode::UNAUTHORIZED, -2015, "Invalid API-key, IP, or permissions for action.")),
+    }
+}
+
+async fn create_listen_key(State(state): State<Arc<SimulatorState>>, headers: HeaderMap) -> Response {
+    if let Some(response) = reject_api_key(&state, &headers) {
+        return response;
+    }
+    let listen_key = format!("sim-listen-key-{}", state.next_id());
+    state.listen_keys.lock().unwrap().insert(listen_key.clone());
+    Json(json!({ "listenKey": listen_key })).into_response()
+}
+
+async fn keepalive_listen_key(
+    Query(query): Query<HashMap<String, String>>,
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+) -> Response {
+    if let Some(response) = reject_api_key(&state, &headers) {
+        return response;
+    }
+    match query.get("listenKey") {
+        Some(key) if state.listen_keys.lock().unwrap().contains(key) => Json(json!({})).into_response(),
+        _ => error(StatusCode::BAD_REQUEST, -1125, "This listenKey does not exist."),
+    }
+}
+
+/// Signed parameters travel in the query string, the body, or both; the
+/// signature covers them in that order.
+fn signed_payload(query: Option<&str>, body: &str) -> String {
+    match (query.filter(|q| !q.is_empty()), body.is_empty()) {
+        (Some(query), true) => query.to_string(),
+        (Some(query), false) => format!("{}{}", query, body),
+        (None, _) => body.to_string(),
+    }
+}
+
+/// The parameters of a signed request, or the status, code and message
+/// Binance rejects a bad API key, signature or timestamp with.
+pub(super) fn signed_params(
+    state: &SimulatorState,
+    headers: &HeaderMap,
+    uri: &axum::http::Uri,
+    body: &str,
+) -> Result<HashMap<String, String>, (StatusCode, i64, &'static str)> {
+    if headers.get("X-MBX-APIKEY").and_then(|key| key.to_str().ok()) != Some(state.binance.api_key.as_str()) {
+        return Err((StatusCode::UNAUTHORIZED, -2015, "Invalid API-key, IP, or permissions for action."));
+    }
+    let payload = signed_payload(uri.query(), body);
+    let Some((unsigned, signature)) = payload.rsplit_once("&signature=") else {
+        return Err((StatusCode::BAD_REQUEST, -1102, "Mandatory parameter 'signature' was not sent, was empty/null, or malformed."));
+    };
+    let expected = hex::encode(hmac_sha256::HMAC::mac(unsigned.as_bytes(), state.binance.api_secret.as_bytes()));
+    if signature != expected {
+        return Err((StatusCode::BAD_REQUEST, -1022, "Signature for this request is not valid."));
+    }
+    let params: HashMap<String, String> = url::form_urlencoded::parse(unsigned.as_bytes()).into_owned().collect();
+    let param = |name: &str| params.get(name).and_then(|value| value.parse::<i64>().ok());
+    let Some(timestamp) = param("timestamp") else {
+        return Err((StatusCode::BAD_REQUEST, -1102, "Mandatory parameter 'timestamp' was not sent, was empty/null, or malformed."));
+    };
+    let age = Utc::now().timestamp_millis() - timestamp;
+    if age > param("recvWindow").unwrap_or(DEFAULT_RECV_WINDOW_MS) || age < -MAX_CLOCK_AHEAD_MS {
+        return Err((StatusCode::BAD_REQUEST, -1021, "Timestamp for this request is outside of the recvWindow."));
+    }
+    Ok(params)
+}
+
+type Params = HashMap<String, String>;
+
+/// The status, code and message of a rejected request.
+type Rejection = (StatusCode, i64, &'static str);
+
+fn reject((status, code, msg): Rejection) -> Response {
+    error(status, code, msg)
+}
+
+async fn new_order(
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+    uri: axum::http::Uri,
+    body: String,
+) -> Response {
+    let params = match signed_params(&state, &headers, &uri, &body) {
+        Ok(params) => params,
+        Err(rejection) => return reject(rejection),
+    };
+    let (order, trade_id) = match place_order(&state, &params) {
+        Ok(placed) => placed,
+        Err(rejection) => return reject(rejection),
+    };
+    // Market and limit orders answer with FULL unless asked otherwise.
+    let response = match params.get("newOrderRespType").map(String::as_str) {
+        Some("ACK") => json!({
+            "symbol": order.symbol,
+            "orderId": order_id(&order),
+            "orderListId": -1,
+            "clientOrderId": order.client_order_id,
+            "transactTime": Utc::now().timestamp_millis()
+        }),
+        Some("RESULT") => new_order_json(&order),
+        _ => {
+            let fills: Vec<_> = trade_id
+                .map(|trade_id| json!({
+                    "price": order.fill_price.to_string(),
+                    "qty": order.quantity.to_string(),
+                    "commission": (order.quantity * order.fill_price * TAKER_FEE).to_string(),
+                    "commissionAsset": null,
+                    "tradeId": trade_id
+                }))
+                .into_iter()
+                .collect();
+            let mut full = new_order_json(&order);
+            full["fills"] = json!(fills);
+            full
+        }
+    };
+    Json(response).into_response()
+}
+
+/// The RESULT form of a new order: as a query returns it, but stamped with
+/// `transactTime` instead of `updateTime`.
+fn new_order_json(order: &SimulatedOrder) -> serde_json::Value {
+    let mut response = order_json(order);
+    if let Some(fields) = response.as_object_mut() {
+        let time = fields.remove("updateTime").unwrap_or_default();
+        fields.insert("transactTime".to_string(), time);
+    }
+    response
+}
+
+/// Whether a price or quantity has more decimals than Binance takes.
+fn over_precision(value: &str) -> bool {
+    value.split_once('.').is_some_and(|(_, decimals)| decimals.len() > MAX_DECIMALS)
+}
+
+/// Checks and records a new order, then publishes its executions. Shared by
+/// `order` and `order/cancelReplace`, which spell the order the same way.
+/// Returns the order and the id of the trade that filled it, if any.
+fn place_order(state: &SimulatorState, params: &Params) -> Result<(SimulatedOrder, Option<i64>), Rejection> {
+    let param = |name: &str| params.get(name).map(String::as_str);
+    let side = match param("side") {
+        Some("BUY") => OrderSide::Buy,
+        Some("SELL") => OrderSide::Sell,
+        _ => return Err((StatusCode::BAD_REQUEST, -1117, "Invalid side.")),
+    };
+    let order_type = match param("type") {
+        Some(order_type @ ("MARKET" | "LIMIT")) => order_type.to_string(),
+        _ => return Err((StatusCode::BAD_REQUEST, -1116, "Invalid orderType.")),
+    };
+    let (Some(symbol), Some(quantity)) = (param("symbol"), param("quantity").and_then(|q| q.parse::<f64>().ok())) else {
+        return Err((StatusCode::BAD_REQUEST, -1102, "Mandatory parameter was not sent, was empty/null, or malformed."));
+    };
+    let price = param("price").and_then(|p| p.parse::<f64>().ok());
+    if order_type == "LIMIT" && (price.is_none() || param("timeInForce").is_none()) {
+        return Err((StatusCode::BAD_REQUEST, -1102, "Mandatory parameter 'price' or 'timeInForce' was not sent, was empty/null, or malformed."));
+    }
+    if [param("quantity"), param("price")].into_iter().flatten().any(over_precision) {
+        return Err((StatusCode::BAD_REQUEST, -1111, "Precision is over the maximum defined for this asset."));
+    }
+    if quantity <= 0.0 {
+        return Err((StatusCode::BAD_REQUEST, -1013, "Filter failure: LOT_SIZE"));
+    }
+    let Some(fill_price) = price.or_else(|| state.last_price(MarketDataSource::Binance, symbol)) else {
+        return Err((StatusCode::BAD_REQUEST, -2010, "Account has insufficient balance for requested action."));
+    };
+
+    let order_id = state.next_id();
+    let client_order_id = param("newClientOrderId")
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("sim-{}", order_id));
+    let order = SimulatedOrder {
+        venue: MarketDataSource::Binance,
+        symbol: symbol.to_string(),
+        side,
+        status: state.initial_status(order_type == "LIMIT"),
+        order_type,
+        quantity,
+        price,
+        client_order_id: Some(client_order_id),
+        exchange_order_id: order_id.to_string(),
+        fill_price,
+        reduce_only: false,
+        position_side: None,
+    };
+    let trade_id = publish_executions(state, &order, Utc::now().timestamp_millis());
+    state.record_order(order.clone());
+    Ok((order, trade_id))
+}
+
+fn order_id(order: &SimulatedOrder) -> u64 {
+    order.exchange_order_id.parse().unwrap_or_default()
+}
+
+fn status_name(status: &OrderStatus) -> &'static str {
+    match status {
+        OrderStatus::New => "NEW",
+        OrderStatus::PartiallyFilled => "PARTIALLY_FILLED",
+        OrderStatus::Filled => "FILLED",
+        OrderStatus::Canceled => "CANCELED",
+        OrderStatus::Rejected | OrderStatus::Failed => "REJECTED",
+        OrderStatus::Expired => "EXPIRED",
+    }
+}
+
+fn side_name(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "BUY",
+        OrderSide::Sell => "SELL",
+    }
+}
+
+/// The order as `GET /api/v3/order` returns it.
+fn order_json(order: &SimulatedOrder) -> serde_json::Value {
+    let filled = order.filled_quantity();
+    json!({
+        "symbol": order.symbol,
+        "orderId": order_id(order),
+        "orderListId": -1,
+        "clientOrderId": order.client_order_id,
+        "price": order.price.unwrap_or(0.0).to_string(),
+        "origQty": order.quantity.to_string(),
+        "executedQty": filled.to_string(),
+        "cummulativeQuoteQty": (filled * order.fill_price).to_string(),
+        "status": status_name(&order.status),
+        "timeInForce": "GTC",
+        "type": order.order_type,
+        "side": side_name(order.side),
+        "updateTime": Utc::now().timestamp_millis()
+    })
+}
+
+/// A cancel response, where `clientOrderId` names the cancel request.
+fn cancel_json(state: &SimulatorState, order: &SimulatedOrder) -> serde_json::Value {
+    let mut canceled = order_json(order);
+    canceled["origClientOrderId"] = json!(order.client_order_id);
+    canceled["clientOrderId"] = json!(format!("sim-cancel-{}", state.next_id()));
+    canceled
+}
+
+/// Whether `order` is the one named by a client order id or, failing that,
+/// an order id.
+fn is_order(order: &SimulatedOrder, client_order_id: Option<&String>, order_id: Option<&String>) -> bool {
+    match (client_order_id, order_id) {
+        (Some(id), _) => order.client_order_id.as_ref() == Some(id),
+        (None, Some(id)) => &order.exchange_order_id == id,
+        (None, None) => false,
+    }
+}
+
+/// Finds the order `params` name through `client_key` or `id_key` on their
+/// symbol, or the error for a missing reference.
+fn order_reference<'a>(
+    params: &'a Params,
+    client_key: &str,
+    id_key: &str,
+) -> Result<impl Fn(&SimulatedOrder) -> bool + 'a, Rejection> {
+    let symbol = params.get("symbol").map(|symbol| symbol.to_uppercase());
+    let (client_order_id, order_id) = (params.get(client_key), params.get(id_key));
+    if symbol.is_none() || (client_order_id.is_none() && order_id.is_none()) {
+        return Err((StatusCode::BAD_REQUEST, -1102, "Mandatory parameter was not sent, was empty/null, or malformed."));
+    }
+    Ok(move |order: &SimulatedOrder| {
+        Some(order.symbol.to_uppercase()) == symbol && is_order(order, client_order_id, order_id)
+    })
+}
+
+async fn query_order(
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+    uri: axum::http::Uri,
+) -> Response {
+    let params = match signed_params(&state, &headers, &uri, "") {
+        Ok(params) => params,
+        Err(rejection) => return reject(rejection),
+    };
+    let matches = match order_reference(&params, "origClientOrderId", "orderId") {
+        Ok(matches) => matches,
+        Err(rejection) => return reject(rejection),
+    };
+    match state.find_order(MarketDataSource::Binance, matches) {
+        Some(order) => Json(order_json(&order)).into_response(),
+        None => error(StatusCode::BAD_REQUEST, -2013, "Order does not exist."),
+    }
+}
+
+async fn cancel_order(
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+    uri: axum::http::Uri,
+) -> Response {
+    let params = match signed_params(&state, &headers, &uri, "") {
+        Ok(params) => params,
+        Err(rejection) => return reject(rejection),
+    };
+    let matches = match order_reference(&params, "origClientOrderId", "orderId") {
+        Ok(matches) => matches,
+        Err(rejection) => return reject(rejection),
+    };
+    match state.cancel_order(MarketDataSource::Binance, matches) {
+        Some(Ok(order)) => Json(cancel(&state, &order)).into_response(),
+        Some(Err(_)) | None => error(StatusCode::BAD_REQUEST, -2011, "Unknown order sent."),
+    }
+}
+
+/// Publishes the cancellation and returns the cancel response.
+fn cancel(state: &SimulatorState, order: &SimulatedOrder) -> serde_json::Value {
+    let canceled = cancel_json(state, order);
+    let cancel_id = canceled["clientOrderId"].as_str().unwrap_or_default();
+    let report = execution_report(order, cancel_id, "CANCELED", 0.0, 0.0, -1, Utc::now().timestamp_millis());
+    state.publish(USER_CHANNEL.to_string(), report);
+    canceled
+}
+
+async fn open_orders(
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+    uri: axum::http::Uri,
+) -> Response {
+    let params = match signed_params(&state, &headers, &uri, "") {
+        Ok(params) => params,
+        Err(rejection) => return reject(rejection),
+    };
+    let orders: Vec<_> = state
+        .open_orders(MarketDataSource::Binance)
+        .iter()
+        .filter(|order| params.get("symbol").is_none_or(|symbol| order.symbol.eq_ignore_ascii_case(symbol)))
+        .map(order_json)
+        .collect();
+    Json(orders).into_response()
+}
+
+/// Cancels every resting order on one symbol.
+async fn cancel_open_orders(
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+    uri: axum::http::Uri,
+) -> Response {
+    let params = match signed_params(&state, &headers, &uri, "") {
+        Ok(params) => params,
+        Err(rejection) => return reject(rejection),
+    };
+    let Some(symbol) = params.get("symbol") else {
+        return error(StatusCode::BAD_REQUEST, -1102, "Mandatory parameter 'symbol' was not sent, was empty/null, or malformed.");
+    };
+    let mut canceled = Vec::new();
+    for open in state.open_orders(MarketDataSource::Binance) {
+        if !open.symbol.eq_ignore_ascii_case(symbol) {
+            continue;
+        }
+        if let Some(Ok(order)) = state.cancel_order(MarketDataSource::Binance, |order| order.exchange_order_id == open.exchange_order_id) {
+            canceled.push(cancel(&state, &order));
+        }
+    }
+    if canceled.is_empty() {
+        return error(StatusCode::BAD_REQUEST, -2011, "Unknown order sent.");
+    }
+    Json(canceled).into_response()
+}
+
+/// Cancels an order and places a new one. With `STOP_ON_FAILURE` nothing is
+/// placed when the cancel fails.
+async fn cancel_replace(
+    State(state): State<Arc<SimulatorState>>,
+    headers: HeaderMap,
+    uri: axum::http::Uri,
+    body: String,
+) -> Response {
+    let params = match signed_params(&state, &headers, &uri, &body) {
+        Ok(params) => params,
+        Err(rejection) => return reject(rejection),
+    };
+    let stop_on_failure = match params.get("cancelReplaceMode").map(String::as_str) {
+        Some("STOP_ON_FAILURE") => true,
+        Some("ALLOW_FAILURE") => false,
+        _ => return error(StatusCode::BAD_REQUEST, -1102, "Mandatory parameter 'cancelReplaceMode' was not sent, was empty/null, or malformed."),
+    };
+    let matches = match order_reference(&params, "cancelOrigClientOrderId", "cancelOrderId") {
+        Ok(matches) => matches,
+        Err(rejection) => return reject(rejection),
+    };
+
+    let unknown = json!({ "code": -2011, "msg": "Unknown order sent." });
+    let cancel_response = match state.cancel_order(MarketDataSource::Binance, matches) {
+        Some(Ok(order)) => Ok(cancel(&state, &order)),
+        Some(Err(_)) | None => Err(unknown),
+    };
+    if stop_on_failure {
+        if let Err(cancel_response) = cancel_response {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(json!({
+                    "code": -2022,
+                    "msg": "Order cancel-replace failed.",
+                    "data": {
+                        "cancelResult": "FAILURE",
+                        "newOrderResult": "NOT_ATTEMPTED",
+                        "cancelResponse": cancel_response,
+                        "newOrderResponse": null
+                    }
+                })),
+            )
+                .into_response();
+        }
+    }
+
+    let new_order_response = match place_order(&state, &params) {
+        Ok((order, _)) => new_order_json(&order),
+        Err(rejection) => return reject(rejection),
+    };
+    let result = |ok: bool| if ok { "SUCCESS" } else { "FAILURE" };
+    let body = json!({
+        "cancelResult": result(cancel_response.is_ok()),
+        "newOrderResult": "SUCCESS",
+        "cancelResponse": cancel_response.as_ref().unwrap_or_else(|e| e),
+        "newOrderResponse": new_order_response
+    });
+    if cancel_response.is_ok() {
+        Json(body).into_response()
+    } else {
+        (
+            StatusCode::CONFLICT,
+            Json(json!({ "code": -2021, "msg": "Order cancel-replace partially failed.", "data": body })),
+        )
+            .into_response()
+    }
+}
+
+/// An `executionReport` for `order`. `client_order_id` is the order's own id,
+/// or the cancel request's on cancellations.
+fn execution_report(
+    order: &SimulatedOrder,
+    client_order_id: &str,
+    execution_type: &str,
+    last_quantity: f64,
+    commission: f64,
+    trade_id: i64,
+    transact_time: i64,
+) -> String {
+    let (status, orig_client_order_id) = match execution_type {
+        "CANCELED" => ("CANCELED", order.client_order_id.as_deref().unwrap_or_default()),
+        "TRADE" => ("FILLED", ""),
+        _ => ("NEW", ""),
+    };
+    json!({
+        "e": "executionReport",
+        "E": transact_time,
+        "s": order.symbol,
+        "c": client_order_id,
+        "S": side_name(order.side),
+        "o": order.order_type,
+        "q": order.quantity.to_string(),
+        "p": order.price.unwrap_or(0.0).to_string(),
+        "C": orig_client_order_id,
+        "x": execution_type,
+        "X": status,
+        "i": order_id(order),
+        "l": last_quantity.to_string(),
+        "L": if last_quantity > 0.0 { order.fill_price } else { 0.0 }.to_string(),
+        "n": commission.to_string(),
+        "N": null,
+        "T": transact_time,
+        "t": trade_id
+    })
+    .to_string()
+}
+
+/// `NEW`, followed by a single `TRADE` that fills the whole order unless it
+/// rests. Returns the trade's id.
+fn publish_executions(state: &SimulatorState, order: &SimulatedOrder, transact_time: i64) -> Option<i64> {
+    let client_order_id = order.client_order_id.as_deref().unwrap_or_default();
+    state.publish(
+        USER_CHANNEL.to_string(),
+        execution_report(order, client_order_id, "NEW", 0.0, 0.0, -1, transact_time),
+    );
+    if order.status != OrderStatus::Filled {
+        return None;
+    }
+    let commission = order.quantity * order.fill_price * TAKER_FEE;
+    let trade_id = state.next_id() as i64;
+    state.publish(
+        USER_CHANNEL.to_string(),
+        execution_report(order, client_order_id, "TRADE", order.quantity, commission, trade_id, transact_time),
+    );
+    Some(trade_id)
+}
@@ -0,0 +1,170 @@
+//! Binance USD-M futures: aggregate trade and mark price streams and order
+//! entry. Requests are signed with the spot credentials.
+
+use super::binance::{error, serve_combined_stream, signed_params};
+use super::{RecordedTrade, SimulatedOrder, SimulatorState};
+use crate::models::{MarketDataSource, OrderSide, OrderStatus, PositionSide};
+use axum::extract::{Query, State, WebSocketUpgrade};
+use axum::http::{HeaderMap, StatusCode, Uri};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use std::sync::Arc;
+
+pub(super) fn router() -> Router<Arc<SimulatorState>> {
+    Router::new()
+        .route("/stream", get(combined_stream))
+        .route("/fapi/v1/order", post(new_order))
+}
+
+fn channel(stream: &str) -> String {
+    format!("binance-futures/{}", stream)
+}
+
+pub(super) fn publish_agg_trade(state: &SimulatorState, symbol: &str, trade: &RecordedTrade) {
+    let stream = format!("{}@aggTrade", symbol.to_lowercase());
+    let message = json!({
+        "stream": stream,
+        "data": {
+            "e": "aggTrade",
+            "E": Utc::now().timestamp_millis(),
+            "s": symbol.to_uppercase(),
+            "a": trade.id,
+            "p": trade.price.to_string(),
+            "q": trade.quantity.to_string(),
+            "f": trade.id,
+            "l": trade.id,
+            "T": trade.time.timestamp_millis(),
+            "m": false
+        }
+    });
+    state.publish(channel(&stream), message.to_string());
+}
+
+pub(super) fn publish_mark_price(
+    state: &SimulatorState,
+    symbol: &str,
+    mark_price: f64,
+    index_price: f64,
+    funding_rate: f64,
+    next_funding_time: DateTime<Utc>,
+) {
+    let stream = format!("{}@markPrice@1s", symbol.to_lowercase());
+    let message = json!({
+        "stream": stream,
+        "data": {
+            "e": "markPriceUpdate",
+            "E": Utc::now().timestamp_millis(),
+            "s": symbol.to_uppercase(),
+            "p": mark_price.to_string(),
+            "i": index_price.to_string(),
+            "P": mark_price.to_string(),
+            "r": funding_rate.to_string(),
+            "T": next_funding_time.timestamp_millis()
+        }
+    });
+    state.publish(channel(&stream), message.to_string());
+}
+
+#[derive(Debug, Deserialize)]
+struct StreamQuery {
+    streams: String,
+}
+
+async fn combined_stream(
+    ws: WebSocketUpgrade,
+    Query(query): Query<StreamQuery>,
+    State(state): State<Arc<SimulatorState>>,
+) -> Response {
+    serve_combined_stream(ws, state, &query.streams, channel)
+}
+
+async fn new_order(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, uri: Uri, body: String) -> Response {
+    let params = match signed_params(&state, &headers, &uri, &body) {
+        Ok(params) => params,
+        Err((status, code, msg)) => return error(status, code, msg),
+    };
+    let param = |name: &str| params.get(name).map(String::as_str);
+    let side = match param("side") {
+        Some("BUY") => OrderSide::Buy,
+        Some("SELL") => OrderSide::Sell,
+        _ => return error(StatusCode::BAD_REQUEST, -1117, "Invalid side."),
+    };
+    let order_type = match param("type") {
+        Some(order_type @ ("MARKET" | "LIMIT")) => order_type.to_string(),
+        _ => return error(StatusCode::BAD_REQUEST, -1116, "Invalid orderType."),
+    };
+    let (Some(symbol), Some(quantity)) = (param("symbol"), param("quantity").and_then(|q| q.parse::<f64>().ok())) else {
+        return error(StatusCode::BAD_REQUEST, -1102, "Mandatory parameter was not sent, was empty/null, or malformed.");
+    };
+    let price = param("price").and_then(|p| p.parse::<f64>().ok());
+    if order_type == "LIMIT" && (price.is_none() || param("timeInForce").is_none()) {
+        return error(StatusCode::BAD_REQUEST, -1102, "Mandatory parameter 'price' or 'timeInForce' was not sent, was empty/null, or malformed.");
+    }
+    let position_side = match param("positionSide") {
+        None | Some("BOTH") => PositionSide::Both,
+        Some("LONG") => PositionSide::Long,
+        Some("SHORT") => PositionSide::Short,
+        Some(_) => return error(StatusCode::BAD_REQUEST, -4006, "Invalid position side."),
+    };
+    let reduce_only = param("reduceOnly") == Some("true");
+    if reduce_only && position_side != PositionSide::Both {
+        return error(StatusCode::BAD_REQUEST, -1106, "Parameter 'reduceOnly' sent when not required.");
+    }
+    let Some(fill_price) = price.or_else(|| state.last_price(MarketDataSource::BinanceFutures, symbol)) else {
+        return error(StatusCode::BAD_REQUEST, -2010, "Account has insufficient balance for requested action.");
+    };
+
+    let delta = match side {
+        OrderSide::Buy => quantity,
+        OrderSide::Sell => -quantity,
+    };
+    {
+        let mut positions = state.futures_positions.lock().unwrap();
+        let position = positions.entry((symbol.to_uppercase(), position_side)).or_default();
+        if reduce_only && (*position * delta >= 0.0 || quantity > position.abs()) {
+            return error(StatusCode::BAD_REQUEST, -2022, "ReduceOnly Order is rejected.");
+        }
+        *position += delta;
+    }
+
+    let order_id = state.next_id();
+    let client_order_id = param("newClientOrderId")
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("sim-{}", order_id));
+    let order = SimulatedOrder {
+        venue: MarketDataSource::BinanceFutures,
+        symbol: symbol.to_string(),
+        side,
+        order_type: order_type.clone(),
+        quantity,
+        price,
+        client_order_id: Some(client_order_id.clone()),
+        exchange_order_id: order_id.to_string(),
+        fill_price,
+        reduce_only,
+        position_side: Some(position_side),
+        status: OrderStatus::Filled,
+    };
+    state.record_order(order);
+
+    Json(json!({
+        "orderId": order_id,
+        "symbol": symbol,
+        "status": "FILLED",
+        "clientOrderId": client_order_id,
+        "price": price.unwrap_or(0.0).to_string(),
+        "avgPrice": fill_price.to_string(),
+        "origQty": quantity.to_string(),
+        "executedQty": quantity.to_string(),
+        "type": order_type,
+        "reduceOnly": reduce_only,
+        "side": param("side"),
+        "positionSide": param("positionSide").unwrap_or("BOTH"),
+        "updateTime": Utc::now().timestamp_millis()
+    }))
+    .into_response()
+}
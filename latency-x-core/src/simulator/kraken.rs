@@ -0,0 +1,658 @@
+//! Kraken spot: the v1 public trade feed, the v2 instrument and book feeds,
+//! the authenticated v1 feed, and the signed private REST calls, including
+//! the order lifecycle.
+
+use super::{run_socket, RecordedTrade, SimulatedOrder, SimulatorState};
+use crate::connectors::kraken::kraken_book_checksum;
+use crate::models::{MarketDataSource, OrderBook, OrderSide, OrderStatus};
+use axum::extract::{Query, State, WebSocketUpgrade};
+use axum::http::HeaderMap;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use base64::{engine::general_purpose, Engine as _};
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256, Sha512};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+const TRADE_CHANNEL_ID: u64 = 337;
+const SPREAD_CHANNEL_ID: u64 = 338;
+/// Fee charged on every fill, in the quote currency.
+const TAKER_FEE: f64 = 0.0026;
+
+pub(super) fn router() -> Router<Arc<SimulatorState>> {
+    Router::new()
+        .route("/ws", get(public_v1))
+        .route("/ws/v2", get(public_v2))
+        .route("/ws-auth", get(private_v1))
+        .route("/0/private/GetWebSocketsToken", post(get_websockets_token))
+        .route("/0/private/AddOrder", post(add_order))
+        .route("/0/private/CancelOrder", post(cancel_order))
+        .route("/0/private/CancelAll", post(cancel_all))
+        .route("/0/private/QueryOrders", post(query_orders))
+        .route("/0/private/OpenOrders", post(open_orders))
+        .route("/0/private/EditOrder", post(edit_order))
+        .route("/0/public/Trades", get(trades))
+}
+
+fn trade_channel(pair: &str) -> String {
+    format!("kraken/trade:{}", pair)
+}
+
+fn spread_channel(pair: &str) -> String {
+    format!("kraken/spread:{}", pair)
+}
+
+fn book_channel(symbol: &str) -> String {
+    format!("kraken/book:{}", symbol)
+}
+
+const OWN_TRADES_CHANNEL: &str = "kraken/ownTrades";
+const OPEN_ORDERS_CHANNEL: &str = "kraken/openOrders";
+
+pub(super) fn publish_trade(state: &SimulatorState, pair: &str, trade: &RecordedTrade) {
+    let trade = json!([TRADE_CHANNEL_ID, [trade_fields(trade)], "trade", pair]);
+    state.publish(trade_channel(pair), trade.to_string());
+}
+
+/// `[price, volume, time, side, type, misc]`, shared by the feed and REST.
+fn trade_fields(trade: &RecordedTrade) -> Vec<Value> {
+    let time = trade.time.timestamp_micros() as f64 / 1_000_000.0;
+    vec![
+        json!(trade.price.to_string()),
+        json!(trade.quantity.to_string()),
+        json!(format!("{:.6}", time)),
+        json!("b"),
+        json!("l"),
+        json!(""),
+    ]
+}
+
+pub(super) fn publish_spread(state: &SimulatorState, pair: &str, bid: f64, bid_size: f64, ask: f64, ask_size: f64) {
+    let time = Utc::now().timestamp_micros() as f64 / 1_000_000.0;
+    let spread = json!([
+        SPREAD_CHANNEL_ID,
+        [bid.to_string(), ask.to_string(), format!("{:.6}", time), bid_size.to_string(), ask_size.to_string()],
+        "spread",
+        pair
+    ]);
+    state.publish(spread_channel(pair), spread.to_string());
+}
+
+fn levels(levels: &[(f64, f64)]) -> Vec<Value> {
+    levels.iter().map(|&(price, qty)| json!({ "price": price, "qty": qty })).collect()
+}
+
+/// Applies the changes and sends them with the checksum of the resulting book.
+pub(super) fn publish_book(state: &SimulatorState, symbol: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+    let checksum = {
+        let mut books = state.books.lock().unwrap();
+        let book = books
+            .entry((MarketDataSource::Kraken, symbol.to_string()))
+            .or_insert_with(|| OrderBook::new(MarketDataSource::Kraken, symbol.to_string()));
+        for &(price, qty) in bids {
+            book.update_bid(price, qty);
+        }
+        for &(price, qty) in asks {
+            book.update_ask(price, qty);
+        }
+        checksum(state, book)
+    };
+    let update = json!({
+        "channel": "book",
+        "type": "update",
+        "data": [{
+            "symbol": symbol,
+            "bids": levels(bids),
+            "asks": levels(asks),
+            "checksum": checksum,
+            "timestamp": Utc::now().to_rfc3339()
+        }]
+    });
+    state.publish(book_channel(symbol), update.to_string());
+}
+
+fn checksum(state: &SimulatorState, book: &OrderBook) -> u32 {
+    let (price_precision, qty_precision) = state.kraken_pairs.get(&book.symbol).copied().unwrap_or((1, 8));
+    kraken_book_checksum(book, price_precision, qty_precision)
+}
+
+fn book_snapshot(state: &SimulatorState, symbol: &str, depth: usize) -> String {
+    let book = state
+        .books
+        .lock()
+        .unwrap()
+        .get(&(MarketDataSource::Kraken, symbol.to_string()))
+        .map(|book| book.truncated(depth))
+        .unwrap_or_else(|| OrderBook::new(MarketDataSource::Kraken, symbol.to_string()));
+    let levels = |levels: &[crate::models::PriceLevel]| -> Vec<Value> {
+        levels.iter().map(|l| json!({ "price": l.price, "qty": l.quantity })).collect()
+    };
+    json!({
+        "channel": "book",
+        "type": "snapshot",
+        "data": [{
+            "symbol": symbol,
+            "bids": levels(&book.bids),
+            "asks": levels(&book.asks),
+            "checksum": checksum(state, &book)
+        }]
+    })
+    .to_string()
+}
+
+async fn public_v1(ws: WebSocketUpgrade, State(state): State<Arc<SimulatorState>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| {
+        run_socket(socket, state, HashSet::new(), |text, channels| {
+            let Ok(request) = serde_json::from_str::<Value>(text) else {
+                return Vec::new();
+            };
+            let (subscribe, status) = match request["event"].as_str() {
+                Some("subscribe") => (true, "subscribed"),
+                Some("unsubscribe") => (false, "unsubscribed"),
+                _ => return Vec::new(),
+            };
+            let (name, channel_id, channel): (&str, u64, fn(&str) -> String) = match request["subscription"]["name"].as_str() {
+                Some("trade") => ("trade", TRADE_CHANNEL_ID, trade_channel),
+                Some("spread") => ("spread", SPREAD_CHANNEL_ID, spread_channel),
+                _ => return Vec::new(),
+            };
+            let pairs = request["pair"].as_array().cloned().unwrap_or_default();
+            pairs
+                .iter()
+                .filter_map(Value::as_str)
+                .map(|pair| {
+                    if subscribe {
+                        channels.insert(channel(pair));
+                    } else {
+                        channels.remove(&channel(pair));
+                    }
+                    let mut reply = json!({
+                        "channelID": channel_id,
+                        "channelName": name,
+                        "event": "subscriptionStatus",
+                        "pair": pair,
+                        "status": status,
+                        "subscription": { "name": name }
+                    });
+                    if let Some(reqid) = request.get("reqid") {
+                        reply["reqid"] = reqid.clone();
+                    }
+                    reply.to_string()
+                })
+                .collect()
+        })
+    })
+}
+
+async fn public_v2(ws: WebSocketUpgrade, State(state): State<Arc<SimulatorState>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| {
+        let handler_state = state.clone();
+        run_socket(socket, state, HashSet::new(), move |text, channels| {
+            let state = &handler_state;
+            let Ok(request) = serde_json::from_str::<Value>(text) else {
+                return Vec::new();
+            };
+            let method = request["method"].as_str().unwrap_or_default();
+            let params = &request["params"];
+            let ack = |symbol: &str| {
+                let mut ack = json!({ "method": method, "success": true, "result": { "channel": "book", "symbol": symbol } });
+                if let Some(req_id) = request.get("req_id") {
+                    ack["req_id"] = req_id.clone();
+                }
+                ack.to_string()
+            };
+            match (method, params["channel"].as_str()) {
+                ("subscribe", Some("instrument")) => {
+                    let pairs: Vec<Value> = state
+                        .kraken_pairs
+                        .iter()
+                        .map(|(symbol, (price_precision, qty_precision))| {
+                            json!({ "symbol": symbol, "price_precision": price_precision, "qty_precision": qty_precision })
+                        })
+                        .collect();
+                    vec![json!({
+                        "channel": "instrument",
+                        "type": "snapshot",
+                        "data": { "assets": [], "pairs": pairs }
+                    })
+                    .to_string()]
+                }
+                ("subscribe", Some("book")) => {
+                    let depth = params["depth"].as_u64().unwrap_or(10) as usize;
+                    let mut replies = Vec::new();
+                    for symbol in params["symbol"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                        channels.insert(book_channel(symbol));
+                        replies.push(ack(symbol));
+                        replies.push(book_snapshot(state, symbol, depth));
+                    }
+                    replies
+                }
+                ("unsubscribe", Some("book")) => {
+                    let mut replies = Vec::new();
+                    for symbol in params["symbol"].as_array().into_iter().flatten().filter_map(Value::as_str) {
+                        channels.remove(&book_channel(symbol));
+                        replies.push(ack(symbol));
+                    }
+                    replies
+                }
+                _ => Vec::new(),
+            }
+        })
+    })
+}
+
+/// ownTrades sends the last 50 executions on subscribing, unless asked not to.
+async fn private_v1(ws: WebSocketUpgrade, State(state): State<Arc<SimulatorState>>) -> impl IntoResponse {
+    ws.on_upgrade(move |socket| {
+        let token = state.kraken_token.clone();
+        let snapshot_state = state.clone();
+        run_socket(socket, state, HashSet::new(), move |text, channels| {
+            let Ok(request) = serde_json::from_str::<Value>(text) else {
+                return Vec::new();
+            };
+            let subscription = &request["subscription"];
+            let name = subscription["name"].as_str().unwrap_or_default();
+            if request["event"] != "subscribe" {
+                return Vec::new();
+            }
+            if subscription["token"].as_str() != Some(token.as_str()) {
+                return vec![json!({
+                    "event": "subscriptionStatus",
+                    "status": "error",
+                    "errorMessage": "EGeneral:Invalid arguments:token",
+                    "subscription": { "name": name }
+                })
+                .to_string()];
+            }
+            let channel = match name {
+                "ownTrades" => OWN_TRADES_CHANNEL,
+                "openOrders" => OPEN_ORDERS_CHANNEL,
+                _ => return Vec::new(),
+            };
+            channels.insert(channel.to_string());
+            let mut replies = vec![json!({
+                "channelName": name,
+                "event": "subscriptionStatus",
+                "status": "subscribed",
+                "subscription": { "name": name }
+            })
+            .to_string()];
+            if name == "ownTrades" && subscription["snapshot"] != false {
+                let own_trades = snapshot_state.kraken_own_trades.lock().unwrap();
+                let trades: Vec<Value> = own_trades
+                    .iter()
+                    .rev()
+                    .take(50)
+                    .map(|(trade_id, trade)| json!({ trade_id.as_str(): trade }))
+                    .collect();
+                replies.push(json!([trades, "ownTrades", { "sequence": snapshot_state.next_id() }]).to_string());
+            }
+            replies
+        })
+    })
+}
+
+#[derive(Debug, Deserialize)]
+struct TradesQuery {
+    pair: String,
+    /// Nanoseconds; only later trades are returned.
+    since: Option<i64>,
+}
+
+/// Trades after `since`, oldest first, with `last` to continue from.
+async fn trades(Query(query): Query<TradesQuery>, State(state): State<Arc<SimulatorState>>) -> Response {
+    let since = query.since.unwrap_or(0);
+    let trades: Vec<RecordedTrade> = state
+        .trades_on(MarketDataSource::Kraken, &query.pair)
+        .into_iter()
+        .filter(|trade| trade.time.timestamp_micros() * 1_000 > since)
+        .take(1000)
+        .collect();
+    let last = trades.last().map_or(since, |trade| trade.time.timestamp_micros() * 1_000);
+    let trades: Vec<Value> = trades
+        .iter()
+        .map(|trade| {
+            let mut fields = trade_fields(trade);
+            // REST carries the time as a number, unlike the feed.
+            fields[2] = json!(trade.time.timestamp_micros() as f64 / 1_000_000.0);
+            fields.push(json!(trade.id));
+            Value::Array(fields)
+        })
+        .collect();
+    Json(json!({ "error": [], "result": { query.pair: trades, "last": last.to_string() } })).into_response()
+}
+
+fn error(message: &str) -> Response {
+    Json(json!({ "error": [message] })).into_response()
+}
+
+/// Checks `API-Key` and `API-Sign` against the raw body and returns the
+/// decoded parameters, or Kraken's error string.
+fn authenticate(state: &SimulatorState, path: &str, headers: &HeaderMap, body: &str) -> Result<HashMap<String, String>, &'static str> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    if header("API-Key") != Some(state.kraken.api_key.as_str()) {
+        return Err("EAPI:Invalid key");
+    }
+    let params: HashMap<String, String> = url::form_urlencoded::parse(body.as_bytes()).into_owned().collect();
+    let Some(nonce) = params.get("nonce") else {
+        return Err("EAPI:Invalid nonce");
+    };
+
+    let secret = general_purpose::STANDARD
+        .decode(&state.kraken.api_secret)
+        .expect("simulator Kraken secret must be base64");
+    let mut mac = Hmac::<Sha512>::new_from_slice(&secret).expect("HMAC accepts any key length");
+    mac.update(path.as_bytes());
+    mac.update(&Sha256::digest(format!("{}{}", nonce, body).as_bytes()));
+    let expected = general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+    if header("API-Sign") != Some(expected.as_str()) {
+        return Err("EAPI:Invalid signature");
+    }
+    Ok(params)
+}
+
+async fn get_websockets_token(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, body: String) -> Response {
+    if let Err(message) = authenticate(&state, "/0/private/GetWebSocketsToken", &headers, &body) {
+        return error(message);
+    }
+    Json(json!({ "error": [], "result": { "token": state.kraken_token, "expires": 900 } })).into_response()
+}
+
+async fn add_order(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, body: String) -> Response {
+    let params = match authenticate(&state, "/0/private/AddOrder", &headers, &body) {
+        Ok(params) => params,
+        Err(message) => return error(message),
+    };
+    let param = |name: &str| params.get(name).map(String::as_str);
+    let side = match param("type") {
+        Some("buy") => OrderSide::Buy,
+        Some("sell") => OrderSide::Sell,
+        _ => return error("EGeneral:Invalid arguments:type"),
+    };
+    let order_type = match param("ordertype") {
+        Some(order_type @ ("market" | "limit")) => order_type.to_string(),
+        _ => return error("EGeneral:Invalid arguments:ordertype"),
+    };
+    let (Some(pair), Some(volume)) = (param("pair"), param("volume").and_then(|v| v.parse::<f64>().ok())) else {
+        return error("EGeneral:Invalid arguments");
+    };
+    let price = param("price").and_then(|p| p.parse::<f64>().ok());
+    let order = match place_order(&state, pair, side, order_type, volume, price, param("cl_ord_id")) {
+        Ok(order) => order,
+        Err(message) => return error(message),
+    };
+
+    Json(json!({
+        "error": [],
+        "result": {
+            "descr": { "order": describe(&order) },
+            "txid": [order.exchange_order_id]
+        }
+    }))
+    .into_response()
+}
+
+/// Records an order under a new txid and publishes its executions. Shared by
+/// `AddOrder` and `EditOrder`.
+fn place_order(
+    state: &SimulatorState,
+    pair: &str,
+    side: OrderSide,
+    order_type: String,
+    volume: f64,
+    price: Option<f64>,
+    cl_ord_id: Option<&str>,
+) -> Result<SimulatedOrder, &'static str> {
+    if order_type == "limit" && price.is_none() {
+        return Err("EGeneral:Invalid arguments:price");
+    }
+    let Some(fill_price) = price.or_else(|| state.last_price(MarketDataSource::Kraken, pair)) else {
+        return Err("EOrder:Unknown position");
+    };
+
+    let id = state.next_id();
+    let order = SimulatedOrder {
+        venue: MarketDataSource::Kraken,
+        symbol: pair.to_string(),
+        side,
+        status: state.initial_status(order_type == "limit"),
+        order_type,
+        quantity: volume,
+        price,
+        client_order_id: cl_ord_id.map(str::to_string),
+        exchange_order_id: format!("OSIM-{:06}", id),
+        fill_price,
+        reduce_only: false,
+        position_side: None,
+    };
+    publish_executions(state, &order);
+    state.record_order(order.clone());
+    Ok(order)
+}
+
+fn side_name(side: OrderSide) -> &'static str {
+    match side {
+        OrderSide::Buy => "buy",
+        OrderSide::Sell => "sell",
+    }
+}
+
+fn describe(order: &SimulatedOrder) -> String {
+    format!("{} {} {} @ {}", side_name(order.side), order.quantity, order.symbol, order.order_type)
+}
+
+fn status_name(status: &OrderStatus) -> &'static str {
+    match status {
+        OrderStatus::New | OrderStatus::PartiallyFilled => "open",
+        OrderStatus::Filled => "closed",
+        OrderStatus::Canceled | OrderStatus::Rejected | OrderStatus::Failed => "canceled",
+        OrderStatus::Expired => "expired",
+    }
+}
+
+/// The order as `QueryOrders` and `OpenOrders` describe it.
+fn order_info(order: &SimulatedOrder) -> Value {
+    let filled = order.filled_quantity();
+    let now = Utc::now().timestamp_micros() as f64 / 1_000_000.0;
+    let mut info = json!({
+        "refid": null,
+        "userref": 0,
+        "status": status_name(&order.status),
+        "opentm": now,
+        "starttm": 0,
+        "expiretm": 0,
+        "descr": {
+            "pair": order.symbol,
+            "type": side_name(order.side),
+            "ordertype": order.order_type,
+            "price": order.price.unwrap_or(0.0).to_string(),
+            "price2": "0",
+            "leverage": "none",
+            "order": describe(order),
+            "close": ""
+        },
+        "vol": order.quantity.to_string(),
+        "vol_exec": filled.to_string(),
+        "cost": (filled * order.fill_price).to_string(),
+        "fee": (filled * order.fill_price * TAKER_FEE).to_string(),
+        "price": if filled > 0.0 { order.fill_price } else { 0.0 }.to_string(),
+        "misc": "",
+        "oflags": "fciq"
+    });
+    if let Some(cl_ord_id) = &order.client_order_id {
+        info["cl_ord_id"] = json!(cl_ord_id);
+    }
+    if order.status != OrderStatus::New {
+        info["closetm"] = json!(now);
+    }
+    info
+}
+
+/// The order `txid` or `cl_ord_id` names.
+fn order_reference(params: &HashMap<String, String>) -> Result<impl Fn(&SimulatedOrder) -> bool + '_, &'static str> {
+    let (txid, cl_ord_id) = (params.get("txid"), params.get("cl_ord_id"));
+    if txid.is_none() && cl_ord_id.is_none() {
+        return Err("EGeneral:Invalid arguments:txid");
+    }
+    Ok(move |order: &SimulatedOrder| match (txid, cl_ord_id) {
+        (Some(txid), _) => &order.exchange_order_id == txid,
+        (None, cl_ord_id) => order.client_order_id.as_ref() == cl_ord_id,
+    })
+}
+
+/// Withdraws a resting order and tells the private feed.
+fn cancel(state: &SimulatorState, matches: impl Fn(&SimulatedOrder) -> bool) -> Result<SimulatedOrder, &'static str> {
+    match state.cancel_order(MarketDataSource::Kraken, matches) {
+        Some(Ok(order)) => {
+            publish_open_order(state, &order.exchange_order_id, json!({ "status": "canceled", "reason": "User requested" }));
+            Ok(order)
+        }
+        Some(Err(_)) | None => Err("EOrder:Unknown order"),
+    }
+}
+
+async fn cancel_order(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, body: String) -> Response {
+    let params = match authenticate(&state, "/0/private/CancelOrder", &headers, &body) {
+        Ok(params) => params,
+        Err(message) => return error(message),
+    };
+    match order_reference(&params).and_then(|matches| cancel(&state, matches)) {
+        Ok(_) => Json(json!({ "error": [], "result": { "count": 1, "pending": false } })).into_response(),
+        Err(message) => error(message),
+    }
+}
+
+async fn cancel_all(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, body: String) -> Response {
+    if let Err(message) = authenticate(&state, "/0/private/CancelAll", &headers, &body) {
+        return error(message);
+    }
+    let mut count = 0;
+    for open in state.open_orders(MarketDataSource::Kraken) {
+        if cancel(&state, |order| order.exchange_order_id == open.exchange_order_id).is_ok() {
+            count += 1;
+        }
+    }
+    Json(json!({ "error": [], "result": { "count": count } })).into_response()
+}
+
+/// Orders by comma-separated `txid`; any unknown txid fails the call.
+async fn query_orders(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, body: String) -> Response {
+    let params = match authenticate(&state, "/0/private/QueryOrders", &headers, &body) {
+        Ok(params) => params,
+        Err(message) => return error(message),
+    };
+    let Some(txids) = params.get("txid") else {
+        return error("EGeneral:Invalid arguments:txid");
+    };
+    let mut result = serde_json::Map::new();
+    for txid in txids.split(',') {
+        let Some(order) = state.find_order(MarketDataSource::Kraken, |order| order.exchange_order_id == txid) else {
+            return error("EOrder:Invalid order");
+        };
+        result.insert(txid.to_string(), order_info(&order));
+    }
+    Json(json!({ "error": [], "result": result })).into_response()
+}
+
+async fn open_orders(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, body: String) -> Response {
+    let params = match authenticate(&state, "/0/private/OpenOrders", &headers, &body) {
+        Ok(params) => params,
+        Err(message) => return error(message),
+    };
+    let open: serde_json::Map<String, Value> = state
+        .open_orders(MarketDataSource::Kraken)
+        .iter()
+        .filter(|order| params.get("cl_ord_id").is_none_or(|id| order.client_order_id.as_ref() == Some(id)))
+        .map(|order| (order.exchange_order_id.clone(), order_info(order)))
+        .collect();
+    Json(json!({ "error": [], "result": { "open": open } })).into_response()
+}
+
+/// Replaces a resting order with one of the same side and type under a new
+/// txid, carrying over whatever `volume` and `price` leave unchanged.
+async fn edit_order(State(state): State<Arc<SimulatorState>>, headers: HeaderMap, body: String) -> Response {
+    let params = match authenticate(&state, "/0/private/EditOrder", &headers, &body) {
+        Ok(params) => params,
+        Err(message) => return error(message),
+    };
+    let (Some(txid), Some(pair)) = (params.get("txid"), params.get("pair")) else {
+        return error("EGeneral:Invalid arguments");
+    };
+    let original = match cancel(&state, |order| &order.exchange_order_id == txid && order.symbol.eq_ignore_ascii_case(pair)) {
+        Ok(original) => original,
+        Err(message) => return error(message),
+    };
+    let volume = params.get("volume").and_then(|v| v.parse::<f64>().ok()).unwrap_or(original.quantity);
+    let price = params.get("price").and_then(|p| p.parse::<f64>().ok()).or(original.price);
+    let replacement = match place_order(&state, &original.symbol, original.side, original.order_type.clone(), volume, price, None) {
+        Ok(order) => order,
+        Err(message) => return error(message),
+    };
+
+    Json(json!({
+        "error": [],
+        "result": {
+            "status": "ok",
+            "txid": replacement.exchange_order_id,
+            "originaltxid": original.exchange_order_id,
+            "volume": volume.to_string(),
+            "price": price.unwrap_or(0.0).to_string(),
+            "orders_cancelled": 1,
+            "descr": { "order": describe(&replacement) }
+        }
+    }))
+    .into_response()
+}
+
+fn publish_open_order(state: &SimulatorState, txid: &str, update: Value) {
+    let message = json!([[{ txid: update }], "openOrders", { "sequence": state.next_id() }]);
+    state.publish(OPEN_ORDERS_CHANNEL.to_string(), message.to_string());
+}
+
+/// A `pending` openOrders entry, then `open` if the order rests, or the
+/// trade and `closed`.
+fn publish_executions(state: &SimulatorState, order: &SimulatedOrder) {
+    let txid = &order.exchange_order_id;
+    publish_open_order(
+        state,
+        txid,
+        json!({
+            "status": "pending",
+            "vol": order.quantity.to_string(),
+            "vol_exec": "0.00000000",
+            "cl_ord_id": order.client_order_id
+        }),
+    );
+    if order.status != OrderStatus::Filled {
+        publish_open_order(state, txid, json!({ "status": "open" }));
+        return;
+    }
+
+    let cost = order.quantity * order.fill_price;
+    let trade_id = format!("TSIM-{:06}", state.next_id());
+    let trade = json!({
+        "ordertxid": txid,
+        "postxid": txid,
+        "pair": order.symbol,
+        "time": format!("{:.6}", Utc::now().timestamp_micros() as f64 / 1_000_000.0),
+        "type": side_name(order.side),
+        "ordertype": order.order_type,
+        "price": order.fill_price.to_string(),
+        "cost": cost.to_string(),
+        "fee": (cost * TAKER_FEE).to_string(),
+        "vol": order.quantity.to_string(),
+        "margin": "0.00000"
+    });
+    state.kraken_own_trades.lock().unwrap().push((trade_id.clone(), trade.clone()));
+    state.publish(
+        OWN_TRADES_CHANNEL.to_string(),
+        json!([[{ trade_id.as_str(): trade }], "ownTrades", { "sequence": state.next_id() }]).to_string(),
+    );
+
+    publish_open_order(state, txid, json!({ "status": "closed", "vol_exec": order.quantity.to_string() }));
+}
@@ -0,0 +1,466 @@
+//! A local stand-in for the Binance and Kraken APIs.
+//!
+//! One axum server on `127.0.0.1` speaks both venues' wire formats: Binance
+//! combined streams and their live (un)subscribe commands, depth snapshots,
+//! trade history, listen keys and signed order entry; Binance USD-M futures
+//! aggregate trade and mark price streams and order entry; and Kraken's v1
+//! trade and spread feeds, v2 book feed, authenticated feed, public trade
+//! history and signed private REST calls. Tests drive the market through [`SimulatorHandle`] and point the real
+//! connectors at it with `with_endpoints`.
+//!
+//! Orders are checked the way the venues check them (API key, signature,
+//! parameter spelling) and fill in full straight away, at the limit price or
+//! the last traded price, with the matching private feed events. With
+//! [`ExchangeSimulator::with_resting_limit_orders`] spot limit orders rest
+//! instead, so they can be queried, amended and canceled. Futures orders also
+//! move a simulated position, which reduce-only orders may shrink but not
+//! grow or flip.
+//!
+//! Only built for tests or with the `simulator` feature.
+
+pub mod binance;
+pub mod binance_futures;
+pub mod kraken;
+
+use crate::connectors::binance::BinanceEndpoints;
+use crate::connectors::kraken::KrakenEndpoints;
+use crate::models::{MarketDataSource, OrderBook, OrderSide, OrderStatus, PositionSide};
+use anyhow::Result;
+use axum::extract::ws::{Message, WebSocket};
+use axum::Router;
+use base64::{engine::general_purpose, Engine as _};
+use chrono::{DateTime, Utc};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{broadcast, watch};
+use tokio::task::JoinHandle;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Credentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+impl Credentials {
+    pub fn new(api_key: &str, api_secret: &str) -> Self {
+        Self {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        }
+    }
+}
+
+/// An order the simulator accepted.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SimulatedOrder {
+    pub venue: MarketDataSource,
+    /// The pair exactly as the client sent it.
+    pub symbol: String,
+    pub side: OrderSide,
+    pub order_type: String,
+    pub quantity: f64,
+    pub price: Option<f64>,
+    pub client_order_id: Option<String>,
+    pub exchange_order_id: String,
+    pub fill_price: f64,
+    /// Futures only.
+    pub reduce_only: bool,
+    pub position_side: Option<PositionSide>,
+    /// `New` while resting, then `Filled` or `Canceled`.
+    pub status: OrderStatus,
+}
+
+impl SimulatedOrder {
+    pub fn filled_quantity(&self) -> f64 {
+        if self.status == OrderStatus::Filled {
+            self.quantity
+        } else {
+            0.0
+        }
+    }
+}
+
+/// A trade printed on a venue, whether or not its feed delivered it.
+#[derive(Debug, Clone)]
+struct RecordedTrade {
+    venue: MarketDataSource,
+    /// The pair under [`price_key`].
+    key: String,
+    /// Consecutive per pair, as Binance numbers trades.
+    id: u64,
+    price: f64,
+    quantity: f64,
+    time: DateTime<Utc>,
+}
+
+/// A message for every client subscribed to `channel`.
+#[derive(Debug, Clone)]
+struct Published {
+    channel: String,
+    text: String,
+}
+
+pub(crate) struct SimulatorState {
+    binance: Credentials,
+    kraken: Credentials,
+    /// Price and quantity precision per Kraken v2 symbol, for checksums.
+    kraken_pairs: HashMap<String, (usize, usize)>,
+    kraken_token: String,
+    feed: broadcast::Sender<Published>,
+    disconnect: watch::Sender<u64>,
+    /// Channel subscriptions made so far, across all connections.
+    subscriptions: watch::Sender<usize>,
+    orders: Mutex<Vec<SimulatedOrder>>,
+    /// Every trade printed, oldest first.
+    trades: Mutex<Vec<RecordedTrade>>,
+    /// Our Kraken executions by trade id, oldest first, for ownTrades snapshots.
+    kraken_own_trades: Mutex<Vec<(String, serde_json::Value)>>,
+    books: Mutex<HashMap<(MarketDataSource, String), OrderBook>>,
+    listen_keys: Mutex<HashSet<String>>,
+    /// Signed futures position per symbol and position side.
+    futures_positions: Mutex<HashMap<(String, PositionSide), f64>>,
+    resting_limit_orders: bool,
+    next_id: AtomicU64,
+}
+
+impl SimulatorState {
+    fn publish(&self, channel: String, text: String) {
+        let _ = self.feed.send(Published { channel, text });
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    fn record_order(&self, order: SimulatedOrder) {
+        self.orders.lock().unwrap().push(order);
+    }
+
+    /// The status a new spot order starts with.
+    fn initial_status(&self, limit: bool) -> OrderStatus {
+        if limit && self.resting_limit_orders {
+            OrderStatus::New
+        } else {
+            OrderStatus::Filled
+        }
+    }
+
+    fn find_order(&self, venue: MarketDataSource, matches: impl Fn(&SimulatedOrder) -> bool) -> Option<SimulatedOrder> {
+        let orders = self.orders.lock().unwrap();
+        orders.iter().find(|order| order.venue == venue && matches(order)).cloned()
+    }
+
+    /// Cancels the first matching order and returns it, or the matching
+    /// order unchanged if it no longer rests.
+    fn cancel_order(
+        &self,
+        venue: MarketDataSource,
+        matches: impl Fn(&SimulatedOrder) -> bool,
+    ) -> Option<Result<SimulatedOrder, SimulatedOrder>> {
+        let mut orders = self.orders.lock().unwrap();
+        let order = orders.iter_mut().find(|order| order.venue == venue && matches(order))?;
+        if order.status != OrderStatus::New {
+            return Some(Err(order.clone()));
+        }
+        order.status = OrderStatus::Canceled;
+        Some(Ok(order.clone()))
+    }
+
+    fn open_orders(&self, venue: MarketDataSource) -> Vec<SimulatedOrder> {
+        let orders = self.orders.lock().unwrap();
+        orders
+            .iter()
+            .filter(|order| order.venue == venue && order.status == OrderStatus::New)
+            .cloned()
+            .collect()
+    }
+
+    /// Price a market order executes at: the last trade on its pair.
+    fn last_price(&self, venue: MarketDataSource, symbol: &str) -> Option<f64> {
+        self.trades_on(venue, symbol).last().map(|trade| trade.price)
+    }
+
+    fn trades_on(&self, venue: MarketDataSource, symbol: &str) -> Vec<RecordedTrade> {
+        let key = price_key(symbol);
+        let trades = self.trades.lock().unwrap();
+        trades.iter().filter(|t| t.venue == venue && t.key == key).cloned().collect()
+    }
+
+    /// Records a trade with the next id on its pair and a timestamp later
+    /// than any before it, so trades are never ambiguous in time.
+    fn record_trade(&self, venue: MarketDataSource, symbol: &str, price: f64, quantity: f64) -> RecordedTrade {
+        let key = price_key(symbol);
+        let mut trades = self.trades.lock().unwrap();
+        let previous = trades.iter().rev().find(|t| t.venue == venue && t.key == key);
+        let mut time = Utc::now();
+        if let Some(previous) = previous {
+            time = time.max(previous.time + chrono::Duration::microseconds(1));
+        }
+        let trade = RecordedTrade {
+            venue,
+            id: previous.map_or(1, |t| t.id + 1),
+            key,
+            price,
+            quantity,
+            time,
+        };
+        trades.push(trade.clone());
+        trade
+    }
+}
+
+/// `XBT/USD`, `XBTUSD` and `xbtusd` all name the same pair.
+fn price_key(symbol: &str) -> String {
+    symbol.replace('/', "").to_uppercase()
+}
+
+/// Configures and starts the simulator.
+pub struct ExchangeSimulator {
+    binance: Credentials,
+    kraken: Credentials,
+    kraken_pairs: HashMap<String, (usize, usize)>,
+    resting_limit_orders: bool,
+}
+
+impl Default for ExchangeSimulator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ExchangeSimulator {
+    pub fn new() -> Self {
+        Self {
+            binance: Credentials::new("binance-sim-key", "binance-sim-secret"),
+            kraken: Credentials::new("kraken-sim-key", &general_purpose::STANDARD.encode("kraken-sim-secret")),
+            kraken_pairs: HashMap::from([
+                ("BTC/USD".to_string(), (1, 8)),
+                ("ETH/USD".to_string(), (2, 8)),
+            ]),
+            resting_limit_orders: false,
+        }
+    }
+
+    pub fn with_binance_credentials(mut self, credentials: Credentials) -> Self {
+        self.binance = credentials;
+        self
+    }
+
+    /// Kraken secrets are base64, as issued by Kraken.
+    pub fn with_kraken_credentials(mut self, credentials: Credentials) -> Self {
+        self.kraken = credentials;
+        self
+    }
+
+    /// Lists a pair on the Kraken v2 instrument channel.
+    pub fn with_kraken_pair(mut self, symbol: &str, price_precision: usize, qty_precision: usize) -> Self {
+        self.kraken_pairs.insert(symbol.to_string(), (price_precision, qty_precision));
+        self
+    }
+
+    /// Leaves Binance and Kraken limit orders resting on the book until they
+    /// are canceled, rather than filling them.
+    pub fn with_resting_limit_orders(mut self) -> Self {
+        self.resting_limit_orders = true;
+        self
+    }
+
+    /// Binds an ephemeral port on localhost and serves until the handle is dropped.
+    pub async fn start(self) -> Result<SimulatorHandle> {
+        let (feed, _) = broadcast::channel(1024);
+        let state = Arc::new(SimulatorState {
+            binance: self.binance,
+            kraken: self.kraken,
+            kraken_pairs: self.kraken_pairs,
+            kraken_token: "kraken-sim-token".to_string(),
+            feed,
+            disconnect: watch::channel(0).0,
+            subscriptions: watch::channel(0).0,
+            orders: Mutex::new(Vec::new()),
+            trades: Mutex::new(Vec::new()),
+            kraken_own_trades: Mutex::new(Vec::new()),
+            books: Mutex::new(HashMap::new()),
+            listen_keys: Mutex::new(HashSet::new()),
+            futures_positions: Mutex::new(HashMap::new()),
+            resting_limit_orders: self.resting_limit_orders,
+            next_id: AtomicU64::new(1),
+        });
+
+        let app = Router::new()
+            .merge(binance::router())
+            .nest("/binance-futures", binance_futures::router())
+            .nest("/kraken", kraken::router())
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            if let Err(e) = axum::serve(listener, app).await {
+                tracing::error!("Exchange simulator stopped: {}", e);
+            }
+        });
+
+        Ok(SimulatorHandle { addr, state, server })
+    }
+}
+
+/// A running simulator. Publishing methods take the pair as the venue's feed
+/// names it, e.g. `BTCUSDT` for Binance, `XBT/USD` for Kraken v1 trades and
+/// spreads and `BTC/USD` for Kraken v2 books.
+pub struct SimulatorHandle {
+    addr: SocketAddr,
+    state: Arc<SimulatorState>,
+    server: JoinHandle<()>,
+}
+
+impl SimulatorHandle {
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    pub fn binance_endpoints(&self) -> BinanceEndpoints {
+        BinanceEndpoints {
+            api_url: format!("http://{}", self.addr),
+            ws_url: format!("ws://{}", self.addr),
+        }
+    }
+
+    pub fn binance_futures_endpoints(&self) -> BinanceEndpoints {
+        BinanceEndpoints {
+            api_url: format!("http://{}/binance-futures", self.addr),
+            ws_url: format!("ws://{}/binance-futures", self.addr),
+        }
+    }
+
+    /// Spot and futures accept the same key, as on Binance.
+    pub fn binance_credentials(&self) -> Credentials {
+        self.state.binance.clone()
+    }
+
+    pub fn kraken_endpoints(&self) -> KrakenEndpoints {
+        KrakenEndpoints {
+            api_url: format!("http://{}/kraken", self.addr),
+            ws_url: format!("ws://{}/kraken/ws", self.addr),
+            ws_v2_url: format!("ws://{}/kraken/ws/v2", self.addr),
+            ws_auth_url: format!("ws://{}/kraken/ws-auth", self.addr),
+        }
+    }
+
+    pub fn kraken_credentials(&self) -> Credentials {
+        self.state.kraken.clone()
+    }
+
+    /// Prints a trade on the venue's public trade feed.
+    pub fn publish_trade(&self, venue: MarketDataSource, symbol: &str, price: f64, quantity: f64) {
+        let trade = self.state.record_trade(venue, symbol, price, quantity);
+        match venue {
+            MarketDataSource::Binance => binance::publish_trade(&self.state, symbol, &trade),
+            MarketDataSource::BinanceFutures => binance_futures::publish_agg_trade(&self.state, symbol, &trade),
+            MarketDataSource::Kraken => kraken::publish_trade(&self.state, symbol, &trade),
+            other => panic!("the simulator does not serve {}", other),
+        }
+    }
+
+    /// Prints a trade that only the venue's REST history shows, as if the
+    /// feed message carrying it were lost.
+    pub fn publish_trade_unseen(&self, venue: MarketDataSource, symbol: &str, price: f64, quantity: f64) {
+        self.state.record_trade(venue, symbol, price, quantity);
+    }
+
+    /// Publishes a best bid/offer update: a `bookTicker` event (Binance) or a
+    /// v1 `spread` message (Kraken).
+    pub fn publish_quote(&self, venue: MarketDataSource, symbol: &str, bid: (f64, f64), ask: (f64, f64)) {
+        match venue {
+            MarketDataSource::Binance => binance::publish_book_ticker(&self.state, symbol, bid.0, bid.1, ask.0, ask.1),
+            MarketDataSource::Kraken => kraken::publish_spread(&self.state, symbol, bid.0, bid.1, ask.0, ask.1),
+            other => panic!("the simulator does not serve {}", other),
+        }
+    }
+
+    /// Publishes a Binance futures `markPriceUpdate`.
+    pub fn publish_mark_price(&self, symbol: &str, mark_price: f64, index_price: f64, funding_rate: f64, next_funding_time: DateTime<Utc>) {
+        binance_futures::publish_mark_price(&self.state, symbol, mark_price, index_price, funding_rate, next_funding_time);
+    }
+
+    /// The simulated futures position on `symbol`, positive when long.
+    pub fn futures_position(&self, symbol: &str, position_side: PositionSide) -> f64 {
+        let positions = self.state.futures_positions.lock().unwrap();
+        positions.get(&(symbol.to_uppercase(), position_side)).copied().unwrap_or_default()
+    }
+
+    /// Applies level changes to the venue's book and publishes them as a
+    /// depth diff (Binance) or a checksummed book update (Kraken v2). A zero
+    /// quantity removes the level.
+    pub fn publish_book(&self, venue: MarketDataSource, symbol: &str, bids: &[(f64, f64)], asks: &[(f64, f64)]) {
+        match venue {
+            MarketDataSource::Binance => binance::publish_depth(&self.state, symbol, bids, asks),
+            MarketDataSource::Kraken => kraken::publish_book(&self.state, symbol, bids, asks),
+            other => panic!("the simulator does not serve {}", other),
+        }
+    }
+
+    pub fn orders(&self) -> Vec<SimulatedOrder> {
+        self.state.orders.lock().unwrap().clone()
+    }
+
+    /// Drops every open WebSocket, as a venue does during maintenance.
+    pub fn disconnect_all(&self) {
+        self.state.disconnect.send_modify(|generation| *generation += 1);
+    }
+
+    /// Waits until clients have made `count` channel subscriptions in total,
+    /// counting resubscriptions after a reconnect.
+    pub async fn wait_for_subscriptions(&self, count: usize) {
+        let mut subscriptions = self.state.subscriptions.subscribe();
+        let _ = subscriptions.wait_for(|made| *made >= count).await;
+    }
+}
+
+impl Drop for SimulatorHandle {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Drives one client connection. Published messages on `channels` are
+/// forwarded; `on_text` answers client requests and may change the
+/// subscriptions. The socket is dropped on [`SimulatorHandle::disconnect_all`].
+async fn run_socket<F>(socket: WebSocket, state: Arc<SimulatorState>, mut channels: HashSet<String>, mut on_text: F)
+where
+    F: FnMut(&str, &mut HashSet<String>) -> Vec<String> + Send,
+{
+    let mut feed = state.feed.subscribe();
+    let mut disconnect = state.disconnect.subscribe();
+    state.subscriptions.send_modify(|made| *made += channels.len());
+    let (mut write, mut read) = socket.split();
+
+    loop {
+        let replies = tokio::select! {
+            published = feed.recv() => match published {
+                Ok(published) if channels.contains(&published.channel) => vec![published.text],
+                Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+            message = read.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    let before = channels.len();
+                    let replies = on_text(&text, &mut channels);
+                    if channels.len() > before {
+                        state.subscriptions.send_modify(|made| *made += channels.len() - before);
+                    }
+                    replies
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(_)) | None => return,
+            },
+            _ = disconnect.changed() => return,
+        };
+        for reply in replies {
+            if write.send(Message::Text(reply)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
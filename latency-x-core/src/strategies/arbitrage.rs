@@ -1,13 +1,15 @@
-use crate::execution::ExecutionGateway;
-use crate::models::{MarketDataSource, Order, OrderSide, Tick, OrderType, OrderStatus};
-use crate::strategies::Strategy;
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::models::{FeedState, FeedStatus, FeedStream, MarketDataSource, Order, OrderReport, OrderSide, Quote, Tick, OrderType, OrderStatus};
+use crate::strategies::{sent_order_id, Strategy};
 use anyhow::Result;
 use async_trait::async_trait;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tracing::info;
+use uuid::Uuid;
+use chrono::Utc;
 use metrics::{counter, gauge};
-use crate::persistence::db::DatabaseManager;
+use crate::instruments::InstrumentRegistry;
 
 pub struct Arbitrage<E1, E2>
 where
@@ -18,9 +20,16 @@ where
     exchange2: Arc<E2>,
     last_tick1: Arc<Mutex<Option<Tick>>>,
     last_tick2: Arc<Mutex<Option<Tick>>>,
+    last_quote1: Arc<Mutex<Option<Quote>>>,
+    last_quote2: Arc<Mutex<Option<Quote>>>,
+    /// The legs of the last cross on each venue, until they are done.
+    in_flight1: Arc<Mutex<Option<Uuid>>>,
+    in_flight2: Arc<Mutex<Option<Uuid>>>,
+    /// Canonical symbol traded on both venues.
+    symbol: String,
     min_spread: f64,
     quantity: f64,
-    db_manager: Arc<DatabaseManager>,
+    instruments: Arc<InstrumentRegistry>,
 }
 
 impl<E1, E2> Arbitrage<E1, E2>
@@ -28,15 +37,120 @@ where
     E1: ExecutionGateway + Send + Sync + ?Sized + 'static,
     E2: ExecutionGateway + Send + Sync + ?Sized + 'static,
 {
-    pub fn new(exchange1: Arc<E1>, exchange2: Arc<E2>, min_spread: f64, quantity: f64, db_manager: Arc<DatabaseManager>) -> Self {
+    pub fn new(exchange1: Arc<E1>, exchange2: Arc<E2>, symbol: String, min_spread: f64, quantity: f64) -> Self {
         Self {
             exchange1,
             exchange2,
             last_tick1: Arc::new(Mutex::new(None)),
             last_tick2: Arc::new(Mutex::new(None)),
+            last_quote1: Arc::new(Mutex::new(None)),
+            last_quote2: Arc::new(Mutex::new(None)),
+            in_flight1: Arc::new(Mutex::new(None)),
+            in_flight2: Arc::new(Mutex::new(None)),
+            symbol,
             min_spread,
             quantity,
-            db_manager,
+            instruments: Arc::new(InstrumentRegistry::new()),
+        }
+    }
+
+    pub fn with_instruments(mut self, instruments: Arc<InstrumentRegistry>) -> Self {
+        self.instruments = instruments;
+        self
+    }
+
+    /// Rounds the configured size down to a lot both venues accept, or gives
+    /// `None` when that falls below either venue's minimum notional.
+    fn order_quantity(&self, price1: f64, price2: f64) -> Option<f64> {
+        let venues = [(MarketDataSource::Binance, price1), (MarketDataSource::Kraken, price2)];
+        let mut quantity = self.quantity;
+        for (venue, _) in venues {
+            if let Some(instrument) = self.instruments.get(venue, &self.symbol) {
+                quantity = instrument.round_quantity(quantity);
+            }
+        }
+        for (venue, price) in venues {
+            if let Some(instrument) = self.instruments.get(venue, &self.symbol) {
+                if !instrument.meets_min_notional(price, quantity) {
+                    return None;
+                }
+            }
+        }
+        (quantity > 0.0).then_some(quantity)
+    }
+
+    /// Whether every leg of the last cross has stopped working. Venues that
+    /// cannot say are taken to be done.
+    async fn settled(&self) -> bool {
+        let mut in_flight1 = self.in_flight1.lock().await;
+        let mut in_flight2 = self.in_flight2.lock().await;
+        if let Some(order_id) = *in_flight1 {
+            if leg_working(self.exchange1.query_order(&self.symbol, order_id).await) {
+                return false;
+            }
+            *in_flight1 = None;
+        }
+        if let Some(order_id) = *in_flight2 {
+            if leg_working(self.exchange2.query_order(&self.symbol, order_id).await) {
+                return false;
+            }
+            *in_flight2 = None;
+        }
+        true
+    }
+
+    /// Buys on one venue and sells the same size on the other: buys on
+    /// exchange1 when `buy_first`, on exchange2 otherwise. Nothing is sent
+    /// while the previous cross is still working.
+    async fn cross(&self, buy_first: bool, quantity: f64, trigger: Option<&Tick>) -> Result<()> {
+        if !self.settled().await {
+            info!("[ARBITRAGE] Previous cross still working, not crossing again");
+            return Ok(());
+        }
+        let order = |side| Order {
+            id: Uuid::new_v4(),
+            symbol: self.symbol.clone(),
+            side,
+            order_type: OrderType::Market,
+            amount: quantity,
+            price: None,
+            status: OrderStatus::New,
+            source: MarketDataSource::Strategy,
+            created_at: Utc::now(),
+            triggering_tick: trigger.map(|tick| Box::new(tick.clone())),
+            reduce_only: false,
+            position_side: None,
+        };
+        let (order1, order2) = if buy_first {
+            (order(OrderSide::Buy), order(OrderSide::Sell))
+        } else {
+            (order(OrderSide::Sell), order(OrderSide::Buy))
+        };
+        let (id1, id2) = (order1.id, order2.id);
+
+        // The buy leg goes first.
+        if buy_first {
+            let sent = self.exchange1.send_order(order1).await?;
+            *self.in_flight1.lock().await = Some(sent_order_id(&sent, id1));
+            let sent = self.exchange2.send_order(order2).await?;
+            *self.in_flight2.lock().await = Some(sent_order_id(&sent, id2));
+        } else {
+            let sent = self.exchange2.send_order(order2).await?;
+            *self.in_flight2.lock().await = Some(sent_order_id(&sent, id2));
+            let sent = self.exchange1.send_order(order1).await?;
+            *self.in_flight1.lock().await = Some(sent_order_id(&sent, id1));
+        }
+        Ok(())
+    }
+}
+
+fn leg_working(report: Result<OrderReport, ExecutionError>) -> bool {
+    match report {
+        Ok(report) => report.status.is_open(),
+        Err(ExecutionError::UnknownOrder(_) | ExecutionError::Unsupported(_)) => false,
+        Err(e) => {
+            info!("[ARBITRAGE] Could not check the last cross: {}", e);
+            true
         }
     }
 }
@@ -48,6 +162,13 @@ where
     E2: ExecutionGateway + Send + Sync + ?Sized + 'static,
 {
     async fn on_tick(&mut self, tick: &Tick) -> Result<()> {
+        if tick.symbol != self.symbol {
+            return Ok(());
+        }
+        // Trade prices are only a stand-in for venues that are not quoting.
+        if self.last_quote1.lock().await.is_some() && self.last_quote2.lock().await.is_some() {
+            return Ok(());
+        }
         let mut last_tick1_guard = self.last_tick1.lock().await;
         let mut last_tick2_guard = self.last_tick2.lock().await;
 
@@ -62,79 +183,86 @@ where
             gauge!("arbitrage_spread", "symbol" => tick1.symbol.clone()).set(spread);
 
             if spread.abs() > self.min_spread {
+                let Some(quantity) = self.order_quantity(tick1.price, tick2.price) else {
+                    info!("[ARBITRAGE] Spread {} found but size is below the venues' minimums", spread);
+                    return Ok(());
+                };
                 info!("[ARBITRAGE] Found opportunity! Spread: {}", spread);
                 counter!("arbitrage_opportunities", "symbol" => tick1.symbol.clone()).increment(1);
 
-                if spread > 0.0 {
-                    // Buy on exchange1, sell on exchange2
-                    let buy_order = Order {
-                        id: Default::default(),
-                        symbol: tick1.symbol.clone(),
-                        side: OrderSide::Buy,
-                        order_type: OrderType::Market,
-                        amount: self.quantity,
-                        price: None,
-                        status: OrderStatus::New,
-                        source: MarketDataSource::Strategy,
-                        created_at: Default::default(),
-                        triggering_tick: Some(Box::new(tick.clone())),
-                    };
-
-                    let sell_order = Order {
-                        id: Default::default(),
-                        symbol: tick2.symbol.clone(),
-                        side: OrderSide::Sell,
-                        order_type: OrderType::Market,
-                        amount: self.quantity,
-                        price: None,
-                        status: OrderStatus::New,
-                        source: MarketDataSource::Strategy,
-                        created_at: Default::default(),
-                        triggering_tick: Some(Box::new(tick.clone())),
-                    };
-
-                    self.db_manager.save_order(&buy_order).await?;
-                    self.db_manager.save_order(&sell_order).await?;
-                    self.exchange1.send_order(buy_order).await.map_err(|e| anyhow::anyhow!(e))?;
-                    self.exchange2.send_order(sell_order).await.map_err(|e| anyhow::anyhow!(e))?;
-
-                } else {
-                    // Buy on exchange2, sell on exchange1
-                    let buy_order = Order {
-                        id: Default::default(),
-                        symbol: tick2.symbol.clone(),
-                        side: OrderSide::Buy,
-                        order_type: OrderType::Market,
-                        amount: self.quantity,
-                        price: None,
-                        status: OrderStatus::New,
-                        source: MarketDataSource::Strategy,
-                        created_at: Default::default(),
-                        triggering_tick: Some(Box::new(tick.clone())),
-                    };
-
-                    let sell_order = Order {
-                        id: Default::default(),
-                        symbol: tick1.symbol.clone(),
-                        side: OrderSide::Sell,
-                        order_type: OrderType::Market,
-                        amount: self.quantity,
-                        price: None,
-                        status: OrderStatus::New,
-                        source: MarketDataSource::Strategy,
-                        created_at: Default::default(),
-                        triggering_tick: Some(Box::new(tick.clone())),
-                    };
-                    self.db_manager.save_order(&buy_order).await?;
-                    self.db_manager.save_order(&sell_order).await?;
-                    self.exchange2.send_order(buy_order).await.map_err(|e| anyhow::anyhow!(e))?;
-                    self.exchange1.send_order(sell_order).await.map_err(|e| anyhow::anyhow!(e))?;
-                }
+                self.cross(spread > 0.0, quantity, Some(tick)).await?;
             }
         }
 
         Ok(())
     }
+
+    /// Compares executable prices: buying at one venue's ask against selling
+    /// at the other's bid.
+    async fn on_quote(&mut self, quote: &Quote) -> Result<()> {
+        if quote.symbol != self.symbol {
+            return Ok(());
+        }
+        let mut last_quote1_guard = self.last_quote1.lock().await;
+        let mut last_quote2_guard = self.last_quote2.lock().await;
+
+        match quote.source {
+            MarketDataSource::Binance => *last_quote1_guard = Some(quote.clone()),
+            MarketDataSource::Kraken => *last_quote2_guard = Some(quote.clone()),
+            _ => (),
+        }
+
+        let (Some(quote1), Some(quote2)) = (&*last_quote1_guard, &*last_quote2_guard) else {
+            return Ok(());
+        };
+        // Buy on exchange1 and sell on exchange2, or the other way round.
+        let edge1 = quote2.bid - quote1.ask;
+        let edge2 = quote1.bid - quote2.ask;
+        let (buy_first, edge) = if edge1 >= edge2 { (true, edge1) } else { (false, edge2) };
+        gauge!("arbitrage_spread", "symbol" => self.symbol.clone()).set(quote2.mid() - quote1.mid());
+
+        if edge > self.min_spread {
+            let (buy_price, sell_price) = if buy_first { (quote1.ask, quote2.bid) } else { (quote2.ask, quote1.bid) };
+            let (price1, price2) = if buy_first { (buy_price, sell_price) } else { (sell_price, buy_price) };
+            // Never take more than the quoted size on either side.
+            let available = if buy_first {
+                quote1.ask_size.min(quote2.bid_size)
+            } else {
+                quote2.ask_size.min(quote1.bid_size)
+            };
+            let Some(quantity) = self.order_quantity(price1, price2) else {
+                info!("[ARBITRAGE] Edge {} found but size is below the venues' minimums", edge);
+                return Ok(());
+            };
+            if quantity > available {
+                info!("[ARBITRAGE] Edge {} found but only {} is quoted", edge, available);
+                return Ok(());
+            }
+            info!("[ARBITRAGE] Found opportunity! Buy {} / sell {}, edge: {}", buy_price, sell_price, edge);
+            counter!("arbitrage_opportunities", "symbol" => self.symbol.clone()).increment(1);
+            self.cross(buy_first, quantity, None).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn on_feed_status(&mut self, status: &FeedStatus) -> Result<()> {
+        if status.state == FeedState::Up || status.symbol.as_ref().is_some_and(|symbol| symbol != &self.symbol) {
+            return Ok(());
+        }
+        // A quiet stream's last price is not a price we can trade against.
+        let (last_tick, last_quote) = match status.source {
+            MarketDataSource::Binance => (&self.last_tick1, &self.last_quote1),
+            MarketDataSource::Kraken => (&self.last_tick2, &self.last_quote2),
+            _ => return Ok(()),
+        };
+        match status.stream {
+            FeedStream::Trades => *last_tick.lock().await = None,
+            FeedStream::Quotes => *last_quote.lock().await = None,
+            FeedStream::Book | FeedStream::MarkPrice => (),
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -142,34 +270,33 @@ mod tests {
     use super::*;
     use crate::execution::mock::MockExecutionGateway;
     use crate::models::{MarketDataSource, Tick};
-    use crate::persistence::db::DatabaseManager;
 
     #[tokio::test]
     async fn test_arbitrage_creates_orders() {
         // Arrange
         let mut mock_execution_gateway1 = MockExecutionGateway::new();
         let mut mock_execution_gateway2 = MockExecutionGateway::new();
-        let db_manager = Arc::new(DatabaseManager::new(":memory:").await.unwrap());
-        db_manager.init().await.unwrap();
 
         mock_execution_gateway1.expect_send_order()
-            .withf(|order| order.side == OrderSide::Buy && order.price == Some(50000.0))
+            .withf(|order| order.side == OrderSide::Buy && order.order_type == OrderType::Market)
             .returning(|_| Ok("order1".to_string()))
             .times(1);
         
         mock_execution_gateway2.expect_send_order()
-            .withf(|order| order.side == OrderSide::Sell && order.price == Some(50200.0))
+            .withf(|order| order.side == OrderSide::Sell && order.order_type == OrderType::Market)
             .returning(|_| Ok("order2".to_string()))
             .times(1);
 
-        let mut strategy = Arbitrage::new(Arc::new(mock_execution_gateway1), Arc::new(mock_execution_gateway2), 100.0, 1.0, db_manager);
+        let mut strategy = Arbitrage::new(Arc::new(mock_execution_gateway1), Arc::new(mock_execution_gateway2), "BTC/USD".to_string(), 100.0, 1.0);
 
         let tick1 = Tick {
             source: MarketDataSource::Binance,
             symbol: "BTC/USD".to_string(),
             price: 50000.0,
             volume: 1.0,
-            received_at: 0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
         };
 
         let tick2 = Tick {
@@ -177,7 +304,9 @@ mod tests {
             symbol: "BTC/USD".to_string(),
             price: 50200.0,
             volume: 1.0,
-            received_at: 0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
         };
 
         // Act
@@ -187,4 +316,135 @@ mod tests {
         // Assert
         // Mocks handle assertions
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_arbitrage_ignores_stale_venue() {
+        let mock_execution_gateway1 = MockExecutionGateway::new();
+        let mock_execution_gateway2 = MockExecutionGateway::new();
+
+        let mut strategy = Arbitrage::new(Arc::new(mock_execution_gateway1), Arc::new(mock_execution_gateway2), "BTC/USD".to_string(), 100.0, 1.0);
+
+        let tick1 = Tick {
+            source: MarketDataSource::Binance,
+            symbol: "BTC/USD".to_string(),
+            price: 50000.0,
+            volume: 1.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
+        };
+
+        let tick2 = Tick {
+            source: MarketDataSource::Kraken,
+            symbol: "BTC/USD".to_string(),
+            price: 50200.0,
+            volume: 1.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
+        };
+
+        let stale = FeedStatus {
+            source: MarketDataSource::Binance,
+            stream: FeedStream::Trades,
+            symbol: Some("BTC/USD".to_string()),
+            state: FeedState::Stale,
+            at: Utc::now(),
+        };
+
+        // The mocks have no expectations, so any order would panic.
+        strategy.on_tick(&tick1).await.unwrap();
+        strategy.on_feed_status(&stale).await.unwrap();
+        strategy.on_tick(&tick2).await.unwrap();
+    }
+
+    fn quote(source: MarketDataSource, bid: f64, ask: f64) -> Quote {
+        Quote {
+            source,
+            symbol: "BTC/USD".to_string(),
+            bid,
+            bid_size: 2.0,
+            ask,
+            ask_size: 2.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_arbitrage_trades_on_executable_prices() {
+        let mut mock_execution_gateway1 = MockExecutionGateway::new();
+        let mut mock_execution_gateway2 = MockExecutionGateway::new();
+
+        mock_execution_gateway1.expect_send_order()
+            .withf(|order| order.side == OrderSide::Sell)
+            .returning(|_| Ok("order1".to_string()))
+            .times(1);
+        mock_execution_gateway2.expect_send_order()
+            .withf(|order| order.side == OrderSide::Buy)
+            .returning(|_| Ok("order2".to_string()))
+            .times(1);
+
+        let mut strategy = Arbitrage::new(Arc::new(mock_execution_gateway1), Arc::new(mock_execution_gateway2), "BTC/USD".to_string(), 100.0, 1.0);
+
+        // Mids are 150 apart but the books overlap by only 50: no trade.
+        strategy.on_quote(&quote(MarketDataSource::Binance, 50100.0, 50200.0)).await.unwrap();
+        strategy.on_quote(&quote(MarketDataSource::Kraken, 49950.0, 50050.0)).await.unwrap();
+        // Kraken's ask now sits 150 below Binance's bid.
+        strategy.on_quote(&quote(MarketDataSource::Kraken, 49900.0, 49950.0)).await.unwrap();
+
+        // With both venues quoting, trade prints are ignored.
+        strategy.on_tick(&Tick {
+            source: MarketDataSource::Binance,
+            symbol: "BTC/USD".to_string(),
+            price: 60000.0,
+            volume: 1.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
+        }).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_arbitrage_waits_for_the_last_cross_to_fill() {
+        let mut mock_execution_gateway1 = MockExecutionGateway::new();
+        let mut mock_execution_gateway2 = MockExecutionGateway::new();
+        mock_execution_gateway1.expect_send_order()
+            .returning(|order| Ok(order.id.to_string()))
+            .times(2);
+        mock_execution_gateway2.expect_send_order()
+            .returning(|order| Ok(order.id.to_string()))
+            .times(2);
+        let report = |order_id, working| OrderReport {
+            order_id: Some(order_id),
+            exchange_order_id: order_id.to_string(),
+            symbol: "BTC/USD".to_string(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Market,
+            status: if working { OrderStatus::PartiallyFilled } else { OrderStatus::Filled },
+            amount: 1.0,
+            filled: 0.5,
+            price: None,
+            avg_fill_price: Some(50000.0),
+            source: MarketDataSource::Binance,
+            updated_at: Utc::now(),
+        };
+        // The first leg is still filling on the second quote and done by the third.
+        let mut checks = 0;
+        mock_execution_gateway1.expect_query_order()
+            .returning(move |_, order_id| {
+                checks += 1;
+                Ok(report(order_id, checks == 1))
+            })
+            .times(2);
+        mock_execution_gateway2.expect_query_order()
+            .returning(|_, _| Err(ExecutionError::Unsupported("query_order")))
+            .times(1);
+
+        let mut strategy = Arbitrage::new(Arc::new(mock_execution_gateway1), Arc::new(mock_execution_gateway2), "BTC/USD".to_string(), 100.0, 1.0);
+        strategy.on_quote(&quote(MarketDataSource::Binance, 49800.0, 49850.0)).await.unwrap();
+        strategy.on_quote(&quote(MarketDataSource::Kraken, 50000.0, 50050.0)).await.unwrap();
+        strategy.on_quote(&quote(MarketDataSource::Kraken, 50001.0, 50050.0)).await.unwrap();
+        strategy.on_quote(&quote(MarketDataSource::Kraken, 50002.0, 50050.0)).await.unwrap();
+    }
+}
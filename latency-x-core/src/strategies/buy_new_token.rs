@@ -86,6 +86,8 @@ impl BuyNewTokenStrategy {
                                             source: MarketDataSource::PumpFun,
                                             created_at: Utc::now(),
                                             triggering_tick: None,
+                                            reduce_only: false,
+                                            position_side: None,
                                         };
                                     
                                         match self.execution_gateway.send_order(order).await {
@@ -1,120 +1,334 @@
-use crate::execution::ExecutionGateway;
-use crate::models::{Order, OrderSide, OrderStatus, Tick, OrderType, MarketDataSource};
-use crate::strategies::Strategy;
-use anyhow::{Result, anyhow};
+use crate::execution::{ExecutionError, ExecutionGateway};
+use crate::models::{Order, OrderSide, OrderStatus, Quote, Tick, OrderType, MarketDataSource};
+use crate::strategies::{sent_order_id, Strategy};
+use anyhow::Result;
 use async_trait::async_trait;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use uuid::Uuid;
 use metrics::{counter, gauge};
 use chrono::Utc;
-use crate::persistence::db::DatabaseManager;
+use crate::instruments::InstrumentRegistry;
+use tracing::warn;
 
 pub struct MarketMaker {
     execution_gateway: Arc<dyn ExecutionGateway + Send + Sync>,
     last_price: Arc<Mutex<Option<f64>>>,
+    last_quote: Arc<Mutex<Option<Quote>>>,
+    working: Arc<Mutex<WorkingQuotes>>,
     spread: f64,
+    requote_threshold: f64,
     quantity: f64,
     symbol: String,
-    db_manager: Arc<DatabaseManager>,
+    instruments: Arc<InstrumentRegistry>,
+}
+
+/// The bid and ask we have resting, and the price they were placed around.
+#[derive(Default)]
+struct WorkingQuotes {
+    /// `None` until both sides are placed, so the next update quotes again.
+    price: Option<f64>,
+    bid: Option<Uuid>,
+    ask: Option<Uuid>,
 }
 
 impl MarketMaker {
-    pub fn new(execution_gateway: Arc<dyn ExecutionGateway + Send + Sync>, spread: f64, quantity: f64, symbol: String, db_manager: Arc<DatabaseManager>) -> Self {
+    /// Quotes are moved once the price has moved by half the spread.
+    pub fn new(execution_gateway: Arc<dyn ExecutionGateway + Send + Sync>, spread: f64, quantity: f64, symbol: String) -> Self {
         Self {
             execution_gateway,
             last_price: Arc::new(Mutex::new(None)),
+            last_quote: Arc::new(Mutex::new(None)),
+            working: Arc::new(Mutex::new(WorkingQuotes::default())),
             spread,
+            requote_threshold: spread / 2.0,
             quantity,
             symbol,
-            db_manager,
+            instruments: Arc::new(InstrumentRegistry::new()),
+        }
+    }
+
+    pub fn with_instruments(mut self, instruments: Arc<InstrumentRegistry>) -> Self {
+        self.instruments = instruments;
+        self
+    }
+
+    /// How far, as a fraction, the price must move before the quotes follow it.
+    pub fn with_requote_threshold(mut self, requote_threshold: f64) -> Self {
+        self.requote_threshold = requote_threshold;
+        self
+    }
+
+    /// Keeps a bid and an ask `spread` either side of `price`, amending the
+    /// resting pair once `price` has moved past the requote threshold.
+    async fn requote(&self, source: MarketDataSource, price: f64) -> Result<()> {
+        let mut working = self.working.lock().await;
+        if working.price.is_some_and(|quoted| (price / quoted - 1.0).abs() < self.requote_threshold) {
+            return Ok(());
+        }
+        working.price = None;
+
+        let mut bid_price = price * (1.0 - self.spread);
+        let mut ask_price = price * (1.0 + self.spread);
+        let mut quantity = self.quantity;
+        if let Some(instrument) = self.instruments.get(source, &self.symbol) {
+            bid_price = instrument.round_price(bid_price);
+            ask_price = instrument.round_price(ask_price);
+            quantity = instrument.round_quantity(quantity);
+        }
+
+        let buy_order = Order {
+            id: Uuid::new_v4(),
+            symbol: self.symbol.clone(),
+            side: OrderSide::Buy,
+            order_type: OrderType::Limit,
+            amount: quantity,
+            price: Some(bid_price),
+            status: OrderStatus::New,
+            source: MarketDataSource::Strategy,
+            created_at: Utc::now(),
+            triggering_tick: None,
+            reduce_only: false,
+            position_side: None,
+        };
+
+        let sell_order = Order {
+            id: Uuid::new_v4(),
+            symbol: self.symbol.clone(),
+            side: OrderSide::Sell,
+            order_type: OrderType::Limit,
+            amount: quantity,
+            price: Some(ask_price),
+            status: OrderStatus::New,
+            source: MarketDataSource::Strategy,
+            created_at: Utc::now(),
+            triggering_tick: None,
+            reduce_only: false,
+            position_side: None,
+        };
+
+        for (order, side) in [(buy_order, "buy"), (sell_order, "sell")] {
+            let resting = match order.side {
+                OrderSide::Buy => &mut working.bid,
+                OrderSide::Sell => &mut working.ask,
+            };
+            match self.replace(*resting, order).await {
+                Ok(order_id) => {
+                    *resting = Some(order_id);
+                    counter!("orders_created", "strategy" => "market_maker", "side" => side).increment(1);
+                }
+                // The next update quotes again.
+                Err(e) if e.is_retryable() => {
+                    warn!("Market maker skipped its {} quote: {}", side, e);
+                    return Ok(());
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        working.price = Some(price);
+        Ok(())
+    }
+
+    /// Amends `resting` into `order`, or places `order` when nothing rests or
+    /// the resting order has already filled or gone. Returns the id it works under.
+    async fn replace(&self, resting: Option<Uuid>, order: Order) -> Result<Uuid, ExecutionError> {
+        if let Some(resting) = resting {
+            match self.execution_gateway.amend_order(resting, order.clone()).await {
+                Ok(amendment) => return Ok(amendment.replacement.order_id.unwrap_or(order.id)),
+                Err(ExecutionError::UnknownOrder(_)) => (),
+                Err(e) => return Err(e),
+            }
         }
+        let order_id = order.id;
+        let sent = self.execution_gateway.send_order(order).await?;
+        Ok(sent_order_id(&sent, order_id))
     }
 }
 
 #[async_trait]
 impl Strategy for MarketMaker {
     async fn on_tick(&mut self, tick: &Tick) -> Result<()> {
+        if tick.symbol != self.symbol {
+            return Ok(());
+        }
         let mut last_price = self.last_price.lock().await;
         gauge!("last_price", "symbol" => tick.symbol.clone()).set(tick.price);
         *last_price = Some(tick.price);
 
+        // Once the venue quotes, the last print is no longer the fair price.
+        if self.last_quote.lock().await.is_some() {
+            return Ok(());
+        }
         if let Some(price) = *last_price {
-            let bid_price = price * (1.0 - self.spread);
-            let ask_price = price * (1.0 + self.spread);
-
-            let buy_order = Order {
-                id: Uuid::new_v4(),
-                symbol: self.symbol.clone(),
-                side: OrderSide::Buy,
-                order_type: OrderType::Limit,
-                amount: self.quantity,
-                price: Some(bid_price),
-                status: OrderStatus::New,
-                source: MarketDataSource::Strategy,
-                created_at: Utc::now(),
-                triggering_tick: None,
-            };
-
-            let sell_order = Order {
-                id: Uuid::new_v4(),
-                symbol: self.symbol.clone(),
-                side: OrderSide::Sell,
-                order_type: OrderType::Limit,
-                amount: self.quantity,
-                price: Some(ask_price),
-                status: OrderStatus::New,
-                source: MarketDataSource::Strategy,
-                created_at: Utc::now(),
-                triggering_tick: None,
-            };
-
-            self.db_manager.save_order(&buy_order).await?;
-            self.db_manager.save_order(&sell_order).await?;
-            self.execution_gateway.send_order(buy_order.clone()).await.map_err(|e| anyhow!(e))?;
-            counter!("orders_created", "strategy" => "market_maker", "side" => "buy").increment(1);
-            
-            self.execution_gateway.send_order(sell_order.clone()).await.map_err(|e| anyhow!(e))?;
-            counter!("orders_created", "strategy" => "market_maker", "side" => "sell").increment(1);
+            self.requote(tick.source, price).await?;
         }
         Ok(())
     }
+
+    async fn on_quote(&mut self, quote: &Quote) -> Result<()> {
+        if quote.symbol != self.symbol {
+            return Ok(());
+        }
+        *self.last_quote.lock().await = Some(quote.clone());
+        self.requote(quote.source, quote.mid()).await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::execution::mock::MockExecutionGateway;
-    use crate::models::{MarketDataSource, Tick};
-    use crate::persistence::db::DatabaseManager;
+    use crate::models::{Amendment, MarketDataSource, OrderReport, Tick};
 
     #[tokio::test]
     async fn test_market_maker_creates_orders() {
         // Arrange
         let mut mock_execution_gateway = MockExecutionGateway::new();
-        let db_manager = Arc::new(DatabaseManager::new(":memory:").await.unwrap());
-        db_manager.init().await.unwrap();
 
         mock_execution_gateway.expect_send_order()
             .returning(|_| Ok("test_order_id".to_string()))
             .times(2);
 
-        let mut strategy = MarketMaker::new(Arc::new(mock_execution_gateway), 0.01, 1.0, "BTCUSDT".to_string(), db_manager);
+        let mut strategy = MarketMaker::new(Arc::new(mock_execution_gateway), 0.01, 1.0, "BTCUSDT".to_string());
         let tick = Tick {
             source: MarketDataSource::Binance,
             symbol: "BTCUSDT".to_string(),
             price: 50000.0,
             volume: 1.0,
             received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
         };
 
         // Act
-        // First tick just sets the price
+        // The first tick quotes both sides; the same price again leaves them resting.
         strategy.on_tick(&tick).await.unwrap();
-        // Second tick should create orders
         strategy.on_tick(&tick).await.unwrap();
 
         // Assert
         // The mock expectations handle the assertion
     }
-} 
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_market_maker_skips_a_quote_while_rate_limited() {
+        let mut mock_execution_gateway = MockExecutionGateway::new();
+        mock_execution_gateway.expect_send_order()
+            .returning(|_| Err(ExecutionError::RateLimited("-1015".to_string())))
+            .times(1);
+
+        let mut strategy = MarketMaker::new(Arc::new(mock_execution_gateway), 0.01, 1.0, "BTCUSDT".to_string());
+        let tick = Tick {
+            source: MarketDataSource::Binance,
+            symbol: "BTCUSDT".to_string(),
+            price: 50000.0,
+            volume: 1.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
+        };
+        strategy.on_tick(&tick).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_market_maker_quotes_around_the_mid() {
+        let mut mock_execution_gateway = MockExecutionGateway::new();
+
+        mock_execution_gateway.expect_send_order()
+            .withf(|order| order.side == OrderSide::Buy && order.price == Some(99.0))
+            .returning(|_| Ok("bid".to_string()))
+            .times(1);
+        mock_execution_gateway.expect_send_order()
+            .withf(|order| order.side == OrderSide::Sell && order.price == Some(101.0))
+            .returning(|_| Ok("ask".to_string()))
+            .times(1);
+
+        let mut strategy = MarketMaker::new(Arc::new(mock_execution_gateway), 0.01, 1.0, "BTCUSDT".to_string());
+        let quote = Quote {
+            source: MarketDataSource::Binance,
+            symbol: "BTCUSDT".to_string(),
+            bid: 99.5,
+            bid_size: 1.0,
+            ask: 100.5,
+            ask_size: 1.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+        };
+        strategy.on_quote(&quote).await.unwrap();
+
+        // A print far from the quote does not move our prices.
+        strategy.on_tick(&Tick {
+            source: MarketDataSource::Binance,
+            symbol: "BTCUSDT".to_string(),
+            price: 120.0,
+            volume: 1.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+            trade_id: None,
+        }).await.unwrap();
+    }
+
+    fn report(order: &Order, status: OrderStatus) -> OrderReport {
+        OrderReport {
+            order_id: Some(order.id),
+            exchange_order_id: order.id.to_string(),
+            symbol: order.symbol.clone(),
+            side: order.side,
+            order_type: order.order_type.clone(),
+            status,
+            amount: order.amount,
+            filled: 0.0,
+            price: order.price,
+            avg_fill_price: None,
+            source: MarketDataSource::Binance,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_market_maker_amends_its_quotes_once_the_price_moves() {
+        let bid_id = Uuid::new_v4();
+        let ask_id = Uuid::new_v4();
+        let mut mock_execution_gateway = MockExecutionGateway::new();
+        mock_execution_gateway.expect_send_order()
+            .withf(|order| order.price == Some(99.0))
+            .returning(move |_| Ok(bid_id.to_string()))
+            .times(1);
+        mock_execution_gateway.expect_send_order()
+            .withf(|order| order.price == Some(101.0))
+            .returning(move |_| Ok(ask_id.to_string()))
+            .times(1);
+        // The bid is still resting and moves; the ask has filled, so a new one goes out.
+        mock_execution_gateway.expect_amend_order()
+            .withf(move |order_id, order| *order_id == bid_id && order.price == Some(108.9))
+            .returning(|order_id, replacement| {
+                let mut canceled = report(&replacement, OrderStatus::Canceled);
+                canceled.order_id = Some(order_id);
+                Ok(Amendment { canceled, replacement: report(&replacement, OrderStatus::New) })
+            })
+            .times(1);
+        mock_execution_gateway.expect_amend_order()
+            .withf(move |order_id, _| *order_id == ask_id)
+            .returning(|_, _| Err(ExecutionError::UnknownOrder("filled".to_string())))
+            .times(1);
+        mock_execution_gateway.expect_send_order()
+            .withf(|order| order.price == Some(111.1))
+            .returning(|order| Ok(order.id.to_string()))
+            .times(1);
+
+        let mut strategy = MarketMaker::new(Arc::new(mock_execution_gateway), 0.01, 1.0, "BTCUSDT".to_string());
+        let quote = |bid, ask| Quote {
+            source: MarketDataSource::Binance,
+            symbol: "BTCUSDT".to_string(),
+            bid,
+            bid_size: 1.0,
+            ask,
+            ask_size: 1.0,
+            received_at: Utc::now(),
+            exchange_ts: None,
+        };
+        strategy.on_quote(&quote(99.5, 100.5)).await.unwrap();
+        // Inside half the spread: the quotes stay where they are.
+        strategy.on_quote(&quote(99.7, 100.7)).await.unwrap();
+        strategy.on_quote(&quote(109.5, 110.5)).await.unwrap();
+    }
+}
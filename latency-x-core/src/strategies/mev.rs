@@ -4,12 +4,14 @@ use crate::{
     models::{MarketDataSource, Order, OrderSide, Tick, OrderType, OrderStatus},
     strategies::Strategy,
 };
-use crate::persistence::db::DatabaseManager;
+use crate::instruments::canonical_symbol;
 use anyhow::Result;
 use async_trait::async_trait;
 use std::sync::Arc;
 use std::collections::HashMap;
 use tokio::sync::Mutex;
+use uuid::Uuid;
+use chrono::Utc;
 
 /// A simple triangular arbitrage MEV strategy.
 ///
@@ -28,12 +30,11 @@ pub struct MevStrategy {
     asset_a: String, // e.g., "ETH"
     asset_b: String, // e.g., "BTC"
     asset_c: String, // e.g., "USDT"
-    pair_ab: String, // e.g., "ETHBTC"
-    pair_bc: String, // e.g., "BTCUSDT"
-    pair_ca: String, // e.g., "ETHUSDT"
+    pair_ab: String, // e.g., "ETH/BTC"
+    pair_bc: String, // e.g., "BTC/USDT"
+    pair_ca: String, // e.g., "ETH/USDT"
     trade_amount_b: f64, // The amount of asset B to start the arbitrage with
     min_profit_threshold: f64, // Minimum profit percentage to execute a trade
-    db_manager: Arc<DatabaseManager>,
 }
 
 #[allow(dead_code)]
@@ -41,11 +42,10 @@ impl MevStrategy {
     pub fn new(
         execution_gw: Arc<dyn ExecutionGateway>,
         config: &MevStrategyConfig,
-        db_manager: Arc<DatabaseManager>,
     ) -> Self {
-        let pair_ab = format!("{}{}", config.asset_a, config.asset_b);
-        let pair_bc = format!("{}{}", config.asset_b, config.asset_c);
-        let pair_ca = format!("{}{}", config.asset_a, config.asset_c);
+        let pair_ab = canonical_symbol(&config.asset_a, &config.asset_b);
+        let pair_bc = canonical_symbol(&config.asset_b, &config.asset_c);
+        let pair_ca = canonical_symbol(&config.asset_a, &config.asset_c);
         Self {
             execution_gw,
             prices: Arc::new(Mutex::new(HashMap::new())),
@@ -57,7 +57,6 @@ impl MevStrategy {
             pair_ca,
             trade_amount_b: config.trade_amount_b,
             min_profit_threshold: config.min_profit_threshold,
-            db_manager,
         }
     }
 
@@ -89,7 +88,7 @@ impl MevStrategy {
 
             // Create orders
             let order1 = Order {
-                id: Default::default(),
+                id: Uuid::new_v4(),
                 symbol: self.pair_ab.clone(),
                 side: OrderSide::Buy,
                 order_type: OrderType::Market,
@@ -97,11 +96,13 @@ impl MevStrategy {
                 price: None,
                 status: OrderStatus::New,
                 source: MarketDataSource::Strategy,
-                created_at: Default::default(),
+                created_at: Utc::now(),
                 triggering_tick: Some(Box::new(tick.clone())),
+                reduce_only: false,
+                position_side: None,
             };
             let order2 = Order {
-                id: Default::default(),
+                id: Uuid::new_v4(),
                 symbol: self.pair_ca.clone(),
                 side: OrderSide::Sell,
                 order_type: OrderType::Market,
@@ -109,11 +110,13 @@ impl MevStrategy {
                 price: None,
                 status: OrderStatus::New,
                 source: MarketDataSource::Strategy,
-                created_at: Default::default(),
+                created_at: Utc::now(),
                 triggering_tick: None,
+                reduce_only: false,
+                position_side: None,
             };
             let order3 = Order {
-                id: Default::default(),
+                id: Uuid::new_v4(),
                 symbol: self.pair_bc.clone(),
                 side: OrderSide::Sell,
                 order_type: OrderType::Market,
@@ -121,35 +124,24 @@ impl MevStrategy {
                 price: None,
                 status: OrderStatus::New,
                 source: MarketDataSource::Strategy,
-                created_at: Default::default(),
+                created_at: Utc::now(),
                 triggering_tick: None,
+                reduce_only: false,
+                position_side: None,
             };
 
-            let db_manager = self.db_manager.clone();
             // Execute orders in sequence
             // In a real scenario, you'd need to handle partial fills and execution delays
             let gw = self.execution_gw.clone();
             tokio::spawn(async move {
-                if let Err(e) = db_manager.save_order(&order1).await {
-                    eprintln!("MEV order 1 failed to save: {}", e);
-                    return;
-                }
                 if let Err(e) = gw.send_order(order1).await {
                     eprintln!("MEV order 1 failed: {}", e);
                     return;
                 }
-                if let Err(e) = db_manager.save_order(&order2).await {
-                    eprintln!("MEV order 2 failed to save: {}", e);
-                    return;
-                }
                 if let Err(e) = gw.send_order(order2).await {
                     eprintln!("MEV order 2 failed: {}", e);
                      return;
                 }
-                if let Err(e) = db_manager.save_order(&order3).await {
-                    eprintln!("MEV order 3 failed to save: {}", e);
-                    return;
-                }
                 if let Err(e) = gw.send_order(order3).await {
                     eprintln!("MEV order 3 failed: {}", e);
                 }
@@ -163,10 +155,9 @@ impl MevStrategy {
 #[async_trait]
 impl Strategy for MevStrategy {
     async fn on_tick(&mut self, tick: &Tick) -> Result<()> {
-        let symbol = tick.symbol.to_uppercase().replace("/", "");
         {
             let mut prices = self.prices.lock().await;
-            prices.insert(symbol, tick.price);
+            prices.insert(tick.symbol.clone(), tick.price);
         }
         self.check_arbitrage(tick).await?;
         Ok(())
@@ -4,11 +4,44 @@ pub mod market_maker;
 pub mod mev;
 
 
-use crate::models::Tick;
+use crate::models::{FeedStatus, MarkPrice, OrderBook, Quote, Tick};
 use anyhow::Result;
 use async_trait::async_trait;
+use uuid::Uuid;
+
+/// The id to query, cancel or amend an order by once `send_order` returned
+/// `sent`. Through the OMS that is the client id it tracks the order under;
+/// a venue's own gateway returns the exchange id, and the order keeps the
+/// client id it was sent with.
+pub(crate) fn sent_order_id(sent: &str, order_id: Uuid) -> Uuid {
+    sent.parse().unwrap_or(order_id)
+}
 
 #[async_trait]
 pub trait Strategy: Send {
     async fn on_tick(&mut self, tick: &Tick) -> Result<()>;
+
+    /// Called whenever a venue's best bid or offer changes. Strategies that
+    /// only price off trades can ignore it.
+    async fn on_quote(&mut self, _quote: &Quote) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with each mark price and funding update from a perpetual
+    /// futures venue. Only basis and funding strategies need it.
+    async fn on_mark_price(&mut self, _mark_price: &MarkPrice) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called with each published book update. Strategies that only price off
+    /// trades can ignore it.
+    async fn on_order_book(&mut self, _book: &OrderBook) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when a feed goes stale, reconnects or recovers, so strategies
+    /// can stop trusting prices from a venue that went quiet.
+    async fn on_feed_status(&mut self, _status: &FeedStatus) -> Result<()> {
+        Ok(())
+    }
 } 
\ No newline at end of file
@@ -1,31 +1,711 @@
 use std::sync::Arc;
+use std::time::Duration;
+use async_trait::async_trait;
 use tokio::sync::{mpsc, broadcast};
-use latency_x_core::connectors::{binance::BinanceConnector, Connector};
-use latency_x_core::config::Config;
+use latency_x_core::connectors::{binance::BinanceConnector, Connector, MarkPriceConnector, OrderBookConnector, QuoteConnector};
+use latency_x_core::connectors::binance_futures::BinanceFuturesConnector;
+use latency_x_core::config::ExchangeConfig;
 use latency_x_core::connectors::kraken::KrakenConnector;
+use latency_x_core::execution::{ExecutionError, ExecutionGateway};
 use latency_x_core::settlement::Settlement;
+use latency_x_core::simulator::{Credentials, ExchangeSimulator, SimulatorHandle};
 use latency_x_core::persistence::db::DatabaseManager;
-use latency_x_core::models::{Fill, Order};
+use latency_x_core::models::{Fill, MarketDataSource, Order, OrderSide, OrderStatus, OrderType, PositionSide};
 use mockall::mock;
 
 mock! {
     pub Settlement {}
 
+    #[async_trait]
     impl Settlement for Settlement {
-        fn new(config: &latency_x_core::config::HeliusConfig) -> Self;
-        async fn get_priority_fee(&self, last_n_blocks: u64, account: &str) -> anyhow::Result<f64>;
-        async fn settle(&self, order: &Order) -> anyhow::Result<String>;
+        async fn send_order(&self, order: &Order) -> Result<String, ExecutionError>;
     }
 }
 
-mock! {
-    pub DatabaseManager {
-        pub async fn new(url: &str) -> anyhow::Result<Self>;
-        pub async fn init(&self) -> anyhow::Result<()>;
-        pub async fn save_fill(&self, fill: &Fill) -> anyhow::Result<()>;
+async fn in_memory_db() -> Arc<DatabaseManager> {
+    let db = DatabaseManager::new("sqlite::memory:").await.unwrap();
+    db.init().await.unwrap();
+    Arc::new(db)
+}
+
+fn settled() -> Arc<MockSettlement> {
+    let mut settlement = MockSettlement::new();
+    settlement
+        .expect_send_order()
+        .returning(|order| Ok(format!("mock_tx_{}", order.id)));
+    Arc::new(settlement)
+}
+
+fn failing_settlement() -> Arc<MockSettlement> {
+    let mut settlement = MockSettlement::new();
+    settlement
+        .expect_send_order()
+        .returning(|_| Err(ExecutionError::Rejected("not a pump.fun mint".to_string())));
+    Arc::new(settlement)
+}
+
+async fn within<T>(future: impl std::future::Future<Output = T>) -> T {
+    tokio::time::timeout(Duration::from_secs(10), future)
+        .await
+        .expect("timed out waiting for the simulator")
+}
+
+async fn binance(sim: &SimulatorHandle, credentials: Credentials, fill_tx: mpsc::Sender<Fill>) -> Arc<BinanceConnector> {
+    let config = ExchangeConfig {
+        api_key: credentials.api_key,
+        api_secret: credentials.api_secret,
+        book_depth: 20,
+    };
+    let (dashboard_tx, _) = broadcast::channel(256);
+    Arc::new(
+        BinanceConnector::new(&config, settled(), Some(fill_tx), dashboard_tx, in_memory_db().await)
+            .with_endpoints(sim.binance_endpoints()),
+    )
+}
+
+async fn kraken(sim: &SimulatorHandle, credentials: Credentials, fill_tx: mpsc::Sender<Fill>) -> Arc<KrakenConnector> {
+    let config = ExchangeConfig {
+        api_key: credentials.api_key,
+        api_secret: credentials.api_secret,
+        book_depth: 10,
+    };
+    let (dashboard_tx, _) = broadcast::channel(256);
+    Arc::new(
+        KrakenConnector::new(&config, settled(), Some(fill_tx), dashboard_tx, in_memory_db().await)
+            .with_endpoints(sim.kraken_endpoints()),
+    )
+}
+
+fn binance_futures(sim: &SimulatorHandle) -> Arc<BinanceFuturesConnector> {
+    let credentials = sim.binance_credentials();
+    let config = ExchangeConfig {
+        api_key: credentials.api_key,
+        api_secret: credentials.api_secret,
+        book_depth: 20,
+    };
+    let (dashboard_tx, _) = broadcast::channel(256);
+    Arc::new(BinanceFuturesConnector::new(&config, dashboard_tx).with_endpoints(sim.binance_futures_endpoints()))
+}
+
+fn limit(symbol: &str, side: OrderSide, amount: f64, price: f64, source: MarketDataSource) -> Order {
+    Order {
+        order_type: OrderType::Limit,
+        price: Some(price),
+        ..Order::market(symbol.to_string(), side, amount, source, None)
     }
 }
 
+#[tokio::test]
+async fn test_binance_trades_survive_a_reconnect() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_000.5, 0.25);
+    let tick = within(rx.recv()).await.unwrap();
+    assert_eq!(tick.source, MarketDataSource::Binance);
+    assert_eq!(tick.symbol, "BTCUSDT");
+    assert_eq!((tick.price, tick.volume), (50_000.5, 0.25));
+    assert!(tick.exchange_ts.is_some() && tick.trade_id.is_some());
+
+    sim.disconnect_all();
+    within(sim.wait_for_subscriptions(2)).await;
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_001.0, 1.0);
+    assert_eq!(within(rx.recv()).await.unwrap().price, 50_001.0);
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_symbols_change_on_the_live_stream() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let feed = connector.clone();
+    let task = tokio::spawn(async move { feed.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    connector.add_symbols(&["ETHUSDT".to_string()]).await.unwrap();
+    within(sim.wait_for_subscriptions(2)).await;
+    sim.publish_trade(MarketDataSource::Binance, "ETHUSDT", 3_000.0, 1.0);
+    assert_eq!(within(rx.recv()).await.unwrap().symbol, "ETHUSDT");
+
+    // Commands are handled in order, so once SOLUSDT is live BTCUSDT is gone.
+    connector.remove_symbols(&["BTCUSDT".to_string()]).await.unwrap();
+    connector.add_symbols(&["SOLUSDT".to_string()]).await.unwrap();
+    within(sim.wait_for_subscriptions(3)).await;
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_000.0, 1.0);
+    sim.publish_trade(MarketDataSource::Binance, "SOLUSDT", 150.0, 1.0);
+    assert_eq!(within(rx.recv()).await.unwrap().symbol, "SOLUSDT");
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_refused_symbols_are_reported_and_dropped() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let feed = connector.clone();
+    let task = tokio::spawn(async move { feed.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    let refused = connector.add_symbols(&["BAD/PAIR".to_string()]).await.unwrap_err();
+    assert!(refused.to_string().contains("Invalid request"), "{}", refused);
+
+    // The refused symbol is not carried into later changes or reconnects.
+    connector.add_symbols(&["ETHUSDT".to_string()]).await.unwrap();
+    sim.disconnect_all();
+    within(sim.wait_for_subscriptions(3)).await;
+    sim.publish_trade(MarketDataSource::Binance, "ETHUSDT", 3_000.0, 1.0);
+    assert_eq!(within(rx.recv()).await.unwrap().symbol, "ETHUSDT");
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_backfills_a_skipped_trade_id() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_000.0, 1.0);
+    sim.publish_trade_unseen(MarketDataSource::Binance, "BTCUSDT", 50_001.0, 2.0);
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_002.0, 3.0);
+    for (id, price) in [(1, 50_000.0), (2, 50_001.0), (3, 50_002.0)] {
+        let tick = within(rx.recv()).await.unwrap();
+        assert_eq!((tick.trade_id, tick.price), (Some(id), price));
+    }
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_readded_symbols_do_not_backfill_the_time_away() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let feed = connector.clone();
+    let task = tokio::spawn(async move { feed.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_000.0, 1.0);
+    assert_eq!(within(rx.recv()).await.unwrap().trade_id, Some(1));
+
+    connector.remove_symbols(&["BTCUSDT".to_string()]).await.unwrap();
+    sim.publish_trade_unseen(MarketDataSource::Binance, "BTCUSDT", 50_001.0, 2.0);
+    connector.add_symbols(&["BTCUSDT".to_string()]).await.unwrap();
+    within(sim.wait_for_subscriptions(2)).await;
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_002.0, 3.0);
+    let tick = within(rx.recv()).await.unwrap();
+    assert_eq!((tick.trade_id, tick.price), (Some(3), 50_002.0));
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_book_syncs_from_snapshot_and_diffs() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe_order_book(&symbols, 5, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_book(MarketDataSource::Binance, "BTCUSDT", &[(100.0, 1.0), (99.0, 2.0)], &[(101.0, 3.0)]);
+    let book = within(rx.recv()).await.unwrap();
+    assert_eq!(book.best_bid().unwrap().price, 100.0);
+    assert_eq!(book.best_ask().unwrap().quantity, 3.0);
+
+    sim.publish_book(MarketDataSource::Binance, "BTCUSDT", &[(100.0, 0.0)], &[(100.5, 1.0)]);
+    let book = within(rx.recv()).await.unwrap();
+    assert_eq!(book.best_bid().unwrap().price, 99.0);
+    assert_eq!(book.best_ask().unwrap().price, 100.5);
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_quotes_follow_book_ticker() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe_quotes(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_quote(MarketDataSource::Binance, "BTCUSDT", (50_000.0, 1.5), (50_000.5, 0.25));
+    let quote = within(rx.recv()).await.unwrap();
+    assert_eq!(quote.source, MarketDataSource::Binance);
+    assert_eq!(quote.symbol, "BTCUSDT");
+    assert_eq!((quote.bid, quote.bid_size), (50_000.0, 1.5));
+    assert_eq!((quote.ask, quote.ask_size), (50_000.5, 0.25));
+    assert_eq!(quote.mid(), 50_000.25);
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_quotes_follow_added_symbols() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let feed = connector.clone();
+    let task = tokio::spawn(async move { feed.subscribe_quotes(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    // Resolves once the venue has acked the new stream.
+    connector.add_symbols(&["ETHUSDT".to_string()]).await.unwrap();
+    connector.remove_symbols(&["BTCUSDT".to_string()]).await.unwrap();
+    sim.publish_quote(MarketDataSource::Binance, "BTCUSDT", (50_000.0, 1.5), (50_000.5, 0.25));
+    sim.publish_quote(MarketDataSource::Binance, "ETHUSDT", (3_000.0, 2.0), (3_000.5, 1.0));
+    assert_eq!(within(rx.recv()).await.unwrap().symbol, "ETHUSDT");
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_order_fills_on_the_user_data_stream() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, mut fill_rx) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+    let user_data = tokio::spawn(connector.clone().subscribe_user_data());
+    within(sim.wait_for_subscriptions(1)).await;
+
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_000.0, 1.0);
+    let order = Order::market("BTCUSDT".to_string(), OrderSide::Buy, 0.5, MarketDataSource::Binance, None);
+    let exchange_order_id = connector.send_order(order.clone()).await.unwrap();
+
+    let placed = sim.orders();
+    assert_eq!(placed.len(), 1);
+    assert_eq!(placed[0].order_type, "MARKET");
+    assert_eq!(placed[0].client_order_id, Some(order.id.to_string()));
+    assert_eq!(placed[0].exchange_order_id, exchange_order_id);
+
+    let fill = within(fill_rx.recv()).await.unwrap();
+    assert_eq!(fill.order_id, order.id);
+    assert_eq!((fill.side, fill.price, fill.quantity), (OrderSide::Buy, 50_000.0, 0.5));
+    assert!(fill.commission > 0.0);
+    // The same trade arrives in the order response and on the stream, but is
+    // published once.
+    assert!(tokio::time::timeout(Duration::from_millis(300), fill_rx.recv()).await.is_err());
+    user_data.abort();
+}
+
+#[tokio::test]
+async fn test_binance_full_order_response_fills_without_the_user_data_stream() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, mut fill_rx) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+
+    sim.publish_trade(MarketDataSource::Binance, "BTCUSDT", 50_000.0, 1.0);
+    // 0.1 + 0.2 is not exactly 0.3, and Binance refuses more than 8 decimals.
+    let order = Order::market("BTCUSDT".to_string(), OrderSide::Sell, 0.1 + 0.2, MarketDataSource::Binance, None);
+    connector.send_order(order.clone()).await.unwrap();
+    assert_eq!(sim.orders()[0].quantity, 0.3);
+
+    let fill = within(fill_rx.recv()).await.unwrap();
+    assert_eq!(fill.order_id, order.id);
+    assert_eq!((fill.side, fill.price, fill.quantity), (OrderSide::Sell, 50_000.0, 0.3));
+    assert!(fill.commission > 0.0);
+}
+
+#[tokio::test]
+async fn test_binance_api_errors_are_classified() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+
+    // Nothing has traded, so there is no price to fill a market order at.
+    let order = Order::market("BTCUSDT".to_string(), OrderSide::Buy, 0.5, MarketDataSource::Binance, None);
+    let error = connector.send_order(order).await.unwrap_err();
+    assert!(matches!(error, ExecutionError::InsufficientFunds(_)), "{}", error);
+    let order = limit("BTCUSDT", OrderSide::Buy, 0.0, 49_000.0, MarketDataSource::Binance);
+    let error = connector.send_order(order).await.unwrap_err();
+    assert!(matches!(error, ExecutionError::Rejected(_)) && error.to_string().contains("-1013"), "{}", error);
+    assert!(!error.is_retryable());
+    let error = connector.cancel_order("BTCUSDT", uuid::Uuid::new_v4()).await.unwrap_err();
+    assert!(matches!(error, ExecutionError::UnknownOrder(_)), "{}", error);
+    assert!(sim.orders().is_empty());
+}
+
+#[tokio::test]
+async fn test_exchange_orders_do_not_go_through_settlement() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (dashboard_tx, _) = broadcast::channel(256);
+    let credentials = sim.binance_credentials();
+    let config = ExchangeConfig { api_key: credentials.api_key, api_secret: credentials.api_secret, book_depth: 20 };
+    let binance = BinanceConnector::new(&config, failing_settlement(), None, dashboard_tx.clone(), in_memory_db().await)
+        .with_endpoints(sim.binance_endpoints());
+    let credentials = sim.kraken_credentials();
+    let config = ExchangeConfig { api_key: credentials.api_key, api_secret: credentials.api_secret, book_depth: 10 };
+    let kraken = KrakenConnector::new(&config, failing_settlement(), None, dashboard_tx, in_memory_db().await)
+        .with_endpoints(sim.kraken_endpoints());
+
+    let order = limit("BTCUSDT", OrderSide::Buy, 0.5, 49_000.0, MarketDataSource::Binance);
+    let order_id = binance.send_order(order).await.unwrap();
+    assert_eq!(sim.orders()[0].exchange_order_id, order_id);
+    let order = limit("XBTUSD", OrderSide::Buy, 0.5, 41_000.0, MarketDataSource::Kraken);
+    let txid = kraken.send_order(order).await.unwrap();
+    assert_eq!(sim.orders()[1].exchange_order_id, txid);
+}
+
+#[tokio::test]
+async fn test_binance_rejects_a_bad_signature() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let credentials = Credentials::new(&sim.binance_credentials().api_key, "another-secret");
+    let connector = binance(&sim, credentials, fill_tx).await;
+
+    let order = limit("BTCUSDT", OrderSide::Sell, 1.0, 50_000.0, MarketDataSource::Binance);
+    let error = connector.send_order(order).await.unwrap_err();
+    assert!(error.to_string().contains("-1022"), "{}", error);
+    assert!(matches!(error, ExecutionError::Unauthorized(_)), "{}", error);
+    assert!(sim.orders().is_empty());
+}
+
+#[tokio::test]
+async fn test_binance_resting_order_is_amended_and_canceled() {
+    let sim = ExchangeSimulator::new().with_resting_limit_orders().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = binance(&sim, sim.binance_credentials(), fill_tx).await;
+
+    let order = limit("BTCUSDT", OrderSide::Buy, 0.5, 49_000.0, MarketDataSource::Binance);
+    connector.send_order(order.clone()).await.unwrap();
+    let open = connector.open_orders(Some("BTCUSDT".to_string())).await.unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!((open[0].order_id, open[0].status.clone(), open[0].filled), (Some(order.id), OrderStatus::New, 0.0));
+
+    let replacement = limit("BTCUSDT", OrderSide::Buy, 0.25, 49_500.0, MarketDataSource::Binance);
+    let amended = connector.amend_order(order.id, replacement.clone()).await.unwrap();
+    assert_eq!((amended.canceled.order_id, amended.canceled.status.clone()), (Some(order.id), OrderStatus::Canceled));
+    let amended = amended.replacement;
+    assert_eq!((amended.order_id, amended.status.clone()), (Some(replacement.id), OrderStatus::New));
+    assert_eq!((amended.amount, amended.price), (0.25, Some(49_500.0)));
+    assert_eq!(connector.query_order("BTCUSDT", order.id).await.unwrap().status, OrderStatus::Canceled);
+    // The original is gone, so a second amend places nothing.
+    let error = connector.amend_order(order.id, limit("BTCUSDT", OrderSide::Buy, 0.25, 49_600.0, MarketDataSource::Binance)).await.unwrap_err();
+    assert!(error.to_string().contains("-2022"), "{}", error);
+
+    let canceled = connector.cancel_all_orders(None).await.unwrap();
+    assert_eq!(canceled.iter().map(|report| report.order_id).collect::<Vec<_>>(), vec![Some(replacement.id)]);
+    assert_eq!(canceled[0].status, OrderStatus::Canceled);
+    assert!(connector.open_orders(None).await.unwrap().is_empty());
+    let error = connector.cancel_order("BTCUSDT", replacement.id).await.unwrap_err();
+    assert!(error.to_string().contains("-2011"), "{}", error);
+    assert_eq!(sim.orders().len(), 2);
+}
+
+#[tokio::test]
+async fn test_kraken_trades_survive_a_reconnect() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["XBT/USD".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_trade(MarketDataSource::Kraken, "XBT/USD", 42_000.1, 0.5);
+    let tick = within(rx.recv()).await.unwrap();
+    assert_eq!(tick.source, MarketDataSource::Kraken);
+    assert_eq!(tick.symbol, "XBT/USD");
+    assert_eq!((tick.price, tick.volume), (42_000.1, 0.5));
+
+    sim.disconnect_all();
+    within(sim.wait_for_subscriptions(2)).await;
+    sim.publish_trade(MarketDataSource::Kraken, "XBT/USD", 42_000.2, 0.5);
+    assert_eq!(within(rx.recv()).await.unwrap().price, 42_000.2);
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_kraken_added_symbols_survive_a_reconnect() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["XBT/USD".to_string()];
+    let feed = connector.clone();
+    let task = tokio::spawn(async move { feed.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    connector.add_symbols(&["ETH/USD".to_string()]).await.unwrap();
+    connector.remove_symbols(&["XBT/USD".to_string()]).await.unwrap();
+    within(sim.wait_for_subscriptions(2)).await;
+
+    sim.disconnect_all();
+    within(sim.wait_for_subscriptions(3)).await;
+    sim.publish_trade(MarketDataSource::Kraken, "XBT/USD", 42_000.0, 0.5);
+    sim.publish_trade(MarketDataSource::Kraken, "ETH/USD", 2_500.0, 2.0);
+    let tick = within(rx.recv()).await.unwrap();
+    assert_eq!((tick.symbol.as_str(), tick.price), ("ETH/USD", 2_500.0));
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_binance_futures_streams_trades_and_mark_prices() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let connector = binance_futures(&sim);
+    let (tx, mut rx) = mpsc::channel(32);
+    let (mark_tx, mut mark_rx) = mpsc::channel(32);
+    let symbols = vec!["BTCUSDT".to_string()];
+    let mark_symbols = symbols.clone();
+    let mark_connector = connector.clone();
+    let marks = tokio::spawn(async move { mark_connector.subscribe_mark_prices(&mark_symbols, mark_tx).await });
+    let trades = tokio::spawn(async move { connector.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(2)).await;
+    sim.publish_trade(MarketDataSource::BinanceFutures, "BTCUSDT", 50_010.0, 0.2);
+    let next_funding = chrono::Utc::now() + chrono::Duration::hours(8);
+    sim.publish_mark_price("BTCUSDT", 50_012.5, 50_000.0, 0.0001, next_funding);
+
+    let tick = within(rx.recv()).await.unwrap();
+    assert_eq!(tick.source, MarketDataSource::BinanceFutures);
+    assert_eq!((tick.price, tick.volume), (50_010.0, 0.2));
+    let mark = within(mark_rx.recv()).await.unwrap();
+    assert_eq!((mark.mark_price, mark.index_price, mark.funding_rate), (50_012.5, 50_000.0, 0.0001));
+    assert_eq!(mark.basis(), 12.5);
+    assert_eq!(mark.next_funding_time.timestamp_millis(), next_funding.timestamp_millis());
+    trades.abort();
+    marks.abort();
+}
+
+#[tokio::test]
+async fn test_binance_futures_reduce_only_orders_cannot_grow_a_position() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let connector = binance_futures(&sim);
+    let futures_order = |side, amount, reduce_only| Order {
+        reduce_only,
+        ..limit("BTCUSDT", side, amount, 50_000.0, MarketDataSource::BinanceFutures)
+    };
+
+    let id = connector.send_order(futures_order(OrderSide::Buy, 0.3, false)).await.unwrap();
+    assert_eq!(sim.orders()[0].exchange_order_id, id);
+    assert!(connector.send_order(futures_order(OrderSide::Buy, 0.1, true)).await.is_err());
+    assert!(connector.send_order(futures_order(OrderSide::Sell, 0.5, true)).await.is_err());
+    connector.send_order(futures_order(OrderSide::Sell, 0.2, true)).await.unwrap();
+    assert!((sim.futures_position("BTCUSDT", PositionSide::Both) - 0.1).abs() < 1e-9);
+
+    // Hedge mode: the short leg is opened and closed on its own.
+    let hedged = |side, reduce_only| Order {
+        position_side: Some(PositionSide::Short),
+        ..futures_order(side, 0.4, reduce_only)
+    };
+    connector.send_order(hedged(OrderSide::Sell, false)).await.unwrap();
+    connector.send_order(hedged(OrderSide::Buy, true)).await.unwrap();
+    assert_eq!(sim.futures_position("BTCUSDT", PositionSide::Short), 0.0);
+
+    let orders = sim.orders();
+    assert_eq!(orders.len(), 4);
+    assert!(orders.iter().all(|order| order.venue == MarketDataSource::BinanceFutures));
+    assert!(orders[1].reduce_only);
+    assert_eq!(orders[3].position_side, Some(PositionSide::Short));
+    assert!(!orders[3].reduce_only);
+}
+
+#[tokio::test]
+async fn test_kraken_backfills_trades_missed_while_reconnecting() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["XBT/USD".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_trade(MarketDataSource::Kraken, "XBT/USD", 42_000.0, 0.5);
+    assert_eq!(within(rx.recv()).await.unwrap().price, 42_000.0);
+
+    sim.disconnect_all();
+    sim.publish_trade_unseen(MarketDataSource::Kraken, "XBT/USD", 42_001.0, 0.25);
+    within(sim.wait_for_subscriptions(2)).await;
+    sim.publish_trade(MarketDataSource::Kraken, "XBT/USD", 42_002.0, 1.0);
+    assert_eq!(within(rx.recv()).await.unwrap().price, 42_001.0);
+    assert_eq!(within(rx.recv()).await.unwrap().price, 42_002.0);
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_kraken_quotes_follow_the_spread_feed() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["XBT/USD".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe_quotes(&symbols, tx).await });
+
+    within(sim.wait_for_subscriptions(1)).await;
+    sim.publish_quote(MarketDataSource::Kraken, "XBT/USD", (42_000.0, 2.0), (42_000.5, 1.0));
+    let quote = within(rx.recv()).await.unwrap();
+    assert_eq!(quote.source, MarketDataSource::Kraken);
+    assert_eq!(quote.symbol, "XBT/USD");
+    assert_eq!((quote.bid, quote.ask), (42_000.0, 42_000.5));
+    assert_eq!((quote.bid_size, quote.ask_size), (2.0, 1.0));
+    assert!(quote.exchange_ts.is_some());
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_kraken_book_passes_checksums() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    sim.publish_book(MarketDataSource::Kraken, "BTC/USD", &[(42_000.0, 1.5), (41_999.5, 2.0)], &[(42_000.5, 0.75)]);
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let (tx, mut rx) = mpsc::channel(32);
+    let symbols = vec!["BTC/USD".to_string()];
+    let task = tokio::spawn(async move { connector.subscribe_order_book(&symbols, 10, tx).await });
+
+    // Published only after the snapshot checksum matched.
+    let snapshot = within(rx.recv()).await.unwrap();
+    assert_eq!(snapshot.bids.len(), 2);
+    assert_eq!(snapshot.best_ask().unwrap().price, 42_000.5);
+
+    sim.publish_book(MarketDataSource::Kraken, "BTC/USD", &[(42_000.0, 0.0)], &[(42_001.0, 3.0)]);
+    let update = within(rx.recv()).await.unwrap();
+    assert_eq!(update.best_bid().unwrap().price, 41_999.5);
+    assert_eq!(update.asks.len(), 2);
+    task.abort();
+}
+
+#[tokio::test]
+async fn test_kraken_book_and_spread_follow_added_symbols() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    sim.publish_book(MarketDataSource::Kraken, "BTC/USD", &[(42_000.0, 1.5)], &[(42_000.5, 0.75)]);
+    sim.publish_book(MarketDataSource::Kraken, "ETH/USD", &[(2_500.0, 4.0)], &[(2_500.5, 1.0)]);
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let (book_tx, mut books) = mpsc::channel(32);
+    let (quote_tx, mut quotes) = mpsc::channel(32);
+    let symbols = vec!["BTC/USD".to_string()];
+    let (book_feed, quote_feed) = (connector.clone(), connector.clone());
+    let book_symbols = symbols.clone();
+    let book_task = tokio::spawn(async move { book_feed.subscribe_order_book(&book_symbols, 10, book_tx).await });
+    let quote_task = tokio::spawn(async move { quote_feed.subscribe_quotes(&symbols, quote_tx).await });
+
+    // The first snapshot shows the book is subscribed past the instrument channel.
+    within(sim.wait_for_subscriptions(2)).await;
+    assert_eq!(within(books.recv()).await.unwrap().symbol, "BTC/USD");
+
+    connector.add_symbols(&["ETH/USD".to_string()]).await.unwrap();
+    let snapshot = within(books.recv()).await.unwrap();
+    assert_eq!(snapshot.symbol, "ETH/USD");
+    assert_eq!(snapshot.best_bid().unwrap().price, 2_500.0);
+    sim.publish_quote(MarketDataSource::Kraken, "ETH/USD", (2_500.0, 4.0), (2_500.5, 1.0));
+    assert_eq!(within(quotes.recv()).await.unwrap().symbol, "ETH/USD");
+    book_task.abort();
+    quote_task.abort();
+}
+
+#[tokio::test]
+async fn test_kraken_order_fills_on_the_private_feed() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, mut fill_rx) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let private = tokio::spawn(connector.clone().subscribe_private());
+    within(sim.wait_for_subscriptions(2)).await;
+
+    let order = limit("XBTUSD", OrderSide::Sell, 0.01, 42_000.0, MarketDataSource::Kraken);
+    let txid = connector.send_order(order.clone()).await.unwrap();
+
+    let placed = sim.orders();
+    assert_eq!(placed.len(), 1);
+    assert_eq!(placed[0].exchange_order_id, txid);
+    assert_eq!(placed[0].order_type, "limit");
+    assert_eq!(placed[0].client_order_id, Some(order.id.to_string()));
+
+    let fill = within(fill_rx.recv()).await.unwrap();
+    assert_eq!(fill.order_id, order.id);
+    assert_eq!((fill.side, fill.price, fill.quantity), (OrderSide::Sell, 42_000.0, 0.01));
+    private.abort();
+}
+
+#[tokio::test]
+async fn test_kraken_fills_made_while_disconnected_are_published_once() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let (fill_tx, mut fill_rx) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+    let private = tokio::spawn(connector.clone().subscribe_private());
+    within(sim.wait_for_subscriptions(2)).await;
+
+    let before = limit("XBTUSD", OrderSide::Sell, 0.01, 42_000.0, MarketDataSource::Kraken);
+    connector.send_order(before.clone()).await.unwrap();
+    assert_eq!(within(fill_rx.recv()).await.unwrap().order_id, before.id);
+
+    // This one fills while the private feed is down and comes back in the
+    // snapshot, alongside the trade already published.
+    sim.disconnect_all();
+    let during = limit("XBTUSD", OrderSide::Buy, 0.02, 41_000.0, MarketDataSource::Kraken);
+    connector.send_order(during.clone()).await.unwrap();
+    within(sim.wait_for_subscriptions(4)).await;
+
+    let fill = within(fill_rx.recv()).await.unwrap();
+    assert_eq!((fill.order_id, fill.quantity), (during.id, 0.02));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(fill_rx.try_recv().is_err());
+    private.abort();
+}
+
+#[tokio::test]
+async fn test_kraken_rejects_a_bad_signature() {
+    let sim = ExchangeSimulator::new().start().await.unwrap();
+    let credentials = Credentials {
+        api_secret: "d3Jvbmctc2VjcmV0".to_string(),
+        ..sim.kraken_credentials()
+    };
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, credentials, fill_tx).await;
+
+    let order = limit("XBTUSD", OrderSide::Buy, 0.01, 42_000.0, MarketDataSource::Kraken);
+    let error = connector.send_order(order).await.unwrap_err();
+    assert!(error.to_string().contains("EAPI:Invalid signature"), "{}", error);
+    assert!(sim.orders().is_empty());
+}
+
+#[tokio::test]
+async fn test_kraken_resting_order_is_amended_and_canceled() {
+    let sim = ExchangeSimulator::new().with_resting_limit_orders().start().await.unwrap();
+    let (fill_tx, _) = mpsc::channel(8);
+    let connector = kraken(&sim, sim.kraken_credentials(), fill_tx).await;
+
+    let order = limit("XBTUSD", OrderSide::Sell, 0.01, 43_000.0, MarketDataSource::Kraken);
+    let txid = connector.send_order(order.clone()).await.unwrap();
+    let open = connector.open_orders(Some("XBTUSD".to_string())).await.unwrap();
+    assert_eq!(open.len(), 1);
+    assert_eq!((open[0].order_id, open[0].exchange_order_id.clone()), (Some(order.id), txid.clone()));
+    assert_eq!(open[0].status, OrderStatus::New);
+
+    let replacement = limit("XBTUSD", OrderSide::Sell, 0.02, 42_500.0, MarketDataSource::Kraken);
+    let amended = connector.amend_order(order.id, replacement.clone()).await.unwrap();
+    assert_eq!((amended.canceled.exchange_order_id.clone(), amended.canceled.status.clone()), (txid.clone(), OrderStatus::Canceled));
+    let amended = amended.replacement;
+    assert_ne!(amended.exchange_order_id, txid);
+    // EditOrder drops the client order id, so the replacement is known by its txid alone.
+    assert_eq!((amended.order_id, amended.amount, amended.price), (Some(replacement.id), 0.02, Some(42_500.0)));
+    assert_eq!(connector.query_order("XBTUSD", order.id).await.unwrap().status, OrderStatus::Canceled);
+
+    let other = limit("ETHUSD", OrderSide::Buy, 1.0, 2_000.0, MarketDataSource::Kraken);
+    connector.send_order(other.clone()).await.unwrap();
+    let canceled = connector.cancel_all_orders(Some("XBTUSD".to_string())).await.unwrap();
+    assert_eq!(canceled.iter().map(|report| report.order_id).collect::<Vec<_>>(), vec![Some(replacement.id)]);
+    assert_eq!(canceled[0].status, OrderStatus::Canceled);
+    assert_eq!(connector.cancel_order("ETHUSD", other.id).await.unwrap().status, OrderStatus::Canceled);
+    assert!(connector.open_orders(None).await.unwrap().is_empty());
+    let error = connector.cancel_order("ETHUSD", other.id).await.unwrap_err();
+    assert!(matches!(error, ExecutionError::UnknownOrder(_)), "{}", error);
+    assert!(error.to_string().contains("EOrder:Unknown order"), "{}", error);
+}
+
 #[tokio::test]
 #[ignore] // This is an integration test and requires a live connection to Binance.
 async fn test_binance_connector_subscribe() {
@@ -33,9 +713,15 @@ async fn test_binance_connector_subscribe() {
     let (fill_tx, _) = mpsc::channel(32);
     let (dashboard_tx, _) = broadcast::channel(32);
     let mock_settlement = Arc::new(MockSettlement::new());
-    let mock_db = Arc::new(MockDatabaseManager::new());
+    let db = in_memory_db().await;
 
-    let connector = Arc::new(BinanceConnector::new(mock_settlement, Some(fill_tx), dashboard_tx, mock_db));
+    // Public market data does not need real credentials.
+    let binance_config = ExchangeConfig {
+        api_key: String::new(),
+        api_secret: String::new(),
+        book_depth: 20,
+    };
+    let connector = Arc::new(BinanceConnector::new(&binance_config, mock_settlement, Some(fill_tx), dashboard_tx, db));
     let symbols = vec!["btcusdt".to_string()];
 
     let subscribe_task = tokio::spawn(async move {
@@ -57,16 +743,20 @@ async fn test_binance_connector_subscribe() {
 #[tokio::test]
 #[ignore] // This is an integration test and requires a live connection to Kraken.
 async fn test_kraken_connector_subscribe() {
-    // Note: This test requires a valid `Config.toml` with Kraken credentials.
-    let config = Config::from_file("../Config.toml").expect("Failed to load config for test");
-    
+    // Public market data does not need real credentials.
+    let kraken_config = ExchangeConfig {
+        api_key: String::new(),
+        api_secret: String::new(),
+        book_depth: 10,
+    };
+
     let (tx, mut rx) = mpsc::channel(32);
     let (fill_tx, _) = mpsc::channel(32);
     let (dashboard_tx, _) = broadcast::channel(32);
     let mock_settlement = Arc::new(MockSettlement::new());
-    let mock_db = Arc::new(MockDatabaseManager::new());
+    let db = in_memory_db().await;
 
-    let connector = Arc::new(KrakenConnector::new(&config.kraken, mock_settlement, Some(fill_tx), dashboard_tx, mock_db));
+    let connector = Arc::new(KrakenConnector::new(&kraken_config, mock_settlement, Some(fill_tx), dashboard_tx, db));
     let symbols = vec!["BTC/USD".to_string()];
 
     let subscribe_task = tokio::spawn(async move {
@@ -83,4 +773,4 @@ async fn test_kraken_connector_subscribe() {
     let tick = result.unwrap().unwrap();
     assert_eq!(tick.symbol.to_uppercase(), "BTC/USD");
     assert!(tick.price > 0.0);
-} 
\ No newline at end of file
+}
@@ -6,6 +6,57 @@ interface Tick {
     price: number;
     volume: number;
     received_at: string;
+    exchange_ts: string | null;
+    trade_id: number | null;
+}
+
+interface Quote {
+    source: string;
+    symbol: string;
+    bid: number;
+    bid_size: number;
+    ask: number;
+    ask_size: number;
+    received_at: string;
+    exchange_ts: string | null;
+}
+
+interface MarkPrice {
+    source: string;
+    symbol: string;
+    mark_price: number;
+    index_price: number;
+    funding_rate: number;
+    next_funding_time: string;
+    received_at: string;
+    exchange_ts: string | null;
+}
+
+interface Candle {
+    source: string;
+    symbol: string;
+    interval_secs: number;
+    open_time: string;
+    open: number;
+    high: number;
+    low: number;
+    close: number;
+    volume: number;
+    trades: number;
+}
+
+interface PriceLevel {
+    price: number;
+    quantity: number;
+}
+
+interface OrderBook {
+    source: string;
+    symbol: string;
+    bids: PriceLevel[];
+    asks: PriceLevel[];
+    last_update_id: number;
+    received_at: string;
 }
 
 interface Trade {
@@ -22,6 +73,20 @@ interface LatencyUpdate {
     latency_us: number;
 }
 
+interface FeedLatency {
+    source: string;
+    symbol: string;
+    latency_us: number;
+}
+
+interface FeedStatus {
+    source: string;
+    stream: 'Trades' | 'Book' | 'Quotes' | 'MarkPrice';
+    symbol: string | null;
+    state: 'Up' | 'Stale' | 'Reconnecting';
+    at: string;
+}
+
 interface LogEntry {
     timestamp: string;
     level: string;
@@ -31,16 +96,28 @@ interface LogEntry {
 
 type DashboardEvent = 
     | { type: 'Tick', data: Tick }
+    | { type: 'Quote', data: Quote }
+    | { type: 'MarkPrice', data: MarkPrice }
+    | { type: 'OrderBook', data: OrderBook }
+    | { type: 'Candle', data: Candle }
     | { type: 'Trade', data: Trade }
     | { type: 'LatencyUpdate', data: LatencyUpdate }
+    | { type: 'FeedLatency', data: FeedLatency }
+    | { type: 'FeedStatus', data: FeedStatus }
     | { type: 'Log', data: LogEntry };
 
-export type { Tick, Trade, LatencyUpdate, LogEntry, DashboardEvent };
+export type { Tick, Quote, MarkPrice, Candle, PriceLevel, OrderBook, Trade, LatencyUpdate, FeedLatency, FeedStatus, LogEntry, DashboardEvent };
 
 export function useWebSocket(url: string) {
     const [ticks, setTicks] = useState<Tick[]>([]);
+    const [quotes, setQuotes] = useState<Record<string, Quote>>({});
+    const [markPrices, setMarkPrices] = useState<Record<string, MarkPrice>>({});
+    const [books, setBooks] = useState<Record<string, OrderBook>>({});
+    const [candles, setCandles] = useState<Record<string, Candle[]>>({});
     const [trades, setTrades] = useState<Trade[]>([]);
     const [latencies, setLatencies] = useState<LatencyUpdate[]>([]);
+    const [feedLatencies, setFeedLatencies] = useState<Record<string, FeedLatency>>({});
+    const [feedStatuses, setFeedStatuses] = useState<Record<string, FeedStatus>>({});
     const [logs, setLogs] = useState<LogEntry[]>([]);
     const [isConnected, setIsConnected] = useState(false);
 
@@ -58,12 +135,32 @@ export function useWebSocket(url: string) {
                 case 'Tick':
                     setTicks(prev => [message.data, ...prev].slice(0, 100)); // Keep last 100 ticks
                     break;
+                case 'Quote':
+                    setQuotes(prev => ({ ...prev, [`${message.data.source}:${message.data.symbol}`]: message.data }));
+                    break;
+                case 'MarkPrice':
+                    setMarkPrices(prev => ({ ...prev, [`${message.data.source}:${message.data.symbol}`]: message.data }));
+                    break;
+                case 'OrderBook':
+                    setBooks(prev => ({ ...prev, [`${message.data.source}:${message.data.symbol}`]: message.data }));
+                    break;
+                case 'Candle': {
+                    const key = `${message.data.source}:${message.data.symbol}:${message.data.interval_secs}`;
+                    setCandles(prev => ({ ...prev, [key]: [...(prev[key] ?? []), message.data].slice(-500) }));
+                    break;
+                }
                 case 'Trade':
                     setTrades(prev => [message.data, ...prev].slice(0, 100));
                     break;
                 case 'LatencyUpdate':
                     setLatencies(prev => [message.data, ...prev].slice(0, 100));
                     break;
+                case 'FeedLatency':
+                    setFeedLatencies(prev => ({ ...prev, [message.data.source]: message.data }));
+                    break;
+                case 'FeedStatus':
+                    setFeedStatuses(prev => ({ ...prev, [`${message.data.source}:${message.data.stream}:${message.data.symbol ?? '*'}`]: message.data }));
+                    break;
                 case 'Log':
                     setLogs(prev => [message.data, ...prev].slice(0, 100));
                     break;
@@ -85,5 +182,5 @@ export function useWebSocket(url: string) {
         };
     }, [url]);
 
-    return { ticks, trades, latencies, logs, isConnected };
+    return { ticks, quotes, markPrices, books, candles, trades, latencies, feedLatencies, feedStatuses, logs, isConnected };
 } 
\ No newline at end of file